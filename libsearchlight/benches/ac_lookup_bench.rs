@@ -0,0 +1,108 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use libsearchlight::search::search_common::{AcTable, AcTableBuilder, IndexableAcTable};
+use tinyrand::{Rand, RandRange, Seeded, StdRand};
+
+criterion_group!(benches, ac_lookup_bench);
+criterion_main!(benches);
+
+const NUM_PATTERNS: usize = 2000;
+const DATA_LEN: usize = 16 * 1024 * 1024;
+
+/// Builds a table of many random patterns, sharing a small set of first bytes so that the start state has a high fan-out
+fn many_pattern_table(rand: &mut StdRand) -> AcTable {
+	let mut builder = AcTableBuilder::new(true);
+
+	for _ in 0..NUM_PATTERNS {
+		let len = rand.next_range(4..12usize);
+		let pattern: Vec<u16> = (0..len).map(|_| rand.next_u16() & 0xff).collect();
+		builder.add_pattern(&pattern);
+	}
+
+	builder.build()
+}
+
+/// Runs the automaton over `data` the way AcCpu did before it used the dense table, looking up each transition by searching its row
+fn linear_lookup_search(table: &AcTable, data: &[u8]) -> usize {
+	let mut states: Vec<u32> = Vec::new();
+	let mut num_matches = 0;
+
+	for &b in data {
+		states.retain_mut(|state| {
+			match table.lookup(*state, b) {
+				Some(elem) => {
+					*state = elem.next_state;
+					if table.table[*state as usize].is_empty() {
+						num_matches += 1;
+						false
+					} else {
+						true
+					}
+				}
+				None => false
+			}
+		});
+
+		if let Some(elem) = table.lookup(0, b) {
+			states.push(elem.next_state);
+		}
+	}
+
+	num_matches
+}
+
+/// Runs the automaton over `data` the way AcCpu does, looking up each transition in the dense table
+fn indexable_lookup_search(table: &IndexableAcTable, data: &[u8]) -> usize {
+	let mut states: Vec<u32> = Vec::new();
+	let mut num_matches = 0;
+
+	for &b in data {
+		states.retain_mut(|state| {
+			match table.lookup(*state, b) {
+				Some((next_state, _)) => {
+					*state = next_state;
+					if table.is_final(*state) {
+						num_matches += 1;
+						false
+					} else {
+						true
+					}
+				}
+				None => false
+			}
+		});
+
+		if let Some((next_state, _)) = table.lookup(0, b) {
+			states.push(next_state);
+		}
+	}
+
+	num_matches
+}
+
+fn ac_lookup_bench(c: &mut Criterion) {
+	let mut rand = StdRand::seed(42);
+
+	let table = many_pattern_table(&mut rand);
+	let data: Vec<u8> = (0..DATA_LEN).map(|_| rand.next_u16() as u8).collect();
+
+	let mut group = c.benchmark_group("ac_lookup");
+	group.sample_size(10);
+	group.throughput(Throughput::Bytes(DATA_LEN as u64));
+
+	group.bench_function("linear", |b| {
+		b.iter(|| black_box(linear_lookup_search(&table, &data)));
+	});
+
+	let indexable_table = table.to_indexable();
+
+	// Both searches should find the same matches, as the random patterns contain no match alls
+	assert_eq!(linear_lookup_search(&table, &data), indexable_lookup_search(&indexable_table, &data));
+
+	group.bench_function("indexable", |b| {
+		b.iter(|| black_box(indexable_lookup_search(&indexable_table, &data)));
+	});
+
+	group.finish();
+}
\ No newline at end of file
@@ -1,3 +1,7 @@
 mod jpeg_data;
+mod entropy;
+mod content_end;
 
-pub use jpeg_data::jpeg_data;
\ No newline at end of file
+pub use jpeg_data::jpeg_data;
+pub use entropy::shannon_entropy;
+pub use content_end::{consistent_entropy_len, text_len, zero_trimmed_len};
\ No newline at end of file
@@ -0,0 +1,62 @@
+use super::shannon_entropy;
+
+/// The largest difference in entropy (in bits per byte) from the mean of the preceding blocks that a block can have while still being taken
+/// as the same content
+const ENTROPY_DEVIATION_THRESHOLD: f32 = 1.5;
+
+/// Returns the length of the text at the start of `data`, i.e. the index of the first byte that is a control character other than the
+/// whitespace ones (tab, line feed, form feed and carriage return). Bytes of 0x80 and above are taken as text, as they are used by UTF-8
+/// and legacy 8-bit encodings
+pub fn text_len(data: &[u8]) -> usize {
+	data.iter().position(|&b| !matches!(b, 0x09 | 0x0a | 0x0c | 0x0d | 0x20..=0x7e | 0x80..=0xff)).unwrap_or(data.len())
+}
+
+/// Returns the length of `data` without any trailing zeros, which are usually unallocated (zeroed) space rather than file content
+pub fn zero_trimmed_len(data: &[u8]) -> usize {
+	data.iter().rposition(|&b| b != 0).map(|i| i + 1).unwrap_or(0)
+}
+
+/// Returns the length of the content at the start of `data` that has a consistent entropy, taken in blocks of `block_size`, i.e. the start of
+/// the first block whose entropy differs from the mean entropy of the blocks before it by more than a threshold (currently 1.5 bits per
+/// byte). The first block is always included
+pub fn consistent_entropy_len(data: &[u8], block_size: usize) -> usize {
+	let mut entropy_sum = 0.0;
+
+	for (i, block) in data.chunks(block_size.max(1)).enumerate() {
+		let entropy = shannon_entropy(block);
+
+		if i > 0 && (entropy - entropy_sum / i as f32).abs() > ENTROPY_DEVIATION_THRESHOLD {
+			return i * block_size;
+		}
+
+		entropy_sum += entropy;
+	}
+
+	data.len()
+}
+
+#[cfg(test)]
+mod test {
+	use super::{consistent_entropy_len, text_len, zero_trimmed_len};
+
+	#[test]
+	fn test_content_end() {
+		let mut data = b"Subject: Test\r\n\r\nSome text\twith whitespace and UTF-8: \xc3\xa9\n".to_vec();
+		let text_end = data.len();
+		data.extend_from_slice(&[ 0x00, 0x41, 0x42 ]);
+
+		assert_eq!(text_len(&data), text_end);
+		assert_eq!(text_len(&data[..text_end]), text_end);
+
+		assert_eq!(zero_trimmed_len(&[ 0x01, 0x00, 0x02, 0x00, 0x00 ]), 3);
+		assert_eq!(zero_trimmed_len(&[ 0x00; 4 ]), 0);
+
+		// Pseudo-random (high entropy) data followed by zeros, with a partial block at the end
+		let mut data: Vec<u8> = (0..2048u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+		data.extend_from_slice(&[ 0x00; 1000 ]);
+
+		assert_eq!(consistent_entropy_len(&data, 512), 2048);
+		assert_eq!(consistent_entropy_len(&data[..2048], 512), 2048);
+		assert_eq!(consistent_entropy_len(&data[2048..], 512), 1000);
+	}
+}
\ No newline at end of file
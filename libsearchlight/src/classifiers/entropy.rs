@@ -0,0 +1,48 @@
+/// Calculates the Shannon entropy of `data`, in bits per byte, so between 0 (every byte is the same) and 8 (every byte value is equally
+/// likely). Compressed and encrypted data have an entropy close to 8, whereas text and uncompressed structured data have a much lower one
+pub fn shannon_entropy(data: &[u8]) -> f32 {
+	let mut counts = [0u32; 256];
+
+	for &b in data {
+		counts[b as usize] += 1;
+	}
+
+	shannon_entropy_of_counts(&counts, data.len())
+}
+
+/// Calculates the Shannon entropy of data of length `data_len`, given the number of times each byte value occurs in it
+pub(crate) fn shannon_entropy_of_counts(counts: &[u32], data_len: usize) -> f32 {
+	// Can't calculate the entropy without data so return 0. Would panic otherwise
+	if data_len == 0 {
+		return 0.0;
+	}
+
+	// And calculate the entropy
+	let mut entropy = 0.0;
+	for &count in counts {
+		if count != 0 {
+			let probability = (count as f32) / (data_len as f32);
+			entropy -= probability * probability.log2();
+		}
+	}
+
+	entropy
+}
+
+#[cfg(test)]
+mod test {
+	use super::shannon_entropy;
+
+	#[test]
+	fn test_shannon_entropy() {
+		assert_eq!(shannon_entropy(&[]), 0.0);
+		assert_eq!(shannon_entropy(&[ 0x41; 1000 ]), 0.0);
+		assert_eq!(shannon_entropy(&[ 0x00, 0xff ]), 1.0);
+
+		let all_values: Vec<u8> = (0..=255).collect();
+		assert!((shannon_entropy(&all_values) - 8.0).abs() < 1e-4);
+
+		let text = b"The quick brown fox jumps over the lazy dog, again and again and again";
+		assert!(shannon_entropy(text) < 5.0);
+	}
+}
\ No newline at end of file
@@ -1,26 +1,9 @@
+use super::entropy::shannon_entropy_of_counts;
+
 const ENTROPY_THRESHOLD: f32 = 0.6;
 const FF00_THRESHOLD: u32 = 0; // Larger values seem to cause problems, especially for smaller cluster sizes
 const FF00_CERTAINTY_THRESHOLD: u32 = 4;
 
-/// Calculate the Shannon entropy of a slice
-fn shannon_entropy(counts: &[u32], data_len: usize) -> f32 {
-	// Can't calculate the entropy without data so return 0. Would panic otherwise
-	if data_len == 0 {
-		return 0.0;
-	}
-
-	// And calculate the entropy
-	let mut entropy = 0.0;
-	for &count in counts {
-		if count != 0 {
-			let probability = (count as f32) / (data_len as f32);
-			entropy -= probability * probability.log2();
-		}
-	}
-
-	entropy
-}
-
 /// Attempts to classify a cluster of file data as JPEG scan data or not, by calculating the Shannon entropy
 /// and comparing it to a threshold (currently of 0.6), and by doing some analysis on the bytes to check
 /// whether 0xff's are followed by valid bytes in a JPEG-compressed datastream, also checking that if RST
@@ -83,7 +66,7 @@ pub fn jpeg_data(cluster: &[u8]) -> (bool, Option<usize>) {
 		}
 	}
 
-	let entropy = shannon_entropy(&counts, bytes_counted);
+	let entropy = shannon_entropy_of_counts(&counts, bytes_counted);
 
 	let entropy_valid = entropy > ENTROPY_THRESHOLD;
 	let contents_valid = count_ff00 >= FF00_THRESHOLD && rst_marker_ordering_valid && !found_invalid_marker;
@@ -63,7 +63,7 @@ mod vulkan_error {
 	}
 }
 
-use std::{fmt::Display, io};
+use std::{fmt::Display, io, time::Duration};
 
 #[cfg(feature = "gpu")]
 pub use self::vulkan_error::VulkanError;
@@ -89,7 +89,22 @@ pub enum Error {
 	VulkanError(VulkanError),
 	ConfigValidationError,
 	IoError(io::Error),
-	LogReadError(String)
+	LogReadError(String),
+	InvalidRegion(String),
+	/// A supplied cluster size is zero, not a power of two, or unreasonably large
+	InvalidClusterSize(String),
+	/// An external pattern file referenced by the config could not be read or contains an invalid pattern
+	PatternFileError(String),
+	/// A searcher found more matches in the searched data than it has capacity to return
+	SearchOutputOverflow { found: usize, capacity: usize },
+	/// The GPU was required for searching but can't be used
+	GpuUnavailable(String),
+	/// A search did not complete within the timeout of the searcher
+	SearchTimeout(Duration),
+	/// Options were requested that can't be honoured when an image is streamed
+	UnsupportedWhenStreaming(String),
+	/// The pool of worker threads could not be created
+	ThreadPoolError(String)
 }
 
 impl Display for Error {
@@ -99,7 +114,15 @@ impl Display for Error {
 			Error::VulkanError(e) => e.to_string(),
 			Error::ConfigValidationError => "Config validation error".to_string(),
 			Error::IoError(e) => e.to_string(),
-			Error::LogReadError(msg) => format!("Log file format error: {msg}")
+			Error::LogReadError(msg) => format!("Log file format error: {msg}"),
+			Error::InvalidRegion(msg) => format!("Invalid region: {msg}"),
+			Error::InvalidClusterSize(msg) => format!("Invalid cluster size: {msg}"),
+			Error::PatternFileError(msg) => format!("Pattern file error: {msg}"),
+			Error::SearchOutputOverflow { found, capacity } => format!("Search output overflowed: Found {found} matches but only have capacity for {capacity}"),
+			Error::GpuUnavailable(msg) => format!("GPU unavailable: {msg}"),
+			Error::SearchTimeout(timeout) => format!("Search did not complete within {:.1}s", timeout.as_secs_f64()),
+			Error::UnsupportedWhenStreaming(options) => format!("Not supported when streaming an image: {options}"),
+			Error::ThreadPoolError(msg) => format!("Failed to create the worker thread pool: {msg}")
 		})
 	}
 }
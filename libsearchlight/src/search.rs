@@ -4,6 +4,10 @@ pub mod pfac_gpu;
 pub mod ac_cpu;
 pub mod pairing;
 
+use std::{sync::Arc, time::Duration};
+
+use rayon::ThreadPool;
+
 use self::{search_common::AcTable, ac_cpu::AcCpu};
 
 use super::error::Error;
@@ -57,7 +61,8 @@ pub trait Searcher {
 	/// or an error if one occurred. Searches may be overlapping
 	/// each other (by `overlap` bytes) and so implementors should either not keep state between
 	/// calls or skip the first `overlap` bytes in their search (overlap will only ever be at the
-	/// start of the slice)
+	/// start of the slice). Implementors that keep state must only continue from it if the slice
+	/// (after the overlap) directly follows the previously searched slice
 	fn search(&mut self, data: &[u8], data_offset: u64, overlap: usize) -> Result<SearchFuture, Error>;
 
 	/// The maximum number of bytes that this Searcher implementor can accept at a time for searching,
@@ -65,11 +70,67 @@ pub trait Searcher {
 	fn max_search_size(&self) -> Option<usize> {
 		None
 	}
+
+	/// The number of bytes that consecutive searched slices need to overlap by for matches straddling the boundary between them to be
+	/// found, given the length of the longest pattern. Default implementation returns `max_pat_len`, but implementors that need more
+	/// context than that can request more
+	fn required_overlap(&self, max_pat_len: usize) -> usize {
+		max_pat_len
+	}
+
+	/// Discards the state kept from previous searches for matches in progress at the end of the searched data, so that the next search
+	/// starts afresh even if it directly follows the previous one, e.g. if the searcher is reused for different data at the same offsets.
+	/// Default implementation does nothing, for implementors that don't keep state between searches
+	fn discard_progress(&mut self) {}
+
+	/// The kind of backend that this Searcher implementor searches with. Default implementation returns `SearcherKind::Cpu`
+	fn active_backend(&self) -> SearcherKind {
+		SearcherKind::Cpu
+	}
+
+	/// Switches to a fallback backend after a search has resulted in an `Error::SearchTimeout`, returning whether it did so, in which case
+	/// the timed out search can be retried. Default implementation returns false
+	fn fall_back(&mut self) -> bool {
+		false
+	}
+}
+
+/// The default time to wait for a search on the GPU to complete before it times out
+pub const DEFAULT_GPU_SEARCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options for searching with the GPU-accelerated PFAC implementation
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GpuSearchOptions {
+	/// How long to wait for each search to complete before it times out
+	pub timeout: Duration,
+	/// Whether to switch to the fallback AC implementation for the rest of the run once a search times out, rather than the search failing
+	pub fall_back_on_timeout: bool
+}
+
+impl Default for GpuSearchOptions {
+	fn default() -> Self {
+		GpuSearchOptions {
+			timeout: DEFAULT_GPU_SEARCH_TIMEOUT,
+			fall_back_on_timeout: false
+		}
+	}
+}
+
+/// The kinds of backend that searching can be performed with
+#[derive(Debug, PartialEq, Eq, Clone, Copy, strum::Display)]
+pub enum SearcherKind {
+	#[strum(to_string = "GPU")]
+	Gpu,
+	#[strum(to_string = "CPU")]
+	Cpu
 }
 
 pub struct DelegatingSearcher {
 	search_impl: Box<dyn Searcher>,
-	max_search_size: Option<usize>
+	max_search_size: Option<usize>,
+	backend: SearcherKind,
+	/// The table and pool to create the fallback AC implementation with if a search on the GPU times out, if falling back is enabled
+	fallback: Option<(AcTable, Arc<ThreadPool>)>
 }
 
 impl DelegatingSearcher {
@@ -77,17 +138,27 @@ impl DelegatingSearcher {
 	/// The GPU-accelerated PFAC implementation will be chosen by default if the
 	/// project was compiled with the GPU feature and the a Vulkan implementation
 	/// with the necessary features is available. Pass `prefer_cpu` as true to
-	/// select the fallback AC implementation by default
-	pub fn new(table: AcTable, prefer_cpu: bool) -> Self {
+	/// select the fallback AC implementation by default. `pool` is the pool of
+	/// threads the fallback AC implementation will search on
+	pub fn new(table: AcTable, prefer_cpu: bool, pool: Arc<ThreadPool>) -> Self {
+		Self::with_gpu_options(table, prefer_cpu, pool, GpuSearchOptions::default())
+	}
+
+	/// As `new`, but the GPU-accelerated PFAC implementation, if chosen, searches with `gpu_options`
+	pub fn with_gpu_options(table: AcTable, prefer_cpu: bool, pool: Arc<ThreadPool>, gpu_options: GpuSearchOptions) -> Self {
 		if !prefer_cpu {
 			#[cfg(feature = "gpu")]
 			{
 				match PfacGpu::new(table.clone()) {
-					Ok(pfac_gpu) => {
-						return DelegatingSearcher {
-							search_impl: Box::new(pfac_gpu),
-							max_search_size: Some(pfac_gpu::INPUT_BUFFER_SIZE as usize)
-						};
+					Ok(mut pfac_gpu) => {
+						pfac_gpu.set_timeout(gpu_options.timeout);
+
+						let mut searcher = Self::with_pfac_gpu(pfac_gpu);
+						if gpu_options.fall_back_on_timeout {
+							searcher.fallback = Some((table, pool));
+						}
+
+						return searcher;
 					}
 					Err(e) => {
 						warn!("Vulkan initialisation failed, falling back to CPU impl of Aho Corasick: {:?}", e);
@@ -96,26 +167,91 @@ impl DelegatingSearcher {
 			}
 		}
 
+		#[cfg(not(feature = "gpu"))]
+		let _ = gpu_options;
+
 		return DelegatingSearcher {
-			search_impl: Box::new(AcCpu::new(table)),
-			max_search_size: None
+			search_impl: Box::new(AcCpu::with_pool(table, pool)),
+			max_search_size: None,
+			backend: SearcherKind::Cpu,
+			fallback: None
 		};
 	}
+
+	/// Creates a DelegatingSearcher with the GPU-accelerated PFAC implementation, returning an error instead of falling back to the AC
+	/// implementation if the project was compiled without the GPU feature or no Vulkan implementation with the necessary features is available.
+	/// Each search times out after `timeout`
+	pub fn new_gpu_required(table: AcTable, timeout: Duration) -> Result<Self, Error> {
+		#[cfg(feature = "gpu")]
+		{
+			PfacGpu::new(table).map(|mut pfac_gpu| {
+				pfac_gpu.set_timeout(timeout);
+				Self::with_pfac_gpu(pfac_gpu)
+			})
+		}
+		#[cfg(not(feature = "gpu"))]
+		{
+			let _ = (table, timeout);
+			Err(Error::GpuUnavailable("Searchlight was compiled without the gpu feature".to_string()))
+		}
+	}
+
+	#[cfg(feature = "gpu")]
+	fn with_pfac_gpu(pfac_gpu: PfacGpu) -> Self {
+		DelegatingSearcher {
+			search_impl: Box::new(pfac_gpu),
+			max_search_size: Some(pfac_gpu::INPUT_BUFFER_SIZE as usize),
+			backend: SearcherKind::Gpu,
+			fallback: None
+		}
+	}
 }
 
 impl Searcher for DelegatingSearcher {
+	/// Searches with the current implementation, which reports the matches in the overlap with the previous search only once (see
+	/// `Searcher::search`)
 	fn search(&mut self, data: &[u8], data_offset: u64, overlap: usize) -> Result<SearchFuture, Error> {
-		match self.search_impl.search(data, data_offset, overlap) {
-			Ok(results) => Ok(results),
-			Err(e) => {
-				Err(Error::from(e))
-			}
-		}
+		self.search_impl.search(data, data_offset, overlap)
 	}
 
 	fn max_search_size(&self) -> Option<usize> {
 		self.max_search_size
 	}
+
+	/// The overlap required by the current implementation, or if falling back on timeout is enabled, at least `max_pat_len`, as the
+	/// fallback AC implementation requires that and the overlap can't change partway through a search
+	fn required_overlap(&self, max_pat_len: usize) -> usize {
+		if self.fallback.is_some() {
+			self.search_impl.required_overlap(max_pat_len).max(max_pat_len)
+		} else {
+			self.search_impl.required_overlap(max_pat_len)
+		}
+	}
+
+	fn discard_progress(&mut self) {
+		self.search_impl.discard_progress()
+	}
+
+	/// The backend that this DelegatingSearcher searches with, which is the CPU if the GPU was unavailable when it was created or if it has
+	/// since fallen back to it
+	fn active_backend(&self) -> SearcherKind {
+		self.backend
+	}
+
+	/// Switches to the fallback AC implementation, if falling back on timeout is enabled. The max search size is kept, so that the data
+	/// is still split into the same blocks. The GPU implementation is dropped, which tears down its device, unless a search that timed out
+	/// still holds it (see `PfacGpu`), in which case it is leaked rather than waited on
+	fn fall_back(&mut self) -> bool {
+		match self.fallback.take() {
+			Some((table, pool)) => {
+				self.search_impl = Box::new(AcCpu::with_pool(table, pool));
+				self.backend = SearcherKind::Cpu;
+
+				true
+			}
+			None => false
+		}
+	}
 }
 
 const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
@@ -184,13 +320,15 @@ mod test {
 	#[cfg(feature = "big_tests")]
 	use crate::utils::iter::ToGappedWindows;
 
-	use super::{clmul, FNV_OFFSET_BASIS, FNV_PRIME};
+	use crate::utils::thread_pool;
+
+	use super::{clmul, search_common::AcTableBuilder, DelegatingSearcher, Searcher, FNV_OFFSET_BASIS, FNV_PRIME};
 
 	#[cfg(feature = "big_tests")]
 	use log::error;
 
 	#[cfg(feature = "big_tests")]
-	use super::{super::utils, ac_cpu::AcCpu, pfac_gpu::PfacGpu, search_common::AcTableBuilder, Searcher, SearchFuture, Match};
+	use super::{super::utils, ac_cpu::AcCpu, pfac_gpu::PfacGpu, SearchFuture, Match};
 
 	#[cfg(feature = "big_tests")]
 	const TEST_FILE: &'static str = "../test_data/nps-2009-canon2-gen6.raw";
@@ -202,6 +340,41 @@ mod test {
 		assert_eq!(clmul(FNV_OFFSET_BASIS, FNV_PRIME), (FNV_OFFSET_BASIS as u128 * FNV_PRIME as u128) as u64);
 	}
 
+	#[test]
+	fn test_delegating_searcher_overlap() {
+		let pattern = &[ 0xaa, 0xbb, 0xcc, 0xdd ];
+
+		// Blocks of 16 bytes, each overlapping the previous by 4. The patterns end exactly at the end of a block (so lie entirely within
+		// the overlap of the next), start exactly at the end of a block, and straddle the start of a block
+		let mut data = vec![ 0u8; 48 ];
+		for start in [ 12, 16, 22, 36 ] {
+			data[start..(start + 4)].copy_from_slice(&[ 0xaa, 0xbb, 0xcc, 0xdd ]);
+		}
+
+		let table = AcTableBuilder::new(true).with_pattern(pattern).build();
+
+		let mut searcher = DelegatingSearcher::new(table.clone(), true, thread_pool(2).unwrap());
+		let expected = searcher.search(&data, 0, 0).unwrap().wait().unwrap();
+		assert_eq!(expected.iter().map(|m| (m.start_idx, m.end_idx)).collect::<Vec<_>>(), vec![ (12, 15), (16, 19), (22, 25), (36, 39) ]);
+
+		// Whether the implementation keeps state between searches (with one thread) or searches the overlap again (with more), the matches
+		// in the overlap are only reported once
+		for num_threads in [ 1, 2 ] {
+			let mut searcher = DelegatingSearcher::new(table.clone(), true, thread_pool(num_threads).unwrap());
+
+			let mut matches = Vec::new();
+			for block_start in (0..data.len()).step_by(12).take_while(|&start| start + 4 < data.len()) {
+				let block = &data[block_start..(block_start + 16).min(data.len())];
+				let overlap = if block_start == 0 { 0 } else { 4 };
+
+				matches.append(&mut searcher.search(block, block_start as u64, overlap).unwrap().wait().unwrap());
+			}
+			matches.sort_by_key(|m| m.start_idx);
+
+			assert_eq!(matches, expected);
+		}
+	}
+
 	// TODO: Hash tests, in particular tests to prove the output of the 16-bit and 8-bit hash functions are identical
 
 	/// Runs the search impl across the test data in 1024*1024 byte windows, returning a map of window index to matches found in that window
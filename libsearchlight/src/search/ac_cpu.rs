@@ -1,6 +1,10 @@
+use std::sync::Arc;
+
+use rayon::ThreadPool;
+
 use crate::error::Error;
 
-use super::{match_id_hash_add_u16, match_id_hash_init, search_common::AcTable, Match, SearchFuture, Searcher};
+use super::{match_id_hash_add_u16, match_id_hash_init, search_common::{AcTable, IndexableAcTable}, Match, SearchFuture, Searcher};
 
 struct AcState {
 	state: u32,
@@ -10,24 +14,42 @@ struct AcState {
 
 pub struct AcCpu {
 	table: AcTable,
-	states: Vec<AcState>
+	/// The dense representation of `table`, which is what is actually used for searching
+	indexable_table: IndexableAcTable,
+	states: Vec<AcState>,
+	/// The offset just past the end of the last searched data, which the next search must continue from for `states` to be valid
+	search_end: Option<u64>,
+	/// The pool that segments are searched in parallel on, if it has more than one thread
+	pool: Option<Arc<ThreadPool>>
 }
 
 impl AcCpu {
 	pub fn new(table: AcTable) -> Self {
 		AcCpu {
+			indexable_table: table.to_indexable(),
 			table,
-			states: Vec::new()
+			states: Vec::new(),
+			search_end: None,
+			pool: None
 		}
 	}
-}
 
-impl Searcher for AcCpu {
-	fn search(&mut self, data: &[u8], data_offset: u64, overlap: usize) -> Result<SearchFuture, Error> {
-		// Account for overlap, since we are keeping state between searches
-		let data = &data[overlap..];
-		let data_offset = data_offset + overlap as u64;
+	/// Create an AcCpu that splits each searched slice into a segment per thread of `pool` and searches them in parallel on it. With more
+	/// than one thread, no state is kept between searches, and instead the overlap is searched again and matches that lie entirely within
+	/// it are discarded
+	pub fn with_pool(table: AcTable, pool: Arc<ThreadPool>) -> Self {
+		AcCpu {
+			indexable_table: table.to_indexable(),
+			table,
+			states: Vec::new(),
+			search_end: None,
+			pool: (pool.current_num_threads() > 1).then_some(pool)
+		}
+	}
 
+	/// Runs the Aho-Corasick automaton over `data`, continuing from and updating `states`. Returns all matches that were completed
+	/// in `data`, with indexes offset by `data_offset`
+	fn search_segment(table: &IndexableAcTable, states: &mut Vec<AcState>, data: &[u8], data_offset: u64) -> Vec<Match> {
 		let mut matches = Vec::new();
 
 		let mut i = 0;
@@ -37,32 +59,32 @@ impl Searcher for AcCpu {
 			}
 
 			let mut j = 0;
-			while j < self.states.len() {
-				if let Some(elem) = self.table.lookup(self.states[j].state, data[i]) {
-					self.states[j].state = elem.next_state;
-					self.states[j].id = match_id_hash_add_u16(self.states[j].id, elem.value);
+			while j < states.len() {
+				if let Some((next_state, value)) = table.lookup(states[j].state, data[i]) {
+					states[j].state = next_state;
+					states[j].id = match_id_hash_add_u16(states[j].id, value);
 
-					if self.table.table[self.states[j].state as usize].is_empty() {
+					if table.is_final(next_state) {
 						matches.push(Match {
-							id: self.states[j].id,
-							start_idx: self.states[j].start_idx as u64,
+							id: states[j].id,
+							start_idx: states[j].start_idx as u64,
 							end_idx: i as u64 + data_offset
 						});
-						self.states.remove(j);
+						states.remove(j);
 						continue;
 					}
 				} else {
-					self.states.remove(j);
+					states.remove(j);
 					continue;
 				}
 
 				j += 1;
 			}
 
-			if let Some(elem) = self.table.lookup(0, data[i]) {
-				self.states.push(AcState {
-					state: elem.next_state,
-					id: match_id_hash_add_u16(match_id_hash_init(), elem.value),
+			if let Some((next_state, value)) = table.lookup(0, data[i]) {
+				states.push(AcState {
+					state: next_state,
+					id: match_id_hash_add_u16(match_id_hash_init(), value),
 					start_idx: i + data_offset as usize
 				})
 			}
@@ -70,13 +92,83 @@ impl Searcher for AcCpu {
 			i += 1;
 		}
 
+		matches
+	}
+
+	/// Searches `data` by splitting it into a segment per thread of `pool` and searching each on it from a fresh state. Each segment is
+	/// extended into the next by the max pattern length so that matches spanning segment boundaries are found once, by the segment that they
+	/// start in. Matches that end within the first `overlap` bytes are discarded, as they will have been found by the previous search
+	fn search_parallel(&self, pool: &ThreadPool, data: &[u8], data_offset: u64, overlap: usize) -> Vec<Match> {
+		let segment_len = data.len().div_ceil(pool.current_num_threads()).max(1);
+		let extension = (self.table.max_pat_len as usize).saturating_sub(1);
+
+		let mut segment_matches: Vec<Vec<Match>> = (0..data.len()).step_by(segment_len).map(|_| Vec::new()).collect();
+
+		pool.scope(|s| {
+			for (seg_start, seg_matches) in (0..data.len()).step_by(segment_len).zip(segment_matches.iter_mut()) {
+				let seg_end = (seg_start + segment_len).min(data.len());
+				let search_end = (seg_end + extension).min(data.len());
+				let table = &self.indexable_table;
+				let segment = &data[seg_start..search_end];
+
+				s.spawn(move |_| {
+					let mut states = Vec::new();
+					*seg_matches = Self::search_segment(table, &mut states, segment, data_offset + seg_start as u64);
+					seg_matches.retain(|m| m.start_idx < data_offset + seg_end as u64);
+				});
+			}
+		});
+
+		let mut matches: Vec<Match> = segment_matches.into_iter().flatten().collect();
+
+		matches.retain(|m| m.end_idx >= data_offset + overlap as u64);
+
+		matches
+	}
+}
+
+impl Searcher for AcCpu {
+	fn search(&mut self, data: &[u8], data_offset: u64, overlap: usize) -> Result<SearchFuture, Error> {
+		if let Some(pool) = &self.pool {
+			let matches = self.search_parallel(pool, data, data_offset, overlap);
+
+			return Ok(SearchFuture::new(|| Ok(matches)));
+		}
+
+		// Account for overlap, since we are keeping state between searches
+		let data = &data[overlap..];
+		let data_offset = data_offset + overlap as u64;
+
+		// The kept state is only valid if this data directly follows the last searched data, e.g. not if some data has been skipped
+		if self.search_end != Some(data_offset) {
+			self.states.clear();
+		}
+		self.search_end = Some(data_offset + data.len() as u64);
+
+		let matches = Self::search_segment(&self.indexable_table, &mut self.states, data, data_offset);
+
 		Ok(SearchFuture::new(|| Ok(matches)))
 	}
+
+	/// With one thread, matches straddling the boundary between consecutive searches are continued from the state at the end of the previous
+	/// search, so no overlap is required. With more, each search starts afresh
+	fn required_overlap(&self, max_pat_len: usize) -> usize {
+		if self.pool.is_some() {
+			max_pat_len
+		} else {
+			0
+		}
+	}
+
+	fn discard_progress(&mut self) {
+		self.states.clear();
+		self.search_end = None;
+	}
 }
 
 #[cfg(test)]
 mod test {
-	use crate::{search::{ac_cpu::AcCpu, match_id_hash_slice_u16, search_common::AcTableBuilder, Match, Searcher}, searchlight::config::MatchString};
+	use crate::{search::{ac_cpu::AcCpu, match_id_hash_slice_u16, search_common::AcTableBuilder, Match, Searcher}, searchlight::config::MatchString, utils::thread_pool};
 
 	#[test]
 	fn test_ac_cpu_single() {
@@ -190,4 +282,78 @@ mod test {
 
 		assert_eq!(matches, expected);
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn test_ac_cpu_threaded() {
+		let buffer = [ 1, 2, 3, 4, 5, 8, 4, 1, 2, 3, 4, 5, 1, 1, 2, 1, 2, 3, 4, 5, 0, 5, 9, 1, 2, 3, 4, 5 ];
+
+		let pattern = &[ 1u16, 2, 3, 4, 5 ];
+		let pattern_id = match_id_hash_slice_u16(pattern);
+
+		let pfac_table = AcTableBuilder::new(true).with_pattern(pattern).build();
+		let mut ac = AcCpu::with_pool(pfac_table, thread_pool(4).unwrap());
+		let mut matches = ac.search(&buffer[..12], 0, 0).unwrap().wait().unwrap();
+		matches.append(&mut ac.search(&buffer[7..], 7, ac.table.max_pat_len as usize).unwrap().wait().unwrap());
+		matches.sort_by_key(|m| m.start_idx);
+
+		let expected = vec![
+			Match {
+				id: pattern_id,
+				start_idx: 0,
+				end_idx: 4
+			},
+			Match {
+				id: pattern_id,
+				start_idx: 7,
+				end_idx: 11
+			},
+			Match {
+				id: pattern_id,
+				start_idx: 15,
+				end_idx: 19
+			},
+			Match {
+				id: pattern_id,
+				start_idx: 23,
+				end_idx: 27
+			}
+		];
+
+		assert_eq!(matches, expected);
+	}
+
+	#[test]
+	fn test_ac_cpu_carried_state() {
+		let buffer: Vec<u8> = [ 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 0, 1, 2, 3 ].repeat(8);
+
+		// A pattern longer than the blocks that are searched, which can only be matched by continuing from the state of previous searches
+		let pattern: Vec<u16> = (1..=12).collect();
+		let table = AcTableBuilder::new(true).with_pattern(&pattern).with_pattern(&[ 0, 1, 2 ]).build();
+
+		// Searched with overlap by several threads, each starting afresh
+		let mut ac = AcCpu::with_pool(table.clone(), thread_pool(4).unwrap());
+		let overlap = ac.required_overlap(table.max_pat_len as usize);
+		let mut expected = ac.search(&buffer[..40], 0, 0).unwrap().wait().unwrap();
+		expected.append(&mut ac.search(&buffer[(40 - overlap)..], (40 - overlap) as u64, overlap).unwrap().wait().unwrap());
+		expected.sort_by_key(|m| m.start_idx);
+
+		// Searched in blocks of 5 bytes without overlap by one thread
+		let mut ac = AcCpu::new(table.clone());
+		assert_eq!(ac.required_overlap(table.max_pat_len as usize), 0);
+		let mut matches = Vec::new();
+		for (i, block) in buffer.chunks(5).enumerate() {
+			matches.append(&mut ac.search(block, i as u64 * 5, 0).unwrap().wait().unwrap());
+		}
+
+		assert_eq!(matches.len(), 16);
+		assert_eq!(matches, expected);
+
+		// Matches in progress are not continued once discarded
+		let mut ac = AcCpu::new(table);
+		let mut matches = ac.search(&buffer[..5], 0, 0).unwrap().wait().unwrap();
+		ac.discard_progress();
+		matches.append(&mut ac.search(&buffer[5..16], 5, 0).unwrap().wait().unwrap());
+
+		assert_eq!(matches, vec![ Match { id: match_id_hash_slice_u16(&[ 0, 1, 2 ]), start_idx: 12, end_idx: 14 } ]);
+	}
+}
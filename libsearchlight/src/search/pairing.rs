@@ -1,9 +1,9 @@
 use core::fmt;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use log::warn;
+use log::{debug, warn};
 
-use crate::searchlight::config::{FileType, PairingStrategy, SearchlightConfig};
+use crate::{searchlight::config::{FileType, PairingStrategy, SearchlightConfig}, validation::RejectionReason};
 
 use super::{match_id_hash_slice_u16, Match};
 
@@ -11,12 +11,15 @@ use super::{match_id_hash_slice_u16, Match};
 pub struct MatchPair<'a> {
 	pub file_type: &'a FileType,
 	pub start_idx: usize,
-	pub end_idx: usize
+	pub end_idx: usize,
+	/// Whether this pair was completed at the file type's max_len because no footer was found in range, in which case `end_idx` is not
+	/// the end of a footer and the file is truncated
+	pub truncated: bool
 }
 
 impl fmt::Debug for MatchPair<'_> {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		f.debug_struct("MatchPair")/*.field("file_type", &self.file_type)*/.field("start_idx", &self.start_idx).field("end_idx", &self.end_idx).finish()
+		f.debug_struct("MatchPair")/*.field("file_type", &self.file_type)*/.field("start_idx", &self.start_idx).field("end_idx", &self.end_idx).field("truncated", &self.truncated).finish()
 	}
 }
 
@@ -24,18 +27,39 @@ impl<'a> MatchPair<'a> {
 	pub fn new(file_type: &'a FileType, start: &Match, end: &Match) -> Self {
 		MatchPair {
 			file_type,
-			start_idx: start.start_idx as usize,
-			end_idx: end.end_idx as usize
+			start_idx: Self::file_start(file_type, start) as usize,
+			end_idx: end.end_idx as usize,
+			truncated: false
 		}
 	}
 
 	pub fn new_sized(file_type: &'a FileType, start: &Match, size: u64) -> Self {
+		let file_start = Self::file_start(file_type, start);
+
 		MatchPair {
 			file_type,
-			start_idx: start.start_idx as usize,
-			end_idx: (start.start_idx + size) as usize
+			start_idx: file_start as usize,
+			end_idx: (file_start + size) as usize,
+			truncated: false
 		}
 	}
+
+	/// Creates a match pair for a file that starts at `start` and, as its footer could not be found in range, is truncated where the furthest
+	/// footer that would be in range (see `in_range`) would end, `max_len` bytes after the start of the header. As with `MatchPair::new`,
+	/// `end_idx` is the index of the last byte of the file
+	pub fn new_truncated(file_type: &'a FileType, start: &Match, max_len: u64) -> Self {
+		MatchPair {
+			file_type,
+			start_idx: Self::file_start(file_type, start) as usize,
+			end_idx: (start.start_idx + max_len) as usize,
+			truncated: true
+		}
+	}
+
+	/// The start of the file whose header is `start`, which is `header_offset` bytes before the header
+	fn file_start(file_type: &FileType, start: &Match) -> u64 {
+		start.start_idx.saturating_sub(file_type.header_offset)
+	}
 }
 
 #[derive(Debug, PartialEq, Clone, Copy, strum::Display)]
@@ -64,6 +88,10 @@ pub fn preprocess_config<'a>(config: &'a SearchlightConfig) -> HashMap<u64, (usi
 		}
 		for footer in &config.file_types[i].footers {
 			let id = match_id_hash_slice_u16(&footer);
+			// Delimiters (footers that are also headers of the same type) are kept as headers, and are told apart when pairing
+			if id_ftype_map.get(&id).is_some_and(|&(ftype_idx, _, part)| ftype_idx == i && part == MatchPart::Header) {
+				continue;
+			}
 			if id_ftype_map.contains_key(&id) {
 				warn!(
 					"Collision detected, matches of this byte sequence may be misattributed (footer: {:?} in type {}) - All byte sequences used in headers and footers should be unique",
@@ -78,6 +106,34 @@ pub fn preprocess_config<'a>(config: &'a SearchlightConfig) -> HashMap<u64, (usi
 	id_ftype_map
 }
 
+/// The preference ranks of the footers of the file types in `id_ftype_map` that have ordered footers, by file type index and footer id, where
+/// the rank of a footer is its index in the configured footers (lower is more preferred). See `footer_rank`
+fn footer_ranks(id_ftype_map: &HashMap<u64, (usize, &FileType, MatchPart)>) -> HashMap<(usize, u64), usize> {
+	let ordered_ftypes: HashMap<usize, &FileType> = id_ftype_map.values()
+		.filter(|(_, ftype, _)| ftype.ordered_footers)
+		.map(|&(ftype_idx, ftype, _)| (ftype_idx, ftype))
+		.collect();
+
+	// In reverse, so that the rank of a footer that is configured more than once is that of its first occurrence
+	ordered_ftypes.into_iter()
+		.flat_map(|(ftype_idx, ftype)| ftype.footers.iter().enumerate().rev().map(move |(rank, footer)| ((ftype_idx, match_id_hash_slice_u16(footer)), rank)))
+		.collect()
+}
+
+/// The preference rank of the footer with id `footer_id` of the file type at `ftype_idx`, where lower is more preferred, from the ranks
+/// computed by `footer_ranks`. All footers have the same rank unless the file type has ordered footers
+fn footer_rank(footer_ranks: &HashMap<(usize, u64), usize>, ftype_idx: usize, footer_id: u64) -> usize {
+	footer_ranks.get(&(ftype_idx, footer_id)).copied().unwrap_or(0)
+}
+
+/// Whether the footer match `footer` of `ftype` is followed in `data` by what the type's `footer_context` requires, if it has one, and so
+/// is worth pairing. `cluster_size` is that of the image, which is overridden by the cluster size of the type, if set
+pub fn footer_in_context(ftype: &FileType, footer: &Match, data: &[u8], cluster_size: u64) -> bool {
+	ftype.footer_context.as_ref().is_none_or(|context| {
+		context.is_satisfied(data, footer.end_idx as usize + 1, ftype.cluster_size.unwrap_or(cluster_size).max(1) as usize)
+	})
+}
+
 fn in_range(header: &Match, footer: &Match, max_size: Option<u64>) -> bool {
 	assert!(footer.end_idx > header.start_idx);
 	if (footer.end_idx - header.start_idx) <= max_size.unwrap_or(u64::MAX) {
@@ -93,10 +149,21 @@ fn in_range(header: &Match, footer: &Match, max_size: Option<u64>) -> bool {
 ///
 /// Matches that were successfully paired or completed with max_len are removed from the input Vec.
 ///
+/// If `end_of_matches` is false, more matches (all starting at or after the last match in `matches`) may follow in a later call, with the
+/// matches that were not removed from `matches`, so headers are only completed or dropped once no match that follows could be paired with
+/// them, i.e. once the last match starts more than the max_len of their file type after them. Pairing all the matches over several calls
+/// like this gives the same pairs as pairing them in one call, as long as no file type has ordered footers
+///
 /// # Panics
 /// Panics if a file type has both no footers and no max length (which would be a config validation error),
 /// or if id_ftype_map is missing any match ids that are present in `matches`.
 pub fn pair<'a>(matches: &mut Vec<Match>, id_ftype_map: &HashMap<u64, (usize, &'a FileType, MatchPart)>, end_of_matches: bool) -> Vec<MatchPair<'a>> {
+	pair_reporting_dropped(matches, id_ftype_map, end_of_matches, &mut |_, _, _| ())
+}
+
+/// As `pair`, but calls `on_dropped` with each header that is removed from `matches` without being paired or completed, along with its file
+/// type and why it was dropped
+pub fn pair_reporting_dropped<'a>(matches: &mut Vec<Match>, id_ftype_map: &HashMap<u64, (usize, &'a FileType, MatchPart)>, end_of_matches: bool, on_dropped: &mut dyn FnMut(&Match, &'a FileType, RejectionReason)) -> Vec<MatchPair<'a>> {
 	// TODO: Maybe add a config that changes how this function works to allow the configurability of scalpel - Currently all we're missing is excluding the footer bytes and allowing duplicate footer/headers
 	//       e.g. if we have 2 identical ids, the id_ftype_list will only contain an entry for 1 of the headers/footers that have that id... This may be difficult to allow with current design, all we know
 	//       about a match is it's id, and if a match maps to multiple different headers/footers that's difficult to handle - though maybe not impossible... But would it make sense? Tbh, I could maybe change
@@ -111,9 +178,28 @@ pub fn pair<'a>(matches: &mut Vec<Match>, id_ftype_map: &HashMap<u64, (usize, &'
 	let mut match_tracker: HashMap<usize, Vec<usize>> = HashMap::new();
 	let mut matches_to_remove = Vec::new();
 
+	// The ids of delimiters, i.e. footers that are also headers of the same type, which are mapped to headers in id_ftype_map
+	let delimiter_ids: HashSet<u64> = id_ftype_map.values()
+		.flat_map(|&(ftype_idx, ftype, _)| ftype.footers.iter().map(move |footer| (ftype_idx, match_id_hash_slice_u16(footer))))
+		.filter(|(ftype_idx, id)| id_ftype_map.get(id).is_some_and(|&(i, _, part)| i == *ftype_idx && part == MatchPart::Header))
+		.map(|(_, id)| id)
+		.collect();
+
+	let footer_ranks = footer_ranks(id_ftype_map);
+
 	for match_idx in 0..matches.len() {
 		let (ftype_idx, ftype, match_part) = *id_ftype_map.get(&matches[match_idx].id).expect(&format!("Match id {} was not found in id_ftype_map", matches[match_idx].id));
 
+		// A delimiter ends the last unpaired header of its type if it is in range, and otherwise starts a new file
+		let match_part = if match_part == MatchPart::Header && delimiter_ids.contains(&matches[match_idx].id) && match_tracker.get(&ftype_idx)
+			.and_then(|match_stack| match_stack.last())
+			.is_some_and(|&mi| in_range(&matches[mi], &matches[match_idx], ftype.max_len))
+		{
+			MatchPart::Footer
+		} else {
+			match_part
+		};
+
 		if ftype.has_footer() && match_part == MatchPart::Header { // If the match file type has footers and this is a header...
 			// Push the index of the match to the match tracker at the file type index
 			if let Some(match_idxs) = match_tracker.get_mut(&ftype_idx) {
@@ -151,7 +237,16 @@ pub fn pair<'a>(matches: &mut Vec<Match>, id_ftype_map: &HashMap<u64, (usize, &'
 						}
 					}
 
-					if let Some((pair_stack_idx, pair_match_idx)) = pair_idxs {
+					// If a more preferred footer of this file type follows in range of the header, leave the header for that footer instead. Only
+					// matches that have been found so far can be considered, so this is only complete if end_of_matches is true
+					let rank = footer_rank(&footer_ranks, ftype_idx, matches[match_idx].id);
+					let preferred_follows = pair_idxs.is_some_and(|(_, pair_match_idx)| rank > 0 && matches[(match_idx + 1)..].iter()
+						.take_while(|m| in_range(&matches[pair_match_idx], m, ftype.max_len))
+						.any(|m| id_ftype_map.get(&m.id).is_some_and(|&(i, _, part)| i == ftype_idx && part == MatchPart::Footer) && footer_rank(&footer_ranks, ftype_idx, m.id) < rank));
+
+					if preferred_follows {
+						matches_to_remove.push(match_idx);
+					} else if let Some((pair_stack_idx, pair_match_idx)) = pair_idxs {
 						complete_matches.push(
 							MatchPair::new(
 								ftype,
@@ -176,8 +271,13 @@ pub fn pair<'a>(matches: &mut Vec<Match>, id_ftype_map: &HashMap<u64, (usize, &'
 					// If there is a previous footer, and that is within bounds of the max size for the file type and this footer is not, then that previous footer is the last one so
 					// complete the match with that one and disregard this footer
 					if let Some((header_idx, &header_match_idx)) = match_stack.iter().enumerate().rfind(|&(_, &e)| id_ftype_map.get(&matches[e].id).unwrap().2 == MatchPart::Header) {
-						if let Some(&mi) = match_stack.get(match_stack.len() - 1) {
-							if mi != header_match_idx && in_range(&matches[header_match_idx], &matches[mi], ftype.max_len) && !in_range(&matches[header_match_idx], &matches[match_idx], ftype.max_len) {
+						if let Some(&last_mi) = match_stack.get(match_stack.len() - 1) {
+							if last_mi != header_match_idx && in_range(&matches[header_match_idx], &matches[last_mi], ftype.max_len) && !in_range(&matches[header_match_idx], &matches[match_idx], ftype.max_len) {
+								// Of the footers after the header (which are all in range, as the last is), pair with the last of the most preferred
+								let (footer_idx, mi) = match_stack.iter().copied().enumerate().skip(header_idx + 1)
+									.min_by_key(|&(si, mi)| (footer_rank(&footer_ranks, ftype_idx, matches[mi].id), std::cmp::Reverse(si)))
+									.unwrap();
+
 								complete_matches.push(
 									MatchPair::new(
 										ftype,
@@ -186,7 +286,7 @@ pub fn pair<'a>(matches: &mut Vec<Match>, id_ftype_map: &HashMap<u64, (usize, &'
 									)
 								);
 								add_footer = false;
-								match_stack.remove(match_stack.len() - 1);
+								match_stack.remove(footer_idx);
 								match_stack.remove(header_idx);
 								matches_to_remove.push(mi);
 								matches_to_remove.push(header_match_idx);
@@ -207,6 +307,11 @@ pub fn pair<'a>(matches: &mut Vec<Match>, id_ftype_map: &HashMap<u64, (usize, &'
 		}
 	}
 
+	// Whether no match that follows those in `matches` could be paired with the header `header` of `ftype`
+	let out_of_reach = |header: &Match, ftype: &FileType| matches.last().is_some_and(|last| {
+		ftype.max_len.is_some_and(|max_len| last.start_idx.saturating_sub(header.start_idx) > max_len)
+	});
+
 	// Process any remaining matches in the match stacks
 	for (_, match_stack) in match_tracker.iter_mut() {
 		let mut i = 0;
@@ -214,9 +319,11 @@ pub fn pair<'a>(matches: &mut Vec<Match>, id_ftype_map: &HashMap<u64, (usize, &'
 			let mut increment = true;
 
 			let match_idx = match_stack[i];
-			let (_, ftype, match_part) = *id_ftype_map.get(&matches[match_idx].id).expect(&format!("Match id {} was not found in id_ftype_map", matches[match_idx].id));
+			let (ftype_idx, ftype, match_part) = *id_ftype_map.get(&matches[match_idx].id).expect(&format!("Match id {} was not found in id_ftype_map", matches[match_idx].id));
 
-			if ftype.pairing == PairingStrategy::PairNext {
+			if ftype.pairing == PairingStrategy::PairNext && !end_of_matches && !out_of_reach(&matches[match_idx], ftype) {
+				// A footer of a later call may yet be paired with this header
+			} else if ftype.pairing == PairingStrategy::PairNext {
 				assert_eq!(match_part, MatchPart::Header);
 				// If the current match part is a header, then if there is a currently-tracked header
 				// that doesn't require a footer, complete it with the file type's max size. If it
@@ -227,23 +334,41 @@ pub fn pair<'a>(matches: &mut Vec<Match>, id_ftype_map: &HashMap<u64, (usize, &'
 						&matches[match_idx],
 						ftype.max_len.expect(&format!("File type {} does not have either at least one footer or a max_len", ftype.extension.clone().unwrap_or("<no extension>".to_string())))
 					));
+				} else if ftype.truncate_at_max_len {
+					complete_matches.push(MatchPair::new_truncated(
+						ftype,
+						&matches[match_idx],
+						ftype.max_len.unwrap_or_else(|| panic!("File type {} is configured to truncate at max_len but does not have a max_len", ftype.extension.clone().unwrap_or("<no extension>".to_string())))
+					));
+				} else {
+					debug!("Rejection: candidate at {:#0x} (type id {}) dropped in pairing: {}", matches[match_idx].start_idx, ftype.type_id, RejectionReason::NoFooterInRange);
+					on_dropped(&matches[match_idx], ftype, RejectionReason::NoFooterInRange);
 				}
 				matches_to_remove.push(match_idx);
 			} else { // PairLast
 				if match_part == MatchPart::Header {
 					let mut pair_idx: Option<usize> = None;
+					let mut pair_rank = usize::MAX;
 					let mut left_range = false;
 					if (i + 1) < match_stack.len() {
 						for j in (i + 1)..match_stack.len() {
 							let (_, _, j_match_part) = *id_ftype_map.get(&matches[match_stack[j]].id).expect(&format!("Match id {} was not found in id_ftype_map", matches[match_stack[j]].id));
 							if j_match_part == MatchPart::Footer && in_range(&matches[match_idx], &matches[match_stack[j]], ftype.max_len) {
-								pair_idx = Some(j);
+								// Pair with the last of the most preferred footers in range
+								let rank = footer_rank(&footer_ranks, ftype_idx, matches[match_stack[j]].id);
+								if rank <= pair_rank {
+									pair_idx = Some(j);
+									pair_rank = rank;
+								}
 							} else if /*j_match_part == MatchPart::Footer && */!in_range(&matches[match_idx], &matches[match_stack[j]], ftype.max_len) {
 								left_range = true;
 							}
 						}
 					}
 
+					// A footer of a later call may yet be in range if the match that left the range is shorter than it
+					let left_range = left_range && (end_of_matches || out_of_reach(&matches[match_idx], ftype));
+
 					if left_range || end_of_matches {
 						if let Some(pair_idx) = pair_idx {
 							complete_matches.push(
@@ -269,7 +394,20 @@ pub fn pair<'a>(matches: &mut Vec<Match>, id_ftype_map: &HashMap<u64, (usize, &'
 								);
 							}
 							matches_to_remove.push(match_idx);
+						} else if ftype.requires_footer && ftype.truncate_at_max_len && (left_range || end_of_matches) {
+							complete_matches.push(
+								MatchPair::new_truncated(
+									ftype,
+									&matches[match_idx],
+									ftype.max_len.unwrap_or_else(|| panic!("File type {} is configured to truncate at max_len but does not have a max_len", ftype.extension.clone().unwrap_or("<no extension>".to_string())))
+								)
+							);
+							matches_to_remove.push(match_idx);
+							match_stack.remove(i);
+							increment = false;
 						} else if ftype.requires_footer && left_range {
+							debug!("Rejection: candidate at {:#0x} (type id {}) dropped in pairing: {}", matches[match_idx].start_idx, ftype.type_id, RejectionReason::NoFooterInRange);
+							on_dropped(&matches[match_idx], ftype, RejectionReason::NoFooterInRange);
 							matches_to_remove.push(match_idx);
 							match_stack.remove(i);
 							increment = false;
@@ -301,11 +439,41 @@ pub fn pair<'a>(matches: &mut Vec<Match>, id_ftype_map: &HashMap<u64, (usize, &'
 	complete_matches
 }
 
+/// Groups the indices into `match_pairs` of pairs that overlap other pairs of the same file type, which can't all be files, as files can't
+/// physically overlap. Each group is in order of start index, and pairs that don't overlap any others are omitted
+pub fn overlap_groups(match_pairs: &[MatchPair]) -> Vec<Vec<usize>> {
+	let mut order: Vec<usize> = (0..match_pairs.len()).collect();
+	order.sort_by_key(|&i| match_pairs[i].start_idx);
+
+	// Map from file type to the index of the last group of that type and the furthest end of any pair in it
+	let mut last_groups: HashMap<*const FileType, (usize, usize)> = HashMap::new();
+	let mut groups: Vec<Vec<usize>> = Vec::new();
+
+	for i in order {
+		let pair = &match_pairs[i];
+
+		match last_groups.get_mut(&(pair.file_type as *const FileType)) {
+			Some((group_idx, end)) if pair.start_idx <= *end => {
+				groups[*group_idx].push(i);
+				*end = (*end).max(pair.end_idx);
+			}
+			_ => {
+				last_groups.insert(pair.file_type, (groups.len(), pair.end_idx));
+				groups.push(vec![ i ]);
+			}
+		}
+	}
+
+	groups.retain(|group| group.len() > 1);
+
+	groups
+}
+
 #[cfg(test)]
 mod test {
-    use crate::{search::{match_id_hash_slice, pairing::MatchPair, Match}, searchlight::config::{FileType, PairingStrategy, SearchlightConfig}};
+    use crate::{search::{match_id_hash_slice, pairing::MatchPair, Match}, searchlight::config::{FileType, FooterContext, PairingStrategy, SearchlightConfig}};
 
-    use super::{pair, preprocess_config};
+    use super::{footer_in_context, overlap_groups, pair, preprocess_config, MatchPart};
 
 	#[test]
 	fn test_pairing() {
@@ -600,76 +768,91 @@ mod test {
 				file_type: &config.file_types[0],
 				start_idx: 0,
 				end_idx: 7,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[0],
 				start_idx: 10,
 				end_idx: 20,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[1],
 				start_idx: 13,
 				end_idx: 23,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[0],
 				start_idx: 27,
 				end_idx: 34,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[0],
 				start_idx: 30,
 				end_idx: 37,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[2],
 				start_idx: 45,
 				end_idx: 52,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[2],
 				start_idx: 57,
 				end_idx: 66,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[3],
 				start_idx: 60,
 				end_idx: 69,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[3],
 				start_idx: 70,
 				end_idx: 81,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[3],
 				start_idx: 73,
 				end_idx: 78,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[0],
 				start_idx: 115,
 				end_idx: 120,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[2],
 				start_idx: 125,
 				end_idx: 134,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[4],
 				start_idx: 140,
 				end_idx: 150,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[5],
 				start_idx: 148,
 				end_idx: 158,
+				truncated: false,
 			},
 			MatchPair {
 				file_type: &config.file_types[0],
 				start_idx: 157,
 				end_idx: 166,
+				truncated: false,
 			},
 		];
 
@@ -693,4 +876,374 @@ mod test {
 		assert_eq!(match_pairs, expected_pairs);
 		assert!(match_list.is_empty());
 	}
+
+	#[test]
+	fn test_pairing_truncate_at_max_len() {
+		let header_id = match_id_hash_slice("ft0_header".as_bytes());
+		let footer_id = match_id_hash_slice("ft0_footer".as_bytes());
+
+		for pairing in [ PairingStrategy::PairNext, PairingStrategy::PairLast ] {
+			let config = SearchlightConfig {
+				file_types: vec![
+					FileType {
+						headers: vec![ "ft0_header".into() ],
+						footers: vec![ "ft0_footer".into() ],
+						extension: Some("ft0".to_string()),
+						pairing,
+						max_len: Some(10),
+						requires_footer: true,
+						truncate_at_max_len: true,
+						..Default::default()
+					},
+				],
+				..Default::default()
+			};
+
+			config.validate().unwrap();
+
+			let id_ftype_map = preprocess_config(&config);
+
+			// The footer ends just past max_len from the start of the header, so the pair ends where a footer just in range would
+			let mut match_list = vec![
+				Match::new(header_id, 5, 6),
+				Match::new(footer_id, 15, 16),
+			];
+
+			let match_pairs = pair(&mut match_list, &id_ftype_map, true);
+
+			let expected_pairs = [
+				MatchPair {
+					file_type: &config.file_types[0],
+					start_idx: 5,
+					end_idx: 15,
+					truncated: true,
+				},
+			];
+
+			assert_eq!(match_pairs, expected_pairs);
+		}
+	}
+
+	#[test]
+	fn test_pairing_header_offset() {
+		let header_id = match_id_hash_slice("DICM".as_bytes());
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "DICM".into() ],
+					extension: Some("dcm".to_string()),
+					max_len: Some(1000),
+					header_offset: 128,
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		config.validate().unwrap();
+
+		let id_ftype_map = preprocess_config(&config);
+
+		// The first header is too close to the start of the image for the whole offset
+		let mut match_list = vec![
+			Match::new(header_id, 100, 103),
+			Match::new(header_id, 2000, 2003),
+		];
+
+		let match_pairs = pair(&mut match_list, &id_ftype_map, true);
+
+		let expected_pairs = [
+			MatchPair {
+				file_type: &config.file_types[0],
+				start_idx: 0,
+				end_idx: 1000,
+				truncated: false,
+			},
+			MatchPair {
+				file_type: &config.file_types[0],
+				start_idx: 1872,
+				end_idx: 2872,
+				truncated: false,
+			},
+		];
+
+		assert_eq!(match_pairs, expected_pairs);
+	}
+
+	#[test]
+	fn test_pairing_ordered_footers() {
+		let header_id = match_id_hash_slice("HEAD".as_bytes());
+		let preferred_footer_id = match_id_hash_slice("FOOT".as_bytes());
+		let other_footer_id = match_id_hash_slice("END".as_bytes());
+
+		for pairing in [ PairingStrategy::PairNext, PairingStrategy::PairLast ] {
+			let mut config = SearchlightConfig {
+				file_types: vec![
+					FileType {
+						headers: vec![ "HEAD".into() ],
+						footers: vec![ "FOOT".into(), "END".into() ],
+						max_len: Some(1000),
+						pairing,
+						requires_footer: true,
+						..Default::default()
+					},
+				],
+				..Default::default()
+			};
+
+			// Pair next would take the closest footer, and pair last the furthest, which is the less preferred one in both cases
+			let match_list = match config.file_types[0].pairing {
+				PairingStrategy::PairNext => vec![
+					Match::new(header_id, 0, 3),
+					Match::new(other_footer_id, 100, 102),
+					Match::new(preferred_footer_id, 200, 203),
+				],
+				PairingStrategy::PairLast => vec![
+					Match::new(header_id, 0, 3),
+					Match::new(preferred_footer_id, 100, 103),
+					Match::new(other_footer_id, 200, 202),
+				]
+			};
+
+			let unordered_end = match config.file_types[0].pairing {
+				PairingStrategy::PairNext => 102,
+				PairingStrategy::PairLast => 202
+			};
+			let ordered_end = match config.file_types[0].pairing {
+				PairingStrategy::PairNext => 203,
+				PairingStrategy::PairLast => 103
+			};
+
+			let id_ftype_map = preprocess_config(&config);
+			let match_pairs = pair(&mut match_list.clone(), &id_ftype_map, true);
+
+			assert_eq!(match_pairs.iter().map(|p| (p.start_idx, p.end_idx)).collect::<Vec<_>>(), vec![ (0, unordered_end) ]);
+
+			config.file_types[0].ordered_footers = true;
+
+			let id_ftype_map = preprocess_config(&config);
+			let match_pairs = pair(&mut match_list.clone(), &id_ftype_map, true);
+
+			assert_eq!(match_pairs.iter().map(|p| (p.start_idx, p.end_idx)).collect::<Vec<_>>(), vec![ (0, ordered_end) ]);
+		}
+	}
+
+	#[test]
+	fn test_pairing_footer_context() {
+		let header_id = match_id_hash_slice(&[ 0xff, 0xd8, 0xff ]);
+		let footer_id = match_id_hash_slice(&[ 0xff, 0xd9 ]);
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\xff\\xd8\\xff".into() ],
+					footers: vec![ "\\xff\\xd9".into() ],
+					extension: Some("jpg".to_string()),
+					max_len: Some(1000),
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		// A JPEG whose EOI is followed by padding, one whose EOI ends on a cluster boundary, and one whose EOI ends the data, each of which
+		// has an inline EOI (as found in scan data) before the real one
+		let mut data = vec![ 0x11; 112 ];
+		for (start, inline_eoi, eoi) in [ (0, 20, 40), (64, 70, 78), (96, 100, 110) ] {
+			data[start..(start + 3)].copy_from_slice(&[ 0xff, 0xd8, 0xff ]);
+			data[inline_eoi..(inline_eoi + 2)].copy_from_slice(&[ 0xff, 0xd9 ]);
+			data[eoi..(eoi + 2)].copy_from_slice(&[ 0xff, 0xd9 ]);
+		}
+		data[42..64].fill(0x00);
+
+		let match_list = vec![
+			Match::new(header_id, 0, 2),
+			Match::new(footer_id, 20, 21),
+			Match::new(footer_id, 40, 41),
+			Match::new(header_id, 64, 66),
+			Match::new(footer_id, 70, 71),
+			Match::new(footer_id, 78, 79),
+			Match::new(header_id, 96, 98),
+			Match::new(footer_id, 100, 101),
+			Match::new(footer_id, 110, 111),
+		];
+
+		let pairs_in_context = |config: &SearchlightConfig| {
+			let id_ftype_map = preprocess_config(config);
+
+			let mut match_list: Vec<Match> = match_list.iter().filter(|m| match id_ftype_map.get(&m.id) {
+				Some((_, ftype, MatchPart::Footer)) => footer_in_context(ftype, m, &data, 16),
+				_ => true
+			}).cloned().collect();
+
+			pair(&mut match_list, &id_ftype_map, true).iter().map(|p| (p.start_idx, p.end_idx)).collect::<Vec<_>>()
+		};
+
+		// Without a context, each header is paired with the inline EOI
+		assert_eq!(pairs_in_context(&config), vec![ (0, 21), (64, 71), (96, 101) ]);
+
+		config.file_types[0].footer_context = Some(FooterContext {
+			followed_by: vec![ "\\x00\\x00".into() ],
+			cluster_boundary: true
+		});
+		config.validate().unwrap();
+
+		assert_eq!(pairs_in_context(&config), vec![ (0, 41), (64, 79), (96, 111) ]);
+
+		// Every position is a cluster boundary in unaligned data, so boundaries are not accepted
+		let context = config.file_types[0].footer_context.as_ref().unwrap();
+		assert!(context.is_satisfied(&data, 80, 16));
+		assert!(!context.is_satisfied(&data, 80, 1));
+
+		// A context that would accept every footer is a config error
+		config.file_types[0].footer_context = Some(FooterContext {
+			followed_by: vec![ "".into() ],
+			cluster_boundary: false
+		});
+		assert!(config.validate().is_err());
+	}
+
+	#[test]
+	fn test_pairing_delimiters() {
+		let delimiter_id = match_id_hash_slice("~~~".as_bytes());
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "~~~".into() ],
+					footers: vec![ "~~~".into() ],
+					extension: Some("rec".to_string()),
+					max_len: Some(100),
+					requires_footer: true,
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		config.validate().unwrap();
+
+		let id_ftype_map = preprocess_config(&config);
+
+		// Consecutive delimiters open and close files, except that a delimiter out of range of the open file starts a new one instead
+		let mut match_list = vec![
+			Match::new(delimiter_id, 0, 2),
+			Match::new(delimiter_id, 10, 12),
+			Match::new(delimiter_id, 20, 22),
+			Match::new(delimiter_id, 30, 32),
+			Match::new(delimiter_id, 200, 202),
+			Match::new(delimiter_id, 400, 402),
+			Match::new(delimiter_id, 450, 452),
+		];
+
+		let match_pairs = pair(&mut match_list, &id_ftype_map, true);
+
+		assert_eq!(match_pairs.iter().map(|p| (p.start_idx, p.end_idx)).collect::<Vec<_>>(), vec![ (0, 12), (20, 32), (400, 452) ]);
+
+		// Which footer pairs with a header can't be told apart from which opens a new file when pairing the last footer
+		config.file_types[0].pairing = PairingStrategy::PairLast;
+		assert!(config.validate().is_err());
+	}
+
+	#[test]
+	fn test_overlap_groups() {
+		let file_type_a = FileType::default();
+		let file_type_b = FileType::default();
+
+		let match_pairs = vec![
+			MatchPair { file_type: &file_type_a, start_idx: 100, end_idx: 199, truncated: false },
+			MatchPair { file_type: &file_type_a, start_idx: 0, end_idx: 149, truncated: false },
+			// Overlaps the pairs of type a, but is a different type
+			MatchPair { file_type: &file_type_b, start_idx: 120, end_idx: 129, truncated: false },
+			MatchPair { file_type: &file_type_a, start_idx: 199, end_idx: 299, truncated: false },
+			MatchPair { file_type: &file_type_a, start_idx: 300, end_idx: 399, truncated: false },
+			MatchPair { file_type: &file_type_b, start_idx: 400, end_idx: 499, truncated: false },
+			MatchPair { file_type: &file_type_b, start_idx: 450, end_idx: 459, truncated: false },
+		];
+
+		assert_eq!(overlap_groups(&match_pairs), vec![ vec![ 1, 0, 3 ], vec![ 5, 6 ] ]);
+	}
+
+	#[test]
+	fn test_pairing_incremental() {
+		let pattern_ids: Vec<u64> = [ "h0", "f0", "h1", "f1", "h2", "f2", "d3" ].iter().map(|p| match_id_hash_slice(p.as_bytes())).collect();
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "h0".into() ],
+					footers: vec![ "f0".into() ],
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(50),
+					requires_footer: true,
+					truncate_at_max_len: true,
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "h1".into() ],
+					footers: vec![ "f1".into() ],
+					pairing: PairingStrategy::PairLast,
+					max_len: Some(40),
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "h2".into() ],
+					footers: vec![ "f2".into() ],
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(30),
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "d3".into() ],
+					footers: vec![ "d3".into() ],
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(20),
+					requires_footer: true,
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		config.validate().unwrap();
+
+		let id_ftype_map = preprocess_config(&config);
+
+		// Pseudo-random matches of all of the patterns, a few bytes apart
+		let mut state = 12345u64;
+		let mut next = |n: u64| {
+			state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+			(state >> 33) % n
+		};
+
+		let mut matches = Vec::new();
+		let mut start = 0;
+		for _ in 0..400 {
+			start += 1 + next(12);
+			matches.push(Match::new(pattern_ids[next(pattern_ids.len() as u64) as usize], start, start + 1));
+		}
+
+		fn sorted(mut match_pairs: Vec<MatchPair>) -> Vec<MatchPair> {
+			match_pairs.sort_by_key(|p| (p.start_idx, p.end_idx));
+			match_pairs
+		}
+
+		let expected = sorted(pair(&mut matches.clone(), &id_ftype_map, true));
+
+		// Pairing the matches as they are found, a block at a time, gives the same pairs as pairing them all at once
+		for block_size in [ 1, 7, 64, 333 ] {
+			let mut unpaired = Vec::new();
+			let mut match_pairs = Vec::new();
+
+			for block in matches.chunks(block_size) {
+				unpaired.extend_from_slice(block);
+				match_pairs.append(&mut pair(&mut unpaired, &id_ftype_map, false));
+			}
+
+			match_pairs.append(&mut pair(&mut unpaired, &id_ftype_map, true));
+
+			assert_eq!(sorted(match_pairs), expected, "block size {block_size}");
+		}
+	}
 }
\ No newline at end of file
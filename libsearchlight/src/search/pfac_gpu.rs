@@ -38,32 +38,94 @@ mod pfac_shaders {
 	}
 }
 
-use std::{io::Write, mem::size_of, ops::DerefMut, sync::Arc, time::Duration};
+use std::{io::Write, mem, ops::DerefMut, sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Duration};
 
 use log::info;
-use vulkano::{instance::{Instance, InstanceCreateInfo}, device::{DeviceExtensions, QueueFlags, physical::{PhysicalDevice, PhysicalDeviceType}, Features, Device, DeviceCreateInfo, QueueCreateInfo, Queue}, VulkanLibrary, memory::{allocator::{StandardMemoryAllocator, MemoryAllocator, AllocationCreateInfo, MemoryTypeFilter, MemoryAllocatePreference, DeviceLayout}, DeviceAlignment}, buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer}, NonZeroDeviceSize, pipeline::{PipelineShaderStageCreateInfo, PipelineLayout, layout::{PipelineDescriptorSetLayoutCreateInfo, PushConstantRange, PipelineLayoutCreateFlags}, ComputePipeline, compute::ComputePipelineCreateInfo, Pipeline, PipelineBindPoint}, descriptor_set::{allocator::{StandardDescriptorSetAllocator, StandardDescriptorSetAllocatorCreateInfo}, PersistentDescriptorSet, WriteDescriptorSet, layout::{DescriptorSetLayoutCreateInfo, DescriptorSetLayoutBinding, DescriptorType}}, image::{Image, ImageCreateInfo, ImageType, ImageUsage, view::ImageView}, format::Format, command_buffer::{allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo}, AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo, CopyBufferInfo}, sync::{self, GpuFuture}, shader::ShaderStage};
+use vulkano::{Validated, instance::{Instance, InstanceCreateInfo}, device::{DeviceExtensions, QueueFlags, physical::{PhysicalDevice, PhysicalDeviceType}, Features, Device, DeviceCreateInfo, QueueCreateInfo, Queue}, VulkanLibrary, memory::{allocator::{StandardMemoryAllocator, MemoryAllocator, AllocationCreateInfo, MemoryTypeFilter, MemoryAllocatePreference, DeviceLayout}, DeviceAlignment}, buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer}, NonZeroDeviceSize, pipeline::{PipelineShaderStageCreateInfo, PipelineLayout, layout::{PipelineDescriptorSetLayoutCreateInfo, PushConstantRange, PipelineLayoutCreateFlags}, ComputePipeline, compute::ComputePipelineCreateInfo, Pipeline, PipelineBindPoint}, descriptor_set::{allocator::{StandardDescriptorSetAllocator, StandardDescriptorSetAllocatorCreateInfo}, PersistentDescriptorSet, WriteDescriptorSet, layout::{DescriptorSetLayoutCreateInfo, DescriptorSetLayoutBinding, DescriptorType}}, image::{Image, ImageCreateInfo, ImageType, ImageUsage, view::ImageView}, format::Format, command_buffer::{allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo}, AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo, CopyBufferInfo}, sync::{self, future::FenceSignalFuture, GpuFuture}, shader::ShaderStage};
 
 use crate::{error::{Error, VulkanError}, utils::iter::ToChunksExact};
 
-use super::{search_common::AcTable, SearchFuture, Match, Searcher};
+use super::{search_common::AcTable, SearchFuture, Match, Searcher, SearcherKind, DEFAULT_GPU_SEARCH_TIMEOUT};
 
 pub const INPUT_BUFFER_SIZE: u64 = 1024 * 1024;
 pub const OUTPUT_BUFFER_SIZE: u64 = 1024 * 1024;
 
+/// The size of a match in the output buffer, in bytes
+const MATCH_OUTPUT_SIZE: usize = 4 * 6;
+/// The number of invocations in a workgroup, which must match the local_size_x of the shader
+const WORKGROUP_SIZE: u64 = 64;
+/// The size of a match in progress in the state buffers, in bytes, which must match STATE_SIZE in the shader
+const STATE_SIZE: u64 = 4 * 5;
+
 pub struct PfacGpu {
 	vkdev: Arc<Device>,
 	vkqueue_comp: Arc<Queue>,
 	vkcmd_buf_alloc: StandardCommandBufferAllocator,
 	vkpipeline: Arc<ComputePipeline>,
-	vkdescriptor_set: Arc<PersistentDescriptorSet>,
+	/// The descriptor sets for searching from the state in each of the state buffers, writing the state at the end of the search to the
+	/// other one
+	vkdescriptor_sets: [Arc<PersistentDescriptorSet>; 2],
 	input_buffer_host: Arc<Buffer>,
 	input_buffer_device: Arc<Buffer>,
 	output_buffer_host: Arc<Buffer>,
-	output_buffer_device: Arc<Buffer>
+	output_buffer_device: Arc<Buffer>,
+	/// The matches in progress at the end of a search are written to one of these, and continued from it by the next search
+	state_buffers: [Arc<Buffer>; 2],
+	/// The offset that the last search started from, and the state buffer it continued from, if any. A search of the same data again,
+	/// e.g. after its output overflowed, continues from the same state
+	in_state: Option<(u64, usize)>,
+	/// The offset that the last search ended at, and the state buffer it wrote its matches in progress to. A search of the data that
+	/// follows continues from them
+	out_state: Option<(u64, usize)>,
+	max_pat_len: u32,
+	/// The number of matches that the output buffer can hold
+	output_capacity: usize,
+	/// How long to wait for each search to complete before it results in an `Error::SearchTimeout`
+	timeout: Duration,
+	/// Whether a search has timed out, after which the GPU may still be running it (or be hung), so no more searches are submitted
+	timed_out: Arc<AtomicBool>
+}
+
+/// The fence of a submitted search. Dropping a `FenceSignalFuture` waits for its fence without a timeout, which blocks forever if the GPU
+/// never completes the search, so instead the future is leaked (along with the device and buffers that it keeps alive) if a search has
+/// timed out, or if this one doesn't complete within the timeout when dropped
+struct PendingSearch<F: GpuFuture> {
+	fence_fut: Option<FenceSignalFuture<F>>,
+	timeout: Duration,
+	timed_out: Arc<AtomicBool>
+}
+
+impl<F: GpuFuture> PendingSearch<F> {
+	/// Waits for the search to complete, marking the searcher as timed out if it doesn't within the timeout
+	fn wait(&self) -> Result<(), Error> {
+		self.fence_fut.as_ref().unwrap().wait(Some(self.timeout)).map_err(|e| match e {
+			Validated::Error(vulkano::VulkanError::Timeout) => {
+				self.timed_out.store(true, Ordering::Relaxed);
+				Error::SearchTimeout(self.timeout)
+			}
+			e => Error::from(e)
+		})
+	}
+}
+
+impl<F: GpuFuture> Drop for PendingSearch<F> {
+	fn drop(&mut self) {
+		if let Some(fence_fut) = self.fence_fut.take() {
+			if self.timed_out.load(Ordering::Relaxed) || fence_fut.wait(Some(self.timeout)).is_err() {
+				mem::forget(fence_fut);
+			}
+		}
+	}
 }
 
 impl PfacGpu {
 	pub fn new(table: AcTable) -> Result<Self, Error> {
+		Self::with_output_buffer_size(table, OUTPUT_BUFFER_SIZE)
+	}
+
+	/// Create a PfacGpu with an output buffer of `output_buffer_size` bytes, which determines the maximum number of matches that can be
+	/// returned from each search. If a search finds more matches than that, the search results in an `Error::SearchOutputOverflow`
+	pub fn with_output_buffer_size(table: AcTable, output_buffer_size: u64) -> Result<Self, Error> {
 		let req_device_extensions = DeviceExtensions::default();
 		let req_features = Features {
 			uniform_and_storage_buffer8_bit_access: true,
@@ -179,7 +241,7 @@ impl PfacGpu {
 				..Default::default()
 			},
 			DeviceLayout::new(
-				NonZeroDeviceSize::new(OUTPUT_BUFFER_SIZE).unwrap(),
+				NonZeroDeviceSize::new(output_buffer_size).unwrap(),
 				DeviceAlignment::new(8).unwrap()
 			).unwrap()
 		).map_err(Error::from)?;
@@ -196,11 +258,31 @@ impl PfacGpu {
 				..Default::default()
 			},
 			DeviceLayout::new(
-				NonZeroDeviceSize::new(OUTPUT_BUFFER_SIZE).unwrap(),
+				NonZeroDeviceSize::new(output_buffer_size).unwrap(),
 				DeviceAlignment::new(8).unwrap()
 			).unwrap()
 		).map_err(Error::from)?;
 
+		// There can be at most one match in progress per index of the last max_pat_len bytes of the searched data
+		let state_buffer_size = 4 + STATE_SIZE * (table.max_pat_len as u64).max(1);
+		let state_buffer = || Buffer::new(
+			Arc::clone(&vkmalloc) as Arc<dyn MemoryAllocator>,
+			BufferCreateInfo {
+				usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_DST,
+				..Default::default()
+			},
+			AllocationCreateInfo {
+				memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+				allocate_preference: MemoryAllocatePreference::AlwaysAllocate,
+				..Default::default()
+			},
+			DeviceLayout::new(
+				NonZeroDeviceSize::new(state_buffer_size).unwrap(),
+				DeviceAlignment::new(8).unwrap()
+			).unwrap()
+		).map_err(Error::from);
+		let state_buffers = [ state_buffer()?, state_buffer()? ];
+
 		let output_subbuffer_host = Subbuffer::new(Arc::clone(&output_buffer_host));
 		{
 			let mut output_subbuffer_host_wlock = output_subbuffer_host.write().unwrap();
@@ -209,7 +291,10 @@ impl PfacGpu {
 
 		let pfac_shader = pfac_shaders::ac::load(Arc::clone(&vkdev)).map_err(Error::from)?
 			.specialize(
-				[(0, table.max_pat_len.into())].into_iter().collect()
+				[
+					(0, table.max_pat_len.into()),
+					(1, ((output_buffer_size / 4) as u32).into())
+				].into_iter().collect()
 			)
 			.map_err(Error::from)?;
 		let pfac_entry_point = pfac_shader.entry_point("main").unwrap();
@@ -238,6 +323,16 @@ impl PfacGpu {
 									descriptor_count: 1,
 									..DescriptorSetLayoutBinding::descriptor_type(DescriptorType::StorageBuffer)
 								}),
+								(3, DescriptorSetLayoutBinding {
+									stages:	ShaderStage::Compute.into(),
+									descriptor_count: 1,
+									..DescriptorSetLayoutBinding::descriptor_type(DescriptorType::StorageBuffer)
+								}),
+								(4, DescriptorSetLayoutBinding {
+									stages:	ShaderStage::Compute.into(),
+									descriptor_count: 1,
+									..DescriptorSetLayoutBinding::descriptor_type(DescriptorType::StorageBuffer)
+								}),
 							].into(),
 							..Default::default()
 						}
@@ -263,23 +358,27 @@ impl PfacGpu {
 			).map_err(Error::from)?
 		};
 
-		let descriptor_set = {
+		let descriptor_sets = {
 			let desc_set_alloc = StandardDescriptorSetAllocator::new(
 				Arc::clone(&vkdev),
 				StandardDescriptorSetAllocatorCreateInfo::default()
 			);
 			let desc_set_layout = Arc::clone(&pfac_pipeline.layout().set_layouts()[0]);
-			PersistentDescriptorSet::new(
+			let descriptor_set = |in_state_idx: usize| PersistentDescriptorSet::new(
 				&desc_set_alloc,
-				desc_set_layout,
+				Arc::clone(&desc_set_layout),
 				[
 					// Descriptors
 					WriteDescriptorSet::buffer(0, Subbuffer::new(Arc::clone(&input_buffer_device))),
-					WriteDescriptorSet::image_view(1, table_imageview_device),
-					WriteDescriptorSet::buffer(2, Subbuffer::new(Arc::clone(&output_buffer_device)))
+					WriteDescriptorSet::image_view(1, Arc::clone(&table_imageview_device)),
+					WriteDescriptorSet::buffer(2, Subbuffer::new(Arc::clone(&output_buffer_device))),
+					WriteDescriptorSet::buffer(3, Subbuffer::new(Arc::clone(&state_buffers[in_state_idx]))),
+					WriteDescriptorSet::buffer(4, Subbuffer::new(Arc::clone(&state_buffers[1 - in_state_idx])))
 				],
 				[]
-			).map_err(Error::from)?
+			).map_err(Error::from);
+
+			[ descriptor_set(0)?, descriptor_set(1)? ]
 		};
 
 		let cmd_buf_alloc = StandardCommandBufferAllocator::new(Arc::clone(&vkdev), StandardCommandBufferAllocatorCreateInfo::default());
@@ -309,14 +408,27 @@ impl PfacGpu {
 			vkqueue_comp,
 			vkcmd_buf_alloc: cmd_buf_alloc,
 			vkpipeline: pfac_pipeline,
-			vkdescriptor_set: descriptor_set,
+			vkdescriptor_sets: descriptor_sets,
 			input_buffer_host,
 			input_buffer_device,
 			output_buffer_host,
 			output_buffer_device,
+			state_buffers,
+			in_state: None,
+			out_state: None,
+			max_pat_len: table.max_pat_len,
+			// The output buffer holds the number of matches (a u32) followed by the matches, each being 6 u32s
+			output_capacity: (output_buffer_size as usize).saturating_sub(4) / MATCH_OUTPUT_SIZE,
+			timeout: DEFAULT_GPU_SEARCH_TIMEOUT,
+			timed_out: Arc::new(AtomicBool::new(false))
 		})
 	}
 
+	/// Sets how long to wait for each search to complete before it results in an `Error::SearchTimeout`, instead of `DEFAULT_GPU_SEARCH_TIMEOUT`
+	pub fn set_timeout(&mut self, timeout: Duration) {
+		self.timeout = timeout;
+	}
+
 	// Attempts to find the best Vulkan implementation and queue family indices for compute and transfer operations, returned in that order
 	fn select_device(instance: &Arc<Instance>, device_extensions: &DeviceExtensions) -> Option<(Arc<PhysicalDevice>, u32)> {
 		instance.enumerate_physical_devices().expect("Cannot enumerate physical devices")
@@ -341,7 +453,33 @@ impl PfacGpu {
 }
 
 impl Searcher for PfacGpu {
-	fn search(&mut self, data: &[u8], data_offset: u64, _overlap: usize) -> Result<SearchFuture, Error> {
+	/// Once a search has timed out, the device can't be relied on, so every search after it results in an `Error::SearchTimeout` without
+	/// being submitted
+	fn search(&mut self, data: &[u8], data_offset: u64, overlap: usize) -> Result<SearchFuture, Error> {
+		if self.timed_out.load(Ordering::Relaxed) {
+			return Err(Error::SearchTimeout(self.timeout));
+		}
+
+		// Matches in progress at the end of the last search are continued if this data follows it, in which case the overlap has already
+		// been searched. Otherwise, the search starts afresh
+		let resume_offset = data_offset + overlap as u64;
+		let in_state_idx = match (self.out_state, self.in_state) {
+			(Some((end, idx)), _) if end == resume_offset => Some(idx),
+			(_, Some((start, idx))) if start == resume_offset => Some(idx),
+			_ => None
+		};
+
+		let (data, data_offset) = if in_state_idx.is_some() {
+			(&data[overlap..], resume_offset)
+		} else {
+			(data, data_offset)
+		};
+
+		// The state buffer that isn't being continued from is written to. When starting afresh, the in state buffer is unused
+		let out_state_idx = in_state_idx.map(|idx| 1 - idx).unwrap_or(0);
+		self.in_state = in_state_idx.map(|idx| (data_offset, idx));
+		self.out_state = Some((data_offset + data.len() as u64, out_state_idx));
+
 		let input_subbuffer_host = Subbuffer::new(Arc::clone(&self.input_buffer_host));
 		let input_bytes_written = {
 			let mut input_subbuffer_host_wlock = input_subbuffer_host.write().unwrap();
@@ -355,9 +493,15 @@ impl Searcher for PfacGpu {
 
 		let shader_pc = pfac_shaders::ac::ExtraInfo {
 			offset: data_offset,
-			input_len: input_bytes_written as u32 // This should never overflow since we're using the number of bytes *written* which we have control over
+			input_len: input_bytes_written as u32, // This should never overflow since we're using the number of bytes *written* which we have control over
+			resume: in_state_idx.is_some() as u32
 		};
 
+		// The shader runs an invocation for each match in progress that can be continued (of which there are at most max_pat_len), and one
+		// for each index of the input, so only enough workgroups to cover those need to be dispatched, rather than enough to cover the whole
+		// input buffer
+		let num_workgroups = (self.max_pat_len as u64 + input_bytes_written as u64).div_ceil(WORKGROUP_SIZE);
+
 		let dispatch_cmd_buf = {
 			let mut builder = AutoCommandBufferBuilder::primary(&self.vkcmd_buf_alloc, self.vkqueue_comp.queue_family_index(), CommandBufferUsage::OneTimeSubmit).map_err(Error::from)?;
 
@@ -366,13 +510,15 @@ impl Searcher for PfacGpu {
 				.map_err(Error::from)?
 				.fill_buffer(Subbuffer::new(Arc::clone(&self.output_buffer_device)).reinterpret::<[u32]>(), 0)
 				.map_err(Error::from)?
+				.fill_buffer(Subbuffer::new(Arc::clone(&self.state_buffers[out_state_idx])).reinterpret::<[u32]>(), 0)
+				.map_err(Error::from)?
 				.bind_pipeline_compute(Arc::clone(&self.vkpipeline))
 				.map_err(Error::from)?
 				.bind_descriptor_sets(
 					PipelineBindPoint::Compute,
 					Arc::clone(&self.vkpipeline.layout()),
 					0,
-					Arc::clone(&self.vkdescriptor_set)
+					Arc::clone(&self.vkdescriptor_sets[in_state_idx.unwrap_or(1)])
 				)
 				.map_err(Error::from)?
 				.push_constants(
@@ -381,7 +527,7 @@ impl Searcher for PfacGpu {
 					shader_pc
 				)
 				.map_err(Error::from)?
-				.dispatch([(INPUT_BUFFER_SIZE / 64) as u32, 1, 1])
+				.dispatch([num_workgroups as u32, 1, 1])
 				.map_err(Error::from)?
 				.copy_buffer(CopyBufferInfo::buffers(Subbuffer::new(Arc::clone(&self.output_buffer_device)), Subbuffer::new(Arc::clone(&self.output_buffer_host))))
 				.map_err(Error::from)?;
@@ -395,19 +541,30 @@ impl Searcher for PfacGpu {
 			.then_signal_fence_and_flush()
 			.map_err(Error::from)?;
 
+		let pending = PendingSearch {
+			fence_fut: Some(fence_fut),
+			timeout: self.timeout,
+			timed_out: Arc::clone(&self.timed_out)
+		};
+
 		let output_buffer_host = Arc::clone(&self.output_buffer_host);
+		let output_capacity = self.output_capacity;
 
 		Ok(SearchFuture::new(move || {
-			fence_fut
-				.wait(Some(Duration::from_secs(30)))
-				.map_err(Error::from)?;
+			pending.wait()?;
 
 			let output_subbuffer_host = Subbuffer::new(output_buffer_host);
 			//let value = &output_subbuffer_host.read().unwrap()[0..((data.len() + 4) * 2)];
 			let output_subbuffer_host_rlock = output_subbuffer_host.read().unwrap();
-			let results_len = u32::from_ne_bytes(output_subbuffer_host_rlock[0..4].try_into().unwrap()).clamp(0, OUTPUT_BUFFER_SIZE as u32 / size_of::<Match>() as u32);
+			let results_len = u32::from_ne_bytes(output_subbuffer_host_rlock[0..4].try_into().unwrap()) as usize;
 			// println!("Results len: {}", results_len);
-			let results: Vec<Match> = output_subbuffer_host_rlock[4..((results_len as usize * 4 * 6) + 4)]
+
+			// The shader counts every match it finds, including those that it had no room to write
+			if results_len > output_capacity {
+				return Err(Error::SearchOutputOverflow { found: results_len, capacity: output_capacity });
+			}
+
+			let mut results: Vec<Match> = output_subbuffer_host_rlock[4..((results_len * MATCH_OUTPUT_SIZE) + 4)]
 				.chunks_exact(4)
 				.map(|chunk| u32::from_ne_bytes(chunk.try_into().unwrap()))
 				.to_chunks_exact(6)
@@ -418,18 +575,39 @@ impl Searcher for PfacGpu {
 				))
 				.collect();
 
+			// When starting afresh, the overlap is searched again, and the matches that lie entirely within it were reported by the previous
+			// search, so are dropped here rather than reaching pairing twice
+			results.retain(|m| m.end_idx >= resume_offset);
+
 			Ok(results)
 		}))
 	}
 
+	fn active_backend(&self) -> SearcherKind {
+		SearcherKind::Gpu
+	}
+
 	fn max_search_size(&self) -> Option<usize> {
 		Some(INPUT_BUFFER_SIZE as usize)
 	}
+
+	/// Matches straddling the boundary between consecutive searches are continued from the state at the end of the previous search, so
+	/// no overlap is required
+	fn required_overlap(&self, _max_pat_len: usize) -> usize {
+		0
+	}
+
+	fn discard_progress(&mut self) {
+		self.in_state = None;
+		self.out_state = None;
+	}
 }
 
 #[cfg(test)]
 mod test {
-	use crate::{search::{match_id_hash_slice_u16, pfac_gpu::PfacGpu, search_common::AcTableBuilder, Match, Searcher}, searchlight::config::MatchString};
+	use std::time::Duration;
+
+	use crate::{error::Error, search::{ac_cpu::AcCpu, match_id_hash_slice_u16, pfac_gpu::{PfacGpu, INPUT_BUFFER_SIZE}, search_common::AcTableBuilder, Match, Searcher}, searchlight::config::MatchString};
 
 	#[test]
 	fn test_pfac_gpu_single() {
@@ -543,4 +721,142 @@ mod test {
 
 		assert_eq!(matches, expected);
 	}
+
+	#[test]
+	fn test_pfac_gpu_output_overflow() {
+		let buffer: Vec<u8> = [ 1, 2, 3 ].repeat(10);
+
+		let pattern = &[ 1u16, 2, 3 ];
+
+		// Room for the match count and 2 matches
+		let pfac_table = AcTableBuilder::new(true).with_pattern(pattern).build();
+		let mut ac = PfacGpu::with_output_buffer_size(pfac_table, 4 + 24 * 2).unwrap();
+
+		let result = ac.search(&buffer, 0, 0).unwrap().wait();
+
+		assert!(matches!(result, Err(Error::SearchOutputOverflow { found: 10, capacity: 2 })));
+
+		// Searching less data should fit
+		let matches = ac.search(&buffer[..6], 0, 0).unwrap().wait().unwrap();
+
+		assert_eq!(matches.len(), 2);
+	}
+
+	#[test]
+	fn test_pfac_gpu_carried_state() {
+		let buffer: Vec<u8> = [ 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 0, 1, 2, 3 ].repeat(8);
+
+		// A pattern longer than the blocks that are searched, which can only be matched by continuing from the state of previous searches
+		let pattern: Vec<u16> = (1..=12).collect();
+		let table = AcTableBuilder::new(true).with_pattern(&pattern).with_pattern(&[ 0, 1, 2 ]).build();
+		let max_pat_len = table.max_pat_len as usize;
+
+		let mut ac = PfacGpu::new(table).unwrap();
+		assert_eq!(ac.required_overlap(max_pat_len), 0);
+
+		// Searched with overlap, as before state was kept between searches
+		let mut expected = ac.search(&buffer[..40], 0, 0).unwrap().wait().unwrap();
+		ac.discard_progress();
+		expected.append(&mut ac.search(&buffer[(40 - max_pat_len)..], (40 - max_pat_len) as u64, max_pat_len).unwrap().wait().unwrap());
+		expected.sort_by_key(|m| m.start_idx);
+
+		// Searched in blocks of 5 bytes without overlap
+		ac.discard_progress();
+		let mut matches = Vec::new();
+		for (i, block) in buffer.chunks(5).enumerate() {
+			matches.append(&mut ac.search(block, i as u64 * 5, 0).unwrap().wait().unwrap());
+		}
+		matches.sort_by_key(|m| m.start_idx);
+
+		assert_eq!(matches.len(), 16);
+		assert_eq!(matches, expected);
+
+		// Searching the same block again (e.g. after the output overflowed) continues from the same state, and doesn't lose the state
+		// for the block after it
+		ac.discard_progress();
+		let mut matches = ac.search(&buffer[..10], 0, 0).unwrap().wait().unwrap();
+		matches.append(&mut ac.search(&buffer[10..20], 10, 0).unwrap().wait().unwrap());
+		let retried = ac.search(&buffer[10..20], 10, 0).unwrap().wait().unwrap();
+		matches.append(&mut ac.search(&buffer[20..32], 20, 0).unwrap().wait().unwrap());
+		matches.sort_by_key(|m| m.start_idx);
+
+		assert_eq!(retried, vec![ Match { id: match_id_hash_slice_u16(&pattern), start_idx: 0, end_idx: 11 }, Match { id: match_id_hash_slice_u16(&[ 0, 1, 2 ]), start_idx: 12, end_idx: 14 } ]);
+		assert_eq!(matches, expected[..4]);
+	}
+
+	#[test]
+	fn test_pfac_gpu_block_boundaries() {
+		// Matches within the overlap, ending exactly on the boundary at 16, starting exactly on it, and straddling it
+		let mut buffer = vec![ 0u8; 32 ];
+		for idx in [ 9, 13, 29 ] {
+			buffer[idx..(idx + 3)].copy_from_slice(&[ 1, 2, 3 ]);
+		}
+		buffer[16..18].copy_from_slice(&[ 4, 5 ]);
+
+		let table = AcTableBuilder::new(true).with_pattern(&[ 1, 2, 3 ]).with_pattern(&[ 4, 5 ]).with_pattern(&[ 3, 4 ]).build();
+
+		let mut cpu = AcCpu::new(table.clone());
+		let expected = cpu.search(&buffer, 0, 0).unwrap().wait().unwrap();
+
+		let mut gpu = PfacGpu::new(table).unwrap();
+
+		// Continuing from the state of the previous search, and starting afresh, in which case the overlap is searched again
+		for discard in [ false, true ] {
+			gpu.discard_progress();
+
+			let mut matches = gpu.search(&buffer[..16], 0, 0).unwrap().wait().unwrap();
+			if discard {
+				gpu.discard_progress();
+			}
+			matches.append(&mut gpu.search(&buffer[8..], 8, 8).unwrap().wait().unwrap());
+			matches.sort_by_key(|m| (m.start_idx, m.id));
+
+			let mut expected = expected.clone();
+			expected.sort_by_key(|m| (m.start_idx, m.id));
+
+			assert_eq!(matches, expected, "discarding progress: {discard}");
+		}
+	}
+
+	#[test]
+	fn test_pfac_gpu_short_blocks() {
+		let buffer: Vec<u8> = (0..=255).cycle().take(300).collect();
+
+		let table = AcTableBuilder::new(true).with_pattern(&[ 61, 62, 63 ]).with_pattern(&[ 126, 127, 128 ]).with_pattern(&[ 42, 43 ]).build();
+
+		let mut cpu = AcCpu::new(table.clone());
+		let mut gpu = PfacGpu::new(table).unwrap();
+
+		// Only enough workgroups for the data are dispatched, so matches ending at the last byte of blocks around the workgroup size (64) or a
+		// multiple of it must still be found
+		for len in [ 1, 43, 63, 64, 65, 128, 129, 300 ] {
+			cpu.discard_progress();
+			gpu.discard_progress();
+
+			let expected = cpu.search(&buffer[..len], 0, 0).unwrap().wait().unwrap();
+
+			assert_eq!(gpu.search(&buffer[..len], 0, 0).unwrap().wait().unwrap(), expected, "block of {len} bytes");
+		}
+	}
+
+	#[test]
+	fn test_pfac_gpu_timeout() {
+		let data = vec![ 1u8; INPUT_BUFFER_SIZE as usize ];
+
+		let table = AcTableBuilder::new(true).with_pattern(&[ 1, 2, 3 ]).build();
+		let mut ac = PfacGpu::new(table).unwrap();
+
+		// A search of a full input buffer is all but certain not to have completed by the time it's waited on with no timeout
+		ac.set_timeout(Duration::ZERO);
+
+		let first = ac.search(&data, 0, 0).unwrap();
+		let second = ac.search(&data, data.len() as u64, 0).unwrap();
+
+		assert!(matches!(first.wait(), Err(Error::SearchTimeout(_))));
+
+		// Neither dropping a search that was in flight at the time nor dropping the searcher blocks, and no more searches are submitted
+		drop(second);
+		assert!(matches!(ac.search(&data, 0, 0).map(|_| ()), Err(Error::SearchTimeout(_))));
+		drop(ac);
+	}
 }
\ No newline at end of file
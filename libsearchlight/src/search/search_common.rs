@@ -1,13 +1,19 @@
-use std::{hash::{Hash, Hasher}, collections::{HashMap, hash_map::DefaultHasher}};
+use std::{hash::{Hash, Hasher}, collections::{HashMap, hash_map::DefaultHasher}, fs, path::{Path, PathBuf}};
 
-use log::debug;
+use log::{debug, info, warn};
 
-use crate::searchlight::config::SearchlightConfig;
+use crate::{search::{match_id_hash_add, match_id_hash_init}, searchlight::config::SearchlightConfig};
 
 use self::ir::{NodeIR, ConnectionIR};
 
 pub const MATCH_ALL_VALUE: u16 = 0x8000;
 
+/// Identifies data encoded with `AcTable::encode`
+const AC_TABLE_ENCODING_MAGIC: &[u8; 4] = b"SLAC";
+/// The version of the format of data encoded with `AcTable::encode`. Must be incremented whenever the format, or the way that tables are
+/// built, changes, so that cached tables from older versions are rejected
+const AC_TABLE_ENCODING_VERSION: u32 = 1;
+
 mod ir {
 	#[derive(Debug, PartialEq)]
 	pub struct NodeIR {
@@ -31,18 +37,26 @@ pub struct AcTableBuilder {
 	max_pat_len: u32
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AcTableElem {
 	pub next_state: u32,
 	pub value: u16
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AcTable {
 	pub table: Vec<Vec<AcTableElem>>,
 	pub max_pat_len: u32
 }
 
+/// A dense representation of an `AcTable`, as given by `AcTable::encode_indexable`, in which transitions are looked up in constant time
+/// rather than by searching the elements of a row
+#[derive(Debug, Clone)]
+pub struct IndexableAcTable {
+	table: Vec<u32>,
+	columns: usize
+}
+
 impl AcTableBuilder {
 	pub fn new(do_suffix_opt: bool) -> Self {
 		let start = NodeIR { next_paths: Vec::new() };
@@ -73,6 +87,39 @@ impl AcTableBuilder {
 		builder
 	}
 
+	/// Builds the table for the headers and footers in `config`, as `from_config(config).build()` does, but if `config.ac_table_cache_dir` is
+	/// set, first looks for a table previously built from the same headers and footers in that directory, and otherwise writes the built table
+	/// there. Cached tables that can't be decoded, e.g. as they are from a different version, are rebuilt
+	pub fn build_from_config(config: &SearchlightConfig) -> AcTable {
+		let Some(cache_dir) = &config.ac_table_cache_dir else {
+			return AcTableBuilder::from_config(config).build();
+		};
+
+		let cache_path = ac_table_cache_path(cache_dir, config);
+
+		match fs::read(&cache_path) {
+			Ok(data) => {
+				if let Some(table) = AcTable::decode(&data) {
+					info!("Loaded cached AC table from {}", cache_path.display());
+					return table;
+				}
+
+				debug!("Cached AC table at {} is stale or corrupt, rebuilding", cache_path.display());
+			}
+			Err(e) => {
+				debug!("No cached AC table could be read at {} ({}), building", cache_path.display(), e);
+			}
+		}
+
+		let table = AcTableBuilder::from_config(config).build();
+
+		if let Err(e) = fs::create_dir_all(cache_dir).and_then(|_| fs::write(&cache_path, table.encode())) {
+			warn!("Failed to write AC table cache to {}: {}", cache_path.display(), e);
+		}
+
+		table
+	}
+
 	pub fn with_pattern(mut self, pattern: &[u16]) -> Self {
 		self.add_pattern(pattern);
 
@@ -165,6 +212,130 @@ impl AcTable {
 
 		accum
 	}
+
+	/// Returns the dense representation of this table, for faster lookups
+	pub fn to_indexable(&self) -> IndexableAcTable {
+		IndexableAcTable {
+			table: self.encode_indexable(),
+			columns: self.indexable_columns()
+		}
+	}
+
+	/// Encodes the table into a compact binary form that can be decoded with `decode`, for caching built tables. The encoded form starts
+	/// with a magic number and version, followed by the max pattern length and the rows of the table, all little-endian
+	pub fn encode(&self) -> Vec<u8> {
+		let mut data = Vec::new();
+
+		data.extend_from_slice(AC_TABLE_ENCODING_MAGIC);
+		data.extend_from_slice(&AC_TABLE_ENCODING_VERSION.to_le_bytes());
+		data.extend_from_slice(&self.max_pat_len.to_le_bytes());
+		data.extend_from_slice(&(self.table.len() as u32).to_le_bytes());
+
+		for row in &self.table {
+			data.extend_from_slice(&(row.len() as u32).to_le_bytes());
+
+			for elem in row {
+				data.extend_from_slice(&elem.next_state.to_le_bytes());
+				data.extend_from_slice(&elem.value.to_le_bytes());
+			}
+		}
+
+		data
+	}
+
+	/// Decodes a table encoded with `encode`, returning None if the data is not an encoded table, is truncated, or was encoded by a
+	/// different version
+	pub fn decode(data: &[u8]) -> Option<AcTable> {
+		let mut i = 0;
+
+		let mut take = |len: usize| -> Option<&[u8]> {
+			let bytes = data.get(i..(i + len))?;
+			i += len;
+			Some(bytes)
+		};
+
+		if take(4)? != AC_TABLE_ENCODING_MAGIC || u32::from_le_bytes(take(4)?.try_into().unwrap()) != AC_TABLE_ENCODING_VERSION {
+			return None;
+		}
+
+		let max_pat_len = u32::from_le_bytes(take(4)?.try_into().unwrap());
+		let num_rows = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+
+		let mut table = Vec::new();
+
+		for _ in 0..num_rows {
+			let row_len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+
+			let mut row = Vec::new();
+			for _ in 0..row_len {
+				let next_state = u32::from_le_bytes(take(4)?.try_into().unwrap());
+				let value = u16::from_le_bytes(take(2)?.try_into().unwrap());
+
+				if next_state as usize >= num_rows {
+					return None;
+				}
+
+				row.push(AcTableElem { next_state, value });
+			}
+
+			table.push(row);
+		}
+
+		if take(1).is_some() {
+			return None;
+		}
+
+		Some(AcTable { table, max_pat_len })
+	}
+}
+
+/// Returns the path of the cached table for the headers and footers of `config` in `cache_dir`, named by the hash of the headers and footers
+fn ac_table_cache_path(cache_dir: &str, config: &SearchlightConfig) -> PathBuf {
+	Path::new(cache_dir).join(format!("ac_table_{:016x}.bin", patterns_hash(config)))
+}
+
+/// Returns the FNV-1a hash of the headers and footers of `config`, which identifies the set of patterns that are searched for
+pub fn patterns_hash(config: &SearchlightConfig) -> u64 {
+	let mut hash = match_id_hash_init();
+
+	for ft in &config.file_types {
+		for pattern in ft.headers.iter().chain(ft.footers.iter()) {
+			// Include the length of each pattern so that different splits of the same values hash differently
+			for b in (pattern.len() as u64).to_le_bytes().into_iter().chain(pattern.iter().flat_map(|v| v.to_le_bytes())) {
+				hash = match_id_hash_add(hash, b);
+			}
+		}
+	}
+
+	hash
+}
+
+impl IndexableAcTable {
+	/// Looks up the transition from `curr_state` on `value`, returning the next state and the value of the transition, which is either `value`
+	/// or `MATCH_ALL_VALUE`. Unlike `AcTable::lookup`, a transition on `value` is always preferred over a match all transition from the same state
+	#[inline]
+	pub fn lookup(&self, curr_state: u32, value: u8) -> Option<(u32, u16)> {
+		let row = curr_state as usize * self.columns;
+
+		if self.is_final(curr_state) {
+			return None;
+		}
+
+		// The start state can't be transitioned to, so 0 indicates that there is no transition
+		match self.table[row + value as usize] {
+			0 => match self.table[row + self.columns - 1] {
+				0 => None,
+				next_state => Some((next_state, MATCH_ALL_VALUE))
+			},
+			next_state => Some((next_state, value as u16))
+		}
+	}
+
+	/// Whether `state` has no transitions, i.e. reaching it completes a match
+	#[inline]
+	pub fn is_final(&self, state: u32) -> bool {
+		self.table[state as usize * self.columns] == u32::MAX
+	}
 }
 
 fn hash_suffix(suffix: &[u16]) -> u64 {
@@ -177,7 +348,11 @@ fn hash_suffix(suffix: &[u16]) -> u64 {
 mod test {
     use crate::search::search_common::ir::{NodeIR, ConnectionIR};
 
-    use super::AcTableBuilder;
+    use std::fs;
+
+    use crate::searchlight::config::{FileType, SearchlightConfig};
+
+    use super::{AcTable, AcTableBuilder};
 
 	// TODO: Make this test actually, well, test something. Need expected values basically
 	// #[test]
@@ -296,4 +471,71 @@ mod test {
 
 		assert_eq!(pb.pat_ir, expected_ir)
 	}
+
+	#[test]
+	fn test_ac_table_encoding() {
+		let table = AcTableBuilder::new(true)
+			.with_pattern(&[ 0x50, 0x4b, 0x03, 0x04 ])
+			.with_pattern(&[ 0xff, 0xd8, 0x8000, 0xe0 ])
+			.build();
+
+		let encoded = table.encode();
+
+		assert_eq!(AcTable::decode(&encoded), Some(table));
+
+		// Truncated data, trailing data, and data from a different version are rejected
+		assert_eq!(AcTable::decode(&encoded[..(encoded.len() - 1)]), None);
+		assert_eq!(AcTable::decode(&[ encoded.as_slice(), &[ 0 ] ].concat()), None);
+
+		let mut other_version = encoded.clone();
+		other_version[4] = other_version[4].wrapping_add(1);
+		assert_eq!(AcTable::decode(&other_version), None);
+	}
+
+	#[test]
+	fn test_indexable_lookup() {
+		let table = AcTableBuilder::new(true)
+			.with_pattern(&[ 0x50, 0x4b, 0x03, 0x04 ])
+			.with_pattern(&[ 0xff, 0xd8, 0x8000, 0xe0 ])
+			.build();
+
+		let indexable = table.to_indexable();
+
+		// The dense lookup should give the same transitions as the linear one, where there is no ambiguity between a value and a match all
+		for state in 0..(table.num_rows() as u32) {
+			for value in 0..=255u8 {
+				assert_eq!(indexable.lookup(state, value), table.lookup(state, value).map(|elem| (elem.next_state, elem.value)));
+			}
+
+			assert_eq!(indexable.is_final(state), table.table[state as usize].is_empty());
+		}
+	}
+
+	#[test]
+	fn test_build_from_config_cached() {
+		let cache_dir = std::env::temp_dir().join(format!("searchlight_test_ac_table_cache_{}", std::process::id()));
+		let _ = fs::remove_dir_all(&cache_dir);
+
+		let config = SearchlightConfig {
+			ac_table_cache_dir: Some(cache_dir.to_str().unwrap().to_string()),
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x50\\x4b\\x03\\x04".into() ],
+					footers: vec![ "\\x50\\x4b\\x05\\x06".into() ],
+					..Default::default()
+				}
+			],
+			..Default::default()
+		};
+
+		let expected = AcTableBuilder::from_config(&config).build();
+
+		assert_eq!(AcTableBuilder::build_from_config(&config), expected);
+		assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 1);
+
+		// The second build should load the cached table
+		assert_eq!(AcTableBuilder::build_from_config(&config), expected);
+
+		fs::remove_dir_all(&cache_dir).unwrap();
+	}
 }
\ No newline at end of file
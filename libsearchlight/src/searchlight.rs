@@ -1,20 +1,57 @@
 pub mod config;
-mod carve_log;
+pub mod carve_log;
+pub mod checkpoint;
+pub mod carve_map;
 
-use std::{collections::VecDeque, fs::{self, File}, io::{IoSlice, Write}, path::{Path, PathBuf}};
+use std::{cell::{Cell, RefCell}, collections::{HashMap, HashSet, VecDeque}, fs::{self, File}, io::{self, Read, Write}, ops::{Range, RangeInclusive}, panic::{self, AssertUnwindSafe}, path::{Path, PathBuf}, slice, sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, RecvTimeoutError}, Arc}, thread, time::{Duration, Instant}};
 
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
 
-use log::{debug, info, log_enabled, trace, Level};
-use memmap::MmapOptions;
+use log::{debug, error, info, log_enabled, trace, warn, Level};
+use memmap::{Mmap, MmapOptions};
+use rayon::ThreadPool;
 
-use crate::{error::Error, search::{pairing::{self, pair, MatchPart}, search_common::AcTableBuilder, DelegatingSearcher, SearchFuture, Searcher}, searchlight::carve_log::CarveLog, utils::{estimate_cluster_size, file_len, iter::ToGappedWindows}, validation::{DelegatingValidator, FileValidationType, FileValidator}};
+use crate::{classifiers, error::Error, search::{match_id_hash_slice_u16, pairing::{self, pair, MatchPair, MatchPart}, search_common::AcTableBuilder, DelegatingSearcher, Match, SearchFuture, Searcher, SearcherKind}, searchlight::carve_log::{CarveLog, CarveLogMember, FilenameOffsetUnit, FragmentChecksum, Fragmentation, FragmentationSummary, RejectEntry, RejectsLog}, utils::{boot_sector::boot_sector_cluster_size, estimate_cluster_size, file_len, thread_pool, filename_template::{sanitise_filename, sanitise_path, FilenameFields}, iter::ToGappedWindows, lznt1, multi_reader::MultiReader, partitions::{parse_partition_table, unpartitioned_space}, progress::ProgressReporter, sparse}, validation::{self, cache::ValidationCache, DelegatingValidator, EmbeddedEncoding, FileValidationInfo, FileValidationType, FileValidator, Fragment, RejectionReason, METADATA_EMBEDDED_NAME, METADATA_OFFSET_NAME, METADATA_ORIGINAL_NAME}};
 
-use self::config::SearchlightConfig;
+use self::{carve_map::{CarveMap, DEFAULT_CARVE_MAP_RESOLUTION}, checkpoint::SearchCheckpoint, config::{EndHeuristic, FileType, FileTypeId, IoStrategy, MatchString, OffsetUnit, OutputLayout, OverlapPolicy, ProgressMode, SearchlightConfig}};
 
 /// Default size of the blocks to load and search disk image data in
 pub const DEFAULT_BLOCK_SIZE: usize = 1024 * 1024;
+/// The granularity at which uniform data (e.g. zeroed unallocated space) is detected and skipped when searching
+const SPARSE_BLOCK_SIZE: usize = 4096;
+/// The smallest block that the entropy end heuristic classifies, as the entropy of smaller blocks (e.g. of unaligned data, with a cluster size
+/// of 1) says little about their content
+const MIN_ENTROPY_BLOCK_SIZE: usize = 512;
+/// The largest cluster size that can be supplied for an image. Filesystems don't use clusters anywhere near this large
+pub const MAX_CLUSTER_SIZE: u64 = 64 * 1024 * 1024;
+/// The default of `SearchlightConfig::appended_data_max_len`
+const DEFAULT_APPENDED_DATA_MAX_LEN: u64 = 16 * 1024 * 1024;
+/// The default of `SearchlightConfig::pipelined_validation_lookahead`
+const DEFAULT_PIPELINED_VALIDATION_LOOKAHEAD: u64 = 64 * 1024 * 1024;
+/// The most candidates that can be waiting to be validated with pipelined validation before the search waits for them
+const PIPELINED_VALIDATION_QUEUE_LEN: usize = 1024;
+
+/// Called as a search progresses with the matches found since it was last called, and the offset before which all matches have been found,
+/// see `search_windowed`
+type OnSearched<'a> = &'a mut dyn FnMut(&[Match], u64) -> Result<(), Error>;
+
+/// Options for what is processed of an image file and what is output, see `Searchlight::process_image_file`
+#[derive(Debug, Clone, Default)]
+pub struct ImageOptions {
+	/// If specified, only this range of byte offsets into the image will be searched and carved from. Offsets in the output
+	/// and carve log remain relative to the start of the image
+	pub region: Option<Range<u64>>,
+	/// Whether to look for a MBR/GPT partition table at the start of the image and, if one is found, search and carve each partition
+	/// separately, estimating the cluster size per partition. The space outside of the partitions is searched and carved separately too
+	pub scan_partitions: bool,
+	/// Whether to also write a mactime bodyfile of the carved files that have a known modification time, see `CarveLog::write_bodyfile`
+	pub write_bodyfile: bool,
+	/// Whether to carve into an existing output directory rather than erroring if it exists. Carved files are renamed if their name
+	/// collides with an existing file, and the carve log is merged with that of the same image or written alongside any others,
+	/// see `CarveLog::write_appending`
+	pub append: bool
+}
 
 pub enum CarveOperationInfo {
 	Image {
@@ -22,55 +59,168 @@ pub enum CarveOperationInfo {
 		config: SearchlightConfig,
 		cluster_size: Option<u64>, // TODO: Handle a cluster size of 1 (unaligned) better in the validators
 		skip_carving: bool,
+		options: ImageOptions,
 	},
 	FromLog {
 		path: String,
+	},
+	/// Search data read from stdin as a stream, e.g. when piped from `dd`. As the data can't be revisited once read, files can't be
+	/// validated or carved in this mode - only a carve log of the potential files is output, see `Searchlight::process_stream`
+	Stdin {
+		config: SearchlightConfig,
 	}
 }
 
+/// The image path used in the carve logs of data read from stdin
+pub const STDIN_IMAGE_PATH: &str = "<stdin>";
+
 impl CarveOperationInfo {
 	pub fn path(&self) -> &str {
 		match &self {
 			CarveOperationInfo::Image { path, .. } => path,
 			CarveOperationInfo::FromLog { path } => path,
+			CarveOperationInfo::Stdin { .. } => STDIN_IMAGE_PATH,
+		}
+	}
+}
+
+/// A potential file that has been validated as being (at least partially) recognised, as passed to the consumer given to
+/// `Searchlight::carve_image_file`
+pub struct CarvedFile<'a> {
+	/// The header-footer pair (or header and max length) that the file was found from
+	pub file_match: &'a MatchPair<'a>,
+	/// The result of validating the file. The fragments are never empty - If the validator did not return any, the whole of `file_match`
+	/// is used as a single fragment
+	pub validation: FileValidationInfo,
+	/// The cluster size that the file was validated with, i.e. that of its file type if configured, or otherwise that of the image
+	pub cluster_size: usize,
+	/// The data appended after the end of the file, if `appended_data` is configured, the file is correct, and it has any
+	pub appended_data: Option<Fragment>,
+	fragment_data: Vec<&'a [u8]>
+}
+
+impl<'a> CarvedFile<'a> {
+	fn new(file_match: &'a MatchPair<'a>, validation: FileValidationInfo, file_data: &'a [u8], cluster_size: usize) -> Self {
+		let fragment_data = validation.fragments.iter().map(|frag| &file_data[frag.start..frag.end]).collect();
+
+		CarvedFile {
+			file_match,
+			validation,
+			cluster_size,
+			appended_data: None,
+			fragment_data
 		}
 	}
+
+	/// Returns the data of each fragment of the file, in order
+	pub fn fragment_data(&self) -> &[&'a [u8]] {
+		&self.fragment_data
+	}
+
+	/// Returns a reader that reads the content of the file, i.e. the data of each fragment in order
+	pub fn reader(&self) -> MultiReader<'_> {
+		MultiReader::new(&self.fragment_data)
+	}
+}
+
+/// A summary of processing an image, as returned by `Searchlight::process_image_file`
+#[derive(Debug, Default)]
+pub struct CarveSummary {
+	/// The number of files that were recognised, which were carved unless carving was skipped
+	pub num_carved_files: usize,
+	/// The configured headers and footers that weren't matched anywhere in the searched data, which may be wrong or redundant
+	pub unmatched_signatures: Vec<UnmatchedSignature>,
+	/// The mean and maximum fragmentation of the recognised files, if `fragmentation_stats` was configured and any were recognised
+	pub fragmentation: Option<FragmentationSummary>,
+	/// The time spent in each phase of processing the image, if `timings` was configured
+	pub timings: Option<PhaseTimings>
+}
+
+/// The time spent in each phase of processing an image, summed over its regions, for finding the bottleneck on a given image
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct PhaseTimings {
+	pub search: Duration,
+	/// The number of bytes that were searched
+	pub searched_bytes: u64,
+	/// The time spent estimating the cluster size, which is zero if the cluster size was given
+	pub cluster_estimation: Duration,
+	pub pairing: Duration,
+	pub validation: Duration,
+	/// The time spent consuming the recognised files, e.g. writing them to the output directory
+	pub carving: Duration
+}
+
+impl PhaseTimings {
+	/// The throughput of the search phase, in bytes per second, or 0 if nothing was searched
+	pub fn search_bytes_per_sec(&self) -> f64 {
+		let secs = self.search.as_secs_f64();
+
+		if secs > 0.0 { self.searched_bytes as f64 / secs } else { 0.0 }
+	}
+}
+
+/// A configured header or footer that wasn't matched anywhere in the searched data
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnmatchedSignature {
+	/// The index of the file type of the signature into the configured file types
+	pub file_type_idx: usize,
+	pub part: MatchPart,
+	pub signature: MatchString
 }
 
 /// The main mediator of the library, this struct manages state and performs carving operations in a configurable manner
 pub struct Searchlight {
 	queue: VecDeque<CarveOperationInfo>,
-	validator: Box<dyn FileValidator>, // TODO: Can I actually just use generics instead of trait objects? Won't need the traits to be object safe then, so maybe can remove the fuckery for searcher_factory and declare a constructor directly in the trait?
-	searcher_factory: Box<dyn Fn(&SearchlightConfig) -> (Box<dyn Searcher>, usize)> // TODO: Probably change this to just directly take the strings for the headers/footers? Or an iterator over them?
+	validator: Arc<dyn FileValidator>, // TODO: Can I actually just use generics instead of trait objects? Won't need the traits to be object safe then, so maybe can remove the fuckery for searcher_factory and declare a constructor directly in the trait?
+	searcher_factory: Box<dyn Fn(&SearchlightConfig, &Arc<ThreadPool>) -> Result<(Box<dyn Searcher>, usize), Error>>, // TODO: Probably change this to just directly take the strings for the headers/footers? Or an iterator over them?
+	/// The backend of the searcher most recently created by `searcher_factory`
+	active_backend: Cell<Option<SearcherKind>>,
+	/// The pool of `threads` worker threads that searching on the CPU and validating run on, created when first needed
+	thread_pool: RefCell<Option<Arc<ThreadPool>>>
 }
 
 impl Default for Searchlight {
 	fn default() -> Self {
 		Searchlight {
 			queue: VecDeque::new(),
-			validator: Box::new(DelegatingValidator::new()),
-			searcher_factory: Box::new(|config: &SearchlightConfig| {
-				let ac_table = AcTableBuilder::from_config(&config).build();
+			validator: Arc::new(DelegatingValidator::new()),
+			searcher_factory: Box::new(|config: &SearchlightConfig, pool: &Arc<ThreadPool>| {
+				let ac_table = AcTableBuilder::build_from_config(config);
+				let max_pat_len = ac_table.max_pat_len as usize;
 
-				(
-					Box::new(DelegatingSearcher::new(ac_table.clone(), false)) as Box<dyn Searcher>,
-					ac_table.max_pat_len as usize
-				)
-			}) as Box<dyn Fn(&SearchlightConfig) -> (Box<dyn Searcher>, usize)>
+				let searcher = if config.require_gpu {
+					DelegatingSearcher::new_gpu_required(ac_table, config.gpu_search_options().timeout)?
+				} else {
+					DelegatingSearcher::with_gpu_options(ac_table, false, Arc::clone(pool), config.gpu_search_options())
+				};
+
+				Ok((Box::new(searcher) as Box<dyn Searcher>, max_pat_len))
+			}) as Box<dyn Fn(&SearchlightConfig, &Arc<ThreadPool>) -> Result<(Box<dyn Searcher>, usize), Error>>,
+			active_backend: Cell::new(None),
+			thread_pool: RefCell::new(None)
 		}
 	}
 }
 
 impl Searchlight  {
-	/// Create a new Searchlight instance with a custom FileValidator impl and a function to generate Searcher impls (along with the required overlap)
-	pub fn new(validator: Box<dyn FileValidator>, searcher_factory: impl Fn(&SearchlightConfig) -> (Box<dyn Searcher>, usize) + 'static) -> Self {
+	/// Create a new Searchlight instance with a custom FileValidator impl and a function to generate Searcher impls (along with the required overlap),
+	/// or an error if a suitable Searcher can't be created. The function is given the pool of worker threads that the Searcher may search on
+	pub fn new(validator: Box<dyn FileValidator>, searcher_factory: impl Fn(&SearchlightConfig, &Arc<ThreadPool>) -> Result<(Box<dyn Searcher>, usize), Error> + 'static) -> Self {
 		Searchlight {
 			queue: VecDeque::new(),
-			validator,
-			searcher_factory: Box::new(searcher_factory)
+			validator: Arc::from(validator),
+			searcher_factory: Box::new(searcher_factory),
+			active_backend: Cell::new(None),
+			thread_pool: RefCell::new(None)
 		}
 	}
 
+	/// The backend (GPU or CPU) of the searcher used by the most recent search, or None if nothing has been searched yet. As the GPU is
+	/// used when available, this shows whether searching fell back to the CPU
+	pub fn active_backend(&self) -> Option<SearcherKind> {
+		self.active_backend.get()
+	}
+
 	/// Add an operation to the queue of operations to be processed
 	pub fn with_operation(mut self, info: CarveOperationInfo) -> Self {
 		self.add_operation(info);
@@ -97,12 +247,15 @@ impl Searchlight  {
 	pub fn process_file(&mut self, output_dir: impl AsRef<str>) -> (Option<CarveOperationInfo>, Result<bool, Error>) {
 		if let Some(info) = self.queue.pop_front() {
 			let result = match info {
-				CarveOperationInfo::Image { ref path, ref config, cluster_size, skip_carving } => {
-					self.process_image_file(output_dir, &path, &config, cluster_size, skip_carving).map(|_| true)
+				CarveOperationInfo::Image { ref path, ref config, cluster_size, skip_carving, ref options } => {
+					self.process_image_file(output_dir, &path, &config, cluster_size, skip_carving, options).map(|_| true)
 				}
 				CarveOperationInfo::FromLog { ref path } => {
 					self.process_log_file(output_dir, &path).map(|_| true)
 				}
+				CarveOperationInfo::Stdin { ref config } => {
+					self.process_stream(output_dir, &mut io::stdin().lock(), config).map(|_| true)
+				}
 			};
 
 			(
@@ -114,242 +267,4083 @@ impl Searchlight  {
 		}
 	}
 
-	pub fn process_image_file(&mut self, output_dir: impl AsRef<str>, path: &str, config: &SearchlightConfig, cluster_size: Option<u64>, skip_carving: bool) -> Result<(), Error> {
-		let (mmap, file_len) = {
-			let mut file = File::open(&path)?;
+	pub fn process_image_file(&mut self, output_dir: impl AsRef<str>, path: &str, config: &SearchlightConfig, cluster_size: Option<u64>, skip_carving: bool, options: &ImageOptions) -> Result<CarveSummary, Error> {
+		Self::check_cluster_size(cluster_size)?;
 
-			let file_len = file_len(&mut file)?;
+		let config = &*config.with_filtered_file_types()?;
 
-			info!("Opened image file {} (size: {} bytes)", &path, file_len);
+		if config.io_strategy == IoStrategy::Stream {
+			info!("Using the stream I/O strategy, as configured");
+			return self.process_image_stream(output_dir, path, config, options);
+		}
 
-			(
-				unsafe { MmapOptions::new().map(&file)? },
-				file_len
-			)
-		};
+		let ImageOptions { ref region, scan_partitions, write_bodyfile, append } = *options;
 
-		assert_eq!(file_len, mmap.len() as u64);
+		let (mmap, regions) = Self::open_image(path, region.clone(), scan_partitions)?;
 
-		let (mut searcher, max_pat_len) = {
-			(self.searcher_factory)(&config)
-		};
+		// Create output directory, erroring if it exists already unless appending to it
+		if append {
+			fs::create_dir_all(output_dir.as_ref())?;
+		} else {
+			fs::create_dir(output_dir.as_ref())?;
+		}
 
-		let block_size = searcher.max_search_size().unwrap_or(DEFAULT_BLOCK_SIZE);
+		let mut num_carved_files = 0;
 
-		assert!(max_pat_len < block_size);
+		let mut log = CarveLog::new(path);
 
-		let num_blocks = {
-			let num_blocks = (file_len as usize - max_pat_len) / (block_size - max_pat_len);
-			if file_len % block_size as u64 != 0 {
-				num_blocks + 1
-			} else {
-				num_blocks
+		// Rejected candidates are only recorded if a rejects log is to be written
+		let mut rejects = config.rejects_log.then(|| RejectsLog::new(path));
+
+		let filename_template = config.filename_template();
+		let mut file_index = 0;
+
+		// The paths (relative to the output directory) of the files carved, which files named by their original names mustn't clash with
+		let mut carved_paths = UsedPaths::default();
+
+		// Writing the carved files to the output directory and logging them is just one way of consuming the carved files
+		let mut write_file = |mut file: CarvedFile| -> Result<(), Error> {
+			let fragments = &file.validation.fragments;
+
+			// Get the minimum index and maximum index of all fragments and designate them the start and end idxs
+			let start_idx = fragments.iter().min_by_key(|frag| frag.start).unwrap().start; // .map_or(pot_file.start_idx, |frag| frag.start);
+			let end_idx = fragments.iter().max_by_key(|frag| frag.end).unwrap().end; // .map_or(pot_file.end_idx + 1, |frag| frag.end);
+
+			// The content only needs to be hashed if the filename includes the hash, which it can only with the hashing feature
+			#[cfg(feature = "hashing")]
+			let hash = filename_template.uses_hash().then(|| {
+				let mut hasher = crc32fast::Hasher::new();
+				for frag in file.fragment_data() {
+					hasher.update(frag);
+				}
+				hasher.finalize()
+			});
+			#[cfg(not(feature = "hashing"))]
+			let hash = None;
+
+			// Offsets in units other than bytes cover the file, so the start is rounded down and the end up
+			let unit_size = config.filename_offset_unit.size(file.cluster_size);
+
+			let ext = file.file_match.file_type.extension.as_deref().unwrap_or("dat");
+
+			let mut filename = filename_template.render(&FilenameFields {
+				start: start_idx / unit_size,
+				end: end_idx.div_ceil(unit_size),
+				ext,
+				type_id: file.file_match.file_type.type_id,
+				hash,
+				index: file_index
+			});
+
+			file_index += 1;
+
+			// The directory of the file relative to the output directory, e.g. validation_type or extension/validation_type
+			let file_dir = config.output_layout.directory(ext, file.validation.validation_type);
+			let validation_dir = Path::new(output_dir.as_ref()).join(&file_dir);
+			let dir_path = file_dir.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "/");
+
+			// Files whose original name was recovered are named by it if original_names is configured, unless it clashes with another file
+			// (including one from a previous carve, when appending), in which case they keep the name from the template. The template name
+			// is recorded in the metadata either way
+			let original_name = file.validation.metadata.get(METADATA_ORIGINAL_NAME).filter(|_| config.original_names).and_then(|name| sanitise_path(name));
+
+			if let Some(original_name) = original_name {
+				if !carved_paths.is_taken(&format!("{dir_path}/{original_name}")) && !validation_dir.join(&original_name).exists() {
+					file.validation.metadata.insert(METADATA_OFFSET_NAME.to_string(), filename);
+					filename = original_name;
+				}
 			}
-		};
 
-		info!("Starting search phase, searching {} bytes in {} blocks of (at most) {} bytes each", file_len, num_blocks, block_size);
+			// When appending, a file from a previous carve may already have this name, as may another file of this carve if the offsets aren't
+			// in bytes, in which case _<n> is inserted before the extension, for the first n that isn't taken. The files of this carve are
+			// checked for in carved_paths, as they aren't written if skipping carving
+			let is_taken = |filename: &str| carved_paths.is_taken(&format!("{dir_path}/{filename}")) || validation_dir.join(filename).exists();
 
-		let mut matches = Vec::new();
-		let mut result_fut: Option<SearchFuture> = None;
+			if (append || config.filename_offset_unit != OffsetUnit::Bytes) && is_taken(&filename) {
+				let (stem, extension) = filename.rsplit_once('.').map_or((filename.as_str(), None), |(stem, extension)| (stem, Some(extension)));
 
-		// PERF: Perhaps use a by-block loading method when doing the sequential search and then go back to the memory map for the random-access carving.
-		//       If possible, when using the GPU search impl, write directly into the vulkan-allocated host-side buffer to avoid a memcpy
-		// PERF: Queuing read operations with io_uring might have a more substantial performance improvement for HDDs, as it may be able to reduce the
-		//       amount of disk rotations - but for a single file, would it be any better? Perhaps look into this
-		for (i, window) in mmap.gapped_windows(block_size, block_size - max_pat_len).enumerate() {
-			// This probably doesn't do a lot but there seems no reason to not have it
-			#[cfg(target_arch = "x86_64")]
-			unsafe { _mm_prefetch::<_MM_HINT_T0>(window.as_ptr() as *const i8) };
+				let mut n = 1;
+				let unique_filename = loop {
+					let candidate = match extension {
+						Some(extension) => format!("{stem}_{n}.{extension}"),
+						None => format!("{stem}_{n}")
+					};
+
+					if !is_taken(&candidate) {
+						break candidate;
+					}
+					n += 1;
+				};
 
-			if let Some(prev_result) = result_fut.take() {
-				matches.append(&mut prev_result.wait().unwrap());
+				filename = unique_filename;
 			}
-			let fut = {
-				if i == 0 {
-					searcher.search(window, 0, 0).unwrap()
-				} else {
-					searcher.search(window, (i * (block_size - max_pat_len)) as u64, max_pat_len).unwrap()
+
+			// Only write out the file content if the skip carving flag is false/not present
+			if !skip_carving {
+				// File to be placed at output_dir/file_dir/filename
+				let filepath = validation_dir.join(&filename);
+
+				// Create the directory of the file (and any directories it is nested in) if it doesn't exist
+				fs::create_dir_all(Path::new(&filepath).parent().unwrap())?;
+
+				let mut out_file = File::create(filepath)?;
+
+				// PERF: Writing to lots of files does seem like a perfect use case for io_uring... but windows... and other platforms... Maybe https://crates.io/crates/nuclei ?
+				//       At the very least, write_vectored should be more performant than repeated write_all calls, but does not seem to behave properly on windows, and nevertheless doesn't guarantee everything is written
+				// FIXME: write_vectored may not write everything
+				// file.write_vectored(
+				// 	&fragments.iter().map(|frag| IoSlice::new(&mmap[frag.start..frag.end])).collect::<Vec<IoSlice>>()
+				// )?;
+				for frag in file.fragment_data() {
+					out_file.write_all(frag)?;
+				}
+			}
+
+			carved_paths.insert(format!("{dir_path}/{filename}"));
+
+			// Extract any embedded files to output_dir/file_dir/<filename>_members/<index>_<name>, or to the sanitised path of the name under
+			// that directory if original_names is configured and it doesn't clash with another member, skipping any that can't be decoded
+			let members_dir = format!("{filename}_members");
+			let mut members = Vec::new();
+			let mut member_paths = UsedPaths::default();
+
+			for (i, embedded) in file.validation.embedded.iter().enumerate() {
+				let member_filename = sanitise_path(&embedded.name)
+					.filter(|path| config.original_names && !member_paths.is_taken(path))
+					.unwrap_or_else(|| format!("{i}_{}", sanitise_filename(&embedded.name)));
+
+				member_paths.insert(member_filename.clone());
+
+				if !skip_carving {
+					let filepath = validation_dir.join(&members_dir).join(&member_filename);
+
+					if let Err(e) = Self::export_embedded(&mmap, &filepath, &embedded.fragments, embedded.encoding) {
+						warn!("Failed to extract \"{}\" from {} to {}: {}", embedded.name, filename, filepath.display(), e);
+						continue;
+					}
 				}
+
+				members.push(CarveLogMember {
+					name: embedded.name.clone(),
+					filename: format!("{members_dir}/{member_filename}"),
+					validation: embedded.validation_type,
+					encoding: embedded.encoding,
+					fragments: embedded.fragments.clone()
+				});
+			}
+
+			// The slack of the file is the rest of the cluster that its last fragment ends in, which is written to
+			// output_dir/file_dir/<filename>.slack
+			let slack = if config.carve_slack {
+				Self::slack_range(fragments.last().unwrap().end, file.cluster_size, mmap.len())
+			} else {
+				None
+			};
+
+			if let (Some(slack), false) = (&slack, skip_carving) {
+				let filepath = validation_dir.join(format!("{filename}.slack"));
+
+				Self::export_fragments(&mmap, &filepath, slice::from_ref(slack))?;
+			}
+
+			// Any data appended after the file is written to output_dir/file_dir/<filename>.appended
+			if let (Some(appended_data), false) = (&file.appended_data, skip_carving) {
+				let filepath = validation_dir.join(format!("{filename}.appended"));
+
+				Self::export_fragments(&mmap, &filepath, slice::from_ref(appended_data))?;
+			}
+
+			// Add entry to log
+			let entry = log.add_entry(file.file_match.file_type.type_id, filename, file.validation.validation_type, file.validation.fragments, file.validation.metadata);
+			// Embedded files are recorded as members of the entry, or as entries of their own after it if embedded_entries is configured
+			let embedded_entries = if config.embedded_entries {
+				members
+			} else {
+				entry.members = members;
+				Vec::new()
 			};
-			result_fut = Some(fut);
+			entry.slack = slack;
+			entry.appended_data = file.appended_data;
+			if config.output_layout != OutputLayout::Validation {
+				entry.directory = Some(dir_path);
+			}
+			if config.fragmentation_stats {
+				entry.fragmentation = Some(Fragmentation::of(&entry.fragments));
+			}
+			if config.fragment_checksums {
+				entry.fragment_checksums = FragmentChecksum::of(&mmap, &entry.fragments);
+			}
+			if config.filename_offset_unit != OffsetUnit::Bytes {
+				entry.filename_offset_unit = Some(FilenameOffsetUnit { unit: config.filename_offset_unit, unit_size: unit_size as u64 });
+			}
+
+			// Embedded files recorded as entries are in the directory of the containing file, as members would be
+			if !embedded_entries.is_empty() {
+				let parent = entry.path();
+				let parent_dir = entry.dir_path();
 
-			if log_enabled!(Level::Info) {
-				// BUG: This is not really correct, as in, we want the progress report to go where the logs are going, without spamming lines, which is why
-				//      we're using \r to repeatedly overwrite the line, but we can only do that to stdout or stderr. By default searchlight (the included
-				//      binary crate) *does* write logs to stderr, but ideally we want libsearchlight to not depend on that behaviour to behave in a sensible
-				//      way. Perhaps we just write a log when we hit a milestone? e.g. 25%, 50%, 75%, 100%... Or perhaps just every X amount of seconds, log
-				//      the current progress. *OR*, perhaps, and this might be a better solution, delegate the progress reporting to outside of this function
-				//      - i.e. we provide a way of getting the current progress (perhaps through a channel) and in another thread, the user interface code
-				//      can report it how it likes?
-				eprint!("\rProgress: {:.2}%", (i as f32 / num_blocks as f32) * 100.0);
+				for member in embedded_entries {
+					let entry = log.add_entry(FileTypeId::Unknown, member.filename, member.validation, member.fragments, HashMap::from([ (METADATA_EMBEDDED_NAME.to_string(), member.name) ]));
+					entry.directory = Some(parent_dir.clone());
+					entry.parent = Some(parent.clone());
+					entry.encoding = Some(member.encoding);
+				}
 			}
+
+			Ok(())
+		};
+
+		let mut matched_ids = HashSet::new();
+		let mut timings = PhaseTimings::default();
+
+		self.check_params(config);
+
+		for region in regions {
+			num_carved_files += self.process_region(&mmap, path, region, config, cluster_size, &mut write_file, rejects.as_mut(), &mut matched_ids, &mut timings)?;
 		}
 
-		if log_enabled!(Level::Info) {
-			eprintln!("\rProgress: 100.00%");
+		let unmatched_signatures = Self::unmatched_signatures(config, &matched_ids);
+
+		if !unmatched_signatures.is_empty() {
+			info!("{} configured signatures were never matched, and may be wrong or redundant:", unmatched_signatures.len());
+			for unmatched in &unmatched_signatures {
+				let file_type = &config.file_types[unmatched.file_type_idx];
+				info!("    {} \"{}\" of type {}", unmatched.part, unmatched.signature, file_type.extension.as_deref().unwrap_or("<no extension>"));
+			}
 		}
 
-		if let Some(result) = result_fut.take() {
-			matches.append(&mut result.wait().unwrap());
+		SearchCheckpoint::remove_all(config, path, mmap.len());
+
+		if !skip_carving {
+			info!("{} successfully validated files exported to {}", num_carved_files, output_dir.as_ref());
 		}
 
-		let num_matches = matches.len();
+		let fragmentation = log.fragmentation_summary();
 
-		matches.sort_by_key(|m| m.start_idx);
+		if let Some(fragmentation) = &fragmentation {
+			info!("Fragmentation of the {} files: {:.2} fragments and {:.0} gap bytes on average, at most {} fragments and {} gap bytes",
+				fragmentation.num_files, fragmentation.mean_fragment_count, fragmentation.mean_gap_bytes, fragmentation.max_fragment_count, fragmentation.max_gap_bytes);
+		}
 
-		let id_ftype_map = &pairing::preprocess_config(&config);
+		if append {
+			let log_path = log.write_appending(output_dir.as_ref(), config.log_format)?;
 
-		// Get the user-supplied cluster size or estimate it based off of headers
-		// A None for cluster size here will indicate that the headers appear to be mostly not allocated on any usual cluster boundaries, or that
-		// has been passed in as the case
-		let cluster_size = cluster_size.unwrap_or_else(|| {
-			let est = estimate_cluster_size(matches.iter().filter(|m| {
-				if let Some((_, _, part)) = id_ftype_map.get(&m.id) {
-					*part == MatchPart::Header
-				} else {
-					assert!(false);
-					panic!() // assert!(false) is not detected as a control flow terminator/does not return ! but is more semantically correct
-				}
-			})).unwrap_or(1); // A cluster size of 1 is effectively the same as not being clustered
+			info!("Carve log written to {}", log_path.display());
+		} else {
+			log.write(output_dir.as_ref(), config.log_format)?;
 
-			info!("Calculated cluster size estimate: {est}");
+			info!("Carve log written to {}{}log.{}", output_dir.as_ref(), std::path::MAIN_SEPARATOR_STR, config.log_format.extension());
+		}
 
-			est
-		});
+		if write_bodyfile {
+			log.write_bodyfile(output_dir.as_ref(), append)?;
 
-		if log_enabled!(Level::Trace) {
-			for m in &matches {
-				if let Some((_, ftype, part)) = id_ftype_map.get(&m.id) {
-					trace!("Match at {}, type {} ({})", m.start_idx, ftype.extension.clone().unwrap_or("<no extension>".to_string()), part);
-				} else {
-					assert!(false);
-				}
-			}
+			info!("Bodyfile written to {}{}bodyfile.txt", output_dir.as_ref(), std::path::MAIN_SEPARATOR_STR);
 		}
 
-		let mut consumable_matches = matches.clone();
-		let match_pairs = pair(&mut consumable_matches, id_ftype_map, true);
+		if let Some(rejects) = rejects {
+			rejects.write(output_dir.as_ref(), append)?;
 
-		info!("Searching complete: Found {} potential files ({} individual matches)", match_pairs.len(), num_matches);
+			info!("Rejects log of {} candidates written to {}{}rejects.jsonl", rejects.rejects.len(), output_dir.as_ref(), std::path::MAIN_SEPARATOR_STR);
+		}
 
-		// Create output directory, erroring if it exists already
-		fs::create_dir(output_dir.as_ref())?;
+		if config.carve_map {
+			let resolution = config.carve_map_resolution.or(cluster_size).unwrap_or(DEFAULT_CARVE_MAP_RESOLUTION);
+			let carve_map = CarveMap::from_log(&log, mmap.len() as u64, resolution);
+			carve_map.write(output_dir.as_ref())?;
+
+			info!("Carve map of {} cells ({} bytes each) written to {}{}carve_map.json", carve_map.num_cells, resolution, output_dir.as_ref(), std::path::MAIN_SEPARATOR_STR);
+		}
+
+		if config.timings {
+			info!(
+				"Timings: search {:.3}s ({:.2} MiB/s), cluster size estimation {:.3}s, pairing {:.3}s, validation {:.3}s, carving {:.3}s",
+				timings.search.as_secs_f64(),
+				timings.search_bytes_per_sec() / (1024.0 * 1024.0),
+				timings.cluster_estimation.as_secs_f64(),
+				timings.pairing.as_secs_f64(),
+				timings.validation.as_secs_f64(),
+				timings.carving.as_secs_f64()
+			);
+		}
+
+		Ok(CarveSummary {
+			num_carved_files,
+			unmatched_signatures,
+			fragmentation,
+			timings: config.timings.then_some(timings)
+		})
+	}
+
+	/// Searches the image file at `path`, and pairs and validates the found files, passing each file that is recognised to `consumer` along
+	/// with a means of reading its content, instead of writing them to disk. The other arguments are as in `process_image_file`. Returns the
+	/// number of files that were passed to `consumer`, or the first error that `consumer` returns, if any
+	pub fn carve_image_file(&mut self, path: &str, config: &SearchlightConfig, cluster_size: Option<u64>, region: Option<Range<u64>>, scan_partitions: bool, mut consumer: impl FnMut(CarvedFile) -> Result<(), Error>) -> Result<usize, Error> {
+		Self::check_cluster_size(cluster_size)?;
+
+		let config = &*config.with_filtered_file_types()?;
+
+		let (mmap, regions) = Self::open_image(path, region, scan_partitions)?;
 
 		let mut num_carved_files = 0;
 
-		let mut log = CarveLog::new(path);
+		self.check_params(config);
 
-		for pot_file in &match_pairs {
-			let validation = self.validator.validate(&mmap, &pot_file, &matches, cluster_size as usize, &config);
+		for region in regions {
+			num_carved_files += self.process_region(&mmap, path, region, config, cluster_size, &mut consumer, None, &mut HashSet::new(), &mut PhaseTimings::default())?;
+		}
 
-			debug!("Potential file at {}-{} (type id {}) validated as: {}, with fragments {:?}", pot_file.start_idx, pot_file.end_idx + 1, pot_file.file_type.type_id, validation.validation_type, validation.fragments);
+		SearchCheckpoint::remove_all(config, path, mmap.len());
 
-			if validation.validation_type != FileValidationType::Unrecognised {
-				let fragments = if validation.fragments.is_empty() {
-					vec![ (pot_file.start_idx..(pot_file.end_idx + 1)) ]
-				} else {
-					validation.fragments
-				};
+		Ok(num_carved_files)
+	}
 
-				// Get the minimum index and maximum index of all fragments and designate them the start and end idxs
-				let start_idx = fragments.iter().min_by_key(|frag| frag.start).unwrap().start; // .map_or(pot_file.start_idx, |frag| frag.start);
-				let end_idx = fragments.iter().max_by_key(|frag| frag.end).unwrap().end; // .map_or(pot_file.end_idx + 1, |frag| frag.end);
+	/// Searches the data read from `reader` (e.g. stdin) as a stream, a block at a time, and pairs the found headers and footers, writing a
+	/// carve log of the potential files to `output_dir`. Only one block of the data is held in memory at a time, so random-access carving is
+	/// not available in this mode: the potential files are not validated or carved, and are all logged as unanalysed. To carve them, the
+	/// data must be saved to a file and processed as an image, or the image path in the carve log changed to point to such a file
+	pub fn process_stream(&mut self, output_dir: impl AsRef<str>, reader: &mut dyn Read, config: &SearchlightConfig) -> Result<(), Error> {
+		let config = &*config.with_filtered_file_types()?;
 
-				// Filename format <start_idx>-<end_idx>.<extension>
-				let filename = format!("{start_idx}-{end_idx}.{}",
-					pot_file.file_type.extension.clone().unwrap_or("dat".to_string())
-				);
+		fs::create_dir(output_dir.as_ref())?;
 
-				// Only write out the file content if the skip carving flag is false/not present
-				if !skip_carving {
-					// File to be placed at output_dir/validation_type/filename
-					let filepath: PathBuf = [
-						output_dir.as_ref(),
-						&validation.validation_type.to_string(),
-						&filename
-					].iter().collect();
-
-					// Create validation directory if it doesn't exist
-					fs::create_dir_all(Path::new(&filepath).parent().unwrap())?;
-
-					let mut file = File::create(filepath)?;
-
-					// PERF: Writing to lots of files does seem like a perfect use case for io_uring... but windows... and other platforms... Maybe https://crates.io/crates/nuclei ?
-					//       At the very least, write_vectored should be more performant than repeated write_all calls, but does not seem to behave properly on windows, and nevertheless doesn't guarantee everything is written
-					// FIXME: write_vectored may not write everything
-					// file.write_vectored(
-					// 	&fragments.iter().map(|frag| IoSlice::new(&mmap[frag.start..frag.end])).collect::<Vec<IoSlice>>()
-					// )?;
-					for frag in &fragments {
-						file.write_all(&mmap[frag.start..frag.end])?;
-					}
-				}
+		self.log_stream_candidates(output_dir, reader, config, STDIN_IMAGE_PATH, false, &mut HashSet::new()).map(|_| ())
+	}
 
-				// Add entry to log
-				log.add_entry(pot_file.file_type.type_id, filename, validation.validation_type, fragments);
+	/// Searches the data read from `reader` as a stream and writes a carve log of the potential files to `output_dir`, which must exist, as for
+	/// `process_stream`, recording `image_path` as the image in the log. The IDs of the patterns that were matched are added to `matched_ids`.
+	/// Returns the number of potential files that were logged
+	fn log_stream_candidates(&mut self, output_dir: impl AsRef<str>, reader: &mut dyn Read, config: &SearchlightConfig, image_path: &str, append: bool, matched_ids: &mut HashSet<u64>) -> Result<usize, Error> {
+		let (mut searcher, max_pat_len) = self.create_searcher(config)?;
 
-				num_carved_files += 1;
+		let (mut matches, stream_len) = search_stream(searcher.as_mut(), reader, max_pat_len)?;
+		self.active_backend.set(Some(searcher.active_backend()));
 
-				// BUG: If some text is written to stderr or stdout between writes of the progress, then there will be no
-				//      line break between the progress report and the output text. Put a space after the progress % to
-				//      make that look less bad but I'm not sure if this is fixable, in a compelling way anyway. Well apart from externalising the progress reporting
-				if log_enabled!(Level::Info) {
-					eprint!("\rProgress: {:.2}% ", (num_carved_files as f32 / match_pairs.len() as f32) * 100.0);
-				}
-			}
+		info!("Read {} bytes from the stream", stream_len);
+
+		let exclusions = config.exclusions();
+		if !exclusions.is_empty() {
+			matches.retain(|m| !exclusions.intersects(&(m.start_idx..(m.end_idx + 1))));
 		}
 
-		if !skip_carving {
-			if log_enabled!(Level::Info) {
-				eprint!("\n");
-			}
-			info!("{} successfully validated files exported to {}", num_carved_files, output_dir.as_ref());
+		let num_matches = matches.len();
+
+		matched_ids.extend(matches.iter().map(|m| m.id));
+
+		matches.sort_by_key(|m| m.start_idx);
+
+		let id_ftype_map = &pairing::preprocess_config(config);
+		let match_pairs = pair(&mut matches, id_ftype_map, true);
+
+		info!("Searching complete: Found {} potential files ({} individual matches)", match_pairs.len(), num_matches);
+
+		let mut log = CarveLog::new(image_path);
+
+		let filename_template = config.filename_template();
+
+		for (index, pot_file) in match_pairs.into_iter().enumerate() {
+			let start_idx = pot_file.start_idx;
+			let end_idx = (pot_file.end_idx + 1).min(stream_len as usize);
+
+			// The data is not available to hash, so {hash} is left empty
+			let filename = filename_template.render(&FilenameFields {
+				start: start_idx,
+				end: end_idx,
+				ext: pot_file.file_type.extension.as_deref().unwrap_or("dat"),
+				type_id: pot_file.file_type.type_id,
+				hash: None,
+				index
+			});
+
+			log.add_entry(pot_file.file_type.type_id, filename, FileValidationType::Unanalysed, vec![ start_idx..end_idx ], HashMap::new());
 		}
 
-		log.write(output_dir.as_ref())?;
+		if append {
+			let log_path = log.write_appending(output_dir.as_ref(), config.log_format)?;
 
-		info!("Carve log written to {}{}log.json", output_dir.as_ref(), std::path::MAIN_SEPARATOR_STR);
+			info!("Carve log written to {}", log_path.display());
+		} else {
+			log.write(output_dir.as_ref(), config.log_format)?;
 
-		Ok(())
+			info!("Carve log written to {}{}log.{}", output_dir.as_ref(), std::path::MAIN_SEPARATOR_STR, config.log_format.extension());
+		}
+
+		Ok(log.files.len())
 	}
 
-	pub fn process_log_file(&mut self, output_dir: impl AsRef<str>, path: &str) -> Result<(), Error> {
-		let log_file_str = fs::read_to_string(path)?;
+	/// Processes the image file at `path` as a stream, with the stream I/O strategy, as for `process_image_file`. The found files are only
+	/// logged. Regions and partition scanning need random access, and bodyfiles modification times from validation, so requesting any of
+	/// them is an error rather than being ignored
+	fn process_image_stream(&mut self, output_dir: impl AsRef<str>, path: &str, config: &SearchlightConfig, options: &ImageOptions) -> Result<CarveSummary, Error> {
+		let ImageOptions { ref region, scan_partitions, write_bodyfile, append } = *options;
 
-		let log: CarveLog = serde_json::from_str(&log_file_str).map_err(|e| Error::LogReadError(e.to_string()))?;
+		let unsupported: Vec<&str> = [ (region.is_some(), "a region"), (scan_partitions, "partition scanning"), (write_bodyfile, "writing a bodyfile") ]
+			.into_iter()
+			.filter_map(|(requested, option)| requested.then_some(option))
+			.collect();
 
-		info!("Processing log \"{}\" - carving {} files from image at \"{}\"", path, log.files.len(), log.image_path);
+		if !unsupported.is_empty() {
+			return Err(Error::UnsupportedWhenStreaming(unsupported.join(", ")));
+		}
 
-		let mmap = {
-			let mut file = File::open(&log.image_path)?;
+		warn!("Streamed images can't be validated or carved - Only a carve log of the potential files is written, with all logged as unanalysed");
 
-			let file_len = file_len(&mut file)?;
+		let mut file = File::open(path)?;
 
-			info!("Opened image file {} (size: {} bytes)", &log.image_path, file_len);
+		if append {
+			fs::create_dir_all(output_dir.as_ref())?;
+		} else {
+			fs::create_dir(output_dir.as_ref())?;
+		}
+
+		let mut matched_ids = HashSet::new();
+		self.log_stream_candidates(output_dir, &mut file, config, path, append, &mut matched_ids)?;
+
+		Ok(CarveSummary {
+			num_carved_files: 0,
+			unmatched_signatures: Self::unmatched_signatures(config, &matched_ids),
+			fragmentation: None,
+			timings: None
+		})
+	}
+
+	/// Validates `data` as a single file of type `file_type`, as if a header had been found at the start of `data` and a footer at the end,
+	/// using this instance's validator. The data is searched first so that validators that make use of other matches have them available.
+	/// This is intended for checking that validators behave as expected on known sample files
+	pub fn validate_sample(&mut self, data: &[u8], file_type: &FileType, config: &SearchlightConfig, cluster_size: usize) -> Result<FileValidationInfo, Error> {
+		let (mut searcher, max_pat_len) = self.create_searcher(config)?;
+
+		let mut matches = search_windowed(searcher.as_mut(), data, 0, max_pat_len, &[ false; 256 ], Some(config.progress), None, None)?;
+		self.active_backend.set(Some(searcher.active_backend()));
+		matches.sort_by_key(|m| m.start_idx);
 
-			unsafe { MmapOptions::new().map(&file)? }
+		let file_match = MatchPair {
+			file_type,
+			start_idx: 0,
+			end_idx: data.len().saturating_sub(1),
+			truncated: false
 		};
 
-		for entry in &log.files {
-			// File to be placed at output_dir/validation_type/filename
-			let filepath: PathBuf = [
-				output_dir.as_ref(),
-				&entry.validation.to_string(),
-				&entry.filename
-			].iter().collect();
+		Ok(self.validator.validate(data, &file_match, &matches, cluster_size, config))
+	}
 
-			// Create validation directory if it doesn't exist
-			fs::create_dir_all(Path::new(&filepath).parent().unwrap())?;
+	/// Creates a searcher for the headers and footers in `config` with the searcher factory, recording its backend, and returns it along
+	/// with the length of the longest pattern. The backend is recorded again after searching, in case the searcher fell back to another
+	fn create_searcher(&self, config: &SearchlightConfig) -> Result<(Box<dyn Searcher>, usize), Error> {
+		let (searcher, max_pat_len) = (self.searcher_factory)(config, &self.thread_pool(config)?)?;
 
-			let mut file = File::create(filepath).unwrap();
+		self.active_backend.set(Some(searcher.active_backend()));
 
-			file.write_vectored(
-				&entry.fragments.iter().map(|frag| IoSlice::new(&mmap[frag.start..frag.end])).collect::<Vec<IoSlice>>()
-			)?;
+		Ok((searcher, max_pat_len))
+	}
+
+	/// Returns the pool of worker threads, creating it with the number of threads in `config` if it hasn't been created yet, or if it has a
+	/// different number of threads
+	fn thread_pool(&self, config: &SearchlightConfig) -> Result<Arc<ThreadPool>, Error> {
+		let mut pool = self.thread_pool.borrow_mut();
+
+		match pool.as_ref() {
+			Some(pool) if pool.current_num_threads() == config.num_threads() => Ok(Arc::clone(pool)),
+			_ => Ok(Arc::clone(pool.insert(thread_pool(config.num_threads())?)))
 		}
+	}
 
-		info!("{} files exported to {}", log.files.len(), output_dir.as_ref());
+	/// Logs the validator parameters of the file types in `config` that have no effect, i.e. that the validator of the type doesn't understand
+	/// or whose values are invalid, which is worth knowing when tuning a config. Done once per carve rather than as parameters are read, as
+	/// they are read for every validation
+	fn check_params(&self, config: &SearchlightConfig) {
+		for ftype in &config.file_types {
+			let extension = ftype.extension.as_deref().unwrap_or("<no extension>");
 
-		Ok(())
+			for key in ftype.params.keys().filter(|key| !self.validator.known_params(ftype.type_id).contains(&key.as_str())) {
+				debug!("Ignoring unknown validator parameter \"{}\" of file type {}", key, extension);
+			}
+
+			for (key, e) in validation::invalid_params(ftype) {
+				warn!("Ignoring invalid value for validator parameter \"{}\" of file type {}: {}", key, extension, e);
+			}
+		}
+	}
+
+	/// Checks that a supplied cluster size is a power of two (1 meaning unaligned) no larger than `MAX_CLUSTER_SIZE`, as the fragmentation
+	/// and reconstruction done by validators relies on it
+	fn check_cluster_size(cluster_size: Option<u64>) -> Result<(), Error> {
+		match cluster_size {
+			Some(cluster_size) if !cluster_size.is_power_of_two() || cluster_size > MAX_CLUSTER_SIZE => {
+				Err(Error::InvalidClusterSize(format!("Cluster size {} is not a power of two between 1 and {}", cluster_size, MAX_CLUSTER_SIZE)))
+			}
+			_ => Ok(())
+		}
+	}
+
+	/// Memory maps the image file at `path`, returning the memory map and the regions of the image to process separately, which are the
+	/// partitions and the unpartitioned space between and around them within `region` if `scan_partitions` is set and a partition table is
+	/// found, or otherwise just `region` (or the whole image if not specified)
+	fn open_image(path: &str, region: Option<Range<u64>>, scan_partitions: bool) -> Result<(Arc<Mmap>, Vec<Range<usize>>), Error> {
+		let (mmap, file_len) = {
+			let mut file = File::open(&path)?;
+
+			let file_len = file_len(&mut file)?;
+
+			info!("Opened image file {} (size: {} bytes)", &path, file_len);
+
+			// The length is given explicitly, as the memory map would otherwise take it from the metadata, which is 0 for block devices
+			(
+				unsafe { MmapOptions::new().len(file_len as usize).map(&file)? },
+				file_len
+			)
+		};
+
+		assert_eq!(file_len, mmap.len() as u64);
+
+		// Check the region, if specified, is within the bounds of the image, and if not specified just use the whole image
+		let region = if let Some(region) = region {
+			if region.start >= region.end || region.end > file_len {
+				return Err(Error::InvalidRegion(format!("Region {}..{} is empty or exceeds the bounds of the image (size: {} bytes)", region.start, region.end, file_len)));
+			}
+
+			info!("Restricting search and carving to region {}..{}", region.start, region.end);
+
+			region.start as usize..region.end as usize
+		} else {
+			0..file_len as usize
+		};
+
+		// If requested, look for a partition table and split the region into the partitions that it lists, so that each is processed separately.
+		// The space outside of the partitions (unallocated gaps and trailing space) is processed too, as it may hold deleted files
+		let regions = if scan_partitions {
+			match parse_partition_table(&mmap) {
+				Some(partitions) => {
+					let mut regions: Vec<Range<usize>> = partitions.iter()
+						.map(|part| (part.start as usize).max(region.start)..(part.end as usize).min(region.end))
+						.filter(|part| !part.is_empty())
+						.collect();
+
+					info!("Found partition table with {} partitions in the searched region: {:?}", regions.len(), regions);
+
+					let gaps: Vec<Range<usize>> = unpartitioned_space(&partitions, region.start as u64..region.end as u64).into_iter()
+						.map(|gap| gap.start as usize..gap.end as usize)
+						.collect();
+
+					if !gaps.is_empty() {
+						info!("Also processing {} unpartitioned ranges in the searched region: {:?}", gaps.len(), gaps);
+					}
+
+					regions.extend(gaps);
+					regions.sort_by_key(|region| region.start);
+
+					regions
+				}
+				None => {
+					info!("No recognised partition table found, processing image as a whole");
+
+					vec![ region ]
+				}
+			}
+		} else {
+			vec![ region ]
+		};
+
+		Ok((Arc::new(mmap), regions))
+	}
+
+	/// Searches `search_ranges` of `mmap` for the headers and footers in `config`, with a searcher produced by the searcher factory. The search
+	/// of each range is checkpointed if `image_path` (the path of `mmap`) is given and checkpointing is configured. `on_searched` is called as
+	/// the search progresses, see `search_windowed`
+	fn search_ranges(&self, mmap: &[u8], search_ranges: &[Range<u64>], config: &SearchlightConfig, progress: Option<ProgressMode>, image_path: Option<&str>, mut on_searched: Option<OnSearched>) -> Result<Vec<Match>, Error> {
+		let (mut searcher, max_pat_len) = self.create_searcher(config)?;
+
+		let block_size = searcher.max_search_size().unwrap_or(DEFAULT_BLOCK_SIZE);
+		let overlap = searcher.required_overlap(max_pat_len);
+
+		// Uniform blocks of data (e.g. zeroed unallocated space) can be skipped when searching as long as no pattern could match in them
+		let skippable = sparse::skippable_bytes(config.file_types.iter().flat_map(|ft| ft.headers.iter().chain(ft.footers.iter())).map(|pat| pat.as_slice()));
+
+		let mut matches = Vec::new();
+
+		for search_range in search_ranges {
+			let search_data = &mmap[(search_range.start as usize)..(search_range.end as usize)];
+			let checkpoint = image_path.and_then(|path| SearchCheckpoint::new(config, path, mmap.len(), search_range.start as usize, search_data.len(), block_size, overlap));
+			matches.append(&mut search_windowed(searcher.as_mut(), search_data, search_range.start as usize, max_pat_len, &skippable, progress, checkpoint, on_searched.as_mut().map(|on_searched| &mut **on_searched as _))?);
+		}
+
+		// The searcher may have fallen back to another backend while searching
+		self.active_backend.set(Some(searcher.active_backend()));
+
+		Ok(matches)
+	}
+
+	/// Searches `search_ranges` of `mmap` in two passes, see `SearchlightConfig::targeted_footer_search`. The first pass searches for all headers
+	/// and the footers of types without a max_len, and the second searches for the remaining footers only in the windows following each header
+	/// within which a footer could be paired with it, i.e. up to max_len from the start of the header (bounded by `data_end`)
+	fn search_targeted(&self, mmap: &[u8], search_ranges: &[Range<u64>], data_end: usize, config: &SearchlightConfig, id_ftype_map: &HashMap<u64, (usize, &FileType, MatchPart)>, image_path: Option<&str>) -> Result<Vec<Match>, Error> {
+		let (first_pass_config, second_pass_config) = config.targeted_search_configs();
+
+		// Only the first pass is checkpointed, as the second only searches the data near headers
+		let mut matches = self.search_ranges(mmap, search_ranges, &first_pass_config, Some(config.progress), image_path, None)?;
+
+		if second_pass_config.file_types.is_empty() {
+			return Ok(matches);
+		}
+
+		// Matches are attributed to file types the same way as when pairing, so that the windows are those of the types they will be paired as
+		let mut windows: Vec<Range<u64>> = matches.iter().filter_map(|m| match id_ftype_map.get(&m.id) {
+			Some((_, ftype, MatchPart::Header)) if ftype.is_footer_search_targeted() => {
+				Some(m.start_idx..(m.start_idx + ftype.max_len.unwrap() + 1).min(data_end as u64))
+			}
+			_ => None
+		}).collect();
+
+		windows.sort_by_key(|w| w.start);
+
+		// Merge overlapping windows so that no data is searched twice, which would produce duplicate matches
+		let mut merged_windows: Vec<Range<u64>> = Vec::new();
+		for window in windows {
+			match merged_windows.last_mut() {
+				Some(last) if window.start <= last.end => last.end = last.end.max(window.end),
+				_ => merged_windows.push(window)
+			}
+		}
+
+		// Only the parts of the windows that are within the searched ranges are searched
+		let footer_ranges: Vec<Range<u64>> = merged_windows.iter().flat_map(|window| {
+			search_ranges.iter().filter_map(|range| {
+				let start = window.start.max(range.start);
+				let end = window.end.min(range.end);
+				(start < end).then_some(start..end)
+			})
+		}).collect();
+
+		info!("Searching for footers in {} bytes following headers", footer_ranges.iter().map(|r| r.end - r.start).sum::<u64>());
+
+		matches.append(&mut self.search_ranges(mmap, &footer_ranges, &second_pass_config, None, None, None)?);
+
+		Ok(matches)
+	}
+
+	/// Searches, pairs and validates the files in `region` of `mmap` (the image at `image_path`), passing each file that is recognised to
+	/// `consumer`, and recording each candidate that isn't in `rejects`, if given. The ids of the matches found are added to `matched_ids`.
+	/// If `timings` is configured, the time spent in each phase is added to `timings`. Returns the number of files passed to `consumer`
+	#[allow(clippy::too_many_arguments)]
+	fn process_region(&mut self, mmap: &Arc<Mmap>, image_path: &str, region: Range<usize>, config: &SearchlightConfig, cluster_size: Option<u64>, consumer: &mut dyn FnMut(CarvedFile) -> Result<(), Error>, rejects: Option<&mut RejectsLog>, matched_ids: &mut HashSet<u64>, timings: &mut PhaseTimings) -> Result<usize, Error> {
+		// Phases are only timed if configured, so that normal runs don't pay for it
+		let phase_start = || config.timings.then(Instant::now);
+
+		// Data that validators and carving have access to - Sliced from the start of the image so offsets stay the same, but bounded by the region end
+		let file_data = &mmap[..region.end];
+
+		let exclusions = config.exclusions();
+
+		// Only the parts of the region that aren't excluded are searched
+		let search_ranges = exclusions.subtract_from((region.start as u64)..(region.end as u64));
+
+		if !exclusions.is_empty() {
+			info!("Excluding {} bytes of the region from the search", region.len() as u64 - search_ranges.iter().map(|r| r.end - r.start).sum::<u64>());
+		}
+
+		let id_ftype_map = &pairing::preprocess_config(&config);
+
+		// A cluster size read from the boot sector doesn't depend on the matches found, so like a supplied one, it is known before searching
+		let known_cluster_size = cluster_size.or_else(|| {
+			if !config.boot_sector_cluster_size {
+				return None;
+			}
+
+			let estimation_start = phase_start();
+
+			let boot_sector = boot_sector_cluster_size(&mmap[region.clone()]);
+
+			match boot_sector {
+				Some((filesystem, boot_sector_cluster_size)) => info!("Read cluster size from the {filesystem} boot sector at {:#0x}: {boot_sector_cluster_size}", region.start),
+				None => info!("No recognised boot sector at {:#0x}, estimating the cluster size from the headers found", region.start)
+			}
+
+			if let Some(estimation_start) = estimation_start {
+				timings.cluster_estimation += estimation_start.elapsed();
+			}
+
+			boot_sector.map(|(_, cluster_size)| cluster_size)
+		});
+
+		if config.pipelined_validation {
+			match (known_cluster_size, Self::pipelining_blocker(config)) {
+				(Some(cluster_size), None) => {
+					return self.process_region_pipelined(mmap, file_data, image_path, &search_ranges, config, id_ftype_map, cluster_size, consumer, rejects, matched_ids, timings);
+				}
+				(None, _) => info!("Validating once the search is complete, as pipelined validation needs the cluster size to be known before searching"),
+				(_, Some(blocker)) => info!("Validating once the search is complete, as pipelined validation can't be used with {blocker}")
+			}
+		}
+
+		let search_start = phase_start();
+
+		let mut matches = if config.targeted_footer_search {
+			self.search_targeted(mmap, &search_ranges, region.end, config, id_ftype_map, Some(image_path))?
+		} else {
+			self.search_ranges(mmap, &search_ranges, config, Some(config.progress), Some(image_path), None)?
+		};
+
+		if let Some(search_start) = search_start {
+			timings.search += search_start.elapsed();
+			timings.searched_bytes += search_ranges.iter().map(|r| r.end - r.start).sum::<u64>();
+		}
+
+		// Drop any matches that touch an excluded range. Since searchers may keep state between searches, this includes any matches that
+		// span an excluded range, which would not really be present in the image
+		if !exclusions.is_empty() {
+			matches.retain(|m| !exclusions.intersects(&(m.start_idx..(m.end_idx + 1))));
+		}
+
+		let num_matches = matches.len();
+
+		matched_ids.extend(matches.iter().map(|m| m.id));
+
+		matches.sort_by_key(|m| m.start_idx);
+
+		// Get the user-supplied cluster size or estimate it based off of headers
+		// A None for cluster size here will indicate that the headers appear to be mostly not allocated on any usual cluster boundaries, or that
+		// has been passed in as the case
+		let cluster_size = known_cluster_size.unwrap_or_else(|| {
+			let estimation_start = phase_start();
+
+			let est = estimate_cluster_size(matches.iter().filter(|m| {
+				if let Some((_, _, part)) = id_ftype_map.get(&m.id) {
+					*part == MatchPart::Header
+				} else {
+					assert!(false);
+					panic!() // assert!(false) is not detected as a control flow terminator/does not return ! but is more semantically correct
+				}
+			})).unwrap_or(1); // A cluster size of 1 is effectively the same as not being clustered
+
+			info!("Calculated cluster size estimate: {est}");
+
+			if let Some(estimation_start) = estimation_start {
+				timings.cluster_estimation += estimation_start.elapsed();
+			}
+
+			est
+		});
+
+		if log_enabled!(Level::Trace) {
+			for m in &matches {
+				Self::trace_match(m, id_ftype_map);
+			}
+		}
+
+		let pairing_start = phase_start();
+
+		let mut consumable_matches: Vec<Match> = matches.iter().filter(|m| Self::is_consumable(m, id_ftype_map, file_data, cluster_size)).cloned().collect();
+
+		if consumable_matches.len() != matches.len() {
+			debug!("Discarded {} header matches that are not cluster-aligned and footer matches that are out of context", matches.len() - consumable_matches.len());
+		}
+
+		// Headers that are dropped without being paired are recorded as rejected candidates, once there is somewhere to record them
+		let mut dropped = Vec::new();
+		let match_pairs = pairing::pair_reporting_dropped(&mut consumable_matches, id_ftype_map, true, &mut |header, ftype, reason| dropped.push((header.clone(), ftype, reason)));
+
+		if let Some(pairing_start) = pairing_start {
+			timings.pairing += pairing_start.elapsed();
+		}
+
+		info!("Searching complete: Found {} potential files ({} individual matches)", match_pairs.len(), num_matches);
+
+		// The starts of the files that each header found would begin, which bound the data appended to carved files
+		let file_starts: Vec<usize> = if config.appended_data {
+			let mut file_starts: Vec<usize> = matches.iter().filter_map(|m| match id_ftype_map.get(&m.id) {
+				Some((_, ftype, MatchPart::Header)) => Some(m.start_idx.saturating_sub(ftype.header_offset) as usize),
+				_ => None
+			}).collect();
+			file_starts.sort_unstable();
+			file_starts
+		} else {
+			Vec::new()
+		};
+
+		let mut validator = CandidateValidator::new(mmap, &self.validator, self.thread_pool(config)?, file_data, config, cluster_size);
+		let mut processor = CandidateProcessor::new(file_data, config, cluster_size, consumer, rejects, file_starts);
+
+		for (header, ftype, reason) in dropped {
+			processor.reject_unpaired(&header, ftype, reason);
+		}
+
+		let overlap_groups = Self::find_overlap_groups(config, &match_pairs);
+
+		// With the prefer_valid overlap policy, all the candidates in a group are validated when the first of them is reached, and the
+		// results of the rest are kept here until they are reached
+		let mut overlap_group_of: HashMap<usize, usize> = HashMap::new();
+		if config.overlap_policy == OverlapPolicy::PreferValid {
+			for (group_idx, group) in overlap_groups.iter().enumerate() {
+				overlap_group_of.extend(group.iter().map(|&i| (i, group_idx)));
+			}
+		}
+		let mut resolved_validations: HashMap<usize, FileValidationInfo> = HashMap::new();
+
+		let mut reporter = ProgressReporter::new(config.progress, "Carving");
+
+		for (i, pot_file) in match_pairs.iter().enumerate() {
+			let validation = if let Some(validation) = resolved_validations.remove(&i) {
+				validation
+			} else if let Some(&group_idx) = overlap_group_of.get(&i) {
+				let group = &overlap_groups[group_idx];
+				let validations = group.iter().map(|&j| validator.validate_candidate(&match_pairs[j], &matches)).collect();
+
+				resolved_validations.extend(group.iter().copied().zip(Self::resolve_overlaps(&match_pairs, group, validations)));
+
+				resolved_validations.remove(&i).unwrap()
+			} else {
+				validator.validate_candidate(pot_file, &matches)
+			};
+
+			processor.carve(pot_file, validation)?;
+
+			reporter.report((i + 1) as f32 / match_pairs.len() as f32);
+		}
+
+		if !match_pairs.is_empty() {
+			reporter.finish();
+		}
+
+		validator.finish(timings);
+
+		Ok(processor.finish(timings))
+	}
+
+	/// Searches `search_ranges` of `mmap`, pairing, validating and carving the candidates in `file_data` while the search is still running,
+	/// see `SearchlightConfig::pipelined_validation`. Otherwise the same as the rest of `process_region`, which must have checked that
+	/// pipelined validation can be used with `config`.
+	///
+	/// Candidates are queued, in a bounded queue, to be validated by a thread of their own (which runs the validators on the pool of worker
+	/// threads) once the search is far enough past their end, and are carved on this thread as their validations come back, between searches
+	#[allow(clippy::too_many_arguments)]
+	fn process_region_pipelined(&self, mmap: &Arc<Mmap>, file_data: &[u8], image_path: &str, search_ranges: &[Range<u64>], config: &SearchlightConfig, id_ftype_map: &HashMap<u64, (usize, &FileType, MatchPart)>, cluster_size: u64, consumer: &mut dyn FnMut(CarvedFile) -> Result<(), Error>, rejects: Option<&mut RejectsLog>, matched_ids: &mut HashSet<u64>, timings: &mut PhaseTimings) -> Result<usize, Error> {
+		let phase_start = || config.timings.then(Instant::now);
+
+		let exclusions = config.exclusions();
+
+		let lookahead = config.pipelined_validation_lookahead.unwrap_or(DEFAULT_PIPELINED_VALIDATION_LOOKAHEAD);
+
+		let mut validator = CandidateValidator::new(mmap, &self.validator, self.thread_pool(config)?, file_data, config, cluster_size);
+		let mut processor = CandidateProcessor::new(file_data, config, cluster_size, consumer, rejects, Vec::new());
+
+		thread::scope(|scope| {
+			// Candidates to validate, with the matches that their validator may look at, and the validated candidates to carve. The worker
+			// never waits on this thread, as the validations are unbounded, and the candidates are bounded so that this thread waits for it
+			// when it falls behind
+			let (candidate_sender, candidate_receiver) = mpsc::sync_channel::<(MatchPair, Vec<Match>)>(PIPELINED_VALIDATION_QUEUE_LEN);
+			let (validation_sender, validation_receiver) = mpsc::channel::<(MatchPair, FileValidationInfo)>();
+
+			let worker = scope.spawn(move || {
+				for (pot_file, matches) in candidate_receiver {
+					let validation = validator.validate_candidate(&pot_file, &matches);
+
+					if validation_sender.send((pot_file, validation)).is_err() {
+						break;
+					}
+				}
+
+				validator
+			});
+
+			// All the matches found so far, which validators are given, and the consumable matches that are yet to be paired, split into those
+			// that start before the point that the search has reached, all of which have been found, and those that start after it
+			let mut matches: Vec<Match> = Vec::new();
+			let mut unpaired: Vec<Match> = Vec::new();
+			let mut unsettled: Vec<Match> = Vec::new();
+			// The candidates that are yet to be validated, and those that have been, which are checked for overlaps once the search is complete
+			let mut candidates: Vec<MatchPair> = Vec::new();
+			let mut validated: Vec<MatchPair> = Vec::new();
+			let mut num_candidates = 0;
+			let mut pairing_time = Duration::ZERO;
+			// The headers that were dropped without being paired, which are recorded as rejected candidates once the rest have been carved
+			let mut dropped = Vec::new();
+
+			let mut carve = |pot_file, validation| -> Result<(), Error> {
+				processor.carve(&pot_file, validation)?;
+
+				if config.overlap_policy != OverlapPolicy::Ignore {
+					validated.push(pot_file);
+				}
+
+				Ok(())
+			};
+
+			// Pairs the matches that start before `searched_to` with those before them, queues the candidates that are ready to be validated,
+			// and carves those that have been
+			let mut advance = |found: &[Match], searched_to: u64, end_of_matches: bool| -> Result<(), Error> {
+				let pairing_start = phase_start();
+
+				for m in found.iter().filter(|m| !exclusions.intersects(&(m.start_idx..(m.end_idx + 1)))) {
+					if log_enabled!(Level::Trace) {
+						Self::trace_match(m, id_ftype_map);
+					}
+
+					matched_ids.insert(m.id);
+
+					if Self::is_consumable(m, id_ftype_map, file_data, cluster_size) {
+						unsettled.push(m.clone());
+					}
+
+					matches.push(m.clone());
+				}
+
+				// The matches of a block are not necessarily in order, but the sorts are stable, so the matches end up in the same order as if they
+				// were all sorted at once
+				matches.sort_by_key(|m| m.start_idx);
+				unsettled.sort_by_key(|m| m.start_idx);
+
+				let num_settled = unsettled.partition_point(|m| m.start_idx < searched_to);
+				unpaired.extend(unsettled.drain(..num_settled));
+
+				let mut paired = pairing::pair_reporting_dropped(&mut unpaired, id_ftype_map, end_of_matches, &mut |header, ftype, reason| dropped.push((header.clone(), ftype, reason)));
+				num_candidates += paired.len();
+				candidates.append(&mut paired);
+
+				if let Some(pairing_start) = pairing_start {
+					pairing_time += pairing_start.elapsed();
+				}
+
+				// Candidates are ready once the matches that their validator may look at, up to max_len beyond their end but no more than the
+				// lookahead, have all been found. They are queued in order of start, which their carving follows as far as is known
+				let matches_end = |pot_file: &MatchPair| (pot_file.end_idx as u64).saturating_add(pot_file.file_type.max_len.map_or(lookahead, |max_len| max_len.min(lookahead)));
+				let (mut ready, waiting): (Vec<MatchPair>, Vec<MatchPair>) = candidates.drain(..).partition(|pot_file| end_of_matches || matches_end(pot_file) < searched_to);
+				candidates = waiting;
+				ready.sort_by_key(|pot_file| Self::candidate_order(config, pot_file));
+
+				for pot_file in ready {
+					let matches_end = matches_end(&pot_file);
+					let candidate_matches = matches[matches.partition_point(|m| m.start_idx < pot_file.start_idx as u64)..matches.partition_point(|m| m.start_idx <= matches_end)].to_vec();
+
+					// The worker only stops early if it panicked, which is propagated when it is joined
+					if candidate_sender.send((pot_file, candidate_matches)).is_err() {
+						break;
+					}
+				}
+
+				while let Ok((pot_file, validation)) = validation_receiver.try_recv() {
+					carve(pot_file, validation)?;
+				}
+
+				Ok(())
+			};
+
+			let search_start = phase_start();
+			// The time spent pairing, queueing and carving while searching, which isn't counted as searching
+			let mut overlapped = Duration::ZERO;
+
+			self.search_ranges(mmap, search_ranges, config, Some(config.progress), Some(image_path), Some(&mut |found: &[Match], searched_to: u64| {
+				let advance_start = phase_start();
+				let result = advance(found, searched_to, false);
+
+				if let Some(advance_start) = advance_start {
+					overlapped += advance_start.elapsed();
+				}
+
+				result
+			}))?;
+
+			if let Some(search_start) = search_start {
+				timings.search += search_start.elapsed().saturating_sub(overlapped);
+				timings.searched_bytes += search_ranges.iter().map(|r| r.end - r.start).sum::<u64>();
+			}
+
+			advance(&[], u64::MAX, true)?;
+
+			// Once the last candidates are queued, the worker stops after validating them, and the rest of the validations are carved
+			drop(candidate_sender);
+
+			for (pot_file, validation) in validation_receiver {
+				carve(pot_file, validation)?;
+			}
+
+			for (header, ftype, reason) in dropped {
+				processor.reject_unpaired(&header, ftype, reason);
+			}
+
+			let validator = worker.join().unwrap_or_else(|payload| panic::resume_unwind(payload));
+
+			timings.pairing += pairing_time;
+
+			info!("Searching complete: Found {} potential files ({} individual matches)", num_candidates, matches.len());
+
+			Self::find_overlap_groups(config, &validated);
+
+			validator.finish(timings);
+
+			Ok(processor.finish(timings))
+		})
+	}
+
+	/// Returns why pipelined validation can't be used with `config`, if it can't, as all the matches need to be found before any candidate is
+	/// validated for some options
+	fn pipelining_blocker(config: &SearchlightConfig) -> Option<&'static str> {
+		if config.targeted_footer_search {
+			Some("targeted footer search")
+		} else if config.appended_data {
+			Some("appended data")
+		} else if config.overlap_policy == OverlapPolicy::PreferValid {
+			Some("the prefer valid overlap policy")
+		} else if config.file_types.iter().any(|ft| ft.ordered_footers) {
+			Some("file types with ordered footers")
+		} else {
+			None
+		}
+	}
+
+	/// Whether the match `m` is paired. Headers of types that are always allocated at the start of a cluster are only paired if they are
+	/// cluster-aligned, and footers of types with a footer context are only paired if they are followed by what it requires
+	fn is_consumable(m: &Match, id_ftype_map: &HashMap<u64, (usize, &FileType, MatchPart)>, file_data: &[u8], cluster_size: u64) -> bool {
+		match id_ftype_map.get(&m.id) {
+			Some((_, ftype, MatchPart::Header)) if ftype.header_aligned => {
+				let cluster_size = ftype.cluster_size.unwrap_or(cluster_size).max(1);
+				m.start_idx.saturating_sub(ftype.header_offset) % cluster_size == 0
+			}
+			Some((_, ftype, MatchPart::Footer)) => pairing::footer_in_context(ftype, m, file_data, cluster_size),
+			_ => true
+		}
+	}
+
+	/// The key that candidates are validated in order of: their start, then end, then the order their file type is configured in
+	fn candidate_order(config: &SearchlightConfig, pot_file: &MatchPair) -> (usize, usize, usize) {
+		let ftype_idx = config.file_types.iter().position(|ftype| std::ptr::eq(ftype, pot_file.file_type)).unwrap_or(usize::MAX);
+
+		(pot_file.start_idx, pot_file.end_idx, ftype_idx)
+	}
+
+	fn trace_match(m: &Match, id_ftype_map: &HashMap<u64, (usize, &FileType, MatchPart)>) {
+		if let Some((_, ftype, part)) = id_ftype_map.get(&m.id) {
+			trace!("Match at {}, type {} ({})", m.start_idx, ftype.extension.clone().unwrap_or("<no extension>".to_string()), part);
+		} else {
+			assert!(false);
+		}
+	}
+
+	/// Groups the candidates in `match_pairs` that overlap other candidates of the same type, logging the groups found, unless the overlap
+	/// policy is to ignore them
+	fn find_overlap_groups(config: &SearchlightConfig, match_pairs: &[MatchPair]) -> Vec<Vec<usize>> {
+		let overlap_groups = if config.overlap_policy != OverlapPolicy::Ignore { pairing::overlap_groups(match_pairs) } else { Vec::new() };
+
+		if !overlap_groups.is_empty() {
+			warn!(
+				"Found {} groups of candidates that overlap other candidates of the same type ({} candidates) - Headers and footers may have been mis-paired",
+				overlap_groups.len(),
+				overlap_groups.iter().map(|group| group.len()).sum::<usize>()
+			);
+
+			for group in &overlap_groups {
+				debug!("Overlapping candidates (type id {}): {:?}", match_pairs[group[0]].file_type.type_id, group.iter().map(|&i| &match_pairs[i]).collect::<Vec<_>>());
+			}
+		}
+
+		overlap_groups
+	}
+
+	/// Chooses which of a group of overlapping candidates (indices into `match_pairs`) are carved, given their validations, in the same order.
+	/// Candidates are accepted from best validated to worst, earliest first for equally good validations, and any that overlap an already
+	/// accepted candidate are rejected
+	fn resolve_overlaps(match_pairs: &[MatchPair], group: &[usize], mut validations: Vec<FileValidationInfo>) -> Vec<FileValidationInfo> {
+		let mut order: Vec<usize> = (0..group.len()).collect();
+		order.sort_by_key(|&k| (validations[k].validation_type, match_pairs[group[k]].start_idx));
+
+		let mut accepted: Vec<RangeInclusive<usize>> = Vec::new();
+
+		for k in order {
+			if validations[k].validation_type == FileValidationType::Unrecognised {
+				continue;
+			}
+
+			let pot_file = &match_pairs[group[k]];
+
+			if accepted.iter().any(|span| pot_file.start_idx <= *span.end() && *span.start() <= pot_file.end_idx) {
+				validations[k] = FileValidationInfo {
+					validation_type: FileValidationType::Unrecognised,
+					rejection_reason: Some(RejectionReason::OverlapsBetterCandidate),
+					..Default::default()
+				};
+			} else {
+				accepted.push(pot_file.start_idx..=pot_file.end_idx);
+			}
+		}
+
+		validations
+	}
+
+	/// Returns the headers and footers in `config` whose match ids are not in `matched_ids`, in the order they are configured
+	fn unmatched_signatures(config: &SearchlightConfig, matched_ids: &HashSet<u64>) -> Vec<UnmatchedSignature> {
+		config.file_types.iter().enumerate().flat_map(|(i, file_type)| {
+			let headers = file_type.headers.iter().map(move |header| (i, MatchPart::Header, header));
+			let footers = file_type.footers.iter().map(move |footer| (i, MatchPart::Footer, footer));
+
+			headers.chain(footers)
+		}).filter(|(_, _, signature)| !matched_ids.contains(&match_id_hash_slice_u16(signature))).map(|(file_type_idx, part, signature)| {
+			UnmatchedSignature {
+				file_type_idx,
+				part,
+				signature: signature.clone()
+			}
+		}).collect()
+	}
+
+	pub fn process_log_file(&mut self, output_dir: impl AsRef<str>, path: &str) -> Result<(), Error> {
+		let log = CarveLog::read(path)?;
+
+		info!("Processing log \"{}\" - carving {} files from image at \"{}\"", path, log.files.len(), log.image_path);
+
+		let mmap = {
+			let mut file = File::open(&log.image_path)?;
+
+			let file_len = file_len(&mut file)?;
+
+			info!("Opened image file {} (size: {} bytes)", &log.image_path, file_len);
+
+			unsafe { MmapOptions::new().len(file_len as usize).map(&file)? }
+		};
+
+		// Failing to export one file shouldn't stop the rest from being exported, so failures are collected (as filename, reason) and reported at the end
+		let mut failures: Vec<(&str, String)> = Vec::new();
+
+		// Embedded files recorded as entries are exported after the other entries, and only if the entry that they were extracted from was,
+		// so the paths of the exported entries are kept
+		let mut exported = HashSet::new();
+		let (parents, children): (Vec<_>, Vec<_>) = log.files.iter().partition(|entry| entry.parent.is_none());
+
+		for entry in parents.into_iter().chain(children) {
+			if let Some(parent) = entry.parent.as_deref().filter(|parent| !exported.contains(*parent)) {
+				let reason = format!("The file it was extracted from, \"{parent}\", was not exported");
+				warn!("Skipping \"{}\": {}", entry.filename, reason);
+				failures.push((&entry.filename, reason));
+				continue;
+			}
+
+			// Skip entries that reference data outside the image, e.g. if the log was generated from a different image
+			if let Some(frag) = entry.fragments.iter().find(|frag| frag.start > frag.end || frag.end > mmap.len()) {
+				let reason = format!("Fragment {:#0x}..{:#0x} lies outside of the image (size: {} bytes)", frag.start, frag.end, mmap.len());
+				warn!("Skipping \"{}\": {}", entry.filename, reason);
+				failures.push((&entry.filename, reason));
+				continue;
+			}
+
+			// The file is still carved if its checksums don't match, as the log may just be out of date
+			if !entry.fragment_checksums.is_empty() && FragmentChecksum::of(&mmap, &entry.fragments) != entry.fragment_checksums {
+				warn!("The content of \"{}\" doesn't match the fragment checksums in the log - The image may have changed since it was carved", entry.filename);
+			}
+
+			// File to be placed at output_dir/validation_type/filename, or in the directory recorded for it if files were arranged differently.
+			// The log may not have been written by us, so the directory and filename are sanitised to keep the file within the output directory
+			let file_dir = sanitise_path(&entry.dir_path()).unwrap_or_default();
+			let Some(filename) = sanitise_path(&entry.filename) else {
+				let reason = "The filename is empty once sanitised".to_string();
+				warn!("Skipping \"{}\": {}", entry.filename, reason);
+				failures.push((&entry.filename, reason));
+				continue;
+			};
+
+			let filepath: PathBuf = [
+				output_dir.as_ref(),
+				&file_dir,
+				&filename
+			].iter().collect();
+
+			let result = match entry.encoding {
+				Some(encoding) => Self::export_embedded(&mmap, &filepath, &entry.fragments, encoding),
+				None => Self::export_fragments(&mmap, &filepath, &entry.fragments)
+			};
+
+			if let Err(e) = result {
+				warn!("Failed to export \"{}\" to {}: {}", entry.filename, filepath.display(), e);
+				failures.push((&entry.filename, e.to_string()));
+				continue;
+			}
+
+			exported.insert(entry.path());
+
+			if let Some(slack) = &entry.slack {
+				let filepath = filepath.with_file_name(format!("{}.slack", entry.filename));
+
+				if slack.start > slack.end || slack.end > mmap.len() {
+					warn!("Skipping the slack of \"{}\": It lies outside of the image (size: {} bytes)", entry.filename, mmap.len());
+				} else if let Err(e) = Self::export_fragments(&mmap, &filepath, slice::from_ref(slack)) {
+					warn!("Failed to export the slack of \"{}\" to {}: {}", entry.filename, filepath.display(), e);
+				}
+			}
+
+			if let Some(appended_data) = &entry.appended_data {
+				let filepath = filepath.with_file_name(format!("{}.appended", entry.filename));
+
+				if appended_data.start > appended_data.end || appended_data.end > mmap.len() {
+					warn!("Skipping the appended data of \"{}\": It lies outside of the image (size: {} bytes)", entry.filename, mmap.len());
+				} else if let Err(e) = Self::export_fragments(&mmap, &filepath, slice::from_ref(appended_data)) {
+					warn!("Failed to export the appended data of \"{}\" to {}: {}", entry.filename, filepath.display(), e);
+				}
+			}
+
+			// Members are extracted to their recorded paths, relative to the directory of the containing file
+			for member in &entry.members {
+				if let Some(frag) = member.fragments.iter().find(|frag| frag.start > frag.end || frag.end > mmap.len()) {
+					let reason = format!("Fragment {:#0x}..{:#0x} lies outside of the image (size: {} bytes)", frag.start, frag.end, mmap.len());
+					warn!("Skipping \"{}\": {}", member.filename, reason);
+					failures.push((&member.filename, reason));
+					continue;
+				}
+
+				let Some(member_path) = sanitise_path(&member.filename) else {
+					let reason = "The filename is empty once sanitised".to_string();
+					warn!("Skipping \"{}\": {}", member.filename, reason);
+					failures.push((&member.filename, reason));
+					continue;
+				};
+
+				let filepath: PathBuf = [ output_dir.as_ref(), &file_dir ].iter().collect::<PathBuf>().join(member_path);
+
+				if let Err(e) = Self::export_embedded(&mmap, &filepath, &member.fragments, member.encoding) {
+					warn!("Failed to extract \"{}\" to {}: {}", member.filename, filepath.display(), e);
+					failures.push((&member.filename, e.to_string()));
+				}
+			}
+		}
+
+		let num_files = log.files.len() + log.files.iter().map(|entry| entry.members.len()).sum::<usize>();
+
+		info!("{} files exported to {}", num_files - failures.len(), output_dir.as_ref());
+
+		if !failures.is_empty() {
+			warn!("Failed to export {} of {} files:", failures.len(), num_files);
+			for (filename, reason) in &failures {
+				warn!("    \"{}\": {}", filename, reason);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Decodes the `encoding`-encoded content in the `fragments` of `data` to a file at `filepath`, creating its parent directory if it doesn't
+	/// exist. The file is removed if the content can't be decoded
+	fn export_embedded(data: &[u8], filepath: &Path, fragments: &[Fragment], encoding: EmbeddedEncoding) -> Result<(), Error> {
+		if let Some(parent) = filepath.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		let fragment_data: Vec<&[u8]> = fragments.iter().map(|frag| &data[frag.start..frag.end]).collect();
+
+		let mut file = io::BufWriter::new(File::create(filepath)?);
+
+		if let Err(e) = encoding.decode(&fragment_data, &mut file).and_then(|_| file.flush()) {
+			drop(file);
+			let _ = fs::remove_file(filepath);
+			return Err(e.into());
+		}
+
+		Ok(())
+	}
+
+	/// The file slack of a file whose last fragment ends at `end`, i.e. the range from there to the end of its cluster (bounded by `data_len`),
+	/// or None if the file ends on a cluster boundary or the cluster size is 1 (unaligned)
+	fn slack_range(end: usize, cluster_size: usize, data_len: usize) -> Option<Fragment> {
+		if cluster_size <= 1 || end % cluster_size == 0 {
+			return None;
+		}
+
+		let slack_end = end.next_multiple_of(cluster_size).min(data_len);
+
+		(end < slack_end).then_some(end..slack_end)
+	}
+
+	/// Estimates the end of the content of `file_match`, whose end isn't known so is carved up to `end`, with `heuristic`, returning the
+	/// estimated end. At least one byte of the file is kept
+	fn heuristic_end(data: &[u8], file_match: &MatchPair, end: usize, heuristic: EndHeuristic, cluster_size: usize) -> usize {
+		let content = &data[file_match.start_idx.min(end)..end];
+
+		let len = match heuristic {
+			EndHeuristic::Text => classifiers::text_len(content),
+			EndHeuristic::Zeros => classifiers::zero_trimmed_len(content),
+			EndHeuristic::Entropy => classifiers::consistent_entropy_len(content, cluster_size.max(MIN_ENTROPY_BLOCK_SIZE))
+		};
+
+		let trimmed_end = (file_match.start_idx + len.max(1)).min(end);
+
+		if trimmed_end < end {
+			debug!("Trimmed candidate at {:#0x} (type id {}) to {:#0x} from {:#0x} with the {} end heuristic", file_match.start_idx, file_match.file_type.type_id, trimmed_end, end, heuristic);
+		}
+
+		trimmed_end
+	}
+
+	/// The data appended after `file_match`, which consists of `fragments`, i.e. the range from the end of its furthest fragment to the start of
+	/// the next file in `file_starts` (sorted), or to max_len from the start of the file, or to `max_appended_len` from its end, whichever is
+	/// first, less any trailing zeros. Returns None if there is none, logging if the file is directly followed by the start of another
+	fn appended_data_range(data: &[u8], file_match: &MatchPair, fragments: &[Fragment], file_starts: &[usize], max_appended_len: u64) -> Option<Fragment> {
+		let end = fragments.iter().map(|frag| frag.end).max()?;
+		let next_start = file_starts.get(file_starts.partition_point(|&start| start < end)).copied();
+
+		if next_start == Some(end) {
+			info!("File at {:#0x} (type id {}) is directly followed by the start of another file at {:#0x} - It may be a polyglot, or have a file appended to it", file_match.start_idx, file_match.file_type.type_id, end);
+			return None;
+		}
+
+		let max_end = file_match.file_type.max_len.map_or(data.len(), |max_len| file_match.start_idx.saturating_add(max_len as usize));
+		let limit = next_start.unwrap_or(data.len()).min(max_end).min(end.saturating_add(max_appended_len as usize)).min(data.len());
+
+		if limit <= end {
+			return None;
+		}
+
+		let appended_end = end + data[end..limit].iter().rposition(|&b| b != 0)? + 1;
+
+		Some(end..appended_end)
+	}
+
+	/// Writes the `fragments` of `data` to a file at `filepath`, creating its parent directory if it doesn't exist
+	fn export_fragments(data: &[u8], filepath: &Path, fragments: &[Fragment]) -> Result<(), Error> {
+		// Create validation directory if it doesn't exist
+		if let Some(parent) = filepath.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		let mut file = File::create(filepath)?;
+
+		// write_vectored may not write everything, so each fragment is written in full in turn
+		for frag in fragments {
+			file.write_all(&data[frag.start..frag.end])?;
+		}
+
+		Ok(())
+	}
+}
+
+/// The paths of files written under a directory, for checking that another file can be written alongside them. A path clashes with those
+/// already written if it is one of them, a directory that one of them is in, or is in a directory that is one of them
+#[derive(Default)]
+struct UsedPaths {
+	files: HashSet<String>,
+	dirs: HashSet<String>
+}
+
+impl UsedPaths {
+	/// The directories that `path` ('/'-separated) is in, from the outermost
+	fn dirs_of(path: &str) -> impl Iterator<Item = &str> {
+		path.match_indices('/').map(|(idx, _)| &path[..idx]).filter(|dir| !dir.is_empty())
+	}
+
+	fn is_taken(&self, path: &str) -> bool {
+		self.files.contains(path) || self.dirs.contains(path) || Self::dirs_of(path).any(|dir| self.files.contains(dir))
+	}
+
+	fn insert(&mut self, path: String) {
+		self.dirs.extend(Self::dirs_of(&path).map(str::to_string));
+		self.files.insert(path);
+	}
+}
+
+/// Validates `pot_file` with `validator`, catching any panic if `catch_validator_panics` is configured
+fn run_validator(validator: &dyn FileValidator, file_data: &[u8], pot_file: &MatchPair, all_matches: &[Match], cluster_size: usize, config: &SearchlightConfig) -> FileValidationInfo {
+	if !config.catch_validator_panics {
+		return validator.validate(file_data, pot_file, all_matches, cluster_size, config);
+	}
+
+	// The validators only read the data, so nothing can be left in an inconsistent state by a panic
+	match panic::catch_unwind(AssertUnwindSafe(|| validator.validate(file_data, pot_file, all_matches, cluster_size, config))) {
+		Ok(validation) => validation,
+		Err(payload) => {
+			let msg = payload.downcast_ref::<&str>().map(|s| s.to_string()).or_else(|| payload.downcast_ref::<String>().cloned()).unwrap_or_default();
+
+			error!("Validator panicked on candidate at {:#0x} (type id {}), skipping it: {}", pot_file.start_idx, pot_file.file_type.type_id, msg);
+
+			FileValidationInfo {
+				validation_type: FileValidationType::Unrecognised,
+				rejection_reason: Some(RejectionReason::ValidatorPanicked),
+				..Default::default()
+			}
+		}
+	}
+}
+
+/// Validates the candidates found in a region, keeping the state that spans the candidates of the region, for `Searchlight::process_region`.
+/// With pipelined validation, it is moved to the thread that candidates are queued for validation on
+struct CandidateValidator<'a> {
+	/// The image that `file_data` is sliced from, which validations that time out keep alive
+	image: &'a Arc<Mmap>,
+	validator: &'a Arc<dyn FileValidator>,
+	/// The pool of worker threads that the validator is run on
+	pool: Arc<ThreadPool>,
+	/// Data that validators and carving have access to - Sliced from the start of the image so offsets stay the same, but bounded by the region end
+	file_data: &'a [u8],
+	config: &'a SearchlightConfig,
+	/// The cluster size of the image, which is overridden by that of the file type of a candidate, if set
+	cluster_size: u64,
+	validation_cache: Option<ValidationCache>,
+	/// The time spent validating
+	validation_time: Duration,
+	/// The number of validations that timed out, which may still be running
+	num_timed_out: usize,
+	/// A copy of `config` that validations with a timeout share, made when the first of them is started
+	worker_config: Option<Arc<SearchlightConfig>>
+}
+
+impl<'a> CandidateValidator<'a> {
+	fn new(image: &'a Arc<Mmap>, validator: &'a Arc<dyn FileValidator>, pool: Arc<ThreadPool>, file_data: &'a [u8], config: &'a SearchlightConfig, cluster_size: u64) -> Self {
+		CandidateValidator {
+			image,
+			validator,
+			pool,
+			file_data,
+			config,
+			cluster_size,
+			validation_cache: config.validation_cache_size.map(ValidationCache::new),
+			validation_time: Duration::ZERO,
+			num_timed_out: 0,
+			worker_config: None
+		}
+	}
+
+	/// File types can override the cluster size that their validator uses, e.g. for files embedded in containers, which aren't cluster-aligned
+	fn type_cluster_size(&self, pot_file: &MatchPair) -> usize {
+		pot_file.file_type.cluster_size.unwrap_or(self.cluster_size) as usize
+	}
+
+	/// Validates `pot_file` on the pool of worker threads. If `validation_timeout_ms` is configured, the validation owns everything that the
+	/// validator is given, so that if it times out (including the time waiting for a thread) it can be abandoned: if it was started, it
+	/// carries on in the background, keeping the image mapped and holding its thread until it finishes, but nothing waits for it
+	fn validate(&mut self, pot_file: &MatchPair, all_matches: &[Match]) -> FileValidationInfo {
+		let cluster_size = self.type_cluster_size(pot_file);
+
+		let Some(timeout) = self.config.validation_timeout_ms else {
+			let (validator, file_data, config) = (self.validator.as_ref(), self.file_data, self.config);
+			return self.pool.install(|| run_validator(validator, file_data, pot_file, all_matches, cluster_size, config));
+		};
+
+		let (image, validator, data_len) = (Arc::clone(self.image), Arc::clone(self.validator), self.file_data.len());
+		let config = Arc::clone(self.worker_config.get_or_insert_with(|| Arc::new(self.config.clone())));
+
+		// The worker gets its own copy of the matches that the validator may look at, those from the start of the candidate to max_len beyond
+		// its end, as the matches can change while it runs (with pipelined validation)
+		let matches_end = pot_file.file_type.max_len.map_or(u64::MAX, |max_len| (pot_file.end_idx as u64).saturating_add(max_len));
+		let matches = all_matches[all_matches.partition_point(|m| m.start_idx < pot_file.start_idx as u64)..all_matches.partition_point(|m| m.start_idx <= matches_end)].to_vec();
+		let (file_type, start_idx, end_idx, truncated) = (pot_file.file_type.clone(), pot_file.start_idx, pot_file.end_idx, pot_file.truncated);
+
+		let (sender, receiver) = mpsc::channel();
+		let abandoned = Arc::new(AtomicBool::new(false));
+		let worker_abandoned = Arc::clone(&abandoned);
+
+		// A panic is sent back to be resumed here, as the pool can't propagate it
+		self.pool.spawn(move || {
+			if worker_abandoned.load(Ordering::Relaxed) {
+				return;
+			}
+
+			let worker_file = MatchPair { file_type: &file_type, start_idx, end_idx, truncated };
+			let _ = sender.send(panic::catch_unwind(AssertUnwindSafe(|| run_validator(validator.as_ref(), &image[..data_len], &worker_file, &matches, cluster_size, &config))));
+		});
+
+		match receiver.recv_timeout(Duration::from_millis(timeout)) {
+			Ok(Ok(validation)) => validation,
+			Ok(Err(payload)) => panic::resume_unwind(payload),
+			Err(RecvTimeoutError::Timeout) => {
+				// If the validation is still waiting for a thread, it is never started
+				abandoned.store(true, Ordering::Relaxed);
+
+				warn!("Validating candidate at {:#0x} (type id {}) took longer than {}ms, carving it unanalysed", pot_file.start_idx, pot_file.file_type.type_id, timeout);
+				self.num_timed_out += 1;
+
+				FileValidationInfo {
+					validation_type: FileValidationType::Unanalysed,
+					rejection_reason: Some(RejectionReason::ValidationTimedOut),
+					..Default::default()
+				}
+			}
+			// The validation only stops without sending a result if it was abandoned
+			Err(RecvTimeoutError::Disconnected) => unreachable!()
+		}
+	}
+
+	/// Validates the candidate `pot_file`, given all the matches found (sorted by start), or at least all of those up to max_len beyond its end
+	fn validate_candidate(&mut self, pot_file: &MatchPair, all_matches: &[Match]) -> FileValidationInfo {
+		let validation_start = self.config.timings.then(Instant::now);
+
+		let (config, file_data) = (self.config, self.file_data);
+
+		let span = (pot_file.end_idx + 1 - pot_file.start_idx) as u64;
+		let oversized = config.max_candidate_span.is_some_and(|max_span| span > max_span);
+
+		if oversized {
+			warn!(
+				"Candidate at {:#0x} (type id {}) spans {} bytes, more than max_candidate_span - It is likely a spurious pairing{}",
+				pot_file.start_idx,
+				pot_file.file_type.type_id,
+				span,
+				if config.skip_oversized_candidates { ", skipping it" } else { "" }
+			);
+		}
+
+		let entropy_in_range = || pot_file.file_type.expected_entropy.is_none_or(|[min, max]| {
+			let end = (pot_file.end_idx + 1).min(file_data.len());
+			(min..=max).contains(&classifiers::shannon_entropy(&file_data[pot_file.start_idx.min(end)..end]))
+		});
+
+		let validation = if oversized && config.skip_oversized_candidates {
+			FileValidationInfo {
+				validation_type: FileValidationType::Unrecognised,
+				rejection_reason: Some(RejectionReason::CandidateTooLarge),
+				..Default::default()
+			}
+		} else if !entropy_in_range() {
+			// Checking the entropy is much cheaper than validating, so candidates that are clearly not of the file type are rejected early
+			FileValidationInfo {
+				validation_type: FileValidationType::Unrecognised,
+				rejection_reason: Some(RejectionReason::EntropyOutOfRange),
+				..Default::default()
+			}
+		} else if let Some(mut validation_cache) = self.validation_cache.take() {
+			let cluster_size = self.type_cluster_size(pot_file);
+			let validation = validation_cache.get_or_validate(file_data, pot_file, cluster_size, || self.validate(pot_file, all_matches));
+			self.validation_cache = Some(validation_cache);
+			validation
+		} else {
+			self.validate(pot_file, all_matches)
+		};
+
+		// The footer of a truncated file is out of range, so the file can't be complete, however much of it up to the max_len of the file type
+		// checks out
+		let validation = if pot_file.truncated && validation.validation_type == FileValidationType::Correct {
+			FileValidationInfo {
+				validation_type: FileValidationType::Partial,
+				rejection_reason: Some(RejectionReason::NoFooterInRange),
+				..validation
+			}
+		} else {
+			validation
+		};
+
+		// Files that fail a checksum or reconstruction may be stored compressed by the filesystem (NTFS), in which case carving them as-is gives
+		// garbage, so flag them as corrupt, with the reason, rather than as e.g. partial
+		let validation = if config.detect_compressed
+			&& validation.rejection_reason.as_ref().is_some_and(RejectionReason::is_checksum_or_reconstruction_failure)
+			&& lznt1::is_possibly_compressed(file_data, pot_file.start_idx + pot_file.file_type.header_offset as usize, self.type_cluster_size(pot_file))
+		{
+			FileValidationInfo {
+				validation_type: FileValidationType::Corrupt,
+				rejection_reason: Some(RejectionReason::PossiblyCompressed),
+				..validation
+			}
+		} else {
+			validation
+		};
+
+		if let Some(validation_start) = validation_start {
+			self.validation_time += validation_start.elapsed();
+		}
+
+		// Candidates that a validator couldn't fully analyse can be configured to land in another bucket, but not those that have no validator
+		match config.unanalysed_as {
+			Some(validation_type) if validation.validation_type == FileValidationType::Unanalysed && validation.rejection_reason != Some(RejectionReason::NoValidator) => {
+				FileValidationInfo {
+					validation_type,
+					..validation
+				}
+			}
+			_ => validation
+		}
+	}
+
+	/// Logs a summary of the candidates validated and adds the time spent validating them to `timings`
+	fn finish(self, timings: &mut PhaseTimings) {
+		if let Some(validation_cache) = self.validation_cache {
+			info!("Reused {} cached validation results", validation_cache.hits());
+		}
+
+		if self.num_timed_out > 0 {
+			warn!("Validation of {} candidates timed out - They have been left running in the background, each holding a worker thread", self.num_timed_out);
+		}
+
+		timings.validation += self.validation_time;
+	}
+}
+
+/// Passes the validated candidates of a region that are recognised to the consumer, keeping the state that spans the candidates of the
+/// region, for `Searchlight::process_region`
+struct CandidateProcessor<'a, 'c> {
+	/// Data that carving has access to - Sliced from the start of the image so offsets stay the same, but bounded by the region end
+	file_data: &'a [u8],
+	config: &'a SearchlightConfig,
+	/// The cluster size of the image, which is overridden by that of the file type of a candidate, if set
+	cluster_size: u64,
+	consumer: &'a mut (dyn FnMut(CarvedFile) -> Result<(), Error> + 'c),
+	rejects: Option<&'a mut RejectsLog>,
+	/// The starts of the files that each header found would begin, which bound the data appended to carved files, if `appended_data` is configured
+	file_starts: Vec<usize>,
+	rejection_counts: HashMap<RejectionReason, usize>,
+	/// The time spent carving
+	carving_time: Duration,
+	num_carved_files: usize
+}
+
+impl<'a, 'c> CandidateProcessor<'a, 'c> {
+	fn new(file_data: &'a [u8], config: &'a SearchlightConfig, cluster_size: u64, consumer: &'a mut (dyn FnMut(CarvedFile) -> Result<(), Error> + 'c), rejects: Option<&'a mut RejectsLog>, file_starts: Vec<usize>) -> Self {
+		CandidateProcessor {
+			file_data,
+			config,
+			cluster_size,
+			consumer,
+			rejects,
+			file_starts,
+			rejection_counts: HashMap::new(),
+			carving_time: Duration::ZERO,
+			num_carved_files: 0
+		}
+	}
+
+	fn type_cluster_size(&self, pot_file: &MatchPair) -> usize {
+		pot_file.file_type.cluster_size.unwrap_or(self.cluster_size) as usize
+	}
+
+	/// Records the header `header` of `file_type`, which was dropped in pairing for `reason` and so is never validated, as a rejected candidate
+	fn reject_unpaired(&mut self, header: &Match, file_type: &FileType, reason: RejectionReason) {
+		*self.rejection_counts.entry(reason).or_insert(0) += 1;
+
+		if let Some(rejects) = self.rejects.as_mut() {
+			rejects.rejects.push(RejectEntry {
+				file_type_id: file_type.type_id,
+				start_idx: header.start_idx.saturating_sub(file_type.header_offset) as usize,
+				end_idx: (header.end_idx + 1) as usize,
+				truncated: false,
+				validation: FileValidationType::Unrecognised,
+				rejection_reason: Some(reason.to_string())
+			});
+		}
+	}
+
+	/// Records the result of validating `pot_file`, passing it to the consumer if it was recognised
+	fn carve(&mut self, pot_file: &MatchPair, validation: FileValidationInfo) -> Result<(), Error> {
+		debug!("Potential file at {}-{} (type id {}) validated as: {}, with fragments {:?}", pot_file.start_idx, pot_file.end_idx + 1, pot_file.file_type.type_id, validation.validation_type, validation.fragments);
+
+		if let Some(reason) = validation.rejection_reason {
+			debug!("Rejection: candidate at {:#0x} (type id {}) validated as {}: {}", pot_file.start_idx, pot_file.file_type.type_id, validation.validation_type, reason);
+
+			*self.rejection_counts.entry(reason).or_insert(0) += 1;
+		}
+
+		if validation.validation_type == FileValidationType::Unrecognised {
+			if let Some(rejects) = self.rejects.as_mut() {
+				rejects.rejects.push(RejectEntry {
+					file_type_id: pot_file.file_type.type_id,
+					start_idx: pot_file.start_idx,
+					end_idx: (pot_file.end_idx + 1).min(self.file_data.len()),
+					truncated: pot_file.truncated,
+					validation: validation.validation_type,
+					rejection_reason: validation.rejection_reason.map(|reason| reason.to_string())
+				});
+			}
+
+			return Ok(());
+		}
+
+		let carving_start = self.config.timings.then(Instant::now);
+
+		let mut validation = validation;
+		if validation.fragments.is_empty() {
+			let end = (pot_file.end_idx + 1).min(self.file_data.len());
+			// Only candidates without a footer span max_len, so the ends of others are known
+			let footerless = pot_file.file_type.footers.is_empty() || pot_file.truncated;
+			let end = match pot_file.file_type.end_heuristic {
+				Some(heuristic) if footerless => Searchlight::heuristic_end(self.file_data, pot_file, end, heuristic, self.type_cluster_size(pot_file)),
+				_ => end
+			};
+
+			validation.fragments = vec![ (pot_file.start_idx..end) ];
+		}
+
+		let mut file = CarvedFile::new(pot_file, validation, self.file_data, self.type_cluster_size(pot_file));
+		if self.config.appended_data && file.validation.validation_type == FileValidationType::Correct {
+			let max_appended_len = self.config.appended_data_max_len.unwrap_or(DEFAULT_APPENDED_DATA_MAX_LEN);
+			file.appended_data = Searchlight::appended_data_range(self.file_data, pot_file, &file.validation.fragments, &self.file_starts, max_appended_len);
+		}
+
+		(self.consumer)(file)?;
+
+		if let Some(carving_start) = carving_start {
+			self.carving_time += carving_start.elapsed();
+		}
+
+		self.num_carved_files += 1;
+
+		Ok(())
+	}
+
+	/// Logs a summary of the candidates rejected and adds the time spent carving them to `timings`, returning the number of files passed to
+	/// the consumer
+	fn finish(self, timings: &mut PhaseTimings) -> usize {
+		if log_enabled!(Level::Debug) {
+			let mut rejection_counts: Vec<_> = self.rejection_counts.into_iter().collect();
+			rejection_counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+			for (reason, count) in rejection_counts {
+				debug!("Rejection summary: {count} candidates: {reason}");
+			}
+		}
+
+		timings.carving += self.carving_time;
+
+		self.num_carved_files
+	}
+}
+
+/// Searches `search_data` in windows of (at most) the searcher's maximum search size, overlapping by the overlap that the searcher requires so
+/// that matches straddling window boundaries are still found. `data_offset` is the offset of `search_data` from the start of the image.
+///
+/// Blocks of `SPARSE_BLOCK_SIZE` bytes that consist entirely of the same byte value, such as zeroed unallocated space, are not searched if
+/// `skippable` (indexed by byte value) indicates no pattern can match within such data, apart from the `max_pat_len` bytes at each edge of a run
+/// of such blocks. Progress is only reported (and the search only logged) if `progress` is Some, in which case it is reported in that mode.
+///
+/// If `checkpoint` is given, the search resumes from it if it has been saved, and it is saved periodically while searching and once the
+/// search is complete.
+///
+/// If `on_searched` is given, it is called with the matches found since it was last called and the offset from the start of the image
+/// before which all matches have been found, each time a block has been searched, while the next block is being searched, and once the
+/// search is complete. Errors that it returns stop the search and are returned
+#[allow(clippy::too_many_arguments)]
+fn search_windowed(searcher: &mut dyn Searcher, search_data: &[u8], data_offset: usize, max_pat_len: usize, skippable: &[bool; 256], progress: Option<ProgressMode>, mut checkpoint: Option<SearchCheckpoint>, mut on_searched: Option<OnSearched>) -> Result<Vec<Match>, Error> {
+	let block_size = searcher.max_search_size().unwrap_or(DEFAULT_BLOCK_SIZE);
+	let overlap = searcher.required_overlap(max_pat_len);
+
+	assert!(overlap < block_size);
+
+	// Matches in progress at the end of anything searched before are not continued, as it may be other data at the same offsets
+	searcher.discard_progress();
+
+	let search_ranges = sparse::non_uniform_ranges(search_data, SPARSE_BLOCK_SIZE, max_pat_len, skippable);
+
+	let num_blocks: usize = search_ranges.iter().map(|range| {
+		let num_blocks = range.len().saturating_sub(overlap) / (block_size - overlap);
+		if range.len() % block_size != 0 {
+			num_blocks + 1
+		} else {
+			num_blocks
+		}
+	}).sum();
+
+	let skipped_len = search_data.len() - search_ranges.iter().map(|range| range.len()).sum::<usize>();
+	if skipped_len > 0 && progress.is_some() {
+		info!("Skipping {} bytes of uniform (e.g. zeroed) data", skipped_len);
+	}
+
+	if progress.is_some() {
+		info!("Starting search phase, searching {} bytes in {} blocks of (at most) {} bytes each", search_data.len() - skipped_len, num_blocks, block_size);
+	}
+
+	let mut reporter = progress.map(|mode| ProgressReporter::new(mode, "Search"));
+
+	// The blocks that were searched before the checkpoint was saved are skipped, as the matches in them are in the checkpoint
+	let (resume_blocks, mut matches) = checkpoint.as_ref().and_then(|checkpoint| checkpoint.load()).unwrap_or((0, Vec::new()));
+
+	if resume_blocks > 0 {
+		info!("Resuming search from checkpoint, skipping {} of {} blocks", resume_blocks.min(num_blocks), num_blocks);
+	}
+
+	// The last dispatched search, along with the window, offset and overlap it was dispatched with
+	let mut result_fut: Option<(SearchFuture, &[u8], u64, usize)> = None;
+	let mut block_num = 0;
+	// The number of matches that have been passed to on_searched
+	let mut num_reported = 0;
+
+	for range in search_ranges {
+		let range_data = &search_data[range.clone()];
+		let range_offset = data_offset + range.start;
+
+		// PERF: Perhaps use a by-block loading method when doing the sequential search and then go back to the memory map for the random-access carving.
+		//       If possible, when using the GPU search impl, write directly into the vulkan-allocated host-side buffer to avoid a memcpy
+		// PERF: Queuing read operations with io_uring might have a more substantial performance improvement for HDDs, as it may be able to reduce the
+		//       amount of disk rotations - but for a single file, would it be any better? Perhaps look into this
+		for (i, window) in range_data.gapped_windows(block_size, block_size - overlap).enumerate() {
+			// The final window may consist entirely of data that overlaps the previous window, and so has already been searched
+			if i != 0 && window.len() <= overlap {
+				break;
+			}
+
+			if block_num < resume_blocks {
+				block_num += 1;
+				continue;
+			}
+
+			// This probably doesn't do a lot but there seems no reason to not have it
+			#[cfg(target_arch = "x86_64")]
+			unsafe { _mm_prefetch::<_MM_HINT_T0>(window.as_ptr() as *const i8) };
+
+			// Searchers that keep state between searches instead of overlapping windows have had their progress discarded, so would miss the
+			// matches straddling the boundary with the skipped blocks. The end of the last skipped block is searched first to pick those back
+			// up, and the matches found in it are dropped, as they are in the checkpoint
+			if block_num == resume_blocks && i != 0 && overlap < max_pat_len {
+				let boundary = i * (block_size - overlap) + overlap;
+				let lead_in = &range_data[boundary.saturating_sub(max_pat_len)..boundary];
+				let lead_in_offset = (range_offset + boundary - lead_in.len()) as u64;
+
+				let result = searcher.search(lead_in, lead_in_offset, 0)?;
+				wait_search(searcher, result, lead_in, lead_in_offset, 0, overlap)?;
+			}
+
+			if let Some((prev_result, prev_window, prev_offset, prev_overlap)) = result_fut.take() {
+				matches.append(&mut wait_search(searcher, prev_result, prev_window, prev_offset, prev_overlap, overlap)?);
+
+				// All blocks before this one have now been searched
+				if let Some(checkpoint) = &mut checkpoint {
+					checkpoint.save(block_num, &matches, false);
+				}
+			}
+			let (window_offset, window_overlap) = {
+				if i == 0 {
+					(range_offset as u64, 0)
+				} else {
+					((range_offset + i * (block_size - overlap)) as u64, overlap)
+				}
+			};
+			let fut = searcher.search(window, window_offset, window_overlap)?;
+			result_fut = Some((fut, window, window_offset, window_overlap));
+
+			// The matches found so far are processed while this window is searched. None of its matches start before it, apart from those
+			// continued from the previous window by searchers that keep state, which start within the last max_pat_len bytes of it
+			if let Some(on_searched) = &mut on_searched {
+				on_searched(&matches[num_reported..], (window_offset + window_overlap as u64).saturating_sub(max_pat_len as u64).min(window_offset))?;
+				num_reported = matches.len();
+			}
+
+			block_num += 1;
+
+			if let Some(reporter) = &mut reporter {
+				reporter.report(block_num as f32 / num_blocks as f32);
+			}
+		}
+	}
+
+	if let Some(reporter) = &mut reporter {
+		reporter.finish();
+	}
+
+	if let Some((result, window, window_offset, window_overlap)) = result_fut.take() {
+		matches.append(&mut wait_search(searcher, result, window, window_offset, window_overlap, overlap)?);
+	}
+
+	if let Some(checkpoint) = &mut checkpoint {
+		checkpoint.save(block_num, &matches, true);
+	}
+
+	if let Some(on_searched) = &mut on_searched {
+		on_searched(&matches[num_reported..], (data_offset + search_data.len()) as u64)?;
+	}
+
+	Ok(matches)
+}
+
+/// Searches the data read from `reader` a block at a time, each block overlapping the previous so that matches straddling the boundary
+/// between them are found. Unlike `search_windowed`, searches are not pipelined, as each block is read into the same buffer. Returns the
+/// matches and the total number of bytes read
+fn search_stream(searcher: &mut dyn Searcher, reader: &mut dyn Read, max_pat_len: usize) -> Result<(Vec<Match>, u64), Error> {
+	let block_size = searcher.max_search_size().unwrap_or(DEFAULT_BLOCK_SIZE);
+	let overlap = searcher.required_overlap(max_pat_len);
+
+	assert!(overlap < block_size);
+
+	searcher.discard_progress();
+
+	let mut matches = Vec::new();
+	let mut block = Vec::with_capacity(block_size);
+	// The offset of the start of the block in the stream, and the number of bytes at the start of the block that were in the previous block
+	let mut block_offset = 0;
+	let mut block_overlap = 0;
+
+	loop {
+		let eof = fill_block(reader, &mut block, block_size)?;
+
+		if block.len() > block_overlap {
+			let result = searcher.search(&block, block_offset, block_overlap)?;
+			matches.append(&mut wait_search(searcher, result, &block, block_offset, block_overlap, overlap)?);
+		}
+
+		if eof {
+			return Ok((matches, block_offset + block.len() as u64));
+		}
+
+		// Keep the end of the block to search again at the start of the next block
+		block_offset += (block.len() - overlap) as u64;
+		block.drain(..(block.len() - overlap));
+		block_overlap = overlap;
+	}
+}
+
+/// Reads from `reader` until `block` is `block_size` bytes long or the end of the data is reached, returning whether the end was reached
+fn fill_block(reader: &mut dyn Read, block: &mut Vec<u8>, block_size: usize) -> Result<bool, Error> {
+	while block.len() < block_size {
+		let len = block.len();
+		block.resize(block_size, 0);
+
+		match reader.read(&mut block[len..]) {
+			Ok(0) => {
+				block.truncate(len);
+				return Ok(true);
+			}
+			Ok(n) => block.truncate(len + n),
+			Err(e) if e.kind() == io::ErrorKind::Interrupted => block.truncate(len),
+			Err(e) => return Err(e.into())
+		}
+	}
+
+	Ok(false)
+}
+
+/// Waits for the result of searching `window`. If the searcher found more matches than it could return, the window is searched again in two
+/// halves (recursively), the second half overlapping the first by `split_overlap` bytes, so that no matches are lost. Errors from the search
+/// are returned, as is the overflow if the window can't be split any further
+fn wait_search(searcher: &mut dyn Searcher, result: SearchFuture, window: &[u8], data_offset: u64, overlap: usize, split_overlap: usize) -> Result<Vec<Match>, Error> {
+	match result.wait() {
+		Ok(matches) => Ok(matches),
+		Err(Error::SearchTimeout(timeout)) if searcher.fall_back() => {
+			warn!("Search of {} bytes at {data_offset:#0x} did not complete within {:.1}s - Falling back to the {} searcher for the rest of the run, starting by searching the same data again", window.len(), timeout.as_secs_f64(), searcher.active_backend());
+
+			// The timed out data is searched again, so that no data is left unsearched
+			let result = searcher.search(window, data_offset, overlap)?;
+			wait_search(searcher, result, window, data_offset, overlap, split_overlap)
+		}
+		Err(Error::SearchOutputOverflow { found, capacity }) => {
+			let mid = overlap + (window.len() - overlap) / 2;
+
+			if mid <= overlap {
+				return Err(Error::SearchOutputOverflow { found, capacity });
+			}
+
+			warn!("Search output overflowed ({found} matches, capacity {capacity}) searching {} bytes at {data_offset:#0x} - Searching again in two halves", window.len());
+
+			let first = &window[..mid];
+			let second_start = mid.saturating_sub(split_overlap);
+			let second = &window[second_start..];
+			let second_offset = data_offset + second_start as u64;
+
+			let first_result = searcher.search(first, data_offset, overlap)?;
+			let mut matches = wait_search(searcher, first_result, first, data_offset, overlap, split_overlap)?;
+
+			let second_result = searcher.search(second, second_offset, mid - second_start)?;
+			matches.append(&mut wait_search(searcher, second_result, second, second_offset, mid - second_start, split_overlap)?);
+
+			Ok(matches)
+		}
+		Err(e) => Err(e)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{collections::HashMap, fs, io::{self, Read}, ops::Deref, panic::{self, AssertUnwindSafe}, path::{Path, PathBuf}, sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex}, thread, time::{Duration, Instant}};
+
+	use rayon::ThreadPool;
+
+	use crate::{error::Error, search::{ac_cpu::AcCpu, match_id_hash_slice_u16, pairing::{MatchPair, MatchPart}, search_common::AcTableBuilder, Match, SearchFuture, Searcher, SearcherKind}, searchlight::config::{EndHeuristic, FileType, FileTypeId, IoStrategy, LogFormat, OffsetUnit, OutputLayout, OverlapPolicy, PairingStrategy, ProgressMode, SearchlightConfig}, utils::{sparse, thread_pool}, validation::{DelegatingValidator, EmbeddedEncoding, EmbeddedFile, FileValidationInfo, FileValidationType, FileValidator, RejectionReason, METADATA_EMBEDDED_NAME, METADATA_OFFSET_NAME, METADATA_ORIGINAL_NAME}};
+
+	use super::{carve_log::{CarveLog, FilenameOffsetUnit, FragmentChecksum, Fragmentation, RejectEntry}, checkpoint::SearchCheckpoint, search_stream, search_windowed, ImageOptions, PhaseTimings, Searchlight, UnmatchedSignature, SPARSE_BLOCK_SIZE};
+
+	/// Wraps AcCpu with a small maximum search size and a larger-than-necessary overlap to exercise the windowing
+	struct SmallWindowSearcher {
+		inner: AcCpu,
+		overlap: usize
+	}
+
+	impl Searcher for SmallWindowSearcher {
+		fn search(&mut self, data: &[u8], data_offset: u64, overlap: usize) -> Result<SearchFuture, Error> {
+			self.inner.search(data, data_offset, overlap)
+		}
+
+		fn max_search_size(&self) -> Option<usize> {
+			Some(16)
+		}
+
+		/// An overlap of 0 relies on the state that the single-threaded AcCpu keeps between searches
+		fn required_overlap(&self, _max_pat_len: usize) -> usize {
+			self.overlap
+		}
+
+		fn discard_progress(&mut self) {
+			self.inner.discard_progress();
+		}
+	}
+
+	#[test]
+	fn test_search_windowed_straddling() {
+		let pattern = &[1u16, 2, 3];
+		let pattern_id = match_id_hash_slice_u16(pattern);
+
+		let mut data = vec![0u8; 64];
+		// Windows are 16 bytes long and start every 8 bytes, so these straddle window boundaries
+		for idx in [ 7, 14, 31, 62 ] {
+			let end = (idx + 3).min(data.len());
+			data[idx..end].copy_from_slice(&[1, 2, 3][..(end - idx)]);
+		}
+
+		for overlap in [ 3, 8 ] {
+			let mut searcher = SmallWindowSearcher {
+				inner: AcCpu::new(AcTableBuilder::new(true).with_pattern(pattern).build()),
+				overlap
+			};
+
+			let mut matches = search_windowed(&mut searcher, &data, 100, pattern.len(), &[ true; 256 ], Some(ProgressMode::Silent), None, None).unwrap();
+			matches.sort_by_key(|m| m.start_idx);
+
+			let expected: Vec<Match> = [ 7, 14, 31 ].into_iter().map(|idx| Match::new(pattern_id, 100 + idx, 100 + idx + 2)).collect();
+
+			assert_eq!(matches, expected);
+		}
+	}
+
+	/// Wraps SmallWindowSearcher, counting the searches and panicking (emulating a crash) on the search after `panic_after`, if set
+	struct InterruptedSearcher {
+		inner: SmallWindowSearcher,
+		searches: usize,
+		panic_after: Option<usize>
+	}
+
+	impl Searcher for InterruptedSearcher {
+		fn search(&mut self, data: &[u8], data_offset: u64, overlap: usize) -> Result<SearchFuture, Error> {
+			if self.panic_after == Some(self.searches) {
+				panic!("Search interrupted");
+			}
+
+			self.searches += 1;
+			self.inner.search(data, data_offset, overlap)
+		}
+
+		fn max_search_size(&self) -> Option<usize> {
+			self.inner.max_search_size()
+		}
+
+		fn required_overlap(&self, max_pat_len: usize) -> usize {
+			self.inner.required_overlap(max_pat_len)
+		}
+
+		fn discard_progress(&mut self) {
+			self.inner.discard_progress();
+		}
+	}
+
+	#[test]
+	fn test_search_windowed_resumes_from_checkpoint() {
+		let pattern = &[1u16, 2, 3];
+
+		let mut data = vec![0u8; 64];
+		for idx in [ 7, 14, 31, 50 ] {
+			data[idx..(idx + 3)].copy_from_slice(&[1, 2, 3]);
+		}
+
+		let test_dir = std::env::temp_dir().join(format!("searchlight_test_search_windowed_resumes_{}", std::process::id()));
+
+		let config = SearchlightConfig {
+			checkpoint_dir: Some(test_dir.to_str().unwrap().to_string()),
+			checkpoint_interval: Some(0),
+			..Default::default()
+		};
+
+		// With no overlap, the search is resumed at 32, so the match at 31 is only found by continuing from the state before it
+		for (overlap, panic_after) in [ (8, 4), (0, 2) ] {
+			let _ = fs::remove_dir_all(&test_dir);
+
+			let searcher = |panic_after| InterruptedSearcher {
+				inner: SmallWindowSearcher {
+					inner: AcCpu::new(AcTableBuilder::new(true).with_pattern(pattern).build()),
+					overlap
+				},
+				searches: 0,
+				panic_after
+			};
+			let checkpoint = || SearchCheckpoint::new(&config, "image.dat", data.len(), 0, data.len(), 16, overlap);
+
+			let mut uninterrupted = searcher(None);
+			let mut expected = search_windowed(&mut uninterrupted, &data, 0, pattern.len(), &[ true; 256 ], Some(ProgressMode::Silent), None, None).unwrap();
+			expected.sort_by_key(|m| m.start_idx);
+
+			assert_eq!(expected.len(), 4);
+
+			// Crash part way through the search, then search again, which resumes from the last checkpoint
+			let mut interrupted = searcher(Some(panic_after));
+			assert!(panic::catch_unwind(AssertUnwindSafe(|| {
+				search_windowed(&mut interrupted, &data, 0, pattern.len(), &[ true; 256 ], Some(ProgressMode::Silent), checkpoint(), None).unwrap()
+			})).is_err());
+
+			let mut resumed = searcher(None);
+			let mut matches = search_windowed(&mut resumed, &data, 0, pattern.len(), &[ true; 256 ], Some(ProgressMode::Silent), checkpoint(), None).unwrap();
+			matches.sort_by_key(|m| m.start_idx);
+
+			assert_eq!(matches, expected);
+			assert!(resumed.searches < uninterrupted.searches);
+		}
+
+		fs::remove_dir_all(&test_dir).unwrap();
+	}
+
+	/// Wraps SmallWindowSearcher, emulating a GPU searcher whose search numbered `timeout_at` times out, and which can fall back to the CPU if
+	/// `can_fall_back` is set
+	struct TimingOutSearcher {
+		inner: SmallWindowSearcher,
+		searches: usize,
+		timeout_at: usize,
+		can_fall_back: bool,
+		fell_back: bool
+	}
+
+	impl Searcher for TimingOutSearcher {
+		fn search(&mut self, data: &[u8], data_offset: u64, overlap: usize) -> Result<SearchFuture, Error> {
+			self.searches += 1;
+
+			if !self.fell_back && self.searches - 1 == self.timeout_at {
+				return Ok(SearchFuture::new(|| Err(Error::SearchTimeout(Duration::from_secs(1)))));
+			}
+
+			self.inner.search(data, data_offset, overlap)
+		}
+
+		fn max_search_size(&self) -> Option<usize> {
+			self.inner.max_search_size()
+		}
+
+		fn required_overlap(&self, max_pat_len: usize) -> usize {
+			self.inner.required_overlap(max_pat_len)
+		}
+
+		fn active_backend(&self) -> SearcherKind {
+			if self.fell_back { SearcherKind::Cpu } else { SearcherKind::Gpu }
+		}
+
+		fn fall_back(&mut self) -> bool {
+			self.fell_back = self.can_fall_back;
+			self.can_fall_back
+		}
+	}
+
+	#[test]
+	fn test_search_windowed_falls_back_on_timeout() {
+		let pattern = &[1u16, 2, 3];
+
+		let mut data = vec![0u8; 64];
+		for idx in [ 7, 14, 31, 50 ] {
+			data[idx..(idx + 3)].copy_from_slice(&[1, 2, 3]);
+		}
+
+		let searcher = |timeout_at, can_fall_back| TimingOutSearcher {
+			inner: SmallWindowSearcher {
+				inner: AcCpu::new(AcTableBuilder::new(true).with_pattern(pattern).build()),
+				overlap: 8
+			},
+			searches: 0,
+			timeout_at,
+			can_fall_back,
+			fell_back: false
+		};
+
+		let mut expected = search_windowed(&mut searcher(usize::MAX, false), &data, 0, pattern.len(), &[ true; 256 ], Some(ProgressMode::Silent), None, None).unwrap();
+		expected.sort_by_key(|m| m.start_idx);
+
+		// The block that timed out is searched again after falling back, so no matches are missed
+		let mut falling_back = searcher(3, true);
+		let mut matches = search_windowed(&mut falling_back, &data, 0, pattern.len(), &[ true; 256 ], Some(ProgressMode::Silent), None, None).unwrap();
+		matches.sort_by_key(|m| m.start_idx);
+
+		assert_eq!(matches, expected);
+		assert_eq!(falling_back.active_backend(), SearcherKind::Cpu);
+
+		// Without falling back, the timeout fails the search
+		assert!(panic::catch_unwind(AssertUnwindSafe(|| {
+			search_windowed(&mut searcher(3, false), &data, 0, pattern.len(), &[ true; 256 ], Some(ProgressMode::Silent), None, None).unwrap()
+		})).is_err());
+	}
+
+	/// Wraps AcCpu, recording the ranges of data that have been searched
+	struct RecordingSearcher {
+		inner: AcCpu,
+		searched: Vec<std::ops::Range<u64>>
+	}
+
+	impl Searcher for RecordingSearcher {
+		fn search(&mut self, data: &[u8], data_offset: u64, overlap: usize) -> Result<SearchFuture, Error> {
+			self.searched.push((data_offset + overlap as u64)..(data_offset + data.len() as u64));
+			self.inner.search(data, data_offset, overlap)
+		}
+	}
+
+	#[test]
+	fn test_search_windowed_skips_uniform() {
+		let patterns: [&[u16]; 2] = [ &[ 0xab, 0x00, 0x00 ], &[ 0x00, 0x00, 0xab, 0xcd ] ];
+
+		// A block of zeros between two blocks of non-uniform data, with the patterns straddling each end of the zeros
+		let mut data: Vec<u8> = (0..(SPARSE_BLOCK_SIZE * 3)).map(|i| (i % 200) as u8 + 1).collect();
+		data[SPARSE_BLOCK_SIZE..(SPARSE_BLOCK_SIZE * 2)].fill(0);
+		data[SPARSE_BLOCK_SIZE - 1] = 0xab;
+		data[(SPARSE_BLOCK_SIZE * 2)..(SPARSE_BLOCK_SIZE * 2 + 2)].copy_from_slice(&[ 0xab, 0xcd ]);
+
+		let mut searcher = RecordingSearcher {
+			inner: AcCpu::new(patterns.iter().fold(AcTableBuilder::new(true), |builder, pat| builder.with_pattern(pat)).build()),
+			searched: Vec::new()
+		};
+
+		let skippable = sparse::skippable_bytes(patterns);
+		assert!(skippable[0x00]);
+
+		let mut matches = search_windowed(&mut searcher, &data, 0, 4, &skippable, Some(ProgressMode::Silent), None, None).unwrap();
+		matches.sort_by_key(|m| m.start_idx);
+
+		let expected = vec![
+			Match::new(match_id_hash_slice_u16(patterns[0]), SPARSE_BLOCK_SIZE as u64 - 1, SPARSE_BLOCK_SIZE as u64 + 1),
+			Match::new(match_id_hash_slice_u16(patterns[1]), SPARSE_BLOCK_SIZE as u64 * 2 - 2, SPARSE_BLOCK_SIZE as u64 * 2 + 1),
+		];
+
+		assert_eq!(matches, expected);
+
+		// The middle of the block of zeros should not have been searched
+		let middle = (SPARSE_BLOCK_SIZE + SPARSE_BLOCK_SIZE / 2) as u64;
+		assert!(!searcher.searched.is_empty());
+		assert!(searcher.searched.iter().all(|range| !range.contains(&middle)));
+	}
+
+	/// Wraps a stateless AcCpu, emulating a searcher that can only return a limited number of matches from each search
+	struct CappedSearcher {
+		inner: AcCpu,
+		capacity: usize
+	}
+
+	impl Searcher for CappedSearcher {
+		fn search(&mut self, data: &[u8], data_offset: u64, overlap: usize) -> Result<SearchFuture, Error> {
+			let matches = self.inner.search(data, data_offset, overlap)?.wait()?;
+			let capacity = self.capacity;
+
+			Ok(SearchFuture::new(move || {
+				if matches.len() > capacity {
+					Err(Error::SearchOutputOverflow { found: matches.len(), capacity })
+				} else {
+					Ok(matches)
+				}
+			}))
+		}
+
+		fn max_search_size(&self) -> Option<usize> {
+			Some(64)
+		}
+	}
+
+	#[test]
+	fn test_search_windowed_output_overflow() {
+		let pattern = &[1u16, 2, 3];
+		let pattern_id = match_id_hash_slice_u16(pattern);
+
+		// Match-dense data, with a match every 5 bytes, so more than the capacity in each 64 byte window
+		let data: Vec<u8> = [ 1, 2, 3, 1, 2 ].repeat(40);
+
+		let mut searcher = CappedSearcher {
+			inner: AcCpu::with_pool(AcTableBuilder::new(true).with_pattern(pattern).build(), thread_pool(2).unwrap()),
+			capacity: 8
+		};
+
+		let mut matches = search_windowed(&mut searcher, &data, 0, pattern.len(), &[ true; 256 ], Some(ProgressMode::Silent), None, None).unwrap();
+		matches.sort_by_key(|m| m.start_idx);
+
+		let expected: Vec<Match> = (0..40).map(|i| Match::new(pattern_id, i * 5, i * 5 + 2)).collect();
+
+		assert_eq!(matches, expected);
+
+		// With no capacity, the windows can't be split small enough for the matches to fit, which is an error rather than a panic
+		searcher.capacity = 0;
+
+		let result = search_windowed(&mut searcher, &data, 0, pattern.len(), &[ true; 256 ], Some(ProgressMode::Silent), None, None);
+
+		assert!(matches!(result, Err(Error::SearchOutputOverflow { capacity: 0, .. })));
+	}
+
+	#[test]
+	fn test_search_stream() {
+		let pattern = &[1u16, 2, 3];
+		let pattern_id = match_id_hash_slice_u16(pattern);
+
+		let data: Vec<u8> = [ 1, 2, 3, 1, 2 ].repeat(40);
+
+		let mut searcher = CappedSearcher {
+			inner: AcCpu::with_pool(AcTableBuilder::new(true).with_pattern(pattern).build(), thread_pool(2).unwrap()),
+			capacity: 8
+		};
+
+		let (mut matches, stream_len) = search_stream(&mut searcher, &mut io::Cursor::new(&data), pattern.len()).unwrap();
+		matches.sort_by_key(|m| m.start_idx);
+
+		let expected: Vec<Match> = (0..40).map(|i| Match::new(pattern_id, i * 5, i * 5 + 2)).collect();
+
+		assert_eq!(matches, expected);
+		assert_eq!(stream_len, data.len() as u64);
+	}
+
+	#[test]
+	fn test_thread_pool() {
+		let searchlight = Searchlight::default();
+		let mut config = SearchlightConfig { threads: Some(2), ..Default::default() };
+
+		// The pool is created once, and kept until a config asks for a different number of threads
+		let pool = searchlight.thread_pool(&config).unwrap();
+		assert_eq!(pool.current_num_threads(), 2);
+		assert!(Arc::ptr_eq(&pool, &searchlight.thread_pool(&config).unwrap()));
+
+		config.threads = Some(3);
+		assert_eq!(searchlight.thread_pool(&config).unwrap().current_num_threads(), 3);
+	}
+
+	#[test]
+	fn test_io_strategy() {
+		// Streamed images are only logged
+		let mut image = vec![ 0u8; 4096 ];
+		image[100..104].copy_from_slice(b"HDR!");
+		image[200..204].copy_from_slice(b"FTR!");
+
+		let test_dir = TestDir::new("io_strategy", &image);
+		let image_path = &test_dir.image_path;
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "HDR!".into() ],
+					footers: vec![ "FTR!".into() ],
+					extension: Some("dat".to_string()),
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024),
+					..Default::default()
+				},
+			],
+			io_strategy: IoStrategy::Stream,
+			..Default::default()
+		};
+
+		let output_dir = test_dir.join("output");
+		let summary = Searchlight::default().process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, None, false, &ImageOptions::default()).unwrap();
+		assert_eq!(summary.num_carved_files, 0);
+		assert!(summary.unmatched_signatures.is_empty());
+
+		let log = CarveLog::read(output_dir.join("log.json")).unwrap();
+		assert_eq!(log.image_path, image_path.to_str().unwrap());
+		assert_eq!(log.files.iter().map(|entry| (entry.validation, entry.fragments.clone())).collect::<Vec<_>>(), vec![ (FileValidationType::Unanalysed, vec![ 100..204 ]) ]);
+		assert!(!output_dir.join("unanalysed").exists());
+
+		// Options that can't be honoured when streaming are rejected
+		let result = Searchlight::default().process_image_file(test_dir.join("region_output").to_str().unwrap(), image_path.to_str().unwrap(), &config, None, false, &ImageOptions { region: Some(0..100), ..Default::default() });
+		assert!(matches!(result, Err(Error::UnsupportedWhenStreaming(_))));
+	}
+
+	#[test]
+	fn test_validate_sample() {
+		let file_type = FileType {
+			headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+			footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+			extension: Some("png".to_string()),
+			type_id: FileTypeId::Png,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let config = SearchlightConfig {
+			file_types: vec![ file_type ],
+			..Default::default()
+		};
+
+		let png = synthetic_png(100);
+
+		let mut searchlight = Searchlight::default();
+
+		let info = searchlight.validate_sample(&png, &config.file_types[0], &config, 1).unwrap();
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ 0..png.len() ]);
+
+		let info = searchlight.validate_sample(&png[..(png.len() - 20)], &config.file_types[0], &config, 1).unwrap();
+		assert_ne!(info.validation_type, FileValidationType::Correct);
+	}
+
+	#[test]
+	#[cfg(not(feature = "gpu"))]
+	fn test_active_backend() {
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					type_id: FileTypeId::Png,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				}
+			],
+			..Default::default()
+		};
+
+		let png = synthetic_png(100);
+
+		let mut searchlight = Searchlight::default();
+		assert_eq!(searchlight.active_backend(), None);
+
+		// Without the gpu feature, searching always falls back to the CPU
+		searchlight.validate_sample(&png, &config.file_types[0], &config, 1).unwrap();
+		assert_eq!(searchlight.active_backend(), Some(SearcherKind::Cpu));
+
+		// Unless the GPU is required, in which case searching fails
+		config.require_gpu = true;
+		let file_type = &config.file_types[0];
+		assert!(matches!(searchlight.validate_sample(&png, file_type, &config, 1), Err(Error::GpuUnavailable(_))));
+	}
+
+	/// The cluster size of the synthetic images that the integration tests carve, unless a test needs another
+	const CLUSTER_SIZE: usize = 512;
+
+	/// A directory in the temporary directory for an integration test, containing the image that the test carves. Derefs to the path of the
+	/// directory, and removes it when dropped, so that it is cleaned up even if the test fails
+	struct TestDir {
+		dir: PathBuf,
+		image_path: PathBuf
+	}
+
+	impl TestDir {
+		/// Creates the directory `searchlight_test_<name>_<pid>`, replacing any left over from a previous run, and writes `image` to
+		/// image.dat in it
+		fn new(name: &str, image: &[u8]) -> Self {
+			let dir = std::env::temp_dir().join(format!("searchlight_test_{name}_{}", std::process::id()));
+			let _ = fs::remove_dir_all(&dir);
+			fs::create_dir_all(&dir).unwrap();
+
+			let image_path = dir.join("image.dat");
+			fs::write(&image_path, image).unwrap();
+
+			TestDir {
+				dir,
+				image_path
+			}
+		}
+	}
+
+	impl Deref for TestDir {
+		type Target = Path;
+
+		fn deref(&self) -> &Path {
+			&self.dir
+		}
+	}
+
+	impl Drop for TestDir {
+		fn drop(&mut self) {
+			let _ = fs::remove_dir_all(&self.dir);
+		}
+	}
+
+	/// Builds a PNG chunk with the given type and data, with a correct CRC
+	fn png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+		let mut chunk = Vec::new();
+		chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+		chunk.extend_from_slice(chunk_type);
+		chunk.extend_from_slice(data);
+		chunk.extend_from_slice(&crc32fast::hash(&chunk[4..]).to_be_bytes());
+		chunk
+	}
+
+	/// Builds a (not decodable, but structurally valid) PNG with an IDAT chunk of `idat_len` bytes
+	fn synthetic_png(idat_len: usize) -> Vec<u8> {
+		let mut png = b"\x89PNG\x0d\x0a\x1a\x0a".to_vec();
+		png.append(&mut png_chunk(b"IHDR", &[ 0, 0, 0, 16, 0, 0, 0, 16, 8, 2, 0, 0, 0 ]));
+		png.append(&mut png_chunk(b"IDAT", &(0..idat_len).map(|i| (i % 251) as u8).collect::<Vec<u8>>()));
+		png.append(&mut png_chunk(b"IEND", &[]));
+		png
+	}
+
+	/// Builds a JPEG with the mandatory segments and `scan_len` bytes of scan data
+	fn synthetic_jpeg(scan_len: usize) -> Vec<u8> {
+		let mut jpeg = vec![ 0xff, 0xd8 ];
+		jpeg.extend_from_slice(&[ 0xff, 0xe0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0x00, 0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00 ]);
+		jpeg.extend_from_slice(&[ 0xff, 0xc0, 0x00, 0x11, 0x08, 0x00, 0x10, 0x00, 0x10, 0x03, 0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01 ]);
+		jpeg.extend_from_slice(&[ 0xff, 0xda, 0x00, 0x0c, 0x03, 0x01, 0x00, 0x02, 0x11, 0x03, 0x11, 0x00, 0x3f, 0x00 ]);
+		jpeg.extend((0..scan_len).map(|i| (i % 251) as u8)); // Never contains 0xff
+		jpeg.extend_from_slice(&[ 0xff, 0xd9 ]);
+		jpeg
+	}
+
+	/// Builds a ZIP containing a single stored file
+	fn synthetic_zip(file_name: &[u8], content: &[u8]) -> Vec<u8> {
+		let crc = crc32fast::hash(content).to_le_bytes();
+		let size = (content.len() as u32).to_le_bytes();
+		let name_len = (file_name.len() as u16).to_le_bytes();
+
+		let mut zip = Vec::new();
+
+		zip.extend_from_slice(&[ 0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 ]);
+		zip.extend_from_slice(&crc);
+		zip.extend_from_slice(&size);
+		zip.extend_from_slice(&size);
+		zip.extend_from_slice(&name_len);
+		zip.extend_from_slice(&[ 0x00, 0x00 ]);
+		zip.extend_from_slice(file_name);
+		zip.extend_from_slice(content);
+
+		let cd_offset = zip.len() as u32;
+
+		zip.extend_from_slice(&[ 0x50, 0x4b, 0x01, 0x02, 0x14, 0x00, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 ]);
+		zip.extend_from_slice(&crc);
+		zip.extend_from_slice(&size);
+		zip.extend_from_slice(&size);
+		zip.extend_from_slice(&name_len);
+		zip.extend_from_slice(&[ 0x00; 16 ]); // Extra field len, comment len, disk number, attributes, and local file header offset (0)
+		zip.extend_from_slice(file_name);
+
+		let cd_size = zip.len() as u32 - cd_offset;
+
+		zip.extend_from_slice(&[ 0x50, 0x4b, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00 ]);
+		zip.extend_from_slice(&cd_size.to_le_bytes());
+		zip.extend_from_slice(&cd_offset.to_le_bytes());
+		zip.extend_from_slice(&[ 0x00, 0x00 ]);
+
+		zip
+	}
+
+	#[test]
+	fn test_process_log_file_skips_failures() {
+		let image: Vec<u8> = (0..=255).collect();
+
+		let test_dir = TestDir::new("process_log_file", &image);
+		let image_path = &test_dir.image_path;
+
+		let mut log = CarveLog::new(image_path.to_str().unwrap());
+		log.add_entry(FileTypeId::Png, "out_of_range.png".to_string(), FileValidationType::Correct, vec![ 200..300 ], HashMap::new());
+		log.add_entry(FileTypeId::Png, "bad\0name.png".to_string(), FileValidationType::Correct, vec![ 0..16 ], HashMap::new());
+		log.add_entry(FileTypeId::Png, "good.png".to_string(), FileValidationType::Correct, vec![ 16..32, 64..80 ], HashMap::new());
+		log.add_entry(FileTypeId::Png, "../escaped.png".to_string(), FileValidationType::Correct, vec![ 0..16 ], HashMap::new());
+		log.files.last_mut().unwrap().directory = Some("../../escaped".to_string());
+		log.write(test_dir.to_str().unwrap(), LogFormat::Json).unwrap();
+
+		let output_dir = test_dir.join("output");
+
+		let mut searchlight = Searchlight::default();
+		searchlight.process_log_file(output_dir.to_str().unwrap(), test_dir.join("log.json").to_str().unwrap()).unwrap();
+
+		let correct_dir = output_dir.join(FileValidationType::Correct.to_string());
+		assert_eq!(fs::read(correct_dir.join("good.png")).unwrap(), [ &image[16..32], &image[64..80] ].concat());
+		assert!(!correct_dir.join("out_of_range.png").exists());
+
+		// Paths in the log can't refer to outside of the output directory
+		assert_eq!(fs::read(output_dir.join("escaped").join("escaped.png")).unwrap(), &image[0..16]);
+		assert!(!test_dir.join("escaped.png").exists());
+	}
+
+	#[test]
+	fn test_carve_synthetic_image() {
+		let png = synthetic_png(100);
+		let fragmented_png = synthetic_png(1500);
+		let jpeg = synthetic_jpeg(1000);
+		let zip = synthetic_zip(b"a.txt", &[ b'a'; 100 ]);
+
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 16 ];
+
+		let png_idx = CLUSTER_SIZE;
+		image[png_idx..(png_idx + png.len())].copy_from_slice(&png);
+
+		// Split the fragmented PNG into two fragments, with an unrelated cluster in between
+		let frag_png_idx = CLUSTER_SIZE * 4;
+		let frag_png_split = CLUSTER_SIZE * 2;
+		let frag_png_gap = frag_png_idx + frag_png_split;
+		image[frag_png_idx..frag_png_gap].copy_from_slice(&fragmented_png[..frag_png_split]);
+		image[frag_png_gap..(frag_png_gap + CLUSTER_SIZE)].fill(0xaa);
+		let frag_png_end = frag_png_gap + CLUSTER_SIZE + fragmented_png.len() - frag_png_split;
+		image[(frag_png_gap + CLUSTER_SIZE)..frag_png_end].copy_from_slice(&fragmented_png[frag_png_split..]);
+
+		let jpeg_idx = CLUSTER_SIZE * 9;
+		image[jpeg_idx..(jpeg_idx + jpeg.len())].copy_from_slice(&jpeg);
+
+		let zip_idx = CLUSTER_SIZE * 13;
+		image[zip_idx..(zip_idx + zip.len())].copy_from_slice(&zip);
+
+		let test_dir = TestDir::new("carve_synthetic_image", &image);
+		let image_path = &test_dir.image_path;
+		let output_dir = test_dir.join("output");
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\xff\\xd8\\xff\\xe0".into() ],
+					extension: Some("jpg".to_string()),
+					type_id: FileTypeId::Jpeg,
+					pairing: PairingStrategy::PairLast,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "\\x50\\x4B\\x03\\x04".into() ],
+					footers: vec![ "\\x50\\x4B\\x05\\x06..................".into() ],
+					extension: Some("zip".to_string()),
+					type_id: FileTypeId::Zip,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024 * 1024),
+					requires_footer: true,
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		assert!(config.validate().is_ok());
+
+		let mut searchlight = Searchlight::default();
+		searchlight.process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), false, &ImageOptions::default()).unwrap();
+
+		let log: CarveLog = serde_json::from_slice(&fs::read(output_dir.join("log.json")).unwrap()).unwrap();
+
+		let mut carved: Vec<_> = log.files.iter().map(|entry| (entry.file_type_id, &entry.validation, entry.fragments.clone())).collect();
+		carved.sort_by_key(|(_, _, fragments)| fragments[0].start);
+
+		let expected = vec![
+			(FileTypeId::Png, &FileValidationType::Correct, vec![ png_idx..(png_idx + png.len()) ]),
+			(FileTypeId::Png, &FileValidationType::Correct, vec![ frag_png_idx..frag_png_gap, (frag_png_gap + CLUSTER_SIZE)..frag_png_end ]),
+			(FileTypeId::Jpeg, &FileValidationType::Correct, vec![ jpeg_idx..(jpeg_idx + jpeg.len()) ]),
+			(FileTypeId::Zip, &FileValidationType::Correct, vec![ zip_idx..(zip_idx + zip.len()) ]),
+		];
+
+		assert_eq!(carved, expected);
+
+		// Check that the carved files match the data that was embedded
+		assert_eq!(fs::read(output_dir.join("correct").join(format!("{}-{}.png", frag_png_idx, frag_png_end))).unwrap(), fragmented_png);
+		assert_eq!(fs::read(output_dir.join("correct").join(format!("{}-{}.zip", zip_idx, zip_idx + zip.len()))).unwrap(), zip);
+
+		// Consuming the carved files directly should give the same files and content, without writing anything
+		let mut consumed = Vec::new();
+		let num_consumed = searchlight.carve_image_file(image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+			let mut content = Vec::new();
+			file.reader().read_to_end(&mut content)?;
+			consumed.push((file.file_match.file_type.type_id, file.validation.validation_type, file.validation.fragments[0].start, content));
+			Ok(())
+		}).unwrap();
+
+		assert_eq!(num_consumed, expected.len());
+
+		consumed.sort_by_key(|(_, _, start, _)| *start);
+
+		assert_eq!(consumed[1], (FileTypeId::Png, FileValidationType::Correct, frag_png_idx, fragmented_png));
+		assert_eq!(consumed[3], (FileTypeId::Zip, FileValidationType::Correct, zip_idx, zip));
+	}
+
+	#[test]
+	fn test_extract_embedded() {
+		let content = b"embedded file content".repeat(10);
+		let zip = synthetic_zip(b"dir/a.txt", &content);
+
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 4 ];
+		let zip_idx = CLUSTER_SIZE;
+		image[zip_idx..(zip_idx + zip.len())].copy_from_slice(&zip);
+
+		let test_dir = TestDir::new("extract_embedded", &image);
+		let image_path = &test_dir.image_path;
+		let output_dir = test_dir.join("output");
+
+		let config = SearchlightConfig {
+			extract_embedded: true,
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x50\\x4B\\x03\\x04".into() ],
+					footers: vec![ "\\x50\\x4B\\x05\\x06..................".into() ],
+					extension: Some("zip".to_string()),
+					type_id: FileTypeId::Zip,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024 * 1024),
+					requires_footer: true,
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		let mut searchlight = Searchlight::default();
+		searchlight.process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), false, &ImageOptions::default()).unwrap();
+
+		let log = CarveLog::read(output_dir.join("log.json")).unwrap();
+
+		assert_eq!(log.files.len(), 1);
+
+		// The member is recorded under the archive, with the path in its name flattened
+		let zip_filename = format!("{}-{}.zip", zip_idx, zip_idx + zip.len());
+		let member = &log.files[0].members[0];
+
+		assert_eq!(log.files[0].members.len(), 1);
+		assert_eq!(member.name, "dir/a.txt");
+		assert_eq!(member.filename, format!("{zip_filename}_members/0_dir_a.txt"));
+		assert_eq!(member.validation, FileValidationType::Correct);
+		assert_eq!(member.encoding, EmbeddedEncoding::Stored);
+
+		assert_eq!(fs::read(output_dir.join("correct").join(&member.filename)).unwrap(), content);
+
+		// Members are extracted again when carving from the log
+		let log_output_dir = test_dir.join("log_output");
+		searchlight.process_log_file(log_output_dir.to_str().unwrap(), output_dir.join("log.json").to_str().unwrap()).unwrap();
+
+		assert_eq!(fs::read(log_output_dir.join("correct").join(&member.filename)).unwrap(), content);
+
+		// With embedded_entries, the member is instead recorded as an entry of its own, referencing the archive as its parent
+		let entries_output_dir = test_dir.join("entries_output");
+		let config = SearchlightConfig {
+			embedded_entries: true,
+			..config
+		};
+
+		searchlight.process_image_file(entries_output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), false, &ImageOptions::default()).unwrap();
+
+		let mut log = CarveLog::read(entries_output_dir.join("log.json")).unwrap();
+
+		assert_eq!(log.files.len(), 2);
+		assert!(log.files[0].members.is_empty());
+		assert_eq!(log.files[1].filename, member.filename);
+		assert_eq!(log.files[1].parent, Some(format!("correct/{zip_filename}")));
+		assert_eq!(log.files[1].encoding, Some(EmbeddedEncoding::Stored));
+		assert_eq!(log.files[1].metadata.get(METADATA_EMBEDDED_NAME).map(String::as_str), Some("dir/a.txt"));
+
+		assert_eq!(fs::read(entries_output_dir.join("correct").join(&member.filename)).unwrap(), content);
+
+		// Children are carved from the log along with their parents, even if listed before them, but not if their parent fails to be carved
+		log.files.reverse();
+		log.write_to(&test_dir.join("entries_log.json"), LogFormat::Json).unwrap();
+
+		let log_output_dir = test_dir.join("entries_log_output");
+		searchlight.process_log_file(log_output_dir.to_str().unwrap(), test_dir.join("entries_log.json").to_str().unwrap()).unwrap();
+
+		assert_eq!(fs::read(log_output_dir.join("correct").join(&zip_filename)).unwrap(), zip);
+		assert_eq!(fs::read(log_output_dir.join("correct").join(&member.filename)).unwrap(), content);
+
+		log.files[1].fragments = vec![ 0..(image.len() + 1) ];
+		log.write_to(&test_dir.join("orphan_log.json"), LogFormat::Json).unwrap();
+
+		let log_output_dir = test_dir.join("orphan_log_output");
+		searchlight.process_log_file(log_output_dir.to_str().unwrap(), test_dir.join("orphan_log.json").to_str().unwrap()).unwrap();
+
+		assert!(!log_output_dir.join("correct").join(&member.filename).exists());
+
+		// Without extract_embedded, no members are found
+		let config = SearchlightConfig {
+			extract_embedded: false,
+			embedded_entries: false,
+			..config
+		};
+
+		searchlight.carve_image_file(image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+			assert!(file.validation.embedded.is_empty());
+			Ok(())
+		}).unwrap();
+	}
+
+	/// Validates with the default validator, adding an original name, and embedded files with the given names that each consist of the
+	/// first 16 bytes of the file
+	struct NamingValidator {
+		inner: DelegatingValidator,
+		original_name: &'static str,
+		embedded_names: &'static [&'static str]
+	}
+
+	impl FileValidator for NamingValidator {
+		fn validate(&self, file_data: &[u8], file_match: &MatchPair, all_matches: &[Match], cluster_size: usize, config: &SearchlightConfig) -> FileValidationInfo {
+			let mut info = self.inner.validate(file_data, file_match, all_matches, cluster_size, config);
+
+			info.metadata.insert(METADATA_ORIGINAL_NAME.to_string(), self.original_name.to_string());
+			info.embedded = self.embedded_names.iter().map(|name| EmbeddedFile {
+				name: name.to_string(),
+				validation_type: FileValidationType::Correct,
+				encoding: EmbeddedEncoding::Stored,
+				fragments: vec![ file_match.start_idx..(file_match.start_idx + 16) ]
+			}).collect();
+
+			info
+		}
+	}
+
+	#[test]
+	fn test_original_names() {
+		let png = synthetic_png(100);
+
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 4 ];
+		for png_idx in [ 0, CLUSTER_SIZE * 2 ] {
+			image[png_idx..(png_idx + png.len())].copy_from_slice(&png);
+		}
+
+		let test_dir = TestDir::new("original_names", &image);
+		let image_path = &test_dir.image_path;
+		let output_dir = test_dir.join("output");
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(CLUSTER_SIZE as u64),
+					..Default::default()
+				},
+			],
+			original_names: true,
+			..Default::default()
+		};
+
+		let process = |config: &SearchlightConfig, output_dir: &PathBuf| {
+			let validator = NamingValidator {
+				inner: DelegatingValidator::new(),
+				original_name: "../photos/holiday.png",
+				embedded_names: &[ "dir/a.txt", "../dir/a.txt", "dir", "", "b.txt" ]
+			};
+
+			let mut searchlight = Searchlight::new(Box::new(validator), |config: &SearchlightConfig, _: &Arc<ThreadPool>| {
+				let ac_table = AcTableBuilder::build_from_config(config);
+
+				Ok((
+					Box::new(AcCpu::new(ac_table.clone())) as Box<dyn Searcher>,
+					ac_table.max_pat_len as usize
+				))
+			});
+
+			searchlight.process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), config, Some(CLUSTER_SIZE as u64), false, &ImageOptions::default()).unwrap();
+
+			CarveLog::read(output_dir.join("log.json")).unwrap()
+		};
+
+		let log = process(&config, &output_dir);
+		let offset_names = [ format!("0-{}.png", png.len()), format!("{}-{}.png", CLUSTER_SIZE * 2, CLUSTER_SIZE * 2 + png.len()) ];
+
+		// The first file is named by its sanitised original name, recording the name it would have had, and the second, whose original name
+		// is the same, keeps its usual name
+		assert_eq!(log.files[0].filename, "photos/holiday.png");
+		assert_eq!(log.files[0].metadata.get(METADATA_ORIGINAL_NAME).map(String::as_str), Some("../photos/holiday.png"));
+		assert_eq!(log.files[0].metadata.get(METADATA_OFFSET_NAME), Some(&offset_names[0]));
+		assert_eq!(log.files[1].filename, offset_names[1]);
+		assert_eq!(log.files[1].metadata.get(METADATA_OFFSET_NAME), None);
+
+		assert_eq!(fs::read(output_dir.join("correct").join("photos").join("holiday.png")).unwrap(), png);
+		assert_eq!(fs::read(output_dir.join("correct").join(&offset_names[1])).unwrap(), png);
+
+		// Embedded files are extracted to their sanitised paths under the members directory, apart from those without a name, or whose path
+		// clashes with that of another member or one of its directories, which keep their usual names
+		let members: Vec<_> = log.files[0].members.iter().map(|member| (member.name.as_str(), member.filename.as_str())).collect();
+
+		assert_eq!(members, vec![
+			("dir/a.txt", "photos/holiday.png_members/dir/a.txt"),
+			("../dir/a.txt", "photos/holiday.png_members/1_.._dir_a.txt"),
+			("dir", "photos/holiday.png_members/2_dir"),
+			("", "photos/holiday.png_members/3_"),
+			("b.txt", "photos/holiday.png_members/b.txt"),
+		]);
+
+		for member in &log.files[0].members {
+			assert_eq!(fs::read(output_dir.join("correct").join(&member.filename)).unwrap(), &png[..16]);
+		}
+
+		// Without original_names, the names recovered are only recorded
+		config.original_names = false;
+
+		let log = process(&config, &test_dir.join("offset_output"));
+
+		assert_eq!(log.files[0].filename, offset_names[0]);
+		assert_eq!(log.files[0].metadata.get(METADATA_OFFSET_NAME), None);
+		assert_eq!(log.files[0].members[0].filename, format!("{}_members/0_dir_a.txt", offset_names[0]));
+	}
+
+	#[test]
+	fn test_targeted_footer_search() {
+		const IEND: &[u8] = b"IEND\xae\x42\x60\x82";
+
+		let png = synthetic_png(100);
+		let fragmented_png = synthetic_png(1500);
+		let zip = synthetic_zip(b"a.txt", &[ b'a'; 100 ]);
+
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 16 ];
+
+		// Stray footers before any header and out of range of every header, which are only found by the single pass search
+		image[100..(100 + IEND.len())].copy_from_slice(IEND);
+		image[(CLUSTER_SIZE * 15)..(CLUSTER_SIZE * 15 + IEND.len())].copy_from_slice(IEND);
+
+		let png_idx = CLUSTER_SIZE;
+		image[png_idx..(png_idx + png.len())].copy_from_slice(&png);
+
+		let frag_png_idx = CLUSTER_SIZE * 4;
+		let frag_png_gap = frag_png_idx + CLUSTER_SIZE * 2;
+		image[frag_png_idx..frag_png_gap].copy_from_slice(&fragmented_png[..(CLUSTER_SIZE * 2)]);
+		image[frag_png_gap..(frag_png_gap + CLUSTER_SIZE)].fill(0xaa);
+		let frag_png_end = frag_png_gap + CLUSTER_SIZE + fragmented_png.len() - CLUSTER_SIZE * 2;
+		image[(frag_png_gap + CLUSTER_SIZE)..frag_png_end].copy_from_slice(&fragmented_png[(CLUSTER_SIZE * 2)..]);
+
+		let zip_idx = CLUSTER_SIZE * 10;
+		image[zip_idx..(zip_idx + zip.len())].copy_from_slice(&zip);
+
+		let test_dir = TestDir::new("targeted_footer_search", &image);
+		let image_path = &test_dir.image_path;
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(CLUSTER_SIZE as u64 * 6),
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "\\x50\\x4B\\x03\\x04".into() ],
+					footers: vec![ "\\x50\\x4B\\x05\\x06..................".into() ],
+					extension: Some("zip".to_string()),
+					type_id: FileTypeId::Zip,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(CLUSTER_SIZE as u64 * 2),
+					requires_footer: true,
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		let carve = |config: &SearchlightConfig| {
+			let mut carved = Vec::new();
+			Searchlight::default().carve_image_file(image_path.to_str().unwrap(), config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+				carved.push((file.file_match.file_type.type_id, file.validation.validation_type, file.validation.fragments.clone()));
+				Ok(())
+			}).unwrap();
+			carved
+		};
+
+		let single_pass = carve(&config);
+
+		config.targeted_footer_search = true;
+		let targeted = carve(&config);
+
+		assert_eq!(single_pass.len(), 3);
+		assert_eq!(targeted, single_pass);
+
+		// The file types that the config filters out aren't carved
+		let mut filtered_config = config.clone();
+		filtered_config.exclude_types = vec![ "zip".to_string() ];
+		let filtered = carve(&filtered_config);
+
+		assert!(!filtered.is_empty());
+		assert_eq!(filtered, single_pass.iter().filter(|(type_id, _, _)| *type_id != FileTypeId::Zip).cloned().collect::<Vec<_>>());
+
+		// Neither pass searches for the footers that are only searched for in the other
+		let (first_pass_config, second_pass_config) = config.targeted_search_configs();
+		assert!(first_pass_config.file_types.iter().all(|ft| ft.footers.is_empty()));
+		assert_eq!(second_pass_config.file_types.len(), 2);
+		assert!(second_pass_config.file_types.iter().all(|ft| ft.headers.is_empty()));
+	}
+
+	/// Records the cluster size that it is called with for each file type
+	struct ClusterSizeRecorder {
+		calls: Arc<Mutex<Vec<(FileTypeId, usize)>>>
+	}
+
+	impl FileValidator for ClusterSizeRecorder {
+		fn validate(&self, _file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+			self.calls.lock().unwrap().push((file_match.file_type.type_id, cluster_size));
+
+			FileValidationInfo::default()
+		}
+	}
+
+	#[test]
+	fn test_file_type_cluster_size() {
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 4 ];
+		image[..8].copy_from_slice(b"\x89PNG\r\n\x1a\n");
+		image[(CLUSTER_SIZE * 2)..(CLUSTER_SIZE * 2 + 4)].copy_from_slice(b"PK\x03\x04");
+
+		let test_dir = TestDir::new("file_type_cluster_size", &image);
+		let image_path = &test_dir.image_path;
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					type_id: FileTypeId::Png,
+					max_len: Some(64),
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "\\x50\\x4B\\x03\\x04".into() ],
+					type_id: FileTypeId::Zip,
+					max_len: Some(64),
+					cluster_size: Some(1),
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		assert!(config.validate().is_ok());
+
+		let calls = Arc::new(Mutex::new(Vec::new()));
+		let mut searchlight = Searchlight::new(Box::new(ClusterSizeRecorder { calls: calls.clone() }), |config: &SearchlightConfig, _: &Arc<ThreadPool>| {
+			let ac_table = AcTableBuilder::build_from_config(config);
+
+			Ok((
+				Box::new(AcCpu::new(ac_table.clone())) as Box<dyn Searcher>,
+				ac_table.max_pat_len as usize
+			))
+		});
+
+		searchlight.carve_image_file(image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), None, false, |_| Ok(())).unwrap();
+
+		// The ZIP validator is given the cluster size of the type, and the PNG validator that of the image
+		assert_eq!(*calls.lock().unwrap(), vec![ (FileTypeId::Png, CLUSTER_SIZE), (FileTypeId::Zip, 1) ]);
+	}
+
+	#[test]
+	fn test_catch_validator_panics() {
+		let png = synthetic_png(1000);
+
+		// A ZIP whose EOCD has a malformed signature, which is only matched by a shorter footer, so the ZIP validator reads the EOCD from the
+		// wrong place and panics on its signature check
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 8 ];
+		image[..4].copy_from_slice(b"PK\x03\x04");
+		image[200..204].copy_from_slice(b"PK\x05\x07");
+
+		let png_idx = CLUSTER_SIZE * 2;
+		image[png_idx..(png_idx + png.len())].copy_from_slice(&png);
+
+		let test_dir = TestDir::new("catch_validator_panics", &image);
+		let image_path = &test_dir.image_path;
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "\\x50\\x4B\\x03\\x04".into() ],
+					footers: vec![ "\\x50\\x4B\\x05\\x06..................".into(), "\\x50\\x4B\\x05\\x07".into() ],
+					extension: Some("zip".to_string()),
+					type_id: FileTypeId::Zip,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024 * 1024),
+					requires_footer: true,
+					..Default::default()
+				},
+			],
+			catch_validator_panics: true,
+			..Default::default()
+		};
+
+		let mut searchlight = Searchlight::default();
+
+		let mut carved = Vec::new();
+		let num_carved = searchlight.carve_image_file(image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+			carved.push((file.file_match.file_type.type_id, file.validation.validation_type));
+			Ok(())
+		}).unwrap();
+
+		// The ZIP is skipped, and the carve carries on to the PNG
+		assert_eq!(num_carved, 1);
+		assert_eq!(carved, vec![ (FileTypeId::Png, FileValidationType::Correct) ]);
+	}
+
+	/// Validates with the default validator, but effectively hangs on the candidate starting at `slow_start`
+	struct SlowValidator {
+		inner: DelegatingValidator,
+		slow_start: usize
+	}
+
+	impl FileValidator for SlowValidator {
+		fn validate(&self, file_data: &[u8], file_match: &MatchPair, all_matches: &[Match], cluster_size: usize, config: &SearchlightConfig) -> FileValidationInfo {
+			if file_match.start_idx == self.slow_start {
+				std::thread::sleep(Duration::from_secs(60));
+			}
+
+			self.inner.validate(file_data, file_match, all_matches, cluster_size, config)
+		}
+	}
+
+	#[test]
+	fn test_validation_timeout() {
+		let png = synthetic_png(100);
+
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 8 ];
+		for png_idx in [ 0, CLUSTER_SIZE * 2, CLUSTER_SIZE * 4 ] {
+			image[png_idx..(png_idx + png.len())].copy_from_slice(&png);
+		}
+
+		let test_dir = TestDir::new("validation_timeout", &image);
+		let image_path = &test_dir.image_path;
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(CLUSTER_SIZE as u64),
+					..Default::default()
+				},
+			],
+			validation_timeout_ms: Some(50),
+			// The hung validation holds one of the threads until it finishes, leaving the other for the rest
+			threads: Some(2),
+			..Default::default()
+		};
+
+		assert!(config.validate().is_ok());
+
+		for pipelined_validation in [ false, true ] {
+			config.pipelined_validation = pipelined_validation;
+
+			let validator = SlowValidator { inner: DelegatingValidator::new(), slow_start: CLUSTER_SIZE * 2 };
+			let mut searchlight = Searchlight::new(Box::new(validator), |config: &SearchlightConfig, _: &Arc<ThreadPool>| {
+				let ac_table = AcTableBuilder::build_from_config(config);
+
+				Ok((
+					Box::new(AcCpu::new(ac_table.clone())) as Box<dyn Searcher>,
+					ac_table.max_pat_len as usize
+				))
+			});
+
+			let carve_start = Instant::now();
+
+			let mut carved = Vec::new();
+			searchlight.carve_image_file(image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+				carved.push((file.file_match.start_idx, file.validation.validation_type, file.validation.rejection_reason));
+				Ok(())
+			}).unwrap();
+
+			// The hung validation is abandoned rather than waited for
+			assert!(carve_start.elapsed() < Duration::from_secs(30));
+
+			// The slow candidate is carved unanalysed, and the others are validated as usual
+			assert_eq!(carved, vec![
+				(0, FileValidationType::Correct, None),
+				(CLUSTER_SIZE * 2, FileValidationType::Unanalysed, Some(RejectionReason::ValidationTimedOut)),
+				(CLUSTER_SIZE * 4, FileValidationType::Correct, None),
+			]);
+		}
+	}
+
+	#[test]
+	fn test_unmatched_signatures() {
+		let mut image = vec![ 0xaa; 1024 ];
+		image[100..104].copy_from_slice(b"HDR!");
+		image[200..204].copy_from_slice(b"FTR!");
+
+		let test_dir = TestDir::new("unmatched_signatures", &image);
+		let image_path = &test_dir.image_path;
+		let output_dir = test_dir.join("output");
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "HDR!".into(), "HDR?".into() ],
+					footers: vec![ "FTR!".into() ],
+					extension: Some("dat".to_string()),
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024),
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "NONE".into() ],
+					footers: vec![ "ENON".into() ],
+					extension: Some("none".to_string()),
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024),
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		let summary = Searchlight::default().process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(1), true, &ImageOptions::default()).unwrap();
+
+		assert_eq!(summary.num_carved_files, 1);
+		assert_eq!(summary.unmatched_signatures, vec![
+			UnmatchedSignature { file_type_idx: 0, part: MatchPart::Header, signature: "HDR?".into() },
+			UnmatchedSignature { file_type_idx: 1, part: MatchPart::Header, signature: "NONE".into() },
+			UnmatchedSignature { file_type_idx: 1, part: MatchPart::Footer, signature: "ENON".into() },
+		]);
+	}
+
+	#[test]
+	fn test_carve_slack() {
+		let png = synthetic_png(100);
+
+		let mut image = vec![ 0xaa; CLUSTER_SIZE ];
+		image.extend_from_slice(&png);
+		image.resize(CLUSTER_SIZE * 3, 0x55);
+
+		let png_end = CLUSTER_SIZE + png.len();
+
+		let test_dir = TestDir::new("carve_slack", &image);
+		let image_path = &test_dir.image_path;
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				}
+			],
+			carve_slack: true,
+			fragmentation_stats: true,
+			fragment_checksums: true,
+			..Default::default()
+		};
+
+		let output_dir = test_dir.join("output");
+		Searchlight::default().process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), false, &ImageOptions::default()).unwrap();
+
+		// The slack is the rest of the cluster that the file ends in
+		let log = CarveLog::read(output_dir.join("log.json")).unwrap();
+		assert_eq!(log.files.len(), 1);
+		assert_eq!(log.files[0].slack, Some(png_end..(CLUSTER_SIZE * 2)));
+		assert_eq!(log.files[0].fragment_checksums, vec![ FragmentChecksum { file_offset: 0, crc32: crc32fast::hash(&png) } ]);
+
+		let slack_path = output_dir.join("correct").join(format!("{}.slack", log.files[0].filename));
+		assert_eq!(fs::read(&slack_path).unwrap(), &image[png_end..(CLUSTER_SIZE * 2)]);
+
+		// The slack is carved again when carving from the log
+		let log_output_dir = test_dir.join("log_output");
+		Searchlight::default().process_log_file(log_output_dir.to_str().unwrap(), output_dir.join("log.json").to_str().unwrap()).unwrap();
+		assert_eq!(fs::read(log_output_dir.join("correct").join(format!("{}.slack", log.files[0].filename))).unwrap(), &image[png_end..(CLUSTER_SIZE * 2)]);
+
+		// Unaligned data has no slack
+		let unaligned_output_dir = test_dir.join("unaligned_output");
+		let summary = Searchlight::default().process_image_file(unaligned_output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(1), false, &ImageOptions::default()).unwrap();
+
+		let log = CarveLog::read(unaligned_output_dir.join("log.json")).unwrap();
+		assert_eq!(log.files[0].slack, None);
+
+		// The fragmentation of the file is recorded and summarised, as configured
+		assert_eq!(log.files[0].fragmentation, Some(Fragmentation { fragment_count: 1, gap_bytes: 0 }));
+		assert_eq!(summary.fragmentation.map(|fragmentation| (fragmentation.num_files, fragmentation.max_fragment_count)), Some((1, 1)));
+	}
+
+	#[test]
+	fn test_appended_data() {
+		let png = synthetic_png(1000);
+		let payload = b"appended payload";
+
+		// A PNG with a payload appended to it, followed by one with another PNG directly after it
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 12 ];
+		image[..png.len()].copy_from_slice(&png);
+		image[png.len()..(png.len() + payload.len())].copy_from_slice(payload);
+
+		let second_idx = CLUSTER_SIZE * 4;
+		image[second_idx..(second_idx + png.len())].copy_from_slice(&png);
+		image[(second_idx + png.len())..(second_idx + png.len() * 2)].copy_from_slice(&png);
+
+		let test_dir = TestDir::new("appended_data", &image);
+		let image_path = &test_dir.image_path;
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				}
+			],
+			appended_data: true,
+			..Default::default()
+		};
+
+		let output_dir = test_dir.join("output");
+		Searchlight::default().process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), false, &ImageOptions::default()).unwrap();
+
+		// Only the payload is appended data, as the PNG directly after the second is carved as itself, and zeros are not included
+		let log = CarveLog::read(output_dir.join("log.json")).unwrap();
+		let appended_data: Vec<_> = log.files.iter().map(|entry| entry.appended_data.clone()).collect();
+		assert_eq!(appended_data, vec![ Some(png.len()..(png.len() + payload.len())), None, None ]);
+
+		let appended_path = output_dir.join("correct").join(format!("{}.appended", log.files[0].filename));
+		assert_eq!(fs::read(&appended_path).unwrap(), payload);
+
+		// The appended data is carved again when carving from the log
+		let log_output_dir = test_dir.join("log_output");
+		Searchlight::default().process_log_file(log_output_dir.to_str().unwrap(), output_dir.join("log.json").to_str().unwrap()).unwrap();
+		assert_eq!(fs::read(log_output_dir.join("correct").join(format!("{}.appended", log.files[0].filename))).unwrap(), payload);
+
+		// At most appended_data_max_len bytes of appended data are carved
+		let config = SearchlightConfig {
+			appended_data_max_len: Some(8),
+			..config
+		};
+
+		let mut appended_data = Vec::new();
+		Searchlight::default().carve_image_file(image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+			appended_data.push(file.appended_data.clone());
+			Ok(())
+		}).unwrap();
+		assert_eq!(appended_data, vec![ Some(png.len()..(png.len() + 8)), None, None ]);
+	}
+
+	#[test]
+	fn test_overlap_policy() {
+		// A PNG embedded in an ancillary chunk of another means that pairing the nearest headers and footers produces two candidates that overlap
+		let inner_png = synthetic_png(100);
+		let mut outer_png = b"\x89PNG\x0d\x0a\x1a\x0a".to_vec();
+		outer_png.append(&mut png_chunk(b"IHDR", &[ 0, 0, 0, 16, 0, 0, 0, 16, 8, 2, 0, 0, 0 ]));
+		outer_png.append(&mut png_chunk(b"prVt", &inner_png));
+		outer_png.append(&mut png_chunk(b"IDAT", &(0..100).map(|i| (i % 251) as u8).collect::<Vec<u8>>()));
+		outer_png.append(&mut png_chunk(b"IEND", &[]));
+
+		let test_dir = TestDir::new("overlap_policy", &outer_png);
+		let image_path = &test_dir.image_path;
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				}
+			],
+			..Default::default()
+		};
+
+		let mut carved = Vec::new();
+		for overlap_policy in [ OverlapPolicy::Ignore, OverlapPolicy::Log, OverlapPolicy::PreferValid ] {
+			config.overlap_policy = overlap_policy;
+
+			let output_dir = test_dir.join(format!("output_{:?}", overlap_policy));
+			Searchlight::default().process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(1), false, &ImageOptions::default()).unwrap();
+
+			let log = CarveLog::read(output_dir.join("log.json")).unwrap();
+			carved.push(log.files.iter().map(|file| (file.validation, file.fragments.clone())).collect::<Vec<_>>());
+		}
+
+		// The candidate from the outer header to the inner footer can only be partially validated, so only the inner PNG is carved when
+		// preferring valid candidates
+		let inner_start = 8 + 25 + 8; // The signature, IHDR chunk, and length and type of the chunk containing the inner PNG
+		let inner_png = (FileValidationType::Correct, vec![ inner_start..(inner_start + inner_png.len()) ]);
+		assert_eq!(carved[0].len(), 2);
+		assert!(carved[0].contains(&inner_png));
+		assert_eq!(carved[1], carved[0]);
+		assert_eq!(carved[2], vec![ inner_png ]);
+	}
+
+	#[test]
+	fn test_invalid_cluster_size() {
+		let test_dir = TestDir::new("invalid_cluster_size", &synthetic_png(100));
+		let image_path = &test_dir.image_path;
+		let output_dir = test_dir.join("output");
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				}
+			],
+			..Default::default()
+		};
+
+		for cluster_size in [ 0, 3, 2 * super::MAX_CLUSTER_SIZE ] {
+			let result = Searchlight::default().process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(cluster_size), false, &ImageOptions::default());
+			assert!(matches!(result, Err(Error::InvalidClusterSize(_))));
+
+			let result = Searchlight::default().carve_image_file(image_path.to_str().unwrap(), &config, Some(cluster_size), None, false, |_| Ok(()));
+			assert!(matches!(result, Err(Error::InvalidClusterSize(_))));
+		}
+
+		// Nothing is written if the cluster size is invalid
+		assert!(!output_dir.exists());
+
+		let summary = Searchlight::default().process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(1), false, &ImageOptions::default()).unwrap();
+		assert_eq!(summary.num_carved_files, 1);
+	}
+
+	#[test]
+	fn test_header_aligned() {
+		// A candidate at the start of a cluster, and one in the middle of a cluster
+		let mut image = vec![ 0xaa; CLUSTER_SIZE * 4 ];
+		image[CLUSTER_SIZE..(CLUSTER_SIZE + 4)].copy_from_slice(b"HDR!");
+		image[(CLUSTER_SIZE + 96)..(CLUSTER_SIZE + 100)].copy_from_slice(b"FTR!");
+		image[(CLUSTER_SIZE * 2 + 100)..(CLUSTER_SIZE * 2 + 104)].copy_from_slice(b"HDR!");
+		image[(CLUSTER_SIZE * 2 + 196)..(CLUSTER_SIZE * 2 + 200)].copy_from_slice(b"FTR!");
+
+		let test_dir = TestDir::new("header_aligned", &image);
+		let image_path = &test_dir.image_path;
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "HDR!".into() ],
+					footers: vec![ "FTR!".into() ],
+					extension: Some("dat".to_string()),
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		let carve = |config: &SearchlightConfig| {
+			let mut carved = Vec::new();
+			Searchlight::default().carve_image_file(image_path.to_str().unwrap(), config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+				carved.push(file.file_match.start_idx);
+				Ok(())
+			}).unwrap();
+			carved
+		};
+
+		assert_eq!(carve(&config), vec![ CLUSTER_SIZE, CLUSTER_SIZE * 2 + 100 ]);
+
+		config.file_types[0].header_aligned = true;
+		assert_eq!(carve(&config), vec![ CLUSTER_SIZE ]);
+	}
+
+	#[test]
+	fn test_max_candidate_span() {
+		// A short candidate, and a long one
+		let mut image = vec![ 0xaa; CLUSTER_SIZE * 8 ];
+		image[..4].copy_from_slice(b"HDR!");
+		image[96..100].copy_from_slice(b"FTR!");
+		image[CLUSTER_SIZE..(CLUSTER_SIZE + 4)].copy_from_slice(b"HDR!");
+		image[(CLUSTER_SIZE * 6 - 4)..(CLUSTER_SIZE * 6)].copy_from_slice(b"FTR!");
+
+		let test_dir = TestDir::new("max_candidate_span", &image);
+		let image_path = &test_dir.image_path;
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "HDR!".into() ],
+					footers: vec![ "FTR!".into() ],
+					extension: Some("dat".to_string()),
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				},
+			],
+			max_candidate_span: Some(1024),
+			..Default::default()
+		};
+
+		let carve = |config: &SearchlightConfig| {
+			let mut carved = Vec::new();
+			Searchlight::default().carve_image_file(image_path.to_str().unwrap(), config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+				carved.push(file.file_match.start_idx);
+				Ok(())
+			}).unwrap();
+			carved
+		};
+
+		// Oversized candidates are only warned about by default
+		assert_eq!(carve(&config), vec![ 0, CLUSTER_SIZE ]);
+
+		config.skip_oversized_candidates = true;
+		assert_eq!(carve(&config), vec![ 0 ]);
+	}
+
+	#[test]
+	fn test_expected_entropy() {
+		// Two candidates of the same type, one containing text and the other containing every byte value, as compressed data would
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 6 ];
+		image[..4].copy_from_slice(b"HDR!");
+		for (i, b) in image[4..1004].iter_mut().enumerate() {
+			*b = b"lorem ipsum dolor sit amet "[i % 27];
+		}
+		image[1004..1008].copy_from_slice(b"FTR!");
+
+		let high_idx = CLUSTER_SIZE * 2;
+		image[high_idx..(high_idx + 4)].copy_from_slice(b"HDR!");
+		for (i, b) in image[(high_idx + 4)..(high_idx + 1028)].iter_mut().enumerate() {
+			*b = (i * 167) as u8;
+		}
+		image[(high_idx + 1028)..(high_idx + 1032)].copy_from_slice(b"FTR!");
+
+		// And a header without a footer, which is dropped in pairing
+		let unpaired_idx = CLUSTER_SIZE * 5;
+		image[unpaired_idx..(unpaired_idx + 4)].copy_from_slice(b"HDR!");
+
+		let test_dir = TestDir::new("expected_entropy", &image);
+		let image_path = &test_dir.image_path;
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "HDR!".into() ],
+					footers: vec![ "FTR!".into() ],
+					extension: Some("dat".to_string()),
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024 * 1024),
+					requires_footer: true,
+					expected_entropy: Some([ 6.0, 8.0 ]),
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		let mut searchlight = Searchlight::default();
+
+		let mut carved = Vec::new();
+		let num_carved = searchlight.carve_image_file(image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+			carved.push(file.file_match.start_idx);
+			Ok(())
+		}).unwrap();
+
+		// Only the high entropy candidate is carved
+		assert_eq!(num_carved, 1);
+		assert_eq!(carved, vec![ high_idx ]);
+
+		// And the others are recorded in the rejects log, if enabled
+		let config = SearchlightConfig {
+			rejects_log: true,
+			..config
+		};
+
+		let output_dir = test_dir.join("output");
+		searchlight.process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), true, &ImageOptions::default()).unwrap();
+
+		let rejects = fs::read_to_string(output_dir.join("rejects.jsonl")).unwrap();
+		let rejects: Vec<RejectEntry> = rejects.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+		assert_eq!(rejects, vec![
+			RejectEntry {
+				file_type_id: FileTypeId::Unknown,
+				start_idx: unpaired_idx,
+				end_idx: unpaired_idx + 4,
+				truncated: false,
+				validation: FileValidationType::Unrecognised,
+				rejection_reason: Some(RejectionReason::NoFooterInRange.to_string())
+			},
+			RejectEntry {
+				file_type_id: FileTypeId::Unknown,
+				start_idx: 0,
+				end_idx: 1008,
+				truncated: false,
+				validation: FileValidationType::Unrecognised,
+				rejection_reason: Some(RejectionReason::EntropyOutOfRange.to_string())
+			}
+		]);
+	}
+
+	#[test]
+	fn test_unanalysed_as() {
+		// A ZIP whose EOCD says that it is part of a multi-disk archive, which isn't analysed, and a candidate of a type without a validator
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 4 ];
+		image[..4].copy_from_slice(b"PK\x03\x04");
+		image[200..204].copy_from_slice(b"PK\x05\x06");
+		image[204..206].copy_from_slice(&1u16.to_le_bytes());
+
+		let other_idx = CLUSTER_SIZE * 2;
+		image[other_idx..(other_idx + 4)].copy_from_slice(b"HDR!");
+		image[(other_idx + 100)..(other_idx + 104)].copy_from_slice(b"FTR!");
+
+		let test_dir = TestDir::new("unanalysed_as", &image);
+		let image_path = &test_dir.image_path;
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x50\\x4B\\x03\\x04".into() ],
+					footers: vec![ "\\x50\\x4B\\x05\\x06..................".into() ],
+					extension: Some("zip".to_string()),
+					type_id: FileTypeId::Zip,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024 * 1024),
+					requires_footer: true,
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "HDR!".into() ],
+					footers: vec![ "FTR!".into() ],
+					extension: Some("dat".to_string()),
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		let mut searchlight = Searchlight::default();
+
+		let mut carve = |config: &SearchlightConfig| {
+			let mut carved = Vec::new();
+			searchlight.carve_image_file(image_path.to_str().unwrap(), config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+				carved.push((file.file_match.file_type.type_id, file.validation.validation_type));
+				Ok(())
+			}).unwrap();
+			carved
+		};
+
+		assert_eq!(carve(&config), vec![ (FileTypeId::Zip, FileValidationType::Unanalysed), (FileTypeId::Unknown, FileValidationType::Unanalysed) ]);
+
+		// Only the candidate that was given to a validator is mapped
+		config.unanalysed_as = Some(FileValidationType::Correct);
+		assert!(config.validate().is_ok());
+		assert_eq!(carve(&config), vec![ (FileTypeId::Zip, FileValidationType::Correct), (FileTypeId::Unknown, FileValidationType::Unanalysed) ]);
+
+		config.unanalysed_as = Some(FileValidationType::Unrecognised);
+		assert!(config.validate().is_err());
+	}
+
+	#[test]
+	fn test_timings() {
+		let png = synthetic_png(1000);
+
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 8 ];
+		image[..png.len()].copy_from_slice(&png);
+
+		let test_dir = TestDir::new("timings", &image);
+		let image_path = &test_dir.image_path;
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				}
+			],
+			..Default::default()
+		};
+
+		// Timings are only measured if configured
+		let summary = Searchlight::default().process_image_file(test_dir.join("untimed").to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), false, &ImageOptions::default()).unwrap();
+		assert_eq!(summary.timings, None);
+
+		config.timings = true;
+
+		let summary = Searchlight::default().process_image_file(test_dir.join("timed").to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), false, &ImageOptions::default()).unwrap();
+		assert_eq!(summary.num_carved_files, 1);
+
+		let timings = summary.timings.unwrap();
+		assert_eq!(timings.searched_bytes, image.len() as u64);
+		// The cluster size was given, so it wasn't estimated
+		assert_eq!(timings.cluster_estimation, Duration::ZERO);
+
+		let timings = PhaseTimings {
+			search: Duration::from_millis(500),
+			searched_bytes: 1000,
+			..Default::default()
+		};
+		assert_eq!(timings.search_bytes_per_sec(), 2000.0);
+		assert_eq!(PhaseTimings::default().search_bytes_per_sec(), 0.0);
+	}
+
+	#[test]
+	fn test_end_heuristic() {
+		// A footerless text file, followed by zeros and then binary data
+		let text = b"From: someone\r\nSubject: Test\r\n\r\nA message without a terminator\r\n";
+
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 4 ];
+		image[..text.len()].copy_from_slice(text);
+		image[1000..1004].copy_from_slice(&[ 0x01, 0x02, 0x03, 0x04 ]);
+
+		let test_dir = TestDir::new("end_heuristic", &image);
+		let image_path = &test_dir.image_path;
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "From: ".into() ],
+					extension: Some("txt".to_string()),
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1500),
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		let mut searchlight = Searchlight::default();
+
+		let mut carve = |config: &SearchlightConfig| {
+			let mut fragments = Vec::new();
+			searchlight.carve_image_file(image_path.to_str().unwrap(), config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+				fragments.extend(file.validation.fragments);
+				Ok(())
+			}).unwrap();
+			fragments
+		};
+
+		// Without a heuristic, the whole max_len span is carved
+		let untrimmed = carve(&config);
+		assert_eq!(untrimmed.len(), 1);
+		assert!(untrimmed[0].end >= 1500);
+
+		config.file_types[0].end_heuristic = Some(EndHeuristic::Text);
+		assert_eq!(carve(&config), vec![ 0..text.len() ]);
+
+		config.file_types[0].end_heuristic = Some(EndHeuristic::Zeros);
+		assert_eq!(carve(&config), vec![ 0..1004 ]);
+
+		// The end of a candidate with a footer is known, so it isn't trimmed
+		config.file_types[0].end_heuristic = Some(EndHeuristic::Text);
+		config.file_types[0].footers = vec![ "\\x01\\x02\\x03\\x04".into() ];
+		assert_eq!(carve(&config), vec![ 0..1004 ]);
+	}
+
+	#[test]
+	fn test_filename_offset_unit() {
+		const CLUSTER_SIZE: usize = 4096;
+
+		let png = synthetic_png(1000);
+
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 4 ];
+		image[(CLUSTER_SIZE * 2)..(CLUSTER_SIZE * 2 + png.len())].copy_from_slice(&png);
+
+		let test_dir = TestDir::new("filename_offset_unit", &image);
+		let image_path = &test_dir.image_path;
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				}
+			],
+			..Default::default()
+		};
+
+		let carve = |config: &SearchlightConfig, name: &str| {
+			let output_dir = test_dir.join(name);
+			Searchlight::default().process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), config, Some(CLUSTER_SIZE as u64), false, &ImageOptions::default()).unwrap();
+
+			let log = CarveLog::read(output_dir.join("log.json")).unwrap();
+			assert!(output_dir.join("correct").join(&log.files[0].filename).exists());
+
+			(log.files[0].filename.clone(), log.files[0].filename_offset_unit)
+		};
+
+		let png_start = CLUSTER_SIZE * 2;
+		let png_end = png_start + png.len();
+
+		assert_eq!(carve(&config, "bytes"), (format!("{png_start}-{png_end}.png"), None));
+
+		// The end is rounded up, so that the range covers the file
+		config.filename_offset_unit = OffsetUnit::Sectors;
+		assert_eq!(carve(&config, "sectors"), (
+			format!("{}-{}.png", png_start / 512, png_end.div_ceil(512)),
+			Some(FilenameOffsetUnit { unit: OffsetUnit::Sectors, unit_size: 512 })
+		));
+
+		config.filename_offset_unit = OffsetUnit::Clusters;
+		assert_eq!(carve(&config, "clusters"), ("2-3.png".to_string(), Some(FilenameOffsetUnit { unit: OffsetUnit::Clusters, unit_size: CLUSTER_SIZE as u64 })));
+
+		// Files in the same clusters would have the same name, so they are disambiguated, even if not carved
+		let mut colliding = vec![ 0u8; CLUSTER_SIZE * 2 ];
+		colliding[..png.len()].copy_from_slice(&png);
+		colliding[(CLUSTER_SIZE / 2)..(CLUSTER_SIZE / 2 + png.len())].copy_from_slice(&png);
+
+		let colliding_path = test_dir.join("colliding.dat");
+		fs::write(&colliding_path, &colliding).unwrap();
+
+		for skip_carving in [ false, true ] {
+			let output_dir = test_dir.join(format!("colliding_{skip_carving}"));
+			Searchlight::default().process_image_file(output_dir.to_str().unwrap(), colliding_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), skip_carving, &ImageOptions::default()).unwrap();
+
+			let log = CarveLog::read(output_dir.join("log.json")).unwrap();
+			let filenames: Vec<&str> = log.files.iter().map(|entry| entry.filename.as_str()).collect();
+
+			assert_eq!(filenames, vec![ "0-1.png", "0-1_1.png" ]);
+		}
+	}
+
+	#[test]
+	fn test_output_layout() {
+		const CLUSTER_SIZE: usize = 4096;
+
+		let png = synthetic_png(1000);
+
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 4 ];
+		image[CLUSTER_SIZE..(CLUSTER_SIZE + png.len())].copy_from_slice(&png);
+
+		let test_dir = TestDir::new("output_layout", &image);
+		let image_path = &test_dir.image_path;
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					max_len: Some(1024 * 1024),
+					..Default::default()
+				}
+			],
+			carve_slack: true,
+			..Default::default()
+		};
+
+		let filename = format!("{}-{}.png", CLUSTER_SIZE, CLUSTER_SIZE + png.len());
+
+		for (layout, dir, recorded) in [
+			(OutputLayout::Validation, [ "correct" ].iter().collect::<PathBuf>(), None),
+			(OutputLayout::ExtensionValidation, [ "png", "correct" ].iter().collect(), Some("png/correct")),
+			(OutputLayout::ValidationExtension, [ "correct", "png" ].iter().collect(), Some("correct/png")),
+		] {
+			config.output_layout = layout;
+
+			let output_dir = test_dir.join(layout.to_string());
+			Searchlight::default().process_image_file(output_dir.to_str().unwrap(), image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), false, &ImageOptions::default()).unwrap();
+
+			// The slack is carved alongside the file, in the same directory
+			assert!(output_dir.join(&dir).join(&filename).exists());
+			assert!(output_dir.join(&dir).join(format!("{filename}.slack")).exists());
+
+			let log = CarveLog::read(output_dir.join("log.json")).unwrap();
+			assert_eq!(log.files[0].directory.as_deref(), recorded);
+
+			// Carving from the log recreates the same layout
+			let log_output_dir = test_dir.join(format!("{layout}_from_log"));
+			Searchlight::default().process_log_file(log_output_dir.to_str().unwrap(), output_dir.join("log.json").to_str().unwrap()).unwrap();
+
+			assert_eq!(fs::read(log_output_dir.join(&dir).join(&filename)).unwrap(), png);
+			assert!(log_output_dir.join(&dir).join(format!("{filename}.slack")).exists());
+		}
+	}
+
+	/// Wraps AcCpu with a small maximum search size so that images are searched in many blocks, counting the searches made. Each search takes
+	/// at least a millisecond, so that candidates validated on another thread while searching are validated before the search is complete
+	struct CountingSearcher {
+		inner: AcCpu,
+		searches: Arc<AtomicUsize>
+	}
+
+	impl Searcher for CountingSearcher {
+		fn search(&mut self, data: &[u8], data_offset: u64, overlap: usize) -> Result<SearchFuture, Error> {
+			self.searches.fetch_add(1, Ordering::Relaxed);
+			thread::sleep(Duration::from_millis(1));
+			self.inner.search(data, data_offset, overlap)
+		}
+
+		fn max_search_size(&self) -> Option<usize> {
+			Some(1024)
+		}
+	}
+
+	/// Validates with the default validator, recording how many searches had been made when the first candidate was validated
+	struct SearchCountingValidator {
+		inner: DelegatingValidator,
+		searches: Arc<AtomicUsize>,
+		searches_before_validation: Arc<Mutex<Option<usize>>>
+	}
+
+	impl FileValidator for SearchCountingValidator {
+		fn validate(&self, file_data: &[u8], file_match: &MatchPair, all_matches: &[Match], cluster_size: usize, config: &SearchlightConfig) -> FileValidationInfo {
+			self.searches_before_validation.lock().unwrap().get_or_insert(self.searches.load(Ordering::Relaxed));
+
+			self.inner.validate(file_data, file_match, all_matches, cluster_size, config)
+		}
+	}
+
+	#[test]
+	fn test_pipelined_validation() {
+		const IEND: &[u8] = b"IEND\xae\x42\x60\x82";
+
+		let png = synthetic_png(100);
+		let fragmented_png = synthetic_png(1500);
+		let jpeg = synthetic_jpeg(1000);
+		let zip = synthetic_zip(b"a.txt", &[ b'a'; 100 ]);
+
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 40 ];
+
+		// A file of a type without a max_len that spans the image, which doesn't hold up the validation of the files within it
+		image[..5].copy_from_slice(b"SLHDR");
+		image[(CLUSTER_SIZE * 39)..(CLUSTER_SIZE * 39 + 5)].copy_from_slice(b"SLFTR");
+
+		// A stray footer before any header
+		image[100..(100 + IEND.len())].copy_from_slice(IEND);
+
+		for png_idx in [ CLUSTER_SIZE, CLUSTER_SIZE * 30 ] {
+			image[png_idx..(png_idx + png.len())].copy_from_slice(&png);
+		}
+
+		let frag_png_idx = CLUSTER_SIZE * 4;
+		let frag_png_gap = frag_png_idx + CLUSTER_SIZE * 2;
+		image[frag_png_idx..frag_png_gap].copy_from_slice(&fragmented_png[..(CLUSTER_SIZE * 2)]);
+		image[frag_png_gap..(frag_png_gap + CLUSTER_SIZE)].fill(0xaa);
+		let frag_png_end = frag_png_gap + CLUSTER_SIZE + fragmented_png.len() - CLUSTER_SIZE * 2;
+		image[(frag_png_gap + CLUSTER_SIZE)..frag_png_end].copy_from_slice(&fragmented_png[(CLUSTER_SIZE * 2)..]);
+
+		let jpeg_idx = CLUSTER_SIZE * 10;
+		image[jpeg_idx..(jpeg_idx + jpeg.len())].copy_from_slice(&jpeg);
+
+		// A ZIP without its end of central directory, which is dropped in pairing, followed by a complete one
+		image[(CLUSTER_SIZE * 16)..(CLUSTER_SIZE * 16 + 30)].copy_from_slice(&zip[..30]);
+		let zip_idx = CLUSTER_SIZE * 24;
+		image[zip_idx..(zip_idx + zip.len())].copy_from_slice(&zip);
+
+		let test_dir = TestDir::new("pipelined_validation", &image);
+		let image_path = &test_dir.image_path;
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\xff\\xd8\\xff\\xe0".into() ],
+					extension: Some("jpg".to_string()),
+					type_id: FileTypeId::Jpeg,
+					pairing: PairingStrategy::PairLast,
+					max_len: Some(CLUSTER_SIZE as u64 * 4),
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "\\x89\\x50\\x4e\\x47\\x0d\\x0a\\x1a\\x0a".into() ],
+					footers: vec![ "\\x49\\x45\\x4e\\x44\\xae\\x42\\x60\\x82".into() ],
+					extension: Some("png".to_string()),
+					type_id: FileTypeId::Png,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(CLUSTER_SIZE as u64 * 6),
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "\\x50\\x4B\\x03\\x04".into() ],
+					footers: vec![ "\\x50\\x4B\\x05\\x06..................".into() ],
+					extension: Some("zip".to_string()),
+					type_id: FileTypeId::Zip,
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(CLUSTER_SIZE as u64 * 2),
+					requires_footer: true,
+					..Default::default()
+				},
+				FileType {
+					headers: vec![ "SLHDR".into() ],
+					footers: vec![ "SLFTR".into() ],
+					extension: Some("dat".to_string()),
+					type_id: FileTypeId::Unknown,
+					pairing: PairingStrategy::PairNext,
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		// Returns the files carved, in the order they were carved, and the number of searches made before the first validation and in total
+		let carve = |config: &SearchlightConfig, cluster_size: Option<u64>| {
+			let searches = Arc::new(AtomicUsize::new(0));
+			let searches_before_validation = Arc::new(Mutex::new(None));
+
+			let validator = SearchCountingValidator {
+				inner: DelegatingValidator::new(),
+				searches: searches.clone(),
+				searches_before_validation: searches_before_validation.clone()
+			};
+
+			let factory_searches = searches.clone();
+			let mut searchlight = Searchlight::new(Box::new(validator), move |config: &SearchlightConfig, _: &Arc<ThreadPool>| {
+				let ac_table = AcTableBuilder::build_from_config(config);
+
+				Ok((
+					Box::new(CountingSearcher { inner: AcCpu::new(ac_table.clone()), searches: factory_searches.clone() }) as Box<dyn Searcher>,
+					ac_table.max_pat_len as usize
+				))
+			});
+
+			let mut carved = Vec::new();
+			searchlight.carve_image_file(image_path.to_str().unwrap(), config, cluster_size, None, false, |file| {
+				carved.push((file.file_match.file_type.type_id, file.validation.validation_type, file.validation.fragments.clone()));
+				Ok(())
+			}).unwrap();
+
+			let searches_before_validation = searches_before_validation.lock().unwrap().unwrap();
+
+			(carved, searches_before_validation, searches.load(Ordering::Relaxed))
+		};
+
+		let (mut serial, searches_before_validation, searches) = carve(&config, Some(CLUSTER_SIZE as u64));
+
+		assert_eq!(serial.len(), 6);
+		assert!(serial.contains(&(FileTypeId::Png, FileValidationType::Correct, vec![ frag_png_idx..frag_png_gap, (frag_png_gap + CLUSTER_SIZE)..frag_png_end ])));
+		assert!(serial.contains(&(FileTypeId::Unknown, FileValidationType::Unanalysed, vec![ 0..(CLUSTER_SIZE * 39 + 5) ])));
+		assert_eq!(searches_before_validation, searches);
+
+		// The same files are carved, but validation starts before the search is complete, including with a lookahead shorter than the max_len
+		// of some of the types
+		config.pipelined_validation = true;
+
+		for lookahead in [ None, Some(CLUSTER_SIZE as u64) ] {
+			config.pipelined_validation_lookahead = lookahead;
+
+			let (mut pipelined, searches_before_validation, searches) = carve(&config, Some(CLUSTER_SIZE as u64));
+
+			pipelined.sort_by_key(|(_, _, fragments)| fragments[0].start);
+			serial.sort_by_key(|(_, _, fragments)| fragments[0].start);
+
+			assert_eq!(pipelined, serial);
+			assert!(searches_before_validation < searches / 2);
+		}
+
+		// Without a known cluster size, validation waits for the search, as the cluster size is estimated from all of the headers
+		let (_, searches_before_validation, searches) = carve(&config, None);
+
+		assert_eq!(searches_before_validation, searches);
+	}
+
+	#[test]
+	fn test_truncated_validation() {
+		// Two candidates whose footers are just past max_len, one with the byte sequence that the file type requires and one without
+		let mut image = vec![ 0u8; CLUSTER_SIZE * 8 ];
+		image[..4].copy_from_slice(b"HDR!");
+		image[100..104].copy_from_slice(b"REQ!");
+		image[1004..1008].copy_from_slice(b"FTR!");
+
+		let missing_idx = CLUSTER_SIZE * 4;
+		image[missing_idx..(missing_idx + 4)].copy_from_slice(b"HDR!");
+		image[(missing_idx + 1004)..(missing_idx + 1008)].copy_from_slice(b"FTR!");
+
+		let test_dir = TestDir::new("truncated_validation", &image);
+		let image_path = &test_dir.image_path;
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "HDR!".into() ],
+					footers: vec![ "FTR!".into() ],
+					requires: vec![ "REQ!".into() ],
+					extension: Some("dat".to_string()),
+					pairing: PairingStrategy::PairNext,
+					max_len: Some(1000),
+					requires_footer: true,
+					truncate_at_max_len: true,
+					..Default::default()
+				},
+			],
+			..Default::default()
+		};
+
+		let mut carved = Vec::new();
+		Searchlight::default().carve_image_file(image_path.to_str().unwrap(), &config, Some(CLUSTER_SIZE as u64), None, false, |file| {
+			carved.push((file.file_match.start_idx, file.validation.validation_type, file.validation.rejection_reason, file.validation.fragments.clone()));
+			Ok(())
+		}).unwrap();
+
+		// Both are validated up to max_len, and the one that checks out is still only partial as it is truncated
+		assert_eq!(carved, vec![
+			(0, FileValidationType::Partial, Some(RejectionReason::NoFooterInRange), vec![ 0..1001 ]),
+			(missing_idx, FileValidationType::FormatError, Some(RejectionReason::RequiredSequenceMissing), vec![ missing_idx..(missing_idx + 1001) ])
+		]);
 	}
 }
\ No newline at end of file
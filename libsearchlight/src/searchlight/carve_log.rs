@@ -1,10 +1,18 @@
-use std::{fs, io, path::PathBuf};
+use std::{collections::HashMap, fs, io::{self, Write}, path::{Path, PathBuf}};
 
+use log::warn;
 use serde::{Deserialize, Serialize};
 
-use crate::validation::{FileValidationType, Fragment};
+use crate::{error::Error, validation::{EmbeddedEncoding, FileValidationType, Fragment, METADATA_MODIFIED_TIME}};
 
-use super::config::FileTypeId;
+use super::config::{FileTypeId, LogFormat, OffsetUnit};
+
+/// The magic bytes at the start of a binary carve log, which distinguish it from a JSON carve log
+const BINARY_LOG_MAGIC: &[u8; 8] = b"SLCLOG\0\0";
+const BINARY_LOG_VERSION: u32 = 9;
+/// The version of the structure of JSON carve logs, which is written to the `version` field of each log. Logs without the field were written
+/// before it was added, and are treated as version 0
+const JSON_LOG_VERSION: u32 = 1;
 
 #[derive(Serialize, Deserialize)]
 pub struct CarveLog { // NOTE: Do any other fields need to be added to this or the entry struct? This is sufficient for carving files after the log is generated at least, but other fields may be useful
@@ -12,11 +20,144 @@ pub struct CarveLog { // NOTE: Do any other fields need to be added to this or t
 	pub files: Vec<CarveLogEntry>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct CarveLogEntry {
 	pub file_type_id: FileTypeId,
 	pub filename: String,
 	pub validation: FileValidationType,
+	pub fragments: Vec<Fragment>,
+	/// Metadata extracted from the file by the validator, if any, keyed by the `METADATA_*` constants in `validation`
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	pub metadata: HashMap<String, String>,
+	/// The files embedded in the file that were extracted alongside it, if `extract_embedded` was configured
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub members: Vec<CarveLogMember>,
+	/// The file slack of the file, i.e. the range from its end to the end of its last cluster, if `carve_slack` was configured and the file
+	/// has any. It is carved alongside the file as `<filename>.slack`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub slack: Option<Fragment>,
+	/// The data appended after the end of the file, up to the next header found in the image, if `appended_data` was configured and the
+	/// file has any. It is carved alongside the file as `<filename>.appended`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub appended_data: Option<Fragment>,
+	/// The checksum of each fragment of the file, in order, if `fragment_checksums` was configured, so that whether a carved file was
+	/// assembled correctly can be checked without validating it again
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub fragment_checksums: Vec<FragmentChecksum>,
+	/// The unit of the offsets in the filename, if it isn't bytes, see `filename_offset_unit`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub filename_offset_unit: Option<FilenameOffsetUnit>,
+	/// The directory that the file was carved to, relative to the output directory, if it isn't the directory of its validation type, see
+	/// `output_layout`. Its members, slack and appended data are carved alongside it
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub directory: Option<String>,
+	/// The path (see `CarveLogEntry::path`) of the entry of the file that this file was extracted from, if it is an embedded file recorded
+	/// as an entry of its own, see `embedded_entries`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub parent: Option<String>,
+	/// How the content of the file is encoded in its fragments, if it is an embedded file recorded as an entry of its own. Other files are
+	/// stored as-is
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub encoding: Option<EmbeddedEncoding>,
+	/// How fragmented the file is, if `fragmentation_stats` was configured
+	#[serde(flatten)]
+	pub fragmentation: Option<Fragmentation>
+}
+
+impl CarveLogEntry {
+	/// The directory that the file was carved to, relative to the output directory, which is `directory` if recorded, and otherwise the
+	/// directory of its validation type
+	pub fn dir_path(&self) -> String {
+		self.directory.clone().unwrap_or_else(|| self.validation.to_string())
+	}
+
+	/// The path that the file was carved to, relative to the output directory, which identifies the entry in `parent` references
+	pub fn path(&self) -> String {
+		format!("{}/{}", self.dir_path(), self.filename)
+	}
+}
+
+/// Measures of how fragmented a carved file is, derived from its fragments
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Fragmentation {
+	pub fragment_count: usize,
+	/// The total number of bytes between consecutive fragments, i.e. the bytes skipped over within the extent of the file
+	pub gap_bytes: u64
+}
+
+/// The unit of the offsets in the filename of a carved file
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FilenameOffsetUnit {
+	pub unit: OffsetUnit,
+	/// The size of the unit in bytes, i.e. what the byte offsets were divided by
+	pub unit_size: u64
+}
+
+/// The checksum of a fragment of a carved file, and where the fragment is in the carved file. The checksum is the CRC-32 (IEEE 802.3, as
+/// used by PNG chunks and ZIP members) of the fragment's data, for files of all types
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FragmentChecksum {
+	/// The offset of the fragment in the carved file, i.e. the total length of the fragments before it
+	pub file_offset: u64,
+	pub crc32: u32
+}
+
+impl FragmentChecksum {
+	/// Computes the checksums of the `fragments` of `data` that make up a file, in order
+	pub fn of(data: &[u8], fragments: &[Fragment]) -> Vec<Self> {
+		let mut file_offset = 0;
+
+		fragments.iter().map(|frag| {
+			let checksum = FragmentChecksum {
+				file_offset,
+				crc32: crc32fast::hash(&data[frag.start..frag.end])
+			};
+			file_offset += frag.len() as u64;
+
+			checksum
+		}).collect()
+	}
+}
+
+/// The mean and maximum fragmentation of a set of carved files
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FragmentationSummary {
+	pub num_files: usize,
+	pub mean_fragment_count: f64,
+	pub max_fragment_count: usize,
+	pub mean_gap_bytes: f64,
+	pub max_gap_bytes: u64
+}
+
+impl Fragmentation {
+	/// Computes the fragmentation of a file consisting of `fragments`, in order. Gaps are measured between the end of each fragment and the
+	/// start of the next, so fragments that go backwards in the image contribute the distance back
+	pub fn of(fragments: &[Fragment]) -> Self {
+		Fragmentation {
+			fragment_count: fragments.len(),
+			gap_bytes: fragments.windows(2).map(|pair| pair[0].end.abs_diff(pair[1].start) as u64).sum()
+		}
+	}
+}
+
+/// A carve log as written in the JSON format, with the version of the format
+#[derive(Serialize)]
+struct VersionedLog<'a> {
+	version: u32,
+	#[serde(flatten)]
+	log: &'a CarveLog
+}
+
+/// A file embedded within a carved file, e.g. a member of a ZIP archive, that was extracted alongside it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CarveLogMember {
+	/// The name of the file as recorded in the containing file
+	pub name: String,
+	/// The path of the extracted file, relative to the directory of the containing file
+	pub filename: String,
+	pub validation: FileValidationType,
+	pub encoding: EmbeddedEncoding,
+	/// The fragments of the encoded content of the file in the image
 	pub fragments: Vec<Fragment>
 }
 
@@ -28,23 +169,753 @@ impl CarveLog {
 		}
 	}
 
-	pub fn add_entry(&mut self, file_type_id: FileTypeId, filename: String, validation: FileValidationType, fragments: Vec<Fragment>) {
+	/// Adds an entry to the log, returning it so that any members, slack and appended data can be added
+	pub fn add_entry(&mut self, file_type_id: FileTypeId, filename: String, validation: FileValidationType, fragments: Vec<Fragment>, metadata: HashMap<String, String>) -> &mut CarveLogEntry {
 		self.files.push(CarveLogEntry {
 			file_type_id,
 			filename,
 			validation,
-			fragments
+			fragments,
+			metadata,
+			members: Vec::new(),
+			slack: None,
+			appended_data: None,
+			fragment_checksums: Vec::new(),
+			filename_offset_unit: None,
+			directory: None,
+			parent: None,
+			encoding: None,
+			fragmentation: None
 		});
+
+		self.files.last_mut().unwrap()
+	}
+
+	/// Summarises the fragmentation of the entries that have it recorded, or returns None if none do
+	pub fn fragmentation_summary(&self) -> Option<FragmentationSummary> {
+		let mut summary = FragmentationSummary::default();
+		let mut total_fragments = 0;
+		let mut total_gap_bytes = 0;
+
+		for fragmentation in self.files.iter().filter_map(|entry| entry.fragmentation) {
+			summary.num_files += 1;
+			summary.max_fragment_count = summary.max_fragment_count.max(fragmentation.fragment_count);
+			summary.max_gap_bytes = summary.max_gap_bytes.max(fragmentation.gap_bytes);
+			total_fragments += fragmentation.fragment_count;
+			total_gap_bytes += fragmentation.gap_bytes;
+		}
+
+		if summary.num_files == 0 {
+			return None;
+		}
+
+		summary.mean_fragment_count = total_fragments as f64 / summary.num_files as f64;
+		summary.mean_gap_bytes = total_gap_bytes as f64 / summary.num_files as f64;
+
+		Some(summary)
+	}
+
+	/// Reads and parses the carve log at `path`, which can be in any of the formats of `LogFormat` except CSV, detected from its content
+	pub fn read(path: impl AsRef<Path>) -> Result<Self, Error> {
+		let log_file = fs::read(path)?;
+
+		if log_file.starts_with(BINARY_LOG_MAGIC) {
+			Self::decode_binary(&log_file)
+		} else {
+			Self::decode_json(&log_file)
+		}
+	}
+
+	/// Decodes a log in the JSON carve log format, migrating it from an older version of the format if necessary, see `JSON_LOG_VERSION`
+	pub fn decode_json(data: &[u8]) -> Result<Self, Error> {
+		let log: serde_json::Value = serde_json::from_slice(data).map_err(|e| Error::LogReadError(e.to_string()))?;
+
+		let version = match log.get("version") {
+			Some(version) => version.as_u64().and_then(|version| u32::try_from(version).ok()).ok_or(Error::LogReadError(format!("Invalid carve log version {version}")))?,
+			None => {
+				warn!("The carve log has no version, so was written by an older version of Searchlight - Reading it as a legacy (version 0) log");
+				0
+			}
+		};
+
+		if version > JSON_LOG_VERSION {
+			return Err(Error::LogReadError(format!("Carve log version {version} is newer than the latest supported version, {JSON_LOG_VERSION} - It was written by a newer version of Searchlight")));
+		}
+
+		let log = Self::migrate_json(log, version)?;
+
+		serde_json::from_value(log).map_err(|e| Error::LogReadError(if version < JSON_LOG_VERSION {
+			format!("The version {version} carve log could not be migrated to version {JSON_LOG_VERSION}, it may be from an incompatible version of Searchlight: {e}")
+		} else {
+			e.to_string()
+		}))
+	}
+
+	/// Migrates a JSON carve log of `version` to `JSON_LOG_VERSION`, one version at a time
+	fn migrate_json(mut log: serde_json::Value, version: u32) -> Result<serde_json::Value, Error> {
+		for from_version in version..JSON_LOG_VERSION {
+			log = match from_version {
+				// Version 1 only added the version field, which is not part of the deserialized log
+				0 => log,
+				_ => return Err(Error::LogReadError(format!("No migration from carve log version {from_version}")))
+			};
+		}
+
+		Ok(log)
+	}
+
+	/// Writes the log to `log.<ext>` in `dir_path`, where the extension is that of `format`
+	pub fn write(&self, dir_path: &str, format: LogFormat) -> Result<(), io::Error> {
+		let filename: PathBuf = [ dir_path, &format!("log.{}", format.extension()) ].into_iter().collect();
+
+		self.write_to(&filename, format)
+	}
+
+	/// Writes the log to `dir_path`, alongside any logs already there, returning the path written to. If there is a log for the same image in
+	/// `dir_path` (`log.<ext>`, or `log_<n>.<ext>` if there are multiple images), the entries of this log are added to it, otherwise this
+	/// log is written to `log.<ext>` if it doesn't exist, or the first `log_<n>.<ext>` that doesn't, where the extension is that of `format`.
+	/// CSV logs can't be read back in, so are never merged
+	pub fn write_appending(&self, dir_path: &str, format: LogFormat) -> Result<PathBuf, Error> {
+		for n in 0.. {
+			let filename = if n == 0 { format!("log.{}", format.extension()) } else { format!("log_{n}.{}", format.extension()) };
+			let path: PathBuf = [ dir_path, &filename ].into_iter().collect();
+
+			if !path.exists() {
+				self.write_to(&path, format)?;
+				return Ok(path);
+			}
+
+			if format == LogFormat::Csv {
+				continue;
+			}
+
+			let mut existing = CarveLog::read(&path)?;
+
+			if existing.image_path == self.image_path {
+				existing.files.extend(self.files.iter().cloned());
+				existing.write_to(&path, format)?;
+				return Ok(path);
+			}
+		}
+
+		unreachable!()
+	}
+
+	/// Writes the log to `path` in `format`
+	pub fn write_to(&self, path: &Path, format: LogFormat) -> Result<(), io::Error> {
+		let buf = match format {
+			LogFormat::Json => {
+				let mut buf = Vec::new();
+				let formatter = serde_json::ser::PrettyFormatter::with_indent(b"\t");
+				let mut ser = serde_json::Serializer::with_formatter(&mut buf, formatter);
+				VersionedLog { version: JSON_LOG_VERSION, log: self }.serialize(&mut ser).unwrap(); // This shouldn't fail... right??
+				buf
+			}
+			LogFormat::Binary => self.encode_binary(),
+			LogFormat::Csv => self.encode_csv().into_bytes()
+		};
+
+		fs::write(path, buf)
+	}
+
+	/// Encodes the log in the binary carve log format, which is much smaller and faster to read and write than JSON for logs of many files.
+	/// All integers are little-endian, and strings are a u32 length followed by that many bytes of UTF-8. The layout is:
+	///
+	/// - The magic bytes `SLCLOG\0\0`, then the format version as a u32 (currently 9)
+	/// - The image path, as a string
+	/// - The number of entries as a u64, followed by each entry, which is:
+	///   - The file type ID and validation type, each as a u8 of the index of the variant in the declaration of the enum
+	///   - The filename, as a string
+	///   - The number of fragments as a u32, followed by the start and end of each fragment as u64s
+	///   - The number of metadata entries as a u32, followed by the key and value of each as strings
+	///   - The number of members as a u32, followed by each member, which is its validation type and encoding as u8s (as for the file
+	///     type ID), its name and filename as strings, and its fragments as for the entry. Version 1 logs have no members
+	///   - The slack of the file, as fragments (of which there are 0 or 1). Logs before version 3 have no slack
+	///   - A u8 of 1 if the fragmentation of the file is recorded, followed by its fragment count as a u64 and its gap bytes as a u64, or
+	///     a u8 of 0 if not. Logs before version 4 have no fragmentation
+	///   - The appended data of the file, as fragments (of which there are 0 or 1). Logs before version 5 have no appended data
+	///   - The number of fragment checksums as a u32, followed by the file offset as a u64 and the CRC-32 as a u32 of each. Logs before
+	///     version 6 have no fragment checksums
+	///   - A u8 of 1 if the filename offset unit of the file is recorded, followed by the unit as a u8 (as for the file type ID) and its size
+	///     as a u64, or a u8 of 0 if not. Logs before version 7 have no filename offset unit
+	///   - The directory of the file, as a string, which is empty if it is the directory of its validation type. Logs before version 8 have
+	///     no directory
+	///   - The path of the parent of the file, as a string, which is empty if it has none, then a u8 of 1 if the encoding of the file is
+	///     recorded, followed by the encoding as a u8 (as for the file type ID), or a u8 of 0 if not. Logs before version 9 have no parent
+	///     or encoding
+	pub fn encode_binary(&self) -> Vec<u8> {
+		fn put_str(buf: &mut Vec<u8>, s: &str) {
+			buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+			buf.extend_from_slice(s.as_bytes());
+		}
+
+		fn put_fragments(buf: &mut Vec<u8>, fragments: &[Fragment]) {
+			buf.extend_from_slice(&(fragments.len() as u32).to_le_bytes());
+			for frag in fragments {
+				buf.extend_from_slice(&(frag.start as u64).to_le_bytes());
+				buf.extend_from_slice(&(frag.end as u64).to_le_bytes());
+			}
+		}
+
+		let mut buf = BINARY_LOG_MAGIC.to_vec();
+		buf.extend_from_slice(&BINARY_LOG_VERSION.to_le_bytes());
+		put_str(&mut buf, &self.image_path);
+		buf.extend_from_slice(&(self.files.len() as u64).to_le_bytes());
+
+		for entry in &self.files {
+			buf.push(entry.file_type_id as u8);
+			buf.push(entry.validation as u8);
+			put_str(&mut buf, &entry.filename);
+			put_fragments(&mut buf, &entry.fragments);
+
+			// Sorted, so that encoding the same log always gives the same bytes
+			let mut metadata: Vec<_> = entry.metadata.iter().collect();
+			metadata.sort();
+
+			buf.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+			for (key, value) in metadata {
+				put_str(&mut buf, key);
+				put_str(&mut buf, value);
+			}
+
+			buf.extend_from_slice(&(entry.members.len() as u32).to_le_bytes());
+			for member in &entry.members {
+				buf.push(member.validation as u8);
+				buf.push(member.encoding as u8);
+				put_str(&mut buf, &member.name);
+				put_str(&mut buf, &member.filename);
+				put_fragments(&mut buf, &member.fragments);
+			}
+
+			put_fragments(&mut buf, entry.slack.as_slice());
+
+			match entry.fragmentation {
+				Some(fragmentation) => {
+					buf.push(1);
+					buf.extend_from_slice(&(fragmentation.fragment_count as u64).to_le_bytes());
+					buf.extend_from_slice(&fragmentation.gap_bytes.to_le_bytes());
+				}
+				None => buf.push(0)
+			}
+
+			put_fragments(&mut buf, entry.appended_data.as_slice());
+
+			buf.extend_from_slice(&(entry.fragment_checksums.len() as u32).to_le_bytes());
+			for checksum in &entry.fragment_checksums {
+				buf.extend_from_slice(&checksum.file_offset.to_le_bytes());
+				buf.extend_from_slice(&checksum.crc32.to_le_bytes());
+			}
+
+			match entry.filename_offset_unit {
+				Some(filename_offset_unit) => {
+					buf.push(1);
+					buf.push(filename_offset_unit.unit as u8);
+					buf.extend_from_slice(&filename_offset_unit.unit_size.to_le_bytes());
+				}
+				None => buf.push(0)
+			}
+
+			put_str(&mut buf, entry.directory.as_deref().unwrap_or(""));
+
+			put_str(&mut buf, entry.parent.as_deref().unwrap_or(""));
+			match entry.encoding {
+				Some(encoding) => {
+					buf.push(1);
+					buf.push(encoding as u8);
+				}
+				None => buf.push(0)
+			}
+		}
+
+		buf
+	}
+
+	/// Decodes a log in the binary carve log format, see `encode_binary`
+	pub fn decode_binary(data: &[u8]) -> Result<Self, Error> {
+		let mut reader = BinaryReader { data, idx: 0 };
+
+		if reader.bytes(BINARY_LOG_MAGIC.len())? != BINARY_LOG_MAGIC {
+			return Err(Error::LogReadError("Not a binary carve log".to_string()));
+		}
+
+		let version = reader.u32()?;
+		if version == 0 || version > BINARY_LOG_VERSION {
+			return Err(Error::LogReadError(format!("Unsupported binary carve log version {version}")));
+		}
+
+		let image_path = reader.string()?;
+		let num_entries = reader.u64()?;
+
+		let mut files = Vec::new();
+
+		for _ in 0..num_entries {
+			let file_type_id = reader.u8()?;
+			let file_type_id = FileTypeId::from_repr(file_type_id).ok_or(Error::LogReadError(format!("Invalid file type ID {file_type_id}")))?;
+			let validation = reader.u8()?;
+			let validation = FileValidationType::from_repr(validation).ok_or(Error::LogReadError(format!("Invalid validation type {validation}")))?;
+			let filename = reader.string()?;
+
+			let fragments = reader.fragments()?;
+
+			let num_metadata = reader.u32()?;
+			let metadata = (0..num_metadata).map(|_| Ok((reader.string()?, reader.string()?))).collect::<Result<HashMap<String, String>, Error>>()?;
+
+			let num_members = if version >= 2 { reader.u32()? } else { 0 };
+			let members = (0..num_members).map(|_| {
+				let validation = reader.u8()?;
+				let validation = FileValidationType::from_repr(validation).ok_or(Error::LogReadError(format!("Invalid validation type {validation}")))?;
+				let encoding = reader.u8()?;
+				let encoding = EmbeddedEncoding::from_repr(encoding).ok_or(Error::LogReadError(format!("Invalid embedded file encoding {encoding}")))?;
+
+				Ok(CarveLogMember {
+					name: reader.string()?,
+					filename: reader.string()?,
+					validation,
+					encoding,
+					fragments: reader.fragments()?
+				})
+			}).collect::<Result<Vec<CarveLogMember>, Error>>()?;
+
+			let slack = if version >= 3 { reader.fragments()?.into_iter().next() } else { None };
+
+			let fragmentation = if version >= 4 && reader.u8()? != 0 {
+				Some(Fragmentation {
+					fragment_count: reader.u64()? as usize,
+					gap_bytes: reader.u64()?
+				})
+			} else {
+				None
+			};
+
+			let appended_data = if version >= 5 { reader.fragments()?.into_iter().next() } else { None };
+
+			let num_fragment_checksums = if version >= 6 { reader.u32()? } else { 0 };
+			let fragment_checksums = (0..num_fragment_checksums).map(|_| Ok(FragmentChecksum {
+				file_offset: reader.u64()?,
+				crc32: reader.u32()?
+			})).collect::<Result<Vec<FragmentChecksum>, Error>>()?;
+
+			let filename_offset_unit = if version >= 7 && reader.u8()? != 0 {
+				let unit = reader.u8()?;
+
+				Some(FilenameOffsetUnit {
+					unit: OffsetUnit::from_repr(unit).ok_or(Error::LogReadError(format!("Invalid offset unit {unit}")))?,
+					unit_size: reader.u64()?
+				})
+			} else {
+				None
+			};
+
+			let directory = if version >= 8 { Some(reader.string()?).filter(|directory| !directory.is_empty()) } else { None };
+
+			let parent = if version >= 9 { Some(reader.string()?).filter(|parent| !parent.is_empty()) } else { None };
+
+			let encoding = if version >= 9 && reader.u8()? != 0 {
+				let encoding = reader.u8()?;
+
+				Some(EmbeddedEncoding::from_repr(encoding).ok_or(Error::LogReadError(format!("Invalid embedded file encoding {encoding}")))?)
+			} else {
+				None
+			};
+
+			files.push(CarveLogEntry {
+				file_type_id,
+				filename,
+				validation,
+				fragments,
+				metadata,
+				members,
+				slack,
+				appended_data,
+				fragment_checksums,
+				filename_offset_unit,
+				directory,
+				parent,
+				encoding,
+				fragmentation
+			});
+		}
+
+		Ok(CarveLog {
+			image_path,
+			files
+		})
+	}
+
+	/// Encodes the log as CSV, with a header row and a row per entry. Fragments are written as `<start>-<end>` separated by `;`, and
+	/// metadata as `<key>=<value>` separated by `;`. Members, slack, appended data, fragment checksums, filename offset units, parents,
+	/// encodings and fragmentation are not included. CSV logs can't be read back in, and are intended for use in other tools
+	pub fn encode_csv(&self) -> String {
+		fn field(s: &str) -> String {
+			if s.contains([ ',', '"', '\n', '\r' ]) {
+				format!("\"{}\"", s.replace('"', "\"\""))
+			} else {
+				s.to_string()
+			}
+		}
+
+		let mut csv = "image_path,file_type_id,filename,validation,fragments,metadata\n".to_string();
+
+		for entry in &self.files {
+			let fragments = entry.fragments.iter().map(|frag| format!("{}-{}", frag.start, frag.end)).collect::<Vec<_>>().join(";");
+
+			let mut metadata: Vec<_> = entry.metadata.iter().map(|(key, value)| format!("{key}={value}")).collect();
+			metadata.sort();
+
+			csv.push_str(&[
+				field(&self.image_path),
+				entry.file_type_id.to_string(),
+				field(&entry.filename),
+				entry.validation.to_string(),
+				fragments,
+				field(&metadata.join(";"))
+			].join(","));
+			csv.push('\n');
+		}
+
+		csv
+	}
+
+	/// Writes the entries that have a known modification time as rows of a mactime bodyfile (format 3.x) to `bodyfile.txt` in `dir_path`,
+	/// for use with timelining tools. The name of each row is the path of the carved file relative to the output directory. Other fields
+	/// are not known for carved files, and so are written as 0, which mactime ignores for times. If `append` is true, the rows are added to
+	/// any existing bodyfile rather than replacing it
+	pub fn write_bodyfile(&self, dir_path: &str, append: bool) -> Result<(), io::Error> {
+		let filename: PathBuf = [ dir_path, "bodyfile.txt" ].into_iter().collect();
+
+		let mut file = io::BufWriter::new(fs::OpenOptions::new().write(true).create(true).append(append).truncate(!append).open(filename)?);
+
+		for entry in &self.files {
+			let Some(mtime) = entry.metadata.get(METADATA_MODIFIED_TIME).and_then(|mtime| mtime.parse::<i64>().ok()) else {
+				continue;
+			};
+
+			let size: usize = entry.fragments.iter().map(|frag| frag.len()).sum();
+
+			// MD5|name|inode|mode_as_string|UID|GID|size|atime|mtime|ctime|crtime
+			writeln!(file, "0|{}/{}|0|0|0|0|{}|0|{}|0|0", entry.dir_path(), entry.filename, size, mtime)?;
+		}
+
+		file.flush()
+	}
+}
+
+/// A record of the candidates (pairs of headers and footers) that were not carved, as they were unrecognised by validation or their header
+/// was dropped in pairing, along with the reason, for investigating near misses, e.g. when tuning a config
+pub struct RejectsLog {
+	pub image_path: String,
+	pub rejects: Vec<RejectEntry>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RejectEntry {
+	pub file_type_id: FileTypeId,
+	/// The start of the candidate, i.e. the start of the header (less the header offset)
+	pub start_idx: usize,
+	/// The end of the candidate, exclusive, or of its header if it was dropped in pairing
+	pub end_idx: usize,
+	/// Whether the candidate was completed at max_len because no footer was found in range
+	pub truncated: bool,
+	pub validation: FileValidationType,
+	/// The displayed form of the `RejectionReason`, if the validator gave one
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub rejection_reason: Option<String>
+}
+
+/// A line of a rejects log, which includes the image path so that lines from different images can be told apart when appending
+#[derive(Serialize)]
+struct RejectLine<'a> {
+	image_path: &'a str,
+	#[serde(flatten)]
+	entry: &'a RejectEntry
+}
+
+impl RejectsLog {
+	pub fn new(image_path: impl Into<String>) -> Self {
+		RejectsLog {
+			image_path: image_path.into(),
+			rejects: Vec::new()
+		}
+	}
+
+	/// Writes the rejected candidates to `rejects.jsonl` in `dir_path`, as a JSON object per line. If `append` is true, the lines are added
+	/// to any existing rejects log rather than replacing it
+	pub fn write(&self, dir_path: &str, append: bool) -> Result<(), io::Error> {
+		let filename: PathBuf = [ dir_path, "rejects.jsonl" ].into_iter().collect();
+
+		let mut file = io::BufWriter::new(fs::OpenOptions::new().write(true).create(true).append(append).truncate(!append).open(filename)?);
+
+		for entry in &self.rejects {
+			serde_json::to_writer(&mut file, &RejectLine { image_path: &self.image_path, entry })?;
+			writeln!(file)?;
+		}
+
+		file.flush()
+	}
+}
+
+/// Reads the values of a binary carve log in order, erroring if the data ends before a value
+struct BinaryReader<'a> {
+	data: &'a [u8],
+	idx: usize
+}
+
+impl<'a> BinaryReader<'a> {
+	fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+		let bytes = self.idx.checked_add(len).and_then(|end| self.data.get(self.idx..end)).ok_or(Error::LogReadError("Unexpected end of binary carve log".to_string()))?;
+		self.idx += len;
+
+		Ok(bytes)
+	}
+
+	fn u8(&mut self) -> Result<u8, Error> {
+		Ok(self.bytes(1)?[0])
+	}
+
+	fn u32(&mut self) -> Result<u32, Error> {
+		Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+	}
+
+	fn u64(&mut self) -> Result<u64, Error> {
+		Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+	}
+
+	fn string(&mut self) -> Result<String, Error> {
+		let len = self.u32()? as usize;
+
+		String::from_utf8(self.bytes(len)?.to_vec()).map_err(|e| Error::LogReadError(e.to_string()))
+	}
+
+	fn fragments(&mut self) -> Result<Vec<Fragment>, Error> {
+		let num_fragments = self.u32()?;
+
+		(0..num_fragments).map(|_| Ok(self.u64()? as usize..self.u64()? as usize)).collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{collections::HashMap, fs};
+
+	use crate::{searchlight::config::{FileTypeId, LogFormat, OffsetUnit}, validation::{EmbeddedEncoding, FileValidationType, METADATA_MODIFIED_TIME}};
+
+	use crate::error::Error;
+
+	use super::{CarveLog, CarveLogMember, FilenameOffsetUnit, FragmentChecksum, Fragmentation, FragmentationSummary, JSON_LOG_VERSION};
+
+	#[test]
+	fn test_write_bodyfile() {
+		let mut log = CarveLog::new("image.dat");
+
+		log.add_entry(FileTypeId::Zip, "0-100.zip".to_string(), FileValidationType::Correct, vec![ 0..60, 80..120 ], HashMap::from([ (METADATA_MODIFIED_TIME.to_string(), "1700000000".to_string()) ]));
+		log.add_entry(FileTypeId::Jpeg, "512-1024.jpg".to_string(), FileValidationType::Partial, vec![ 512..1024 ], HashMap::new());
+
+		let test_dir = std::env::temp_dir().join(format!("searchlight_test_write_bodyfile_{}", std::process::id()));
+		let _ = fs::remove_dir_all(&test_dir);
+		fs::create_dir_all(&test_dir).unwrap();
+
+		log.write_bodyfile(test_dir.to_str().unwrap(), false).unwrap();
+
+		assert_eq!(fs::read_to_string(test_dir.join("bodyfile.txt")).unwrap(), "0|correct/0-100.zip|0|0|0|0|100|0|1700000000|0|0\n");
+
+		fs::remove_dir_all(&test_dir).unwrap();
+	}
+
+	#[test]
+	fn test_write_appending() {
+		let test_dir = std::env::temp_dir().join(format!("searchlight_test_write_appending_{}", std::process::id()));
+		let _ = fs::remove_dir_all(&test_dir);
+		fs::create_dir_all(&test_dir).unwrap();
+		let dir = test_dir.to_str().unwrap();
+
+		let mut first = CarveLog::new("image.dat");
+		first.add_entry(FileTypeId::Png, "0-100.png".to_string(), FileValidationType::Correct, vec![ 0..100 ], HashMap::new());
+
+		let mut second = CarveLog::new("image.dat");
+		second.add_entry(FileTypeId::Png, "0-100_1.png".to_string(), FileValidationType::Correct, vec![ 0..100 ], HashMap::new());
+
+		let mut other = CarveLog::new("other.dat");
+		other.add_entry(FileTypeId::Jpeg, "512-1024.jpg".to_string(), FileValidationType::Partial, vec![ 512..1024 ], HashMap::new());
+
+		assert_eq!(first.write_appending(dir, LogFormat::Json).unwrap(), test_dir.join("log.json"));
+
+		// Logs of the same image should be merged, and logs of other images written alongside
+		assert_eq!(second.write_appending(dir, LogFormat::Json).unwrap(), test_dir.join("log.json"));
+		assert_eq!(other.write_appending(dir, LogFormat::Json).unwrap(), test_dir.join("log_1.json"));
+
+		let merged = CarveLog::read(test_dir.join("log.json")).unwrap();
+		assert_eq!(merged.files.iter().map(|entry| entry.filename.as_str()).collect::<Vec<_>>(), vec![ "0-100.png", "0-100_1.png" ]);
+
+		let other = CarveLog::read(test_dir.join("log_1.json")).unwrap();
+		assert_eq!(other.image_path, "other.dat");
+		assert_eq!(other.files.len(), 1);
+
+		fs::remove_dir_all(&test_dir).unwrap();
 	}
 
-	pub fn write(&self, dir_path: &str) -> Result<(), io::Error> {
-		let mut buf = Vec::new();
-		let formatter = serde_json::ser::PrettyFormatter::with_indent(b"\t");
-		let mut ser = serde_json::Serializer::with_formatter(&mut buf, formatter);
-		self.serialize(&mut ser).unwrap(); // This shouldn't fail... right??
+	#[test]
+	fn test_json_log_version() {
+		let mut log = CarveLog::new("image.dat");
+		log.add_entry(FileTypeId::Png, "0-100.png".to_string(), FileValidationType::Correct, vec![ 0..100 ], HashMap::new());
+
+		let test_dir = std::env::temp_dir().join(format!("searchlight_test_json_log_version_{}", std::process::id()));
+		let _ = fs::remove_dir_all(&test_dir);
+		fs::create_dir_all(&test_dir).unwrap();
+
+		// Written logs record the version, and are read back as-is
+		log.write(test_dir.to_str().unwrap(), LogFormat::Json).unwrap();
+		let written: serde_json::Value = serde_json::from_slice(&fs::read(test_dir.join("log.json")).unwrap()).unwrap();
+		assert_eq!(written["version"], JSON_LOG_VERSION);
+		assert_eq!(CarveLog::read(test_dir.join("log.json")).unwrap().files[0].filename, "0-100.png");
+
+		fs::remove_dir_all(&test_dir).unwrap();
+
+		// Legacy logs without a version are migrated
+		let legacy = br#"{ "image_path": "image.dat", "files": [ { "file_type_id": "png", "filename": "0-100.png", "validation": "correct", "fragments": [ { "start": 0, "end": 100 } ] } ] }"#;
+		let migrated = CarveLog::decode_json(legacy).unwrap();
+		assert_eq!(migrated.image_path, "image.dat");
+		assert_eq!(migrated.files[0].fragments, vec![ 0..100 ]);
+
+		// Legacy logs that can't be migrated are reported as such
+		let Err(Error::LogReadError(msg)) = CarveLog::decode_json(br#"{ "image_path": "image.dat", "entries": [] }"#) else {
+			panic!("Expected an unmigratable legacy log to fail to be read");
+		};
+		assert!(msg.contains("version 0 carve log could not be migrated"), "{msg}");
+
+		// Logs from newer versions are rejected
+		let Err(Error::LogReadError(msg)) = CarveLog::decode_json(br#"{ "version": 1000, "image_path": "image.dat", "files": [] }"#) else {
+			panic!("Expected a log of a newer version to fail to be read");
+		};
+		assert!(msg.contains("newer than the latest supported version"), "{msg}");
+	}
+
+	#[test]
+	fn test_fragmentation() {
+		assert_eq!(Fragmentation::of(&[ 0..100 ]), Fragmentation { fragment_count: 1, gap_bytes: 0 });
+		assert_eq!(Fragmentation::of(&[ 0..100, 150..200, 400..500 ]), Fragmentation { fragment_count: 3, gap_bytes: 250 });
+		assert_eq!(Fragmentation::of(&[ 400..500, 0..100 ]), Fragmentation { fragment_count: 2, gap_bytes: 500 });
+
+		let mut log = CarveLog::new("image.dat");
+		assert_eq!(log.fragmentation_summary(), None);
+
+		for fragments in [ vec![ 0..100 ], vec![ 200..300, 400..500, 1000..1100 ] ] {
+			let entry = log.add_entry(FileTypeId::Jpeg, format!("{}.jpg", fragments[0].start), FileValidationType::Correct, fragments, HashMap::new());
+			entry.fragmentation = Some(Fragmentation::of(&entry.fragments));
+		}
+		// Entries without fragmentation recorded are not counted
+		log.add_entry(FileTypeId::Jpeg, "2000.jpg".to_string(), FileValidationType::Correct, vec![ 2000..2100, 3000..3100 ], HashMap::new());
+
+		assert_eq!(log.fragmentation_summary(), Some(FragmentationSummary {
+			num_files: 2,
+			mean_fragment_count: 2.0,
+			max_fragment_count: 3,
+			mean_gap_bytes: 300.0,
+			max_gap_bytes: 600
+		}));
+
+		// Fragmentation is written as fields of the entry in JSON, and only if recorded
+		let json = serde_json::to_value(&log).unwrap();
+		assert_eq!(json["files"][1]["fragment_count"], 3);
+		assert_eq!(json["files"][1]["gap_bytes"], 600);
+		assert!(json["files"][2].get("fragment_count").is_none());
+
+		let decoded: CarveLog = serde_json::from_value(json).unwrap();
+		assert_eq!(decoded.files[1].fragmentation, log.files[1].fragmentation);
+		assert_eq!(decoded.files[2].fragmentation, None);
+	}
+
+	#[test]
+	fn test_fragment_checksums() {
+		let mut data = vec![ 0u8; 64 ];
+		data[10..19].copy_from_slice(b"123456789");
+		data[40..44].copy_from_slice(b"abcd");
+
+		// The CRC-32 of "123456789" is the standard check value
+		assert_eq!(FragmentChecksum::of(&data, &[ 10..19, 40..44 ]), vec![
+			FragmentChecksum { file_offset: 0, crc32: 0xcbf43926 },
+			FragmentChecksum { file_offset: 9, crc32: crc32fast::hash(b"abcd") }
+		]);
+		assert_eq!(FragmentChecksum::of(&data, &[]), vec![]);
+	}
+
+	#[test]
+	fn test_binary_log() {
+		let mut log = CarveLog::new("image.dat");
+
+		log.add_entry(FileTypeId::Zip, "0-100.zip".to_string(), FileValidationType::Correct, vec![ 0..60, 80..120 ], HashMap::from([ (METADATA_MODIFIED_TIME.to_string(), "1700000000".to_string()) ])).members = vec![
+			CarveLogMember {
+				name: "dir/a.txt".to_string(),
+				filename: "0-100.zip_members/0_dir_a.txt".to_string(),
+				validation: FileValidationType::Correct,
+				encoding: EmbeddedEncoding::Deflate,
+				fragments: vec![ 30..60, 80..90 ]
+			}
+		];
+		let entry = log.add_entry(FileTypeId::Dicom, "512-1000.dcm".to_string(), FileValidationType::Unrecognised, vec![ 512..1000 ], HashMap::new());
+		entry.slack = Some(1000..1024);
+		entry.appended_data = Some(1000..1100);
+		entry.fragment_checksums = vec![ FragmentChecksum { file_offset: 0, crc32: 0xcbf43926 } ];
+		entry.filename_offset_unit = Some(FilenameOffsetUnit { unit: OffsetUnit::Sectors, unit_size: 512 });
+		entry.directory = Some("dcm/unrecognised".to_string());
+		entry.fragmentation = Some(Fragmentation::of(&entry.fragments));
+		let entry = log.add_entry(FileTypeId::Unknown, "0-100.zip_members/0_dir_a.txt".to_string(), FileValidationType::Correct, vec![ 30..60, 80..90 ], HashMap::new());
+		entry.parent = Some("correct/0-100.zip".to_string());
+		entry.encoding = Some(EmbeddedEncoding::Deflate);
+
+		let encoded = log.encode_binary();
+		let decoded = CarveLog::decode_binary(&encoded).unwrap();
+
+		assert_eq!(decoded.image_path, log.image_path);
+		assert_eq!(decoded.files.len(), 3);
+		for (decoded, entry) in decoded.files.iter().zip(&log.files) {
+			assert_eq!(decoded.file_type_id, entry.file_type_id);
+			assert_eq!(decoded.filename, entry.filename);
+			assert_eq!(decoded.validation, entry.validation);
+			assert_eq!(decoded.fragments, entry.fragments);
+			assert_eq!(decoded.metadata, entry.metadata);
+			assert_eq!(decoded.members, entry.members);
+			assert_eq!(decoded.slack, entry.slack);
+			assert_eq!(decoded.appended_data, entry.appended_data);
+			assert_eq!(decoded.fragment_checksums, entry.fragment_checksums);
+			assert_eq!(decoded.filename_offset_unit, entry.filename_offset_unit);
+			assert_eq!(decoded.directory, entry.directory);
+			assert_eq!(decoded.parent, entry.parent);
+			assert_eq!(decoded.encoding, entry.encoding);
+			assert_eq!(decoded.fragmentation, entry.fragmentation);
+		}
+
+		// Version 1 logs, which are the same but without the member counts, slack, fragmentation, appended data, fragment checksums,
+		// filename offset units, directories, parents and encodings, can still be read
+		let mut v1_log = CarveLog::new("image.dat");
+		v1_log.add_entry(FileTypeId::Jpeg, "512-1024.jpg".to_string(), FileValidationType::Partial, vec![ 512..1024 ], HashMap::new());
+
+		let mut v1 = v1_log.encode_binary();
+		v1[8..12].copy_from_slice(&1u32.to_le_bytes());
+		v1.truncate(v1.len() - 27);
+
+		let decoded = CarveLog::decode_binary(&v1).unwrap();
+		assert_eq!(decoded.files[0].fragments, vec![ 512..1024 ]);
+		assert!(decoded.files[0].members.is_empty());
+
+		// Truncated logs are errors rather than panics
+		assert!(CarveLog::decode_binary(&encoded[..(encoded.len() - 3)]).is_err());
+
+		// Logs are read in the format that they were written in
+		let test_dir = std::env::temp_dir().join(format!("searchlight_test_binary_log_{}", std::process::id()));
+		let _ = fs::remove_dir_all(&test_dir);
+		fs::create_dir_all(&test_dir).unwrap();
+
+		log.write(test_dir.to_str().unwrap(), LogFormat::Binary).unwrap();
+		assert_eq!(CarveLog::read(test_dir.join("log.bin")).unwrap().files.len(), 3);
 
-		let filename: PathBuf = [ dir_path, "log.json" ].into_iter().collect();
+		log.write_to(&test_dir.join("log.csv"), LogFormat::Csv).unwrap();
+		assert_eq!(fs::read_to_string(test_dir.join("log.csv")).unwrap(), concat!(
+			"image_path,file_type_id,filename,validation,fragments,metadata\n",
+			"image.dat,zip,0-100.zip,correct,0-60;80-120,modified_time=1700000000\n",
+			"image.dat,dicom,512-1000.dcm,unrecognised,512-1000,\n",
+			"image.dat,unknown,0-100.zip_members/0_dir_a.txt,correct,30-60;80-90,\n"
+		));
 
-		fs::write(filename, buf)
+		fs::remove_dir_all(&test_dir).unwrap();
 	}
 }
\ No newline at end of file
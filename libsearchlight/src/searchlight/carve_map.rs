@@ -0,0 +1,120 @@
+use std::{fs, io::{self, Write}, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{carve_log::CarveLog, config::FileTypeId};
+
+/// The number of bytes that each cell of a carve map covers if neither a resolution nor a cluster size is supplied
+pub const DEFAULT_CARVE_MAP_RESOLUTION: u64 = 4096;
+
+/// A map of which parts of an image were claimed by carved files, and of what type, at a resolution of a number of bytes per cell. The
+/// map is run-length encoded, so that it stays small for huge images, and can be rendered e.g. as a bitmap to visualise fragmentation and
+/// the unallocated gaps between files
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CarveMap {
+	pub image_path: String,
+	pub image_len: u64,
+	/// The number of bytes that each cell covers
+	pub resolution: u64,
+	/// The number of cells that the image is divided into, the last of which may cover fewer than `resolution` bytes
+	pub num_cells: u64,
+	/// The runs of consecutive cells that are claimed by carved files of the same type, in order. Cells not in any run are unclaimed
+	pub runs: Vec<CarveMapRun>
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CarveMapRun {
+	/// The index of the first cell of the run
+	pub start: u64,
+	/// The number of cells in the run
+	pub len: u64,
+	pub file_type_id: FileTypeId
+}
+
+impl CarveMap {
+	/// Creates a carve map of an image of length `image_len` from the fragments of the files in `log`, with cells of `resolution` bytes. A
+	/// cell is claimed by a file if any of the file's data is in it. Cells claimed by files of different types are attributed to the type of
+	/// the file that starts first
+	pub fn from_log(log: &CarveLog, image_len: u64, resolution: u64) -> Self {
+		let mut claims: Vec<(u64, u64, FileTypeId)> = log.files.iter().flat_map(|entry| {
+			entry.fragments.iter().filter(|frag| !frag.is_empty()).map(|frag| {
+				(frag.start as u64 / resolution, (frag.end as u64).div_ceil(resolution), entry.file_type_id)
+			})
+		}).collect();
+		claims.sort_by_key(|&(start, _, _)| start);
+
+		let mut runs: Vec<CarveMapRun> = Vec::new();
+
+		for (start, end, file_type_id) in claims {
+			// Runs are in order and don't overlap, so only the last one can overlap or be adjacent to this claim
+			let (start, extends_last) = match runs.last() {
+				Some(last) if start <= last.start + last.len => (last.start + last.len, last.file_type_id == file_type_id),
+				_ => (start, false)
+			};
+
+			if start >= end {
+				continue;
+			}
+
+			if extends_last {
+				runs.last_mut().unwrap().len += end - start;
+			} else {
+				runs.push(CarveMapRun {
+					start,
+					len: end - start,
+					file_type_id
+				});
+			}
+		}
+
+		CarveMap {
+			image_path: log.image_path.clone(),
+			image_len,
+			resolution,
+			num_cells: image_len.div_ceil(resolution),
+			runs
+		}
+	}
+
+	/// Writes the carve map to `carve_map.json` in `dir_path`, replacing any existing one
+	pub fn write(&self, dir_path: &str) -> Result<(), io::Error> {
+		let filename: PathBuf = [ dir_path, "carve_map.json" ].into_iter().collect();
+
+		let mut file = io::BufWriter::new(fs::File::create(filename)?);
+
+		serde_json::to_writer(&mut file, self)?;
+
+		file.flush()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::HashMap;
+
+	use crate::{searchlight::{carve_log::CarveLog, config::FileTypeId}, validation::FileValidationType};
+
+	use super::{CarveMap, CarveMapRun};
+
+	#[test]
+	fn test_carve_map() {
+		let mut log = CarveLog::new("image.dat");
+
+		// A fragmented JPEG, with a PNG in the gap between its fragments that shares a cell with its first fragment
+		log.add_entry(FileTypeId::Jpeg, "0-4096.jpg".to_string(), FileValidationType::Correct, vec![ 0..1000, 3072..4096 ], HashMap::new());
+		log.add_entry(FileTypeId::Png, "1000-2048.png".to_string(), FileValidationType::Correct, vec![ 1000..2048 ], HashMap::new());
+		// Adjacent to the end of the JPEG
+		log.add_entry(FileTypeId::Jpeg, "4096-5000.jpg".to_string(), FileValidationType::Partial, vec![ 4096..5000 ], HashMap::new());
+		log.add_entry(FileTypeId::Zip, "8192-8193.zip".to_string(), FileValidationType::Correct, vec![ 8192..8193 ], HashMap::new());
+
+		let map = CarveMap::from_log(&log, 10000, 1024);
+
+		assert_eq!(map.num_cells, 10);
+		assert_eq!(map.runs, vec![
+			CarveMapRun { start: 0, len: 1, file_type_id: FileTypeId::Jpeg },
+			CarveMapRun { start: 1, len: 1, file_type_id: FileTypeId::Png },
+			CarveMapRun { start: 3, len: 2, file_type_id: FileTypeId::Jpeg },
+			CarveMapRun { start: 8, len: 1, file_type_id: FileTypeId::Zip },
+		]);
+	}
+}
\ No newline at end of file
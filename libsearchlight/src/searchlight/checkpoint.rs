@@ -0,0 +1,190 @@
+use std::{fs, io, path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use log::{debug, warn};
+
+use crate::search::{match_id_hash_add, match_id_hash_init, search_common::patterns_hash, Match};
+
+use super::config::SearchlightConfig;
+
+/// The magic bytes at the start of a search checkpoint
+const CHECKPOINT_MAGIC: &[u8; 8] = b"SLCKPT\0\0";
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// The size of the fixed part of a checkpoint: magic, version, number of completed blocks and number of matches
+const CHECKPOINT_HEADER_SIZE: usize = 8 + 4 + 8 + 8;
+/// The size of each match in a checkpoint: id, start index and end index
+const CHECKPOINT_MATCH_SIZE: usize = 8 * 3;
+
+/// The interval between checkpoints if none is configured
+pub const DEFAULT_CHECKPOINT_INTERVAL_SECS: u64 = 300;
+
+/// The checkpoint of the search of one range of an image, which holds the number of blocks whose search has completed and the matches found
+/// in them, so that an interrupted search can be resumed from the next block. Checkpoints are stored in the configured `checkpoint_dir`,
+/// named by a hash of the image path and length, the searched patterns, the range and the block size and overlap, so that a checkpoint
+/// of a different image, config or searcher is never resumed from. The layout (all integers little-endian) is:
+///
+/// ```text
+/// magic: [u8; 8] = "SLCKPT\0\0"
+/// version: u32
+/// completed_blocks: u64
+/// num_matches: u64
+/// matches: [id: u64, start_idx: u64, end_idx: u64; num_matches]
+/// ```
+pub struct SearchCheckpoint {
+	path: PathBuf,
+	interval: Duration,
+	last_saved: Instant
+}
+
+impl SearchCheckpoint {
+	/// Creates the checkpoint for searching `range` of the image at `image_path` (of length `image_len`) with the patterns of `config`, in
+	/// blocks of `block_size` that overlap by `overlap`. Returns None if checkpointing isn't configured
+	pub fn new(config: &SearchlightConfig, image_path: &str, image_len: usize, range_start: usize, range_len: usize, block_size: usize, overlap: usize) -> Option<Self> {
+		let dir = config.checkpoint_dir.as_ref()?;
+
+		let mut hash = patterns_hash(config);
+		for b in [ range_start, range_len, block_size, overlap ].into_iter().flat_map(|v| (v as u64).to_le_bytes()) {
+			hash = match_id_hash_add(hash, b);
+		}
+
+		Some(SearchCheckpoint {
+			path: Path::new(dir).join(format!("{}{:016x}.bin", Self::image_prefix(image_path, image_len), hash)),
+			interval: Duration::from_secs(config.checkpoint_interval.unwrap_or(DEFAULT_CHECKPOINT_INTERVAL_SECS)),
+			last_saved: Instant::now()
+		})
+	}
+
+	/// The start of the filenames of the checkpoints of the image at `image_path`
+	fn image_prefix(image_path: &str, image_len: usize) -> String {
+		let mut hash = match_id_hash_init();
+		for &b in image_path.as_bytes().iter().chain((image_len as u64).to_le_bytes().iter()) {
+			hash = match_id_hash_add(hash, b);
+		}
+
+		format!("checkpoint_{hash:016x}_")
+	}
+
+	/// Loads the saved number of completed blocks and the matches found in them, if there is a checkpoint that can be read
+	pub fn load(&self) -> Option<(usize, Vec<Match>)> {
+		let data = fs::read(&self.path).ok()?;
+
+		if data.len() < CHECKPOINT_HEADER_SIZE || !data.starts_with(CHECKPOINT_MAGIC) || u32::from_le_bytes(data[8..12].try_into().unwrap()) != CHECKPOINT_VERSION {
+			debug!("Checkpoint at {} is stale or corrupt, ignoring it", self.path.display());
+			return None;
+		}
+
+		let completed_blocks = u64::from_le_bytes(data[12..20].try_into().unwrap()) as usize;
+		let num_matches = u64::from_le_bytes(data[20..28].try_into().unwrap()) as usize;
+
+		let matches_data = &data[CHECKPOINT_HEADER_SIZE..];
+		if num_matches.checked_mul(CHECKPOINT_MATCH_SIZE) != Some(matches_data.len()) {
+			debug!("Checkpoint at {} is truncated, ignoring it", self.path.display());
+			return None;
+		}
+
+		let matches = matches_data.chunks_exact(CHECKPOINT_MATCH_SIZE).map(|m| {
+			Match::new(
+				u64::from_le_bytes(m[0..8].try_into().unwrap()),
+				u64::from_le_bytes(m[8..16].try_into().unwrap()),
+				u64::from_le_bytes(m[16..24].try_into().unwrap())
+			)
+		}).collect();
+
+		Some((completed_blocks, matches))
+	}
+
+	/// Saves the checkpoint if the checkpoint interval has passed since it was last saved, or if `force` is set. Failing to save is only
+	/// logged, as the search can carry on without checkpoints
+	pub fn save(&mut self, completed_blocks: usize, matches: &[Match], force: bool) {
+		if !force && self.last_saved.elapsed() < self.interval {
+			return;
+		}
+
+		if let Err(e) = self.write(completed_blocks, matches) {
+			warn!("Failed to write search checkpoint to {}: {}", self.path.display(), e);
+		}
+
+		self.last_saved = Instant::now();
+	}
+
+	fn write(&self, completed_blocks: usize, matches: &[Match]) -> Result<(), io::Error> {
+		let mut data = Vec::with_capacity(CHECKPOINT_HEADER_SIZE + matches.len() * CHECKPOINT_MATCH_SIZE);
+
+		data.extend_from_slice(CHECKPOINT_MAGIC);
+		data.extend_from_slice(&CHECKPOINT_VERSION.to_le_bytes());
+		data.extend_from_slice(&(completed_blocks as u64).to_le_bytes());
+		data.extend_from_slice(&(matches.len() as u64).to_le_bytes());
+
+		for m in matches {
+			data.extend_from_slice(&m.id.to_le_bytes());
+			data.extend_from_slice(&m.start_idx.to_le_bytes());
+			data.extend_from_slice(&m.end_idx.to_le_bytes());
+		}
+
+		if let Some(parent) = self.path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		// Write to a temporary file first so that a crash while writing can't leave a corrupt checkpoint in place of a good one
+		let tmp_path = self.path.with_extension("tmp");
+		fs::write(&tmp_path, data)?;
+		fs::rename(tmp_path, &self.path)
+	}
+
+	/// Removes all checkpoints of the image at `image_path` in the checkpoint directory of `config`, whatever patterns they were searched
+	/// with, once they are no longer needed
+	pub fn remove_all(config: &SearchlightConfig, image_path: &str, image_len: usize) {
+		let Some(dir) = &config.checkpoint_dir else {
+			return;
+		};
+
+		let prefix = Self::image_prefix(image_path, image_len);
+
+		let Ok(entries) = fs::read_dir(dir) else {
+			return;
+		};
+
+		for entry in entries.flatten() {
+			if entry.file_name().to_string_lossy().starts_with(&prefix) {
+				if let Err(e) = fs::remove_file(entry.path()) {
+					warn!("Failed to remove search checkpoint {}: {}", entry.path().display(), e);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::Match, searchlight::config::SearchlightConfig};
+
+	use super::SearchCheckpoint;
+
+	#[test]
+	fn test_search_checkpoint() {
+		let test_dir = std::env::temp_dir().join(format!("searchlight_test_search_checkpoint_{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&test_dir);
+
+		let config = SearchlightConfig {
+			checkpoint_dir: Some(test_dir.to_str().unwrap().to_string()),
+			..Default::default()
+		};
+
+		let matches = vec![ Match::new(1, 10, 12), Match::new(2, 4000, 4007) ];
+
+		let mut checkpoint = SearchCheckpoint::new(&config, "image.dat", 8192, 0, 8192, 4096, 8).unwrap();
+		assert_eq!(checkpoint.load(), None);
+
+		checkpoint.save(1, &matches, true);
+		assert_eq!(checkpoint.load(), Some((1, matches.clone())));
+
+		// A checkpoint of a different image or range isn't resumed from
+		assert_eq!(SearchCheckpoint::new(&config, "image.dat", 8193, 0, 8192, 4096, 8).unwrap().load(), None);
+		assert_eq!(SearchCheckpoint::new(&config, "image.dat", 8192, 4096, 4096, 4096, 8).unwrap().load(), None);
+
+		SearchCheckpoint::remove_all(&config, "image.dat", 8192);
+		assert_eq!(checkpoint.load(), None);
+
+		std::fs::remove_dir_all(&test_dir).unwrap();
+	}
+}
\ No newline at end of file
@@ -1,22 +1,157 @@
-use std::{collections::HashMap, fmt::Display, ops::Deref};
+use std::{borrow::Cow, collections::HashMap, fmt::Display, fs, ops::Deref, path::{Path, PathBuf}, thread, time::Duration};
 
-use log::error;
-use serde::{Deserialize, Serialize};
+use log::{error, info};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
 
-use crate::{error::Error, search::{match_id_hash_slice_u16, pairing::MatchPart}, utils::str_parse::parse_match_str};
+use crate::{error::Error, validation::FileValidationType, search::{match_id_hash_slice_u16, pairing::MatchPart, search_common::MATCH_ALL_VALUE, GpuSearchOptions, DEFAULT_GPU_SEARCH_TIMEOUT}, utils::{exclusions::ExclusionList, filename_template::FilenameTemplate, str_parse::{check_match_str, expand_alternations, parse_match_str}}};
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct SearchlightConfig {
 	pub max_reconstruction_search_len: Option<u64>,
+	/// Whether searching forwards when reconstructing fragmented data stops at the next file header found in the image
+	#[serde(default)]
+	pub bound_reconstruction_by_headers: bool,
+	/// How the ZIP validator treats a central directory entry count that doesn't match the local file headers found, see `ZipEntryCountPolicy`
+	#[serde(default)]
+	pub zip_entry_count_policy: ZipEntryCountPolicy,
+	/// Whether a validator panicking is caught, marking the candidate as unrecognised, rather than aborting the carve
+	#[serde(default)]
+	pub catch_validator_panics: bool,
+	/// Whether files that fail a checksum or reconstruction are checked for NTFS (LZNT1) compression, and marked corrupt if so. Defaults to false
+	#[serde(default)]
+	pub detect_compressed: bool,
+	/// The number of milliseconds that validating a candidate may take before it is abandoned and the candidate carved as unanalysed
+	#[serde(default)]
+	pub validation_timeout_ms: Option<u64>,
+	/// Whether the footers of file types with a max_len are searched for in a second pass, only within max_len of their headers
+	#[serde(default)]
+	pub targeted_footer_search: bool,
+	/// The length in bytes above which a candidate is warned about, as it is likely a spurious pairing
+	#[serde(default)]
+	pub max_candidate_span: Option<u64>,
+	/// Whether candidates longer than max_candidate_span are skipped as unrecognised rather than only warned about
+	#[serde(default)]
+	pub skip_oversized_candidates: bool,
+	/// What is done about candidates that overlap another candidate of the same type, see `OverlapPolicy`
+	#[serde(default)]
+	pub overlap_policy: OverlapPolicy,
+	/// The number of worker threads that searching on the CPU and validating share. Defaults to the available parallelism
+	#[serde(default)]
+	pub threads: Option<usize>,
+	/// Whether searching fails with an error if the GPU can't be used, rather than falling back to the CPU. Defaults to false
+	#[serde(default)]
+	pub require_gpu: bool,
+	/// The number of seconds to wait for each search on the GPU to complete before it times out. Defaults to `DEFAULT_GPU_SEARCH_TIMEOUT`
+	#[serde(default)]
+	pub gpu_timeout: Option<u64>,
+	/// Whether searching switches to the CPU for the rest of the run once a search on the GPU times out, rather than failing
+	#[serde(default)]
+	pub gpu_timeout_fallback: bool,
+	/// Ranges of byte offsets into the image, as `[start, end]` pairs (end exclusive), that are excluded from searching and carving
+	#[serde(default)]
+	pub exclude: Vec<[u64; 2]>,
+	/// The maximum number of validation results to cache, so that duplicate candidates aren't validated again
+	#[serde(default)]
+	pub validation_cache_size: Option<usize>,
+	/// A directory to cache the search automaton (AC table) built from the headers and footers in
+	#[serde(default)]
+	pub ac_table_cache_dir: Option<String>,
+	/// A directory to periodically save the progress of searching images in, so that interrupted searches resume, see `SearchCheckpoint`
+	#[serde(default)]
+	pub checkpoint_dir: Option<String>,
+	/// The minimum number of seconds between search checkpoints. Defaults to `DEFAULT_CHECKPOINT_INTERVAL_SECS`
+	#[serde(default)]
+	pub checkpoint_interval: Option<u64>,
+	/// The template for the filenames of carved files, see `FilenameTemplate`. Defaults to `DEFAULT_FILENAME_TEMPLATE`
+	#[serde(default)]
+	pub filename_template: Option<String>,
+	/// The unit of the `{start}` and `{end}` offsets in the filenames of carved files, see `OffsetUnit`
+	#[serde(default)]
+	pub filename_offset_unit: OffsetUnit,
+	/// How carved files are arranged in subdirectories of the output directory, see `OutputLayout`
+	#[serde(default)]
+	pub output_layout: OutputLayout,
+	/// The format to write carve logs in. Defaults to JSON
+	#[serde(default)]
+	pub log_format: LogFormat,
+	/// Whether a log of the candidates that were not carved is written alongside the carve log, as `rejects.jsonl`
+	#[serde(default)]
+	pub rejects_log: bool,
+	/// Whether a map of which parts of the image were claimed by carved files is written alongside the carve log, see `CarveMap`
+	#[serde(default)]
+	pub carve_map: bool,
+	/// The number of bytes that each cell of the carve map covers
+	#[serde(default)]
+	pub carve_map_resolution: Option<u64>,
+	/// Whether the files embedded in carved files, e.g. ZIP members and JPEG thumbnails, are extracted alongside them
+	#[serde(default)]
+	pub extract_embedded: bool,
+	/// Whether the files extracted by `extract_embedded` are recorded in the carve log as entries of their own
+	#[serde(default)]
+	pub embedded_entries: bool,
+	/// Whether carved files are named by the names that validators recover from them, where there is one
+	#[serde(default)]
+	pub original_names: bool,
+	/// Whether the file slack of each carved file is carved alongside it as `<filename>.slack`
+	#[serde(default)]
+	pub carve_slack: bool,
+	/// Whether how fragmented each carved file is gets recorded in the carve log and summarised, see `Fragmentation`
+	#[serde(default)]
+	pub fragmentation_stats: bool,
+	/// Whether data appended after the end of each correct file is carved alongside it as `<filename>.appended`
+	#[serde(default)]
+	pub appended_data: bool,
+	/// With `appended_data`, the most bytes of data appended after a file that are carved
+	#[serde(default)]
+	pub appended_data_max_len: Option<u64>,
+	/// Whether the time spent in each phase of processing an image is measured and reported
+	#[serde(default)]
+	pub timings: bool,
+	/// Whether the cluster size, if not supplied, is read from a FAT, exFAT or NTFS boot sector before it is estimated
+	#[serde(default)]
+	pub boot_sector_cluster_size: bool,
+	/// Whether the CRC-32 of each fragment of each carved file is recorded in the carve log, see `FragmentChecksum`
+	#[serde(default)]
+	pub fragment_checksums: bool,
+	/// Whether candidates are validated and carved while the image is still being searched
+	#[serde(default)]
+	pub pipelined_validation: bool,
+	/// With `pipelined_validation`, the most bytes beyond the end of a candidate that the search must be past before it is validated
+	#[serde(default)]
+	pub pipelined_validation_lookahead: Option<u64>,
+	/// The validation type that candidates which validators couldn't fully analyse are carved as, instead of Unanalysed
+	#[serde(default)]
+	pub unanalysed_as: Option<FileValidationType>,
+	/// How image files are read when processed, see `IoStrategy`
+	#[serde(default)]
+	pub io_strategy: IoStrategy,
+	/// How the progress of searching and carving is reported. Defaults to continuous
+	#[serde(default)]
+	pub progress: ProgressMode,
+	/// The names (extensions or type_ids) of the file types to carve, or all if empty
+	#[serde(default)]
+	pub only_types: Vec<String>,
+	/// The names of file types not to carve, matched as for only_types, and applied after it. Empty by default
+	#[serde(default)]
+	pub exclude_types: Vec<String>,
 	#[serde(rename = "file_type")]
 	pub file_types: Vec<FileType>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Default)]
+#[derive(Deserialize, Debug, PartialEq, Default, Clone)]
 pub struct FileType { // TODO: Add minimum length, and use that minimum length when pairing
+	/// The headers of the file type. Alternation groups in each header are expanded at parse time, see `MatchString::expand`
+	#[serde(default, deserialize_with = "deserialize_expanded_match_strings")]
 	pub headers: Vec<MatchString>,
-	#[serde(default)]
+	/// The footers of the file type, whose alternation groups are expanded at parse time. Footers that are also headers are delimiters
+	#[serde(default, deserialize_with = "deserialize_expanded_match_strings")]
 	pub footers: Vec<MatchString>,
+	/// Path to a file of additional headers, one per line, relative to the directory of the config file
+	#[serde(default)]
+	pub headers_file: Option<String>,
+	/// Path to a file of additional footers, one per line, relative to the directory of the config file
+	#[serde(default)]
+	pub footers_file: Option<String>,
 	#[serde(default)]
 	pub extension: Option<String>,
 	#[serde(default)]
@@ -25,7 +160,118 @@ pub struct FileType { // TODO: Add minimum length, and use that minimum length w
 	pub pairing: PairingStrategy,
 	pub max_len: Option<u64>,
 	#[serde(default)]
-	pub requires_footer: bool
+	pub requires_footer: bool,
+	/// Whether headers with no footer within max_len are carved as truncated files, when requires_footer is set
+	#[serde(default)]
+	pub truncate_at_max_len: bool,
+	/// Whether validators verify the CRCs of this type, reconstructing fragmented data when they don't match
+	#[serde(default)]
+	pub verify_crc: Option<bool>,
+	/// The number of bytes before the header at which files of this type start, e.g. 128 for DICOM
+	#[serde(default)]
+	pub header_offset: u64,
+	/// Whether an extra cluster past the end of each file is carved, to catch trailing data that the validator doesn't recognise
+	#[serde(default)]
+	pub carve_extra_cluster: bool,
+	/// Byte sequences that must all appear in a candidate for it to be Correct, for types without a dedicated validator
+	#[serde(default)]
+	pub requires: Vec<MatchString>,
+	/// Whether the footers are preferences in the order that they are configured, rather than equivalent
+	#[serde(default)]
+	pub ordered_footers: bool,
+	/// The range of Shannon entropy, as `[min, max]` bits per byte, outside of which candidates are rejected before validation
+	#[serde(default)]
+	pub expected_entropy: Option<[f32; 2]>,
+	/// The cluster size passed to the validator of this type, instead of that of the image
+	#[serde(default)]
+	pub cluster_size: Option<u64>,
+	/// Whether only the headers of this type whose candidate start is at the start of a cluster are paired
+	#[serde(default)]
+	pub header_aligned: bool,
+	/// A field in files of this type that holds the size of the file, see `SizeField`
+	#[serde(default)]
+	pub size_field: Option<SizeField>,
+	/// What must follow a footer of this type for it to be paired, see `FooterContext`
+	#[serde(default)]
+	pub footer_context: Option<FooterContext>,
+	/// How the end of candidates of this type is estimated when it isn't known, see `EndHeuristic`
+	#[serde(default)]
+	pub end_heuristic: Option<EndHeuristic>,
+	/// Parameters for the validator of this type, documented on it as `PARAM_*` constants in `validation`
+	#[serde(default)]
+	pub params: HashMap<String, toml::Value>
+}
+
+/// A field at a fixed offset in a file that holds the size of the file, as in e.g. BMP, WAV and ISO-BMFF files. The size of the file is the
+/// value of the field plus `addend`, plus the offset of the end of the field if `relative` is set
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct SizeField {
+	/// The offset of the field from the start of the file (not the header, if header_offset is set)
+	pub offset: u64,
+	/// The width of the field in bytes, which is 2, 4 or 8
+	pub width: u8,
+	/// The byte order of the field, "le" (or "little") or "be" (or "big"), e.g. big-endian for QuickTime atoms and little-endian for BMP.
+	/// Defaults to little-endian
+	#[serde(default)]
+	pub endianness: Endianness,
+	/// Whether the size is of the data following the field, rather than of the whole file, e.g. for RIFF files such as WAV
+	#[serde(default)]
+	pub relative: bool,
+	/// A number of bytes added to the size, e.g. for formats where the size excludes a trailer
+	#[serde(default)]
+	pub addend: i64
+}
+
+/// What must immediately follow a footer for it to be paired. A footer at the end of the data is always accepted, as nothing follows it
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct FooterContext {
+	/// Byte sequences, one of which must follow the footer. Alternation groups are expanded at parse time, as for headers and footers
+	#[serde(default, deserialize_with = "deserialize_expanded_match_strings")]
+	pub followed_by: Vec<MatchString>,
+	/// Whether a footer that ends on a cluster boundary is accepted whatever follows it, as the next cluster may not belong to the file.
+	/// Has no effect on unaligned data (a cluster size of 1). Defaults to false
+	#[serde(default)]
+	pub cluster_boundary: bool
+}
+
+impl FooterContext {
+	/// Whether the data following a footer that ends at `footer_end` (exclusive) in `data`, which has a cluster size of `cluster_size`,
+	/// satisfies this context
+	pub fn is_satisfied(&self, data: &[u8], footer_end: usize, cluster_size: usize) -> bool {
+		let following = data.get(footer_end..).unwrap_or(&[]);
+
+		following.is_empty()
+			|| (self.cluster_boundary && cluster_size > 1 && footer_end % cluster_size == 0)
+			|| self.followed_by.iter().any(|sequence| {
+				sequence.len() <= following.len() && sequence.iter().zip(following).all(|(&v, &b)| v == MATCH_ALL_VALUE || v == b as u16)
+			})
+	}
+}
+
+/// The byte order of a multi-byte field. Defaults to little-endian
+#[derive(Deserialize, Debug, PartialEq, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+	#[default]
+	#[serde(alias = "le")]
+	Little,
+	#[serde(alias = "be")]
+	Big
+}
+
+impl Endianness {
+	/// Decodes `field`, an unsigned integer in this byte order, or returns None if it isn't 2, 4 or 8 bytes wide
+	pub fn decode(self, field: &[u8]) -> Option<u64> {
+		Some(match (self, field.len()) {
+			(Endianness::Little, 2) => u16::from_le_bytes(field.try_into().unwrap()) as u64,
+			(Endianness::Little, 4) => u32::from_le_bytes(field.try_into().unwrap()) as u64,
+			(Endianness::Little, 8) => u64::from_le_bytes(field.try_into().unwrap()),
+			(Endianness::Big, 2) => u16::from_be_bytes(field.try_into().unwrap()) as u64,
+			(Endianness::Big, 4) => u32::from_be_bytes(field.try_into().unwrap()) as u64,
+			(Endianness::Big, 8) => u64::from_be_bytes(field.try_into().unwrap()),
+			_ => return None
+		})
+	}
 }
 
 #[derive(Deserialize, Debug, PartialEq, Clone)]
@@ -50,6 +296,64 @@ impl From<&str> for MatchString {
 	}
 }
 
+impl FileType {
+	/// Whether validators should verify CRCs and attempt reconstruction on a mismatch, see `verify_crc`
+	pub fn should_verify_crc(&self) -> bool {
+		self.verify_crc.unwrap_or(true)
+	}
+
+	/// Returns the validator parameter `key` (see `params`) as a `T`, or None if it isn't set. A value that isn't a valid `T` is treated as
+	/// unset - This is called for every validation, so such values are instead warned about once per carve, see `validation::invalid_params`
+	pub fn param<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+		self.params.get(key)?.clone().try_into().ok()
+	}
+}
+
+impl MatchString {
+	/// Expands the alternation groups in `value`, e.g. `\xff(\xd8|\xd9)`, returning a MatchString for each of the expanded forms, or a
+	/// description of the problem if the groups are malformed. See `expand_alternations` for the grammar
+	pub fn expand(value: &str) -> Result<Vec<MatchString>, String> {
+		Ok(expand_alternations(value)?.iter().map(|s| MatchString::from(s.as_str())).collect())
+	}
+}
+
+/// Deserializes a list of match strings, expanding the alternation groups of each into multiple match strings
+fn deserialize_expanded_match_strings<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<MatchString>, D::Error> {
+	let strings: Vec<String> = Vec::deserialize(deserializer)?;
+
+	let mut match_strings = Vec::new();
+	for s in &strings {
+		match_strings.extend(MatchString::expand(s).map_err(|e| serde::de::Error::custom(format!("Invalid match string \"{s}\": {e}")))?);
+	}
+
+	Ok(match_strings)
+}
+
+/// Reads the match strings in the pattern file at `path`, expanding alternation groups. See `SearchlightConfig::load_pattern_files`
+fn read_pattern_file(path: &Path) -> Result<Vec<MatchString>, Error> {
+	let contents = fs::read_to_string(path).map_err(|e| Error::PatternFileError(format!("Could not read \"{}\": {}", path.display(), e)))?;
+
+	let mut patterns = Vec::new();
+
+	for (i, line) in contents.lines().enumerate() {
+		let line = line.trim();
+
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let expanded = expand_alternations(line).map_err(|e| Error::PatternFileError(format!("\"{}\", line {}: {}", path.display(), i + 1, e)))?;
+
+		for pattern in expanded {
+			check_match_str(&pattern).map_err(|e| Error::PatternFileError(format!("\"{}\", line {}: {}", path.display(), i + 1, e)))?;
+
+			patterns.push(MatchString::from(pattern));
+		}
+	}
+
+	Ok(patterns)
+}
+
 impl Deref for MatchString {
 	type Target = Vec<u16>;
 
@@ -75,17 +379,191 @@ impl Display for MatchString {
 	}
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, strum::Display, Clone, Copy)]
+/// The ID of the validator for a file type. The order of the variants is part of the binary carve log format (see `CarveLog::encode_binary`),
+/// so new variants should be added at the end
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, strum::Display, strum::FromRepr, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
+#[repr(u8)]
 pub enum FileTypeId {
 	Unknown,
 	Jpeg,
 	Png,
-	Zip
+	Zip,
+	Mp3,
+	Eml,
+	Psd,
+	Dicom,
+	#[serde(rename = "sparse_img")]
+	#[strum(serialize = "sparse_img")]
+	SparseImg,
+	Font,
+	Gif,
+	Lnk,
+	Jp2,
+	Ogg,
+	Opus,
+	Wim,
+	Wav,
+	Avi,
+	Webp
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+/// The format of a carve log. Binary logs are much smaller and faster to read and write than JSON for images with many files, and CSV is for
+/// use in other tools, as CSV logs can't be read back in. See `CarveLog`
+#[derive(Deserialize, Debug, PartialEq, Default, Clone, Copy, strum::EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum LogFormat {
+	#[default]
+	Json,
+	Binary,
+	Csv
+}
+
+impl LogFormat {
+	/// The file extension of logs in this format
+	pub fn extension(&self) -> &'static str {
+		match self {
+			LogFormat::Json => "json",
+			LogFormat::Binary => "bin",
+			LogFormat::Csv => "csv"
+		}
+	}
+}
+
+/// How the progress of searching and carving is reported, see `ProgressReporter`
+#[derive(Deserialize, Debug, PartialEq, Default, Clone, Copy, strum::EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ProgressMode {
+	/// The percentage is continuously rewritten on the current line of stderr. Only used if stderr is an interactive terminal, otherwise
+	/// milestones are logged instead
+	#[default]
+	Continuous,
+	/// The progress is logged at 25%, 50%, 75% and 100%, which keeps log lines clean
+	Milestones,
+	/// No progress is reported
+	Silent
+}
+
+/// The size of the sectors that sector offsets are in terms of
+pub const SECTOR_SIZE: u64 = 512;
+
+/// The unit of the offsets in the filenames of carved files, see `SearchlightConfig::filename_offset_unit`. Start offsets are rounded down to
+/// the unit, and end offsets up, so that the range covers the file
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Default, Clone, Copy, strum::EnumString, strum::Display, strum::FromRepr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+#[repr(u8)]
+pub enum OffsetUnit {
+	#[default]
+	Bytes,
+	/// Sectors of `SECTOR_SIZE` bytes
+	Sectors,
+	/// Clusters of the cluster size that each file was validated with, which is that of its file type if configured, or otherwise that of
+	/// the image (supplied or estimated)
+	Clusters
+}
+
+impl OffsetUnit {
+	/// The size of the unit in bytes, for a file with a cluster size of `cluster_size`
+	pub fn size(self, cluster_size: usize) -> usize {
+		match self {
+			OffsetUnit::Bytes => 1,
+			OffsetUnit::Sectors => SECTOR_SIZE as usize,
+			OffsetUnit::Clusters => cluster_size.max(1)
+		}
+	}
+}
+
+/// How carved files are arranged in subdirectories of the output directory, see `SearchlightConfig::output_layout`
+#[derive(Deserialize, Debug, PartialEq, Eq, Default, Clone, Copy, strum::EnumString, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum OutputLayout {
+	/// `<validation>/<file>`, e.g. `correct/0-1024.png`
+	#[default]
+	Validation,
+	/// `<extension>/<validation>/<file>`, e.g. `png/correct/0-1024.png`, for browsing by file type
+	ExtensionValidation,
+	/// `<validation>/<extension>/<file>`, e.g. `correct/png/0-1024.png`
+	ValidationExtension
+}
+
+impl OutputLayout {
+	/// The directory, relative to the output directory, that files with the extension `extension` that were validated as `validation` are
+	/// carved to
+	pub fn directory(self, extension: &str, validation: FileValidationType) -> PathBuf {
+		let validation = validation.to_string();
+
+		match self {
+			OutputLayout::Validation => PathBuf::from(validation),
+			OutputLayout::ExtensionValidation => [ extension, &validation ].iter().collect(),
+			OutputLayout::ValidationExtension => [ &validation, extension ].iter().collect()
+		}
+	}
+}
+
+/// How image files are read when they are processed
+#[derive(Deserialize, Debug, PartialEq, Default, Clone, Copy, strum::EnumString, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum IoStrategy {
+	/// Images are memory mapped, so that any part of them can be read when validating and carving. This is the fastest, but mapping very
+	/// large images can fail
+	#[default]
+	Mmap,
+	/// Images are read a block at a time, as when processing stdin, so only a block is held in memory at once. The found files can't be
+	/// validated or carved in this mode, and are only logged as unanalysed (see `Searchlight::process_stream`)
+	Stream
+}
+
+/// How a mismatch between the number of entries in the central directory of a ZIP archive and the number of local file headers found for them
+/// is treated. Local file headers can be missing from the matches without the archive being corrupt, e.g. if the ZIP header isn't configured
+/// or a local file header is fragmented
+#[derive(Deserialize, Debug, PartialEq, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ZipEntryCountPolicy {
+	/// Any mismatch marks the archive as Corrupt
+	#[default]
+	Strict,
+	/// Fewer headers than entries marks the archive as Partial, and more marks it as Corrupt
+	Partial,
+	/// Only more headers than entries marks the archive as Corrupt, and fewer are ignored
+	ExcessOnly
+}
+
+/// How the end of the content of a candidate whose end isn't known is estimated, see `FileType::end_heuristic`. Each uses a classifier in
+/// `classifiers`
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum EndHeuristic {
+	/// The content ends at the first byte that can't be text, for text formats, see `classifiers::text_len`
+	Text,
+	/// The content ends before any trailing zeros, see `classifiers::zero_trimmed_len`
+	Zeros,
+	/// The content ends at the first cluster whose entropy differs markedly from that of the content before it, e.g. for compressed formats
+	/// followed by the uncompressed data of another file, see `classifiers::consistent_entropy_len`
+	Entropy
+}
+
+/// How candidates of the same type whose spans overlap are handled
+#[derive(Deserialize, Debug, PartialEq, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+	/// Overlaps are not checked for
+	Ignore,
+	/// Overlaps are logged, but all the overlapping candidates are carved
+	#[default]
+	Log,
+	/// Overlaps are logged, and of each group of overlapping candidates, only those that validate best and don't overlap each other are carved,
+	/// with the rest rejected
+	PreferValid
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy)]
 pub enum PairingStrategy {
 	#[serde(rename = "next")]
 	PairNext,
@@ -97,11 +575,110 @@ impl SearchlightConfig {
 	pub fn validate(&self) -> Result<(), Error> {
 		let mut error = false;
 
+		if self.threads == Some(0) {
+			error!("Config: threads is set to 0 - Configure at least 1 thread, or leave unset to use the available parallelism");
+			error = true;
+		}
+
+		if self.carve_map_resolution == Some(0) {
+			error!("Config: carve_map_resolution is set to 0 - Configure a resolution of at least 1 byte per cell, or leave unset to use the default");
+			error = true;
+		}
+
+		if self.validation_timeout_ms == Some(0) {
+			error!("Config: validation_timeout_ms is set to 0 - Configure a timeout of at least 1 millisecond, or leave unset for no timeout");
+			error = true;
+		}
+
+		if self.gpu_timeout == Some(0) {
+			error!("Config: gpu_timeout is set to 0 - Configure a timeout of at least 1 second, or leave unset to use the default");
+			error = true;
+		}
+
+		if self.require_gpu && self.gpu_timeout_fallback {
+			error!("Config: require_gpu and gpu_timeout_fallback are both set - Searching can't fall back to the CPU if the GPU is required");
+			error = true;
+		}
+
+		if self.validation_cache_size == Some(0) {
+			error!("Config: validation_cache_size is set to 0 - Configure a cache size of at least 1, or leave unset to disable validation caching");
+			error = true;
+		}
+
+		if self.unanalysed_as == Some(FileValidationType::Unrecognised) {
+			error!("Config: unanalysed_as is set to unrecognised - Unanalysed candidates are always carved, so map them to a bucket that is carved, or leave unset to keep them unanalysed");
+			error = true;
+		}
+
+		if let Some(template) = &self.filename_template {
+			if let Err(e) = FilenameTemplate::parse(template) {
+				error!("Config: filename_template \"{template}\" is invalid: {e}");
+				error = true;
+			}
+		}
+
+		for &[start, end] in &self.exclude {
+			if start >= end {
+				error!("Config: Excluded range [{start}, {end}] is empty - The start of an excluded range must be less than the end");
+				error = true;
+			}
+		}
+
 		for ft in &self.file_types {
+			if ft.headers.is_empty() {
+				error!("Config: File type {} has no headers - Configure at least one header, or a headers_file", ft.extension.clone().unwrap_or("<no extension>".to_string()));
+				error = true;
+			}
 			if !ft.has_footer() && ft.max_len.is_none() {
 				error!("Config: File type {} has no footers or a configured max length - Configure at least one footer or a max_len", ft.extension.clone().unwrap_or("<no extension>".to_string()));
 				error = true;
 			}
+			if ft.truncate_at_max_len && ft.max_len.is_none() {
+				error!("Config: File type {} is configured to truncate at max_len but has no configured max_len - Configure a max_len", ft.extension.clone().unwrap_or("<no extension>".to_string()));
+				error = true;
+			}
+			if ft.cluster_size == Some(0) {
+				error!("Config: File type {} has a cluster_size of 0 - The cluster size must be at least 1", ft.extension.clone().unwrap_or("<no extension>".to_string()));
+				error = true;
+			}
+			if let Some([min, max]) = ft.expected_entropy {
+				if !(0.0..=8.0).contains(&min) || !(0.0..=8.0).contains(&max) || min > max {
+					error!("Config: File type {} has an invalid expected_entropy [{min}, {max}] - Entropy is between 0 and 8, and the minimum must not be greater than the maximum", ft.extension.clone().unwrap_or("<no extension>".to_string()));
+					error = true;
+				}
+			}
+			if let Some(size_field) = &ft.size_field {
+				if ![ 2, 4, 8 ].contains(&size_field.width) {
+					error!("Config: File type {} has a size_field with a width of {} - The width must be 2, 4 or 8 bytes", ft.extension.clone().unwrap_or("<no extension>".to_string()), size_field.width);
+					error = true;
+				}
+				if ft.max_len.is_some_and(|max_len| size_field.offset + size_field.width as u64 > max_len) {
+					error!("Config: File type {} has a size_field that ends past its max_len - The field must lie within the file", ft.extension.clone().unwrap_or("<no extension>".to_string()));
+					error = true;
+				}
+			}
+			if ft.has_delimiter() && ft.pairing == PairingStrategy::PairLast {
+				error!("Config: File type {} has a footer that is also a header, which is only supported with the \"next\" pairing strategy", ft.extension.clone().unwrap_or("<no extension>".to_string()));
+				error = true;
+			}
+			if ft.has_delimiter() && ft.header_aligned {
+				error!("Config: File type {} has a footer that is also a header, so can't be header_aligned, as the ends of files are not aligned", ft.extension.clone().unwrap_or("<no extension>".to_string()));
+				error = true;
+			}
+			if ft.requires.iter().any(|required| required.is_empty()) {
+				error!("Config: File type {} has an empty required byte sequence - Remove it from requires", ft.extension.clone().unwrap_or("<no extension>".to_string()));
+				error = true;
+			}
+			if let Some(footer_context) = &ft.footer_context {
+				if !ft.has_footer() {
+					error!("Config: File type {} has a footer_context but no footers - Remove the footer_context, or configure a footer", ft.extension.clone().unwrap_or("<no extension>".to_string()));
+					error = true;
+				}
+				if footer_context.followed_by.iter().any(|sequence| sequence.is_empty()) {
+					error!("Config: File type {} has an empty byte sequence in footer_context.followed_by - Remove it, as it would accept every footer", ft.extension.clone().unwrap_or("<no extension>".to_string()));
+					error = true;
+				}
+			}
 			if !ft.has_footer() && ft.requires_footer {
 				error!("Config: File type {} has no footers but is configured to require a footer - This is an oxymoron", ft.extension.clone().unwrap_or("<no extension>".to_string()));
 				error = true;
@@ -127,6 +704,11 @@ impl SearchlightConfig {
 				}
 			}
 			for footer in &self.file_types[i].footers {
+				// Delimiters (footers that are also headers of the same type) are not collisions
+				if self.file_types[i].headers.contains(footer) {
+					continue;
+				}
+
 				let id = match_id_hash_slice_u16(&footer);
 				if collision_sets.contains_key(&id) {
 					// return Err(Error::ConfigValidationError(format!(
@@ -174,18 +756,204 @@ impl SearchlightConfig {
 			Ok(())
 		}
 	}
+
+	/// Loads the headers and footers from the external pattern files referenced by each file type, resolving relative paths against
+	/// `config_dir`, and adds them to the file type's headers and footers. Should be called once after the config is deserialized and
+	/// before it is validated. Pattern files contain one match string per line, in the same syntax as in the config (including alternation
+	/// groups). Blank lines and lines starting with '#' are ignored
+	pub fn load_pattern_files(&mut self, config_dir: impl AsRef<Path>) -> Result<(), Error> {
+		for ft in &mut self.file_types {
+			if let Some(headers_file) = &ft.headers_file {
+				ft.headers.append(&mut read_pattern_file(&config_dir.as_ref().join(headers_file))?);
+			}
+			if let Some(footers_file) = &ft.footers_file {
+				ft.footers.append(&mut read_pattern_file(&config_dir.as_ref().join(footers_file))?);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Removes the file types that aren't selected by only_types, or are excluded by exclude_types, from file_types, and then clears the
+	/// filters, so that filtering again does nothing. Each name matches file types whose extension (ignoring case) or type_id is the name.
+	/// Returns an error if a name matches none of the file types, as it is likely a typo, or if no file types are left
+	pub fn filter_file_types(&mut self) -> Result<(), Error> {
+		let matches_name = |ft: &FileType, name: &str| {
+			ft.extension.as_deref().is_some_and(|ext| ext.eq_ignore_ascii_case(name)) || ft.type_id.to_string().eq_ignore_ascii_case(name)
+		};
+
+		let mut error = false;
+
+		for name in self.only_types.iter().chain(&self.exclude_types) {
+			if !self.file_types.iter().any(|ft| matches_name(ft, name)) {
+				error!("Config: The file type filter \"{name}\" doesn't match the extension or type_id of any configured file type");
+				error = true;
+			}
+		}
+
+		if error {
+			return Err(Error::ConfigValidationError);
+		}
+
+		let num_types = self.file_types.len();
+
+		self.file_types.retain(|ft| {
+			(self.only_types.is_empty() || self.only_types.iter().any(|name| matches_name(ft, name)))
+				&& !self.exclude_types.iter().any(|name| matches_name(ft, name))
+		});
+
+		if self.file_types.is_empty() {
+			error!("Config: No file types are left to carve after applying only_types and exclude_types");
+			return Err(Error::ConfigValidationError);
+		}
+
+		if self.file_types.len() != num_types {
+			info!("Carving {} of the {} configured file types", self.file_types.len(), num_types);
+		}
+
+		self.only_types.clear();
+		self.exclude_types.clear();
+
+		Ok(())
+	}
+
+	/// Returns the config with `filter_file_types` applied, which is only copied if it has filters to apply
+	pub fn with_filtered_file_types(&self) -> Result<Cow<'_, SearchlightConfig>, Error> {
+		if self.only_types.is_empty() && self.exclude_types.is_empty() {
+			return Ok(Cow::Borrowed(self));
+		}
+
+		let mut config = self.clone();
+		config.filter_file_types()?;
+
+		Ok(Cow::Owned(config))
+	}
+
+	/// Returns the configured number of threads, or the available parallelism if not configured
+	pub fn num_threads(&self) -> usize {
+		self.threads.unwrap_or_else(|| thread::available_parallelism().map(|n| n.get()).unwrap_or(1)).max(1)
+	}
+
+	/// Returns the options for searching with the GPU, from gpu_timeout and gpu_timeout_fallback
+	pub fn gpu_search_options(&self) -> GpuSearchOptions {
+		GpuSearchOptions {
+			timeout: self.gpu_timeout.map(Duration::from_secs).unwrap_or(DEFAULT_GPU_SEARCH_TIMEOUT),
+			fall_back_on_timeout: self.gpu_timeout_fallback
+		}
+	}
+
+	/// Returns the configured excluded ranges as an ExclusionList
+	pub fn exclusions(&self) -> ExclusionList {
+		ExclusionList::new(self.exclude.iter().map(|&[start, end]| start..end))
+	}
+
+	/// Returns the configured filename template, or the default if none is configured. Assumes that the config has been validated, and
+	/// falls back to the default if the configured template is invalid
+	pub fn filename_template(&self) -> FilenameTemplate {
+		self.filename_template.as_ref().and_then(|template| FilenameTemplate::parse(template).ok()).unwrap_or_default()
+	}
+
+	/// Splits the patterns of the config into those searched for in each pass of a targeted footer search (see `targeted_footer_search`): all
+	/// headers and the footers of types without a max_len in the first, and the rest of the footers in the second. Patterns that are searched
+	/// for in the first pass are not repeated in the second. The returned configs only hold the patterns and the settings used to build and
+	/// checkpoint searchers
+	pub fn targeted_search_configs(&self) -> (SearchlightConfig, SearchlightConfig) {
+		let first_pass_types: Vec<FileType> = self.file_types.iter().map(|ft| FileType {
+			headers: ft.headers.clone(),
+			footers: if ft.is_footer_search_targeted() { Vec::new() } else { ft.footers.clone() },
+			max_len: ft.max_len,
+			..Default::default()
+		}).collect();
+
+		let second_pass_types: Vec<FileType> = self.file_types.iter().filter(|ft| ft.is_footer_search_targeted()).map(|ft| FileType {
+			footers: ft.footers.iter().filter(|footer| {
+				!first_pass_types.iter().any(|first_ft| first_ft.headers.contains(footer) || first_ft.footers.contains(footer))
+			}).cloned().collect(),
+			max_len: ft.max_len,
+			..Default::default()
+		}).filter(|ft| ft.has_footer()).collect();
+
+		(self.with_search_types(first_pass_types), self.with_search_types(second_pass_types))
+	}
+
+	/// A config with the given file types and the settings of this config that are used to build and checkpoint searchers
+	fn with_search_types(&self, file_types: Vec<FileType>) -> SearchlightConfig {
+		SearchlightConfig {
+			threads: self.threads,
+			require_gpu: self.require_gpu,
+			gpu_timeout: self.gpu_timeout,
+			gpu_timeout_fallback: self.gpu_timeout_fallback,
+			ac_table_cache_dir: self.ac_table_cache_dir.clone(),
+			checkpoint_dir: self.checkpoint_dir.clone(),
+			checkpoint_interval: self.checkpoint_interval,
+			file_types,
+			..Default::default()
+		}
+	}
 }
 
 impl FileType {
 	pub fn has_footer(&self) -> bool {
 		self.footers.len() != 0
 	}
+
+	/// Whether any footer of this type is also a header of it, i.e. a delimiter, see `footers`
+	pub fn has_delimiter(&self) -> bool {
+		self.footers.iter().any(|footer| self.headers.contains(footer))
+	}
+
+	/// Whether the footers of this type are only searched for after its headers when `targeted_footer_search` is set, which needs a max_len
+	pub fn is_footer_search_targeted(&self) -> bool {
+		self.has_footer() && self.max_len.is_some()
+	}
 }
 
 impl Default for SearchlightConfig {
     fn default() -> Self {
         Self {
 			max_reconstruction_search_len: None,
+			bound_reconstruction_by_headers: false,
+			zip_entry_count_policy: ZipEntryCountPolicy::default(),
+			catch_validator_panics: false,
+			detect_compressed: false,
+			validation_timeout_ms: None,
+			targeted_footer_search: false,
+			max_candidate_span: None,
+			skip_oversized_candidates: false,
+			overlap_policy: OverlapPolicy::default(),
+			threads: None,
+			require_gpu: false,
+			gpu_timeout: None,
+			gpu_timeout_fallback: false,
+			exclude: Vec::new(),
+			validation_cache_size: None,
+			ac_table_cache_dir: None,
+			checkpoint_dir: None,
+			checkpoint_interval: None,
+			filename_template: None,
+			filename_offset_unit: OffsetUnit::default(),
+			output_layout: OutputLayout::default(),
+			log_format: LogFormat::default(),
+			rejects_log: false,
+			carve_map: false,
+			carve_map_resolution: None,
+			extract_embedded: false,
+			embedded_entries: false,
+			original_names: false,
+			carve_slack: false,
+			fragmentation_stats: false,
+			appended_data: false,
+			appended_data_max_len: None,
+			timings: false,
+			boot_sector_cluster_size: false,
+			fragment_checksums: false,
+			pipelined_validation: false,
+			pipelined_validation_lookahead: None,
+			unanalysed_as: None,
+			io_strategy: IoStrategy::default(),
+			progress: ProgressMode::default(),
+			only_types: Vec::new(),
+			exclude_types: Vec::new(),
 			file_types: Vec::new(),
 		}
     }
@@ -201,4 +969,190 @@ impl Default for PairingStrategy {
 	fn default() -> Self {
 		PairingStrategy::PairNext
 	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{borrow::Cow, fs};
+
+	use crate::validation::invalid_params;
+
+	use super::{Endianness, MatchString, SearchlightConfig, ZipEntryCountPolicy};
+
+	#[test]
+	fn test_config_expands_alternations() {
+		let config: SearchlightConfig = serde_json::from_str(r#"{
+			"file_type": [
+				{ "extension": "jpg", "headers": [ "\\xff\\xd8(\\xff\\xe0|\\xff\\xe1)" ], "max_len": 1024 }
+			]
+		}"#).unwrap();
+
+		assert_eq!(config.file_types[0].headers, vec![ MatchString::from("\\xff\\xd8\\xff\\xe0"), MatchString::from("\\xff\\xd8\\xff\\xe1") ]);
+		assert!(config.validate().is_ok());
+
+		// One of the expanded forms collides with another type's header
+		let config: SearchlightConfig = serde_json::from_str(r#"{
+			"file_type": [
+				{ "extension": "jpg", "headers": [ "\\xff\\xd8(\\xff\\xe0|\\xff\\xe1)" ], "max_len": 1024 },
+				{ "extension": "dat", "headers": [ "\\xff\\xd8\\xff\\xe1" ], "max_len": 1024 }
+			]
+		}"#).unwrap();
+
+		assert!(config.validate().is_err());
+
+		// Malformed groups, which include unescaped literal parentheses, can't be parsed
+		for header in [ "\\xff\\xd8(\\xff\\xe0|)", "(c", "c)", "a|b" ] {
+			let config = format!(r#"{{ "file_type": [ {{ "extension": "jpg", "headers": [ "{header}" ], "max_len": 1024 }} ] }}"#);
+			assert!(serde_json::from_str::<SearchlightConfig>(&config).is_err());
+		}
+	}
+
+	#[test]
+	fn test_file_type_params() {
+		let config: SearchlightConfig = toml::from_str(r#"
+			[[file_type]]
+			extension = "zip"
+			headers = [ 'PK\x03\x04' ]
+			max_len = 1024
+			params = { max_reconstruction_search_len = 4096, entry_count_policy = "partial" }
+		"#).unwrap();
+
+		let file_type = &config.file_types[0];
+
+		assert_eq!(file_type.param::<u64>("max_reconstruction_search_len"), Some(4096));
+		assert_eq!(file_type.param::<ZipEntryCountPolicy>("entry_count_policy"), Some(ZipEntryCountPolicy::Partial));
+		assert_eq!(file_type.param::<bool>("missing"), None);
+
+		// A value of the wrong type is treated as unset
+		assert_eq!(file_type.param::<bool>("max_reconstruction_search_len"), None);
+
+		assert!(invalid_params(file_type).is_empty());
+
+		let mut file_type = file_type.clone();
+		file_type.params.insert("entry_count_policy".to_string(), toml::Value::Integer(1));
+		file_type.params.insert("unknown".to_string(), toml::Value::Integer(1));
+
+		let invalid = invalid_params(&file_type);
+		assert_eq!(invalid.len(), 1);
+		assert_eq!(invalid[0].0, "entry_count_policy");
+	}
+
+	#[test]
+	fn test_size_field_endianness() {
+		let config: SearchlightConfig = toml::from_str(r#"
+			[[file_type]]
+			extension = "mov"
+			headers = [ '....ftypqt' ]
+			max_len = 1024
+			size_field = { offset = 0, width = 4, endianness = "be" }
+
+			[[file_type]]
+			extension = "bmp"
+			headers = [ 'BM' ]
+			max_len = 1024
+			size_field = { offset = 2, width = 4, endianness = "le" }
+
+			[[file_type]]
+			extension = "wav"
+			headers = [ 'RIFF' ]
+			max_len = 1024
+			size_field = { offset = 4, width = 4, relative = true }
+		"#).unwrap();
+
+		let endiannesses: Vec<_> = config.file_types.iter().map(|ft| ft.size_field.unwrap().endianness).collect();
+		assert_eq!(endiannesses, vec![ Endianness::Big, Endianness::Little, Endianness::Little ]);
+		assert!(config.validate().is_ok());
+
+		assert_eq!(Endianness::Big.decode(&[ 0x01, 0x02 ]), Some(0x0102));
+		assert_eq!(Endianness::Little.decode(&[ 0x01, 0x02, 0x03, 0x04 ]), Some(0x04030201));
+		assert_eq!(Endianness::Big.decode(&[ 0, 0, 0, 0, 0, 0, 0x01, 0x00 ]), Some(0x0100));
+		assert_eq!(Endianness::Little.decode(&[ 0x01, 0x02, 0x03 ]), None);
+
+		// Only widths of 2, 4 and 8 bytes are accepted
+		let config: SearchlightConfig = toml::from_str(r#"
+			[[file_type]]
+			extension = "bmp"
+			headers = [ 'BM' ]
+			max_len = 1024
+			size_field = { offset = 2, width = 3 }
+		"#).unwrap();
+
+		assert!(config.validate().is_err());
+	}
+
+	#[test]
+	fn test_load_pattern_files() {
+		let test_dir = std::env::temp_dir().join(format!("searchlight_test_load_pattern_files_{}", std::process::id()));
+		let _ = fs::remove_dir_all(&test_dir);
+		fs::create_dir_all(&test_dir).unwrap();
+
+		fs::write(test_dir.join("jpeg_headers.txt"), "# JPEG headers\n\\xff\\xd8\\xff(\\xe0|\\xe1)\n\n  \\xff\\xd8\\xff\\xdb  \n").unwrap();
+		fs::write(test_dir.join("bad_headers.txt"), "\\xff\\xd8\n\\xzz\n").unwrap();
+
+		let mut config: SearchlightConfig = serde_json::from_str(r#"{
+			"file_type": [
+				{ "extension": "jpg", "headers": [ "\\xff\\xd8\\xff\\xee" ], "headers_file": "jpeg_headers.txt", "max_len": 1024 }
+			]
+		}"#).unwrap();
+
+		config.load_pattern_files(&test_dir).unwrap();
+
+		assert_eq!(config.file_types[0].headers, vec![
+			MatchString::from("\\xff\\xd8\\xff\\xee"),
+			MatchString::from("\\xff\\xd8\\xff\\xe0"),
+			MatchString::from("\\xff\\xd8\\xff\\xe1"),
+			MatchString::from("\\xff\\xd8\\xff\\xdb")
+		]);
+		assert!(config.validate().is_ok());
+
+		// Invalid lines and missing files are errors
+		config.file_types[0].headers_file = Some("bad_headers.txt".to_string());
+		assert!(config.load_pattern_files(&test_dir).is_err());
+
+		config.file_types[0].headers_file = Some("missing_headers.txt".to_string());
+		assert!(config.load_pattern_files(&test_dir).is_err());
+
+		fs::remove_dir_all(&test_dir).unwrap();
+	}
+
+	#[test]
+	fn test_filter_file_types() {
+		let config_str = r#"{
+			"file_type": [
+				{ "extension": "jpg", "type_id": "jpeg", "headers": [ "\\xff\\xd8" ] },
+				{ "extension": "png", "type_id": "png", "headers": [ "\\x89PNG" ] },
+				{ "extension": "zip", "type_id": "zip", "headers": [ "PK\\x03\\x04" ] }
+			]
+		}"#;
+
+		let extensions = |config: &SearchlightConfig| config.file_types.iter().map(|ft| ft.extension.clone().unwrap()).collect::<Vec<_>>();
+
+		// Names match either the extension or the type_id
+		let mut config: SearchlightConfig = serde_json::from_str(config_str).unwrap();
+		config.only_types = vec![ "JPEG".to_string(), "png".to_string() ];
+		config.filter_file_types().unwrap();
+		assert_eq!(extensions(&config), vec![ "jpg", "png" ]);
+
+		let mut config: SearchlightConfig = serde_json::from_str(config_str).unwrap();
+		config.exclude_types = vec![ "zip".to_string() ];
+		config.filter_file_types().unwrap();
+		assert_eq!(extensions(&config), vec![ "jpg", "png" ]);
+
+		// Filtering again does nothing, as the filters have been applied
+		config.filter_file_types().unwrap();
+		assert_eq!(extensions(&config), vec![ "jpg", "png" ]);
+
+		let config: SearchlightConfig = serde_json::from_str(config_str).unwrap();
+		assert!(matches!(config.with_filtered_file_types().unwrap(), Cow::Borrowed(_)));
+
+		// Names that match no file type, and filters that leave none, are errors
+		let mut config: SearchlightConfig = serde_json::from_str(config_str).unwrap();
+		config.only_types = vec![ "jpg".to_string(), "gif".to_string() ];
+		assert!(config.filter_file_types().is_err());
+
+		let mut config: SearchlightConfig = serde_json::from_str(config_str).unwrap();
+		config.only_types = vec![ "jpg".to_string() ];
+		config.exclude_types = vec![ "jpeg".to_string() ];
+		assert!(config.filter_file_types().is_err());
+	}
 }
\ No newline at end of file
@@ -3,25 +3,73 @@ pub mod str_parse;
 pub mod fragments_index;
 pub mod subrange;
 pub mod multi_reader;
+pub mod partitions;
+pub mod boot_sector;
+pub mod exclusions;
+pub mod sparse;
+pub mod lznt1;
+pub mod filename_template;
+pub mod progress;
 
-use std::{collections::BTreeMap, fs::File, io::{self, Seek}, ops::Range};
+use std::{collections::BTreeMap, fs::File, io::{self, Seek}, ops::Range, sync::Arc};
 
-use crate::{search::Match, utils::subrange::IntoSubrangesExact, validation::Fragment};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::{error::Error, search::Match, utils::subrange::IntoSubrangesExact, validation::Fragment};
 
 #[cfg(test)]
 pub fn init_test_logger() {
 	let _ = env_logger::builder().is_test(true).try_init();
 }
 
-/// Get the length of the file, by querying metadata and as a last resort seeking to the end of the file and getting the offset
+/// Get the length of the file, by querying metadata and as a last resort seeking to the end of the file and getting the offset. Block devices
+/// (e.g. `/dev/sdb`) have a length of 0 in their metadata, so their size is queried from the kernel on Linux, or found by seeking otherwise
 pub fn file_len(file: &mut File) -> Result<u64, io::Error> {
 	if let Ok(metadata) = file.metadata() {
-		Ok(metadata.len())
-	} else {
-		let size = file.seek(io::SeekFrom::End(0))?;
-		file.seek(io::SeekFrom::Start(0))?;
-		Ok(size)
+		#[cfg(unix)]
+		let is_block_device = std::os::unix::fs::FileTypeExt::is_block_device(&metadata.file_type());
+		#[cfg(not(unix))]
+		let is_block_device = false;
+
+		if !is_block_device {
+			return Ok(metadata.len());
+		}
+
+		#[cfg(target_os = "linux")]
+		if let Ok(size) = block_device_len(file) {
+			return Ok(size);
+		}
 	}
+
+	let size = file.seek(io::SeekFrom::End(0))?;
+	file.seek(io::SeekFrom::Start(0))?;
+	Ok(size)
+}
+
+// BLKGETSIZE64, defined as _IOR(0x12, 114, size_t) in linux/fs.h
+#[cfg(target_os = "linux")]
+nix::ioctl_read!(blkgetsize64, 0x12, 114, u64);
+
+/// Get the size of the block device `file` in bytes with the BLKGETSIZE64 ioctl
+#[cfg(target_os = "linux")]
+fn block_device_len(file: &File) -> Result<u64, io::Error> {
+	use std::os::fd::AsRawFd;
+
+	let mut size: u64 = 0;
+
+	unsafe { blkgetsize64(file.as_raw_fd(), &mut size) }?;
+
+	Ok(size)
+}
+
+/// Creates a pool of `num_threads` (at least one) worker threads, for searching and validating to share
+pub fn thread_pool(num_threads: usize) -> Result<Arc<ThreadPool>, Error> {
+	ThreadPoolBuilder::new()
+		.num_threads(num_threads.max(1))
+		.thread_name(|idx| format!("searchlight-worker-{idx}"))
+		.build()
+		.map(Arc::new)
+		.map_err(|e| Error::ThreadPoolError(e.to_string()))
 }
 
 /// Calculates the next multiple of `multiple` from `num`. E.g. `next_multiple_of(7, 3) == 9`,
@@ -36,6 +84,28 @@ pub fn prev_multiple_of(num: usize, multiple: usize) -> usize {
 	(num / multiple) * multiple
 }
 
+/// Converts a date and time (assumed to be UTC) to the number of seconds since the Unix epoch, or None if any of the fields are out of range.
+/// Seconds of up to 60 are allowed, to account for leap seconds
+pub fn unix_timestamp(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> Option<i64> {
+	const DAYS_IN_MONTH: [u32; 12] = [ 31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31 ];
+
+	let is_leap_year = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+
+	if !(1..=12).contains(&month) || day == 0 || day > DAYS_IN_MONTH[month as usize - 1] || (month == 2 && day == 29 && !is_leap_year) || hour > 23 || minute > 59 || second > 60 {
+		return None;
+	}
+
+	// Days since the epoch of the civil date, from http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	let days = era * 146097 + day_of_era - 719468;
+
+	Some(days * 86400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64)
+}
+
 /// Estimates the cluster size by iterating over each found header and collecting the number of times each header is divisible by
 /// each power of two between 512 and 65,536, taking the mode of those counts. Also counts the number of times a header is not divisible
 /// by any power of two and if that is more common than a power of two, None is returned to indicate an estimate that most files are not
@@ -148,11 +218,27 @@ pub fn simplify_ranges<T>(ranges: &mut Vec<Range<T>>) where T: PartialEq + Ord +
 	}
 }
 
+/// Takes a vec of ranges sorted by start, and merges any ranges that overlap or touch (where the end of a range is equal to the start of the
+/// next) into one. Unlike `simplify_ranges`, which keeps overlapping ranges separate as their order matters, this is for when the ranges
+/// are a set of bytes, e.g. the fragments of multiple reconstructed parts of a file, so that no bytes are carved twice
+pub fn merge_ranges<T>(ranges: &mut Vec<Range<T>>) where T: PartialEq + Ord + Copy {
+	let mut merged: Vec<Range<T>> = Vec::with_capacity(ranges.len());
+
+	for range in ranges.drain(..) {
+		match merged.last_mut() {
+			Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+			_ => merged.push(range)
+		}
+	}
+
+	*ranges = merged;
+}
+
 #[cfg(test)]
 mod test {
     use crate::{search::Match, utils::estimate_cluster_size};
 
-    use super::{generate_fragmentations, simplify_ranges};
+    use super::{file_len, generate_fragmentations, merge_ranges, simplify_ranges, unix_timestamp};
 
 	#[test]
 	fn test_cluster_size_estimates() {
@@ -180,6 +266,29 @@ mod test {
 		assert_eq!(est_cs, Some(1024))
 	}
 
+	#[test]
+	fn test_file_len() {
+		let path = std::env::temp_dir().join(format!("searchlight_test_file_len_{}", std::process::id()));
+		std::fs::write(&path, [ 0xaa; 1234 ]).unwrap();
+
+		let mut file = std::fs::File::open(&path).unwrap();
+		assert_eq!(file_len(&mut file).unwrap(), 1234);
+
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn test_unix_timestamp() {
+		assert_eq!(unix_timestamp(1970, 1, 1, 0, 0, 0), Some(0));
+		assert_eq!(unix_timestamp(2000, 2, 29, 12, 30, 15), Some(951827415));
+		assert_eq!(unix_timestamp(2023, 12, 31, 23, 59, 59), Some(1704067199));
+		assert_eq!(unix_timestamp(1969, 12, 31, 23, 59, 59), Some(-1));
+
+		assert_eq!(unix_timestamp(2023, 2, 29, 0, 0, 0), None);
+		assert_eq!(unix_timestamp(2023, 13, 1, 0, 0, 0), None);
+		assert_eq!(unix_timestamp(2023, 1, 1, 24, 0, 0), None);
+	}
+
 	#[test]
 	fn test_generate_fragmentations() {
 		let cluster_size = 2;
@@ -233,4 +342,28 @@ mod test {
 
 		assert_eq!(test_data, expected);
 	}
+
+	#[test]
+	fn test_merge_ranges() {
+		let test_data = vec![
+			0..5,
+			5..10,
+			11..15,
+			14..20,
+			16..18,
+			20..30,
+			32..40
+		];
+
+		let mut merged = test_data.clone();
+		merge_ranges(&mut merged);
+
+		assert_eq!(merged, vec![ 0..10, 11..30, 32..40 ]);
+
+		// simplify_ranges only merges the touching ranges, and leaves the overlapping ones
+		let mut simplified = test_data;
+		simplify_ranges(&mut simplified);
+
+		assert_eq!(simplified, vec![ 0..10, 11..15, 14..20, 16..18, 20..30, 32..40 ]);
+	}
 }
\ No newline at end of file
@@ -0,0 +1,121 @@
+use crate::searchlight::MAX_CLUSTER_SIZE;
+
+const BOOT_SECTOR_SIZE: usize = 512;
+const BOOT_SECTOR_SIGNATURE: [u8; 2] = [ 0x55, 0xaa ];
+const BOOT_SECTOR_SIGNATURE_OFFSET: usize = 510;
+
+const OEM_ID_OFFSET: usize = 3;
+const NTFS_OEM_ID: &[u8; 8] = b"NTFS    ";
+const EXFAT_OEM_ID: &[u8; 8] = b"EXFAT   ";
+
+/// The offsets of the fields of the BIOS parameter block that FAT and NTFS boot sectors share
+const BYTES_PER_SECTOR_OFFSET: usize = 11;
+const SECTORS_PER_CLUSTER_OFFSET: usize = 13;
+const FAT_RESERVED_SECTORS_OFFSET: usize = 14;
+const FAT_NUM_FATS_OFFSET: usize = 16;
+
+/// The offsets of the log2 of the bytes per sector and sectors per cluster in exFAT boot sectors
+const EXFAT_BYTES_PER_SECTOR_SHIFT_OFFSET: usize = 108;
+const EXFAT_SECTORS_PER_CLUSTER_SHIFT_OFFSET: usize = 109;
+
+const MIN_CLUSTER_SIZE: u64 = 512;
+
+/// The filesystem whose boot sector a cluster size was read from
+#[derive(Debug, PartialEq, Eq, Clone, Copy, strum::Display)]
+pub enum Filesystem {
+	#[strum(to_string = "FAT")]
+	Fat,
+	#[strum(to_string = "exFAT")]
+	ExFat,
+	#[strum(to_string = "NTFS")]
+	Ntfs
+}
+
+/// Attempts to read the cluster size of the filesystem from a FAT, exFAT or NTFS boot sector at the start of `data`, which records it exactly,
+/// returning the filesystem and cluster size. Returns None if there is no recognised boot sector, or if the cluster size that it records
+/// isn't sensible (a power of two between 512 bytes and `MAX_CLUSTER_SIZE`)
+pub fn boot_sector_cluster_size(data: &[u8]) -> Option<(Filesystem, u64)> {
+	if data.len() < BOOT_SECTOR_SIZE || data[BOOT_SECTOR_SIGNATURE_OFFSET..(BOOT_SECTOR_SIGNATURE_OFFSET + 2)] != BOOT_SECTOR_SIGNATURE {
+		return None;
+	}
+
+	let oem_id = &data[OEM_ID_OFFSET..(OEM_ID_OFFSET + 8)];
+	let bytes_per_sector = u16::from_le_bytes(data[BYTES_PER_SECTOR_OFFSET..(BYTES_PER_SECTOR_OFFSET + 2)].try_into().unwrap()) as u64;
+	let sectors_per_cluster = data[SECTORS_PER_CLUSTER_OFFSET];
+
+	let (filesystem, cluster_size) = if oem_id == EXFAT_OEM_ID {
+		let shift = data[EXFAT_BYTES_PER_SECTOR_SHIFT_OFFSET] as u32 + data[EXFAT_SECTORS_PER_CLUSTER_SHIFT_OFFSET] as u32;
+
+		(Filesystem::ExFat, 1u64.checked_shl(shift)?)
+	} else if oem_id == NTFS_OEM_ID {
+		// Sectors per cluster values above 0x80 are negative powers of two, i.e. the number of sectors is 2 to the power of their negation
+		let sectors_per_cluster = if sectors_per_cluster > 0x80 { 1u64.checked_shl(256 - sectors_per_cluster as u32)? } else { sectors_per_cluster as u64 };
+
+		(Filesystem::Ntfs, bytes_per_sector.checked_mul(sectors_per_cluster)?)
+	} else {
+		// FAT boot sectors have no distinctive OEM ID, so are recognised by their jump instruction and plausible BIOS parameter block fields
+		let jump_valid = (data[0] == 0xeb && data[2] == 0x90) || data[0] == 0xe9;
+		let reserved_sectors = u16::from_le_bytes(data[FAT_RESERVED_SECTORS_OFFSET..(FAT_RESERVED_SECTORS_OFFSET + 2)].try_into().unwrap());
+		let num_fats = data[FAT_NUM_FATS_OFFSET];
+
+		if !jump_valid || reserved_sectors == 0 || num_fats == 0 || !sectors_per_cluster.is_power_of_two() {
+			return None;
+		}
+
+		(Filesystem::Fat, bytes_per_sector * sectors_per_cluster as u64)
+	};
+
+	(cluster_size.is_power_of_two() && (MIN_CLUSTER_SIZE..=MAX_CLUSTER_SIZE).contains(&cluster_size)).then_some((filesystem, cluster_size))
+}
+
+#[cfg(test)]
+mod test {
+	use super::{boot_sector_cluster_size, Filesystem};
+
+	fn boot_sector(jump: [u8; 3], oem_id: &[u8; 8], bytes_per_sector: u16, sectors_per_cluster: u8) -> Vec<u8> {
+		let mut data = vec![ 0u8; 512 ];
+		data[0..3].copy_from_slice(&jump);
+		data[3..11].copy_from_slice(oem_id);
+		data[11..13].copy_from_slice(&bytes_per_sector.to_le_bytes());
+		data[13] = sectors_per_cluster;
+		data[510] = 0x55;
+		data[511] = 0xaa;
+		data
+	}
+
+	#[test]
+	fn test_boot_sector_cluster_size() {
+		let mut fat = boot_sector([ 0xeb, 0x3c, 0x90 ], b"MSDOS5.0", 512, 8);
+		fat[14..16].copy_from_slice(&32u16.to_le_bytes());
+		fat[16] = 2;
+		assert_eq!(boot_sector_cluster_size(&fat), Some((Filesystem::Fat, 4096)));
+
+		// FAT boot sectors without any FATs aren't recognised
+		fat[16] = 0;
+		assert_eq!(boot_sector_cluster_size(&fat), None);
+
+		let ntfs = boot_sector([ 0xeb, 0x52, 0x90 ], b"NTFS    ", 512, 8);
+		assert_eq!(boot_sector_cluster_size(&ntfs), Some((Filesystem::Ntfs, 4096)));
+
+		// Clusters of 2^12 sectors are recorded as -12
+		let ntfs = boot_sector([ 0xeb, 0x52, 0x90 ], b"NTFS    ", 512, 0xf4);
+		assert_eq!(boot_sector_cluster_size(&ntfs), Some((Filesystem::Ntfs, 512 << 12)));
+
+		// Negative powers that are too large to shift by are rejected rather than overflowing
+		let ntfs = boot_sector([ 0xeb, 0x52, 0x90 ], b"NTFS    ", 512, 0x81);
+		assert_eq!(boot_sector_cluster_size(&ntfs), None);
+
+		let mut exfat = boot_sector([ 0xeb, 0x76, 0x90 ], b"EXFAT   ", 0, 0);
+		exfat[108] = 9;
+		exfat[109] = 6;
+		assert_eq!(boot_sector_cluster_size(&exfat), Some((Filesystem::ExFat, 32768)));
+
+		// Cluster sizes that aren't sensible are not used
+		let ntfs = boot_sector([ 0xeb, 0x52, 0x90 ], b"NTFS    ", 500, 8);
+		assert_eq!(boot_sector_cluster_size(&ntfs), None);
+
+		let mut no_signature = boot_sector([ 0xeb, 0x52, 0x90 ], b"NTFS    ", 512, 8);
+		no_signature[510] = 0;
+		assert_eq!(boot_sector_cluster_size(&no_signature), None);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,95 @@
+use std::ops::Range;
+
+/// A set of byte ranges of an image that are excluded from searching and carving, such as filesystem metadata that is known to generate
+/// many false positives. The ranges are stored sorted and merged so that they can be queried by binary search
+#[derive(Debug, Default, PartialEq)]
+pub struct ExclusionList {
+	ranges: Vec<Range<u64>>
+}
+
+impl ExclusionList {
+	/// Creates an exclusion list from the given ranges, which may be in any order and may overlap. Empty ranges are ignored
+	pub fn new(ranges: impl IntoIterator<Item = Range<u64>>) -> Self {
+		let mut ranges: Vec<Range<u64>> = ranges.into_iter().filter(|r| !r.is_empty()).collect();
+		ranges.sort_by_key(|r| r.start);
+
+		let mut merged: Vec<Range<u64>> = Vec::with_capacity(ranges.len());
+
+		for range in ranges {
+			match merged.last_mut() {
+				Some(last) if range.start <= last.end => {
+					last.end = last.end.max(range.end);
+				}
+				_ => merged.push(range)
+			}
+		}
+
+		ExclusionList {
+			ranges: merged
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ranges.is_empty()
+	}
+
+	/// Whether `idx` lies within an excluded range
+	pub fn contains(&self, idx: u64) -> bool {
+		self.intersects(&(idx..(idx + 1)))
+	}
+
+	/// Whether any part of `range` lies within an excluded range
+	pub fn intersects(&self, range: &Range<u64>) -> bool {
+		let i = self.ranges.partition_point(|r| r.end <= range.start);
+
+		self.ranges.get(i).is_some_and(|r| r.start < range.end)
+	}
+
+	/// Returns the parts of `range` that are not excluded, in order
+	pub fn subtract_from(&self, range: Range<u64>) -> Vec<Range<u64>> {
+		let mut remaining = Vec::new();
+		let mut start = range.start;
+
+		let i = self.ranges.partition_point(|r| r.end <= range.start);
+
+		for excluded in self.ranges[i..].iter().take_while(|r| r.start < range.end) {
+			if excluded.start > start {
+				remaining.push(start..excluded.start);
+			}
+			start = excluded.end;
+		}
+
+		if start < range.end {
+			remaining.push(start..range.end);
+		}
+
+		remaining
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::ExclusionList;
+
+	#[test]
+	fn test_exclusion_list() {
+		let exclusions = ExclusionList::new([ 40..50, 10..20, 15..25, 30..30 ]);
+
+		assert_eq!(exclusions, ExclusionList::new([ 10..25, 40..50 ]));
+
+		assert!(!exclusions.contains(9));
+		assert!(exclusions.contains(10));
+		assert!(exclusions.contains(24));
+		assert!(!exclusions.contains(25));
+		assert!(exclusions.contains(45));
+		assert!(!exclusions.contains(50));
+
+		assert!(exclusions.intersects(&(0..11)));
+		assert!(!exclusions.intersects(&(25..40)));
+		assert!(exclusions.intersects(&(49..60)));
+
+		assert_eq!(exclusions.subtract_from(0..60), vec![ 0..10, 25..40, 50..60 ]);
+		assert_eq!(exclusions.subtract_from(12..45), vec![ 25..40 ]);
+		assert_eq!(exclusions.subtract_from(41..49), Vec::<std::ops::Range<u64>>::new());
+	}
+}
\ No newline at end of file
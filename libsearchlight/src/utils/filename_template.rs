@@ -0,0 +1,271 @@
+use crate::searchlight::config::FileTypeId;
+
+/// The filename template used if none is configured, which gives filenames of the format `<start>-<end>.<ext>`
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{start}-{end}.{ext}";
+
+/// The characters that are not allowed in filenames, as they are path separators (on any platform)
+const PATH_SEPARATORS: [char; 2] = [ '/', '\\' ];
+
+/// The characters other than path separators that are not allowed in filenames on Windows
+const RESERVED_CHARS: [char; 7] = [ ':', '<', '>', '"', '|', '?', '*' ];
+
+/// The names that refer to devices on Windows, with or without an extension, and so can't be used as filenames there
+const RESERVED_NAMES: [&str; 22] = [
+	"CON", "PRN", "AUX", "NUL",
+	"COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+	"LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9"
+];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Placeholder {
+	Start,
+	End,
+	Ext,
+	Type,
+	#[cfg(feature = "hashing")]
+	Hash,
+	Index
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum TemplatePart {
+	Literal(String),
+	Placeholder(Placeholder)
+}
+
+/// The values that are substituted into a `FilenameTemplate` to produce the filename of a carved file
+pub struct FilenameFields<'a> {
+	/// The minimum start index of the fragments of the file, in the configured offset unit
+	pub start: usize,
+	/// The maximum end index of the fragments of the file, in the configured offset unit
+	pub end: usize,
+	pub ext: &'a str,
+	pub type_id: FileTypeId,
+	/// The CRC-32 of the content of the file, if it has been calculated. `{hash}` is replaced with nothing if not
+	pub hash: Option<u32>,
+	/// The index of the file in the order that files were carved, which is unique to each file of a carve
+	pub index: usize
+}
+
+/// A template for the filenames of carved files, which is literal text with placeholders that are replaced with the values of each file:
+/// `{start}` and `{end}` (the range of the file in the image, in bytes unless configured otherwise, see `OffsetUnit`), `{ext}` (the extension
+/// of the file type), `{type}` (the file type ID), `{hash}` (the CRC-32 of the file content, in hex, which requires the `hashing` feature)
+/// and `{index}` (the index of the file in the order that it was carved, so templates that include it produce a unique filename for each
+/// file). Literal braces are written as `{{` and `}}`
+#[derive(Debug, PartialEq, Clone)]
+pub struct FilenameTemplate {
+	parts: Vec<TemplatePart>
+}
+
+impl FilenameTemplate {
+	/// Parses `template`, returning a description of the problem if it contains an unrecognised or unclosed placeholder, `{hash}` without
+	/// the `hashing` feature, or a path separator
+	pub fn parse(template: &str) -> Result<Self, String> {
+		let mut parts = Vec::new();
+		let mut literal = String::new();
+		let mut chars = template.chars().peekable();
+
+		while let Some(c) = chars.next() {
+			match c {
+				'{' if chars.peek() == Some(&'{') => {
+					chars.next();
+					literal.push('{');
+				}
+				'}' if chars.peek() == Some(&'}') => {
+					chars.next();
+					literal.push('}');
+				}
+				'{' => {
+					let mut name = String::new();
+					loop {
+						match chars.next() {
+							Some('}') => break,
+							Some(c) => name.push(c),
+							None => return Err(format!("Unclosed placeholder \"{{{name}\"")),
+						}
+					}
+
+					let placeholder = match name.as_str() {
+						"start" => Placeholder::Start,
+						"end" => Placeholder::End,
+						"ext" => Placeholder::Ext,
+						"type" => Placeholder::Type,
+						#[cfg(feature = "hashing")]
+						"hash" => Placeholder::Hash,
+						#[cfg(not(feature = "hashing"))]
+						"hash" => return Err("\"{hash}\" requires searchlight to be built with the hashing feature".to_string()),
+						"index" => Placeholder::Index,
+						_ => return Err(format!("Unrecognised placeholder \"{{{name}}}\""))
+					};
+
+					if !literal.is_empty() {
+						parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+					}
+					parts.push(TemplatePart::Placeholder(placeholder));
+				}
+				'}' => return Err("Unmatched \"}\" - Literal braces should be written as \"{{\" or \"}}\"".to_string()),
+				c if PATH_SEPARATORS.contains(&c) => return Err(format!("Path separator \"{c}\" is not allowed in filenames")),
+				c => literal.push(c)
+			}
+		}
+
+		if !literal.is_empty() {
+			parts.push(TemplatePart::Literal(literal));
+		}
+
+		if parts.is_empty() {
+			return Err("The template is empty".to_string());
+		}
+
+		Ok(FilenameTemplate {
+			parts
+		})
+	}
+
+	/// Whether the template includes `{hash}`, and so the content of each file needs to be hashed
+	#[cfg(feature = "hashing")]
+	pub fn uses_hash(&self) -> bool {
+		self.parts.contains(&TemplatePart::Placeholder(Placeholder::Hash))
+	}
+
+	/// Produces the filename of a file with the values in `fields`, sanitised by `sanitise_filename`, so that neither the substituted values
+	/// (e.g. a configured extension) nor the literals can make it refer to another directory or be invalid on any platform
+	pub fn render(&self, fields: &FilenameFields) -> String {
+		let mut filename = String::new();
+
+		for part in &self.parts {
+			match part {
+				TemplatePart::Literal(literal) => filename.push_str(literal),
+				TemplatePart::Placeholder(placeholder) => {
+					let value = match placeholder {
+						Placeholder::Start => fields.start.to_string(),
+						Placeholder::End => fields.end.to_string(),
+						Placeholder::Ext => fields.ext.to_string(),
+						Placeholder::Type => fields.type_id.to_string(),
+						#[cfg(feature = "hashing")]
+						Placeholder::Hash => fields.hash.map(|hash| format!("{hash:08x}")).unwrap_or_default(),
+						Placeholder::Index => fields.index.to_string(),
+					};
+
+					filename.push_str(&value);
+				}
+			}
+		}
+
+		sanitise_filename(&filename)
+	}
+}
+
+/// Replaces the path separators in `name` with underscores and makes it valid on any platform like the components of `sanitise_path`, so
+/// that it can be used as a filename that doesn't refer to another directory, e.g. for the name of a ZIP member, which may include a path
+pub fn sanitise_filename(name: &str) -> String {
+	let filename = name.replace(PATH_SEPARATORS, "_");
+
+	// "." and ".." would refer to the directory itself and its parent
+	if filename.chars().all(|c| c == '.') {
+		filename.replace('.', "_")
+	} else {
+		sanitise_component(&filename)
+	}
+}
+
+/// Turns `name`, which may include a path, into a relative path (separated by '/') that stays within the directory it is joined to, e.g. for
+/// the name of a ZIP member to be used as the name of the extracted file. Empty, "." and ".." components are dropped rather than resolved, and
+/// the rest are made valid names on any platform, see `sanitise_component`, which also defuses drive prefixes like "C:". Returns None if
+/// nothing is left of the name
+pub fn sanitise_path(name: &str) -> Option<String> {
+	let components: Vec<String> = name.split(PATH_SEPARATORS)
+		.filter(|component| component.chars().any(|c| c != '.'))
+		.map(sanitise_component)
+		.collect();
+
+	(!components.is_empty()).then(|| components.join("/"))
+}
+
+/// Makes `component`, a name without path separators, valid on any platform: characters that aren't allowed in names on Windows and control
+/// characters are replaced with underscores, and names that refer to devices on Windows (e.g. "CON" or "nul.txt") are prefixed with one
+fn sanitise_component(component: &str) -> String {
+	let component: String = component.chars().map(|c| if RESERVED_CHARS.contains(&c) || c.is_control() { '_' } else { c }).collect();
+
+	let stem = component.split('.').next().unwrap_or_default().trim_end();
+
+	if RESERVED_NAMES.iter().any(|name| name.eq_ignore_ascii_case(stem)) {
+		format!("_{component}")
+	} else {
+		component
+	}
+}
+
+impl Default for FilenameTemplate {
+	fn default() -> Self {
+		FilenameTemplate::parse(DEFAULT_FILENAME_TEMPLATE).unwrap()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::searchlight::config::FileTypeId;
+
+	use super::{sanitise_path, FilenameFields, FilenameTemplate};
+
+	#[test]
+	fn test_filename_template() {
+		let fields = FilenameFields {
+			start: 512,
+			end: 1024,
+			ext: "jpg",
+			type_id: FileTypeId::Jpeg,
+			hash: Some(0xabc),
+			index: 3
+		};
+
+		assert_eq!(FilenameTemplate::default().render(&fields), "512-1024.jpg");
+		assert_eq!(FilenameTemplate::parse("{index}_{type}{{x}}.{ext}").unwrap().render(&fields), "3_jpeg{x}.jpg");
+
+		#[cfg(feature = "hashing")]
+		assert_eq!(FilenameTemplate::parse("{index}_{hash}.{ext}").unwrap().render(&fields), "3_00000abc.jpg");
+		#[cfg(not(feature = "hashing"))]
+		assert!(FilenameTemplate::parse("{index}_{hash}.{ext}").is_err());
+
+		assert!(FilenameTemplate::parse("{start}/{end}").is_err());
+		assert!(FilenameTemplate::parse("{start}-{size}").is_err());
+		assert!(FilenameTemplate::parse("{start").is_err());
+		assert!(FilenameTemplate::parse("").is_err());
+
+		// Path separators can't be introduced through the substituted values either
+		let fields = FilenameFields {
+			ext: "../x",
+			..fields
+		};
+		assert_eq!(FilenameTemplate::parse("{ext}").unwrap().render(&fields), ".._x");
+
+		// Nor can anything that's invalid on Windows, including in literals
+		let fields = FilenameFields {
+			ext: "nul",
+			..fields
+		};
+		assert_eq!(FilenameTemplate::parse("{ext}").unwrap().render(&fields), "_nul");
+		assert_eq!(FilenameTemplate::parse("{start}:{end}?.{ext}").unwrap().render(&fields), "512_1024_.nul");
+	}
+
+	#[test]
+	fn test_sanitise_path() {
+		assert_eq!(sanitise_path("dir/sub/a.txt").as_deref(), Some("dir/sub/a.txt"));
+		assert_eq!(sanitise_path("dir\\sub\\a.txt").as_deref(), Some("dir/sub/a.txt"));
+
+		// Nothing can refer outside of the directory the path is joined to
+		assert_eq!(sanitise_path("/etc/passwd").as_deref(), Some("etc/passwd"));
+		assert_eq!(sanitise_path("../../a.txt").as_deref(), Some("a.txt"));
+		assert_eq!(sanitise_path("dir/./../a.txt").as_deref(), Some("dir/a.txt"));
+		assert_eq!(sanitise_path("C:\\Windows\\a.txt").as_deref(), Some("C_/Windows/a.txt"));
+
+		// Nor can it be invalid on Windows
+		assert_eq!(sanitise_path("dir/a<b>\"c|d?e*.txt").as_deref(), Some("dir/a_b__c_d_e_.txt"));
+		assert_eq!(sanitise_path("a\tb\u{0}.txt").as_deref(), Some("a_b_.txt"));
+		assert_eq!(sanitise_path("CON/nul.txt/Com1 .tar.gz").as_deref(), Some("_CON/_nul.txt/_Com1 .tar.gz"));
+		assert_eq!(sanitise_path("CONSOLE/lpt10.txt").as_deref(), Some("CONSOLE/lpt10.txt"));
+
+		assert_eq!(sanitise_path(""), None);
+		assert_eq!(sanitise_path("../"), None);
+	}
+}
\ No newline at end of file
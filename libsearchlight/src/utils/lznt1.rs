@@ -0,0 +1,90 @@
+/// The size of the data that each LZNT1 chunk decompresses to, at most
+const LZNT1_CHUNK_SIZE: usize = 4096;
+/// The size of the header that precedes each LZNT1 chunk
+const LZNT1_CHUNK_HEADER_SIZE: usize = 2;
+/// The value of the signature bits (12-14) of a LZNT1 chunk header
+const LZNT1_CHUNK_SIGNATURE: u16 = 0b011;
+/// The flag (bit 15) of a LZNT1 chunk header that indicates the chunk data is compressed
+const LZNT1_COMPRESSED_FLAG: u16 = 0x8000;
+
+/// Decodes the LZNT1 chunk header at the start of `data`, returning the total length of the chunk (including the header) and whether the
+/// chunk is compressed, or None if it is not a valid chunk header. A header of 0 marks the end of the compressed data, and so is not valid
+fn decode_chunk_header(data: &[u8]) -> Option<(usize, bool)> {
+	let header = u16::from_le_bytes(data.get(..LZNT1_CHUNK_HEADER_SIZE)?.try_into().unwrap());
+
+	if header == 0 || (header >> 12) & 0b111 != LZNT1_CHUNK_SIGNATURE {
+		return None;
+	}
+
+	let data_len = (header & 0x0fff) as usize + 1;
+	let is_compressed = header & LZNT1_COMPRESSED_FLAG != 0;
+
+	// Uncompressed chunks always hold a whole chunk of data
+	if !is_compressed && data_len != LZNT1_CHUNK_SIZE {
+		return None;
+	}
+
+	Some((LZNT1_CHUNK_HEADER_SIZE + data_len, is_compressed))
+}
+
+/// Whether the file with a header match at `start_idx` in `file_data` looks to be stored in an NTFS-compressed (LZNT1) compression unit, in
+/// which case the header would be found just after a LZNT1 chunk header and flag byte at the start of a cluster, and the rest of the file would
+/// not be carvable without decompressing it. This is a heuristic: The preceding bytes must form a compressed chunk header, and the chunk must
+/// be followed (within the data) by either another valid chunk header or the end marker
+pub fn is_possibly_compressed(file_data: &[u8], start_idx: usize, cluster_size: usize) -> bool {
+	// The chunk header and the flag byte of the first group of tokens precede the (literal) header bytes
+	let Some(chunk_idx) = start_idx.checked_sub(LZNT1_CHUNK_HEADER_SIZE + 1) else {
+		return false;
+	};
+
+	// Compression units start on a cluster boundary
+	if chunk_idx % cluster_size.max(1) != 0 {
+		return false;
+	}
+
+	let Some((chunk_len, true)) = decode_chunk_header(&file_data[chunk_idx..]) else {
+		return false;
+	};
+
+	// The flag byte must mark at least the first token as a literal, as the header was found uncompressed
+	if file_data[chunk_idx + LZNT1_CHUNK_HEADER_SIZE] & 1 != 0 {
+		return false;
+	}
+
+	let next_chunk_idx = chunk_idx + chunk_len;
+
+	match file_data.get(next_chunk_idx..(next_chunk_idx + LZNT1_CHUNK_HEADER_SIZE)) {
+		Some([ 0, 0 ]) => true,
+		Some(next_header) => decode_chunk_header(next_header).is_some(),
+		None => false
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::is_possibly_compressed;
+
+	#[test]
+	fn test_is_possibly_compressed() {
+		let mut data = vec![ 0xaa; 512 ];
+
+		// A compressed chunk of 100 bytes (including the header) at 256, with a PNG signature as the first literals, followed by the end marker
+		data[256..258].copy_from_slice(&(0xb000u16 | (100 - 3)).to_le_bytes());
+		data[258] = 0x00;
+		data[259..267].copy_from_slice(b"\x89PNG\x0d\x0a\x1a\x0a");
+		data[356..358].copy_from_slice(&[ 0, 0 ]);
+
+		assert!(is_possibly_compressed(&data, 259, 256));
+		assert!(is_possibly_compressed(&data, 259, 1));
+
+		// Not at the start of a cluster
+		assert!(!is_possibly_compressed(&data, 259, 512));
+
+		// Not followed by a valid chunk header or the end marker
+		data[356..358].copy_from_slice(&[ 0xaa, 0xaa ]);
+		assert!(!is_possibly_compressed(&data, 259, 256));
+
+		// Not preceded by a chunk header
+		assert!(!is_possibly_compressed(&data, 3, 1));
+	}
+}
\ No newline at end of file
@@ -0,0 +1,219 @@
+use std::ops::Range;
+
+/// The sector size that partition table LBAs are assumed to be in terms of
+const SECTOR_SIZE: u64 = 512;
+
+const MBR_SIGNATURE: [u8; 2] = [ 0x55, 0xaa ];
+const MBR_SIGNATURE_OFFSET: usize = 510;
+const MBR_PARTITION_TABLE_OFFSET: usize = 446;
+const MBR_PARTITION_ENTRY_SIZE: usize = 16;
+const MBR_PARTITION_ENTRY_COUNT: usize = 4;
+const MBR_PARTITION_TYPE_EMPTY: u8 = 0x00;
+const MBR_PARTITION_TYPE_GPT_PROTECTIVE: u8 = 0xee;
+
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+const GPT_HEADER_OFFSET: usize = SECTOR_SIZE as usize;
+const GPT_HEADER_SIZE: usize = 92;
+/// The minimum size of a GPT partition entry, the actual size is stored in the GPT header
+const GPT_PARTITION_ENTRY_MIN_SIZE: usize = 128;
+
+/// Attempts to parse a MBR or GPT partition table at the start of `data`, returning the byte ranges of each partition that lies
+/// (at least partially) within `data`, clamped to the bounds of `data`, in the order they appear in the partition table. Returns None
+/// if no recognised partition table is present.
+///
+/// Logical partitions within MBR extended partitions are not parsed, instead the extended partition is returned as a whole. All LBAs
+/// are assumed to be in terms of 512-byte sectors
+pub fn parse_partition_table(data: &[u8]) -> Option<Vec<Range<u64>>> {
+	if data.len() < SECTOR_SIZE as usize || data[MBR_SIGNATURE_OFFSET..(MBR_SIGNATURE_OFFSET + 2)] != MBR_SIGNATURE {
+		return None;
+	}
+
+	let mut partitions = Vec::new();
+
+	for i in 0..MBR_PARTITION_ENTRY_COUNT {
+		let entry_idx = MBR_PARTITION_TABLE_OFFSET + i * MBR_PARTITION_ENTRY_SIZE;
+		let entry = &data[entry_idx..(entry_idx + MBR_PARTITION_ENTRY_SIZE)];
+
+		let partition_type = entry[4];
+		let start_lba = u32::from_le_bytes(entry[8..12].try_into().unwrap()) as u64;
+		let num_sectors = u32::from_le_bytes(entry[12..16].try_into().unwrap()) as u64;
+
+		match partition_type {
+			MBR_PARTITION_TYPE_EMPTY => (),
+			MBR_PARTITION_TYPE_GPT_PROTECTIVE => {
+				return parse_gpt(data);
+			}
+			_ => {
+				partitions.push((start_lba * SECTOR_SIZE)..((start_lba + num_sectors) * SECTOR_SIZE));
+			}
+		}
+	}
+
+	let partitions = clamp_partitions(partitions, data.len() as u64);
+
+	if partitions.is_empty() {
+		None
+	} else {
+		Some(partitions)
+	}
+}
+
+/// Parses a GPT header at LBA 1 and the partition entries it refers to
+fn parse_gpt(data: &[u8]) -> Option<Vec<Range<u64>>> {
+	if data.len() < GPT_HEADER_OFFSET + GPT_HEADER_SIZE {
+		return None;
+	}
+
+	let header = &data[GPT_HEADER_OFFSET..(GPT_HEADER_OFFSET + GPT_HEADER_SIZE)];
+
+	if &header[0..8] != GPT_SIGNATURE {
+		return None;
+	}
+
+	let entries_lba = u64::from_le_bytes(header[72..80].try_into().unwrap());
+	let num_entries = u32::from_le_bytes(header[80..84].try_into().unwrap()) as usize;
+	let entry_size = u32::from_le_bytes(header[84..88].try_into().unwrap()) as usize;
+
+	if entry_size < GPT_PARTITION_ENTRY_MIN_SIZE {
+		return None;
+	}
+
+	let entries_idx = entries_lba.checked_mul(SECTOR_SIZE)? as usize;
+
+	let mut partitions = Vec::new();
+
+	for i in 0..num_entries {
+		let entry_idx = entries_idx.saturating_add(i * entry_size);
+		let Some(entry) = data.get(entry_idx..entry_idx.saturating_add(entry_size)) else {
+			break;
+		};
+
+		// An all-zero partition type GUID indicates an unused entry
+		if entry[0..16].iter().all(|&b| b == 0) {
+			continue;
+		}
+
+		let first_lba = u64::from_le_bytes(entry[32..40].try_into().unwrap());
+		let last_lba = u64::from_le_bytes(entry[40..48].try_into().unwrap()); // Inclusive
+
+		if last_lba < first_lba {
+			continue;
+		}
+
+		partitions.push(first_lba.saturating_mul(SECTOR_SIZE)..last_lba.saturating_add(1).saturating_mul(SECTOR_SIZE));
+	}
+
+	let partitions = clamp_partitions(partitions, data.len() as u64);
+
+	if partitions.is_empty() {
+		None
+	} else {
+		Some(partitions)
+	}
+}
+
+/// Clamps each partition to `data_len`, removing any that are then empty
+fn clamp_partitions(partitions: Vec<Range<u64>>, data_len: u64) -> Vec<Range<u64>> {
+	partitions.into_iter()
+		.map(|part| part.start.min(data_len)..part.end.min(data_len))
+		.filter(|part| !part.is_empty())
+		.collect()
+}
+
+/// Returns the byte ranges within `region` that aren't covered by any of `partitions`, i.e. the gaps before, between and after the
+/// partitions, in ascending order. Partitions may be in any order and may overlap
+pub fn unpartitioned_space(partitions: &[Range<u64>], region: Range<u64>) -> Vec<Range<u64>> {
+	let mut partitions = partitions.to_vec();
+	partitions.sort_by_key(|part| part.start);
+
+	let mut gaps = Vec::new();
+	let mut covered_to = region.start;
+
+	for part in partitions {
+		if part.start > covered_to {
+			gaps.push(covered_to..part.start.min(region.end));
+		}
+		covered_to = covered_to.max(part.end);
+	}
+
+	if covered_to < region.end {
+		gaps.push(covered_to..region.end);
+	}
+
+	gaps.retain(|gap| !gap.is_empty());
+
+	gaps
+}
+
+#[cfg(test)]
+mod test {
+	use super::{parse_partition_table, unpartitioned_space};
+
+	fn mbr_entry(data: &mut [u8], idx: usize, partition_type: u8, start_lba: u32, num_sectors: u32) {
+		let entry_idx = 446 + idx * 16;
+		data[entry_idx + 4] = partition_type;
+		data[(entry_idx + 8)..(entry_idx + 12)].copy_from_slice(&start_lba.to_le_bytes());
+		data[(entry_idx + 12)..(entry_idx + 16)].copy_from_slice(&num_sectors.to_le_bytes());
+	}
+
+	#[test]
+	fn test_parse_mbr() {
+		let mut data = vec![0u8; 512 * 64];
+		data[510] = 0x55;
+		data[511] = 0xaa;
+
+		mbr_entry(&mut data, 0, 0x83, 8, 16);
+		mbr_entry(&mut data, 2, 0x07, 32, 64); // Exceeds the bounds of data so should be clamped
+
+		let expected = vec![
+			(8 * 512)..(24 * 512),
+			(32 * 512)..(64 * 512)
+		];
+
+		assert_eq!(parse_partition_table(&data), Some(expected));
+	}
+
+	#[test]
+	fn test_parse_gpt() {
+		let mut data = vec![0u8; 512 * 64];
+		data[510] = 0x55;
+		data[511] = 0xaa;
+
+		mbr_entry(&mut data, 0, 0xee, 1, 63);
+
+		data[512..520].copy_from_slice(b"EFI PART");
+		data[(512 + 72)..(512 + 80)].copy_from_slice(&2u64.to_le_bytes());
+		data[(512 + 80)..(512 + 84)].copy_from_slice(&4u32.to_le_bytes());
+		data[(512 + 84)..(512 + 88)].copy_from_slice(&128u32.to_le_bytes());
+
+		for (i, (first_lba, last_lba)) in [ (34u64, 39u64), (48, 55) ].into_iter().enumerate() {
+			let entry_idx = 1024 + i * 128;
+			data[entry_idx] = 0xaf; // Non-zero partition type GUID
+			data[(entry_idx + 32)..(entry_idx + 40)].copy_from_slice(&first_lba.to_le_bytes());
+			data[(entry_idx + 40)..(entry_idx + 48)].copy_from_slice(&last_lba.to_le_bytes());
+		}
+
+		let expected = vec![
+			(34 * 512)..(40 * 512),
+			(48 * 512)..(56 * 512)
+		];
+
+		assert_eq!(parse_partition_table(&data), Some(expected));
+	}
+
+	#[test]
+	fn test_parse_no_partition_table() {
+		let data = vec![0u8; 512 * 4];
+
+		assert_eq!(parse_partition_table(&data), None);
+	}
+
+	#[test]
+	fn test_unpartitioned_space() {
+		let partitions = vec![ 2048..4096, 512..1024, 3072..5120 ];
+
+		assert_eq!(unpartitioned_space(&partitions, 0..8192), vec![ 0..512, 1024..2048, 5120..8192 ]);
+		assert_eq!(unpartitioned_space(&partitions, 768..4608), vec![ 1024..2048 ]);
+		assert_eq!(unpartitioned_space(&[], 0..8192), vec![ 0..8192 ]);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,98 @@
+use std::io::{self, IsTerminal};
+
+use log::{info, log_enabled, Level};
+
+use crate::searchlight::config::ProgressMode;
+
+/// The percentages at which progress is logged in `ProgressMode::Milestones`
+const MILESTONES: [u32; 4] = [ 25, 50, 75, 100 ];
+
+/// Reports the progress of a phase of carving (e.g. searching) in the configured `ProgressMode`. Progress is only reported if info logs are
+/// enabled, as it was when it was always written continuously
+pub struct ProgressReporter {
+	mode: ProgressMode,
+	/// The name of the phase, which prefixes milestone logs
+	phase: &'static str,
+	/// The index into `MILESTONES` of the next milestone to be logged
+	next_milestone: usize,
+	/// Whether continuous progress has been written to the current line, which then needs ending
+	line_written: bool
+}
+
+impl ProgressReporter {
+	/// Creates a reporter for `phase` in `mode`. Continuous progress overwrites the current line of stderr, which only makes sense for an
+	/// interactive terminal, so milestones are logged instead if stderr is not a terminal
+	pub fn new(mode: ProgressMode, phase: &'static str) -> Self {
+		let mode = match mode {
+			ProgressMode::Continuous if !io::stderr().is_terminal() => ProgressMode::Milestones,
+			mode => mode
+		};
+
+		ProgressReporter {
+			mode,
+			phase,
+			next_milestone: 0,
+			line_written: false
+		}
+	}
+
+	/// Reports that the phase is `fraction` (between 0 and 1) of the way through
+	pub fn report(&mut self, fraction: f32) {
+		match self.mode {
+			ProgressMode::Continuous if log_enabled!(Level::Info) => {
+				// BUG: This writes straight to stderr rather than to wherever the logs are going, and if some text is written to stderr or stdout
+				//      between writes of the progress, then there will be no line break between the progress report and the output text. Put a
+				//      space after the progress % to make that look less bad. Milestones avoid this, but ideally progress would be delegated to the
+				//      user interface code (perhaps through a channel) so it can report it how it likes
+				eprint!("\rProgress: {:.2}% ", fraction * 100.0);
+				self.line_written = true;
+			}
+			ProgressMode::Milestones => {
+				let percent = (fraction * 100.0) as u32;
+				let mut reached = None;
+				while self.next_milestone < MILESTONES.len() && MILESTONES[self.next_milestone] <= percent {
+					reached = Some(MILESTONES[self.next_milestone]);
+					self.next_milestone += 1;
+				}
+
+				// Only the highest milestone reached is logged if several were passed at once
+				if let Some(milestone) = reached {
+					info!("{} progress: {}%", self.phase, milestone);
+				}
+			}
+			ProgressMode::Continuous | ProgressMode::Silent => ()
+		}
+	}
+
+	/// Reports that the phase is complete, ending the line of continuous progress
+	pub fn finish(&mut self) {
+		self.report(1.0);
+
+		if self.line_written {
+			eprintln!();
+			self.line_written = false;
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::searchlight::config::ProgressMode;
+
+	use super::ProgressReporter;
+
+	#[test]
+	fn test_progress_milestones() {
+		let mut reporter = ProgressReporter::new(ProgressMode::Milestones, "Test");
+
+		reporter.report(0.1);
+		assert_eq!(reporter.next_milestone, 0);
+
+		// Several milestones passed at once
+		reporter.report(0.6);
+		assert_eq!(reporter.next_milestone, 2);
+
+		reporter.finish();
+		assert_eq!(reporter.next_milestone, 4);
+	}
+}
\ No newline at end of file
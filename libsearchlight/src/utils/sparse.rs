@@ -0,0 +1,133 @@
+use std::ops::Range;
+
+use crate::search::search_common::MATCH_ALL_VALUE;
+
+/// Whether every byte of `data` is `byte`. Compares a word at a time, as this is run over all searched data
+pub fn is_uniform(data: &[u8], byte: u8) -> bool {
+	let word = u64::from_ne_bytes([ byte; 8 ]);
+
+	let words = data.chunks_exact(8);
+	let remainder = words.remainder();
+
+	words.map(|w| u64::from_ne_bytes(w.try_into().unwrap())).all(|w| w == word) && remainder.iter().all(|&b| b == byte)
+}
+
+/// Returns, for each byte value, whether data consisting entirely of that byte value can be skipped when searching for `patterns`, which is
+/// the case unless a pattern could match somewhere in it, i.e. all the non-"match all" values in the pattern are that byte value
+pub fn skippable_bytes<'a>(patterns: impl IntoIterator<Item = &'a [u16]>) -> [bool; 256] {
+	let mut skippable = [ true; 256 ];
+
+	for pattern in patterns {
+		let mut values = pattern.iter().filter(|&&v| v != MATCH_ALL_VALUE);
+
+		match values.next() {
+			Some(&first) => {
+				if values.all(|&v| v == first) {
+					skippable[first as usize] = false;
+				}
+			}
+			None => {
+				// A pattern of only "match all" values matches anything
+				return [ false; 256 ];
+			}
+		}
+	}
+
+	skippable
+}
+
+/// Finds the runs of whole blocks of `block_size` bytes (aligned to the start of `data`) that each consist entirely of the same skippable
+/// byte value, returning the ranges of `data` that are left to search, in order. The ranges extend `context` bytes into each adjacent
+/// run, so that matches straddling the boundary of a run are still found as long as they are no longer than `context`
+pub fn non_uniform_ranges(data: &[u8], block_size: usize, context: usize, skippable: &[bool; 256]) -> Vec<Range<usize>> {
+	let mut runs: Vec<(Range<usize>, u8)> = Vec::new();
+
+	for (i, block) in data.chunks_exact(block_size).enumerate() {
+		let byte = block[0];
+
+		if !skippable[byte as usize] || !is_uniform(block, byte) {
+			continue;
+		}
+
+		let start = i * block_size;
+
+		match runs.last_mut() {
+			Some((run, run_byte)) if run.end == start && *run_byte == byte => {
+				run.end += block_size;
+			}
+			_ => runs.push((start..(start + block_size), byte))
+		}
+	}
+
+	let mut ranges = Vec::new();
+	let mut start = 0;
+
+	for (run, _) in runs {
+		// Runs at the very start or end of the data have nothing to straddle on that side
+		let skip_start = if run.start == 0 { 0 } else { run.start + context };
+		let skip_end = if run.end == data.len() { run.end } else { run.end.saturating_sub(context) };
+
+		if skip_start >= skip_end {
+			continue;
+		}
+
+		if skip_start > start {
+			ranges.push(start..skip_start);
+		}
+		start = skip_end;
+	}
+
+	if start < data.len() {
+		ranges.push(start..data.len());
+	}
+
+	ranges
+}
+
+#[cfg(test)]
+mod test {
+	use super::{is_uniform, non_uniform_ranges, skippable_bytes};
+
+	#[test]
+	fn test_is_uniform() {
+		let mut data = vec![ 0u8; 37 ];
+
+		assert!(is_uniform(&data, 0));
+		assert!(!is_uniform(&data, 0xff));
+
+		data[36] = 1;
+		assert!(!is_uniform(&data, 0));
+		assert!(is_uniform(&data[..36], 0));
+	}
+
+	#[test]
+	fn test_skippable_bytes() {
+		let patterns: [&[u16]; 3] = [ &[ 0x50, 0x4b ], &[ 0xff, 0x8000, 0xff ], &[ 0x00, 0x01 ] ];
+
+		let skippable = skippable_bytes(patterns);
+
+		assert!(skippable[0x00]);
+		assert!(!skippable[0xff]);
+		assert!(skippable[0x50]);
+
+		assert_eq!(skippable_bytes([ &[ 0x8000u16, 0x8000 ][..] ]), [ false; 256 ]);
+	}
+
+	#[test]
+	fn test_non_uniform_ranges() {
+		let mut data = vec![ 0u8; 16 * 8 ];
+		data[..16].fill(0x11);
+		data[(16 * 4)..(16 * 5)].fill(0x22);
+		data[16 * 5] = 0x33;
+
+		let ranges = non_uniform_ranges(&data, 16, 4, &[ true; 256 ]);
+
+		// Runs are 0..16 (0x11), 16..64 (0x00), 64..80 (0x22) and 96..128 (0x00), with 80..96 not uniform
+		assert_eq!(ranges, vec![ 12..20, 60..68, 76..100 ]);
+
+		// Runs of 0x00 are not skipped if 0x00 is not skippable
+		let mut skippable = [ true; 256 ];
+		skippable[0x00] = false;
+		assert_eq!(non_uniform_ranges(&data, 16, 4, &skippable), vec![ 12..68, 76..128 ]);
+	}
+}
\ No newline at end of file
@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use unicode_segmentation::UnicodeSegmentation;
 
 use crate::search::search_common::MATCH_ALL_VALUE;
@@ -6,6 +8,9 @@ use crate::search::search_common::MATCH_ALL_VALUE;
 /// "match all" '.' for matching any byte value (can be escaped as \.). Collects the resolved values,
 /// or 0x8000 in the case of '.'s, into a Vec<u16>.
 ///
+/// Alternation groups are not processed by this function - See `expand_alternations` - although the
+/// escape sequences \(, \) and \| are resolved to the literal characters.
+///
 /// Ignores any errors or unexpected values/conditions that occur, e.g. invalid escape sequences such
 /// as \i will be ignored.
 pub fn parse_match_str(string: &str) -> Vec<u16> {
@@ -38,6 +43,15 @@ pub fn parse_match_str(string: &str) -> Vec<u16> {
 				"." => {
 					buf.push(b'.' as u16);
 				}
+				"(" => {
+					buf.push(b'(' as u16);
+				}
+				")" => {
+					buf.push(b')' as u16);
+				}
+				"|" => {
+					buf.push(b'|' as u16);
+				}
 				"x" => {
 					if (i + 2) < gcs.len() {
 						let hex_str = &gcs[(i + 1)..=(i + 2)].join("");
@@ -73,9 +87,159 @@ pub fn parse_match_str(string: &str) -> Vec<u16> {
 	buf
 }
 
+/// Checks that a match string is well-formed, i.e. is not empty and contains only escape sequences that `parse_match_str` recognises
+/// (including \xXX having two hex digits), returning a description of the problem if not. `parse_match_str` itself ignores such errors
+pub fn check_match_str(string: &str) -> Result<(), String> {
+	let gcs: Vec<&str> = string.graphemes(true).collect();
+
+	if gcs.is_empty() {
+		return Err("Match string is empty".to_string());
+	}
+
+	let mut i = 0;
+	while i < gcs.len() {
+		if gcs[i] == "\\" {
+			match gcs.get(i + 1) {
+				Some(&("\\" | "n" | "t" | "r" | "0" | "." | "(" | ")" | "|")) => {
+					i += 2;
+				}
+				Some(&"x") => {
+					let hex_str = gcs.get((i + 2)..(i + 4)).map(|hex| hex.concat());
+
+					if hex_str.is_none_or(|hex_str| u8::from_str_radix(&hex_str, 16).is_err()) {
+						return Err(format!("Invalid hex escape sequence at character {}: Expected two hex digits after \\x", i + 1));
+					}
+
+					i += 4;
+				}
+				Some(c) => {
+					return Err(format!("Unrecognised escape sequence \\{c} at character {}", i + 1));
+				}
+				None => {
+					return Err("Match string ends with an unterminated escape sequence".to_string());
+				}
+			}
+		} else {
+			i += 1;
+		}
+	}
+
+	Ok(())
+}
+
+/// Expands the alternation groups in a match string, returning every string that the match string can match, in order of the
+/// alternatives. The grammar is:
+///
+/// ```text
+/// match_str   := (literal | group)*
+/// group       := '(' alternative ('|' alternative)* ')'
+/// alternative := match_str, which must not be empty
+/// ```
+///
+/// Where `literal` is anything accepted by `parse_match_str`. Groups may be nested, and a string with multiple groups expands to every
+/// combination of their alternatives, e.g. `\xff(\xd8|\xd9)(a|b)` expands to `\xff\xd8a`, `\xff\xd8b`, `\xff\xd9a` and `\xff\xd9b`.
+/// The characters '(', ')' and '|' must be escaped with a '\' to match them literally, so a '(' without a matching ')', a ')' or '|'
+/// outside of a group, or an empty alternative, is an error, for which a description of the problem is returned. Escape sequences are left
+/// as-is for `parse_match_str` to process.
+pub fn expand_alternations(string: &str) -> Result<Vec<String>, String> {
+	let gcs: Vec<&str> = string.graphemes(true).collect();
+
+	expand_graphemes(&gcs, 0)
+}
+
+/// Expands the graphemes `gcs`, which start at grapheme `offset` of the whole match string, for the positions in error messages
+fn expand_graphemes(gcs: &[&str], offset: usize) -> Result<Vec<String>, String> {
+	let mut expansions = vec![ String::new() ];
+
+	let mut i = 0;
+	while i < gcs.len() {
+		match gcs[i] {
+			"\\" => {
+				// Keep the escape sequence intact, which for our purposes only requires not interpreting the next grapheme
+				let end = (i + 2).min(gcs.len());
+				let escape_seq = gcs[i..end].concat();
+				for expansion in &mut expansions {
+					expansion.push_str(&escape_seq);
+				}
+				i = end;
+				continue;
+			}
+			"(" => {
+				let Some((alternatives, group_len)) = split_group(&gcs[i..]) else {
+					return Err(format!("Unclosed group at character {} - A literal '(' should be escaped as \\(", offset + i + 1));
+				};
+
+				let mut expanded_alternatives = Vec::new();
+				for alt in alternatives {
+					if alt.is_empty() {
+						return Err(format!("Empty alternative at character {}", offset + i + alt.start + 1));
+					}
+
+					expanded_alternatives.extend(expand_graphemes(&gcs[(i + alt.start)..(i + alt.end)], offset + i + alt.start)?);
+				}
+
+				expansions = expansions.iter()
+					.flat_map(|expansion| expanded_alternatives.iter().map(move |alt| format!("{expansion}{alt}")))
+					.collect();
+
+				i += group_len;
+				continue;
+			}
+			c @ (")" | "|") => {
+				return Err(format!("'{c}' outside of a group at character {} - A literal '{c}' should be escaped as \\{c}", offset + i + 1));
+			}
+			_ => ()
+		}
+
+		for expansion in &mut expansions {
+			expansion.push_str(gcs[i]);
+		}
+
+		i += 1;
+	}
+
+	Ok(expansions)
+}
+
+/// Splits the group at the start of `gcs` (which must start with a '(') into its top-level alternatives, returning the ranges of `gcs`
+/// that are the alternatives and the number of graphemes that make up the whole group, or None if the group is not closed
+fn split_group(gcs: &[&str]) -> Option<(Vec<Range<usize>>, usize)> {
+	let mut alternatives = Vec::new();
+	let mut depth = 0;
+	let mut alt_start = 1;
+
+	let mut i = 1;
+	while i < gcs.len() {
+		match gcs[i] {
+			"\\" => {
+				i += 1;
+			}
+			"(" => {
+				depth += 1;
+			}
+			")" if depth == 0 => {
+				alternatives.push(alt_start..i);
+				return Some((alternatives, i + 1));
+			}
+			")" => {
+				depth -= 1;
+			}
+			"|" if depth == 0 => {
+				alternatives.push(alt_start..i);
+				alt_start = i + 1;
+			}
+			_ => ()
+		}
+
+		i += 1;
+	}
+
+	None
+}
+
 #[cfg(test)]
 mod test {
-    use super::parse_match_str;
+    use super::{check_match_str, expand_alternations, parse_match_str};
 
 	#[test]
 	fn test_parse_match_str() {
@@ -89,4 +253,35 @@ mod test {
 
 		assert_eq!(expected, computed);
 	}
+
+	#[test]
+	fn test_expand_alternations() {
+		assert_eq!(expand_alternations("\\xff\\xd8").unwrap(), vec![ "\\xff\\xd8" ]);
+		assert_eq!(expand_alternations("\\xff(\\xd8|\\xd9)").unwrap(), vec![ "\\xff\\xd8", "\\xff\\xd9" ]);
+		assert_eq!(expand_alternations("(a|b)x(c|d)").unwrap(), vec![ "axc", "axd", "bxc", "bxd" ]);
+		assert_eq!(expand_alternations("a(b(c|d)|x)e").unwrap(), vec![ "abce", "abde", "axe" ]);
+
+		// Escaped metacharacters are left as literals
+		assert_eq!(expand_alternations("\\(a\\|b\\)").unwrap(), vec![ "\\(a\\|b\\)" ]);
+
+		// But unescaped ones must form a group, and alternatives can't be empty
+		assert!(expand_alternations("(a|b").is_err());
+		assert!(expand_alternations("a)b").is_err());
+		assert!(expand_alternations("a|b").is_err());
+		assert!(expand_alternations("a(b|)e").is_err());
+		assert!(expand_alternations("a(b(|c))e").is_err());
+		assert!(expand_alternations("a()e").is_err());
+
+		assert_eq!(parse_match_str("\\(\\|\\)"), vec![ b'(' as u16, b'|' as u16, b')' as u16 ]);
+	}
+
+	#[test]
+	fn test_check_match_str() {
+		assert!(check_match_str("\\x7f\\0\\r\\t\\n\\x9aPK..\\.\\(\\|\\)").is_ok());
+		assert!(check_match_str("").is_err());
+		assert!(check_match_str("\\s").is_err());
+		assert!(check_match_str("\\xy1").is_err());
+		assert!(check_match_str("\\x1").is_err());
+		assert!(check_match_str("PK\\").is_err());
+	}
 }
\ No newline at end of file
@@ -1,16 +1,34 @@
 pub mod jpeg;
 pub mod png;
 pub mod zip;
+pub mod mp3;
+pub mod eml;
+pub mod psd;
+pub mod dicom;
+pub mod requires;
+pub mod size_field;
+pub mod sparse_img;
+pub mod font;
+pub mod gif;
+pub mod lnk;
+pub mod jp2;
+pub mod ogg;
+pub mod riff;
+pub mod isobmff;
+pub mod wim;
+pub mod cache;
 
-use std::{collections::HashMap, ops::Range};
+use std::{collections::HashMap, fmt, io::{self, Read, Write}, ops::Range};
 
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::{search::{pairing::MatchPair, Match}, searchlight::config::{FileTypeId, SearchlightConfig}};
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::{FileType, FileTypeId, SearchlightConfig, ZipEntryCountPolicy}, utils::multi_reader::MultiReader};
 
-use self::{jpeg::JpegValidator, png::PngValidator, zip::ZipValidator};
+use self::{dicom::DicomValidator, eml::EmlValidator, font::FontValidator, gif::GifValidator, jp2::Jp2Validator, jpeg::JpegValidator, lnk::LnkValidator, mp3::Mp3Validator, ogg::OggValidator, png::PngValidator, psd::PsdValidator, requires::RequiresValidator, riff::RiffValidator, size_field::SizeFieldValidator, sparse_img::SparseImgValidator, wim::WimValidator, zip::ZipValidator};
 
-pub trait FileValidator {
+/// Validators are shared with the detached threads that candidates are validated on if `validation_timeout_ms` is configured, so must be `Send`
+/// and `Sync`
+pub trait FileValidator: Send + Sync {
 	/// Attempts to reconstruct and validate a potential file indicated by a given header-footer pair as belonging to a particular file format, decided per
 	/// implementor (although there is nothing stopping one from making a master validator). This function should return a validation type, indicating the
 	/// level of validity of the data (see FileValidationType variant docs for details) as well as an optional Vec listing all the fragments of the
@@ -18,8 +36,74 @@ pub trait FileValidator {
 	///
 	/// `cluster_size` is given to aid reconstruction logic. It must not be assumed that cluster_size is any sensible value, as users can pass in anything.
 	/// Additionally, a cluster size of 1 indicates that files in the image aren't allocated on cluster boundaries. Similarly, a reference to the whole
-	/// config is given to aid reconstruction
+	/// config is given to aid reconstruction. `all_matches` is sorted by start, so the matches near an offset can be found by binary search
 	fn validate(&self, file_data: &[u8], file_match: &MatchPair, all_matches: &[Match], cluster_size: usize, config: &SearchlightConfig) -> FileValidationInfo;
+
+	/// The keys of the validator parameters (see `FileType::params`) that this validator understands for file types of `type_id`. Default
+	/// implementation returns none
+	fn known_params(&self, _type_id: FileTypeId) -> &'static [&'static str] {
+		&[]
+	}
+
+	/// A short description of what this validator checks, for help text and documentation. Default implementation returns an empty string
+	fn description(&self) -> &'static str {
+		""
+	}
+
+	/// Whether this validator attempts to reconstruct fragmented files, rather than only validating contiguous data. Default implementation
+	/// returns false
+	fn reconstructs_fragmentation(&self) -> bool {
+		false
+	}
+}
+
+/// A description of a dedicated validator, as listed by `DelegatingValidator::supported_types`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ValidatorInfo {
+	pub type_id: FileTypeId,
+	/// What the validator checks, see `FileValidator::description`
+	pub description: &'static str,
+	/// Whether the validator attempts to reconstruct fragmented files, see `FileValidator::reconstructs_fragmentation`
+	pub reconstructs_fragmentation: bool
+}
+
+/// How candidates of a configured file type are validated, as reported by `DelegatingValidator::coverage`
+#[derive(Debug, PartialEq, Clone, Copy, strum::Display)]
+pub enum ValidationCoverage {
+	/// By the dedicated validator of the type's type_id
+	#[strum(to_string = "dedicated validator")]
+	Dedicated,
+	/// Generically, by reading the file length from the configured size field
+	#[strum(to_string = "size field")]
+	SizeField,
+	/// Generically, by checking for the configured required sequences
+	#[strum(to_string = "required sequences")]
+	Requires,
+	/// Not validated, so candidates are carved by the span from their header to their footer (or max_len)
+	#[strum(to_string = "not validated (carved by header/footer span)")]
+	None
+}
+
+/// The validation coverage of a configured file type, as reported by `DelegatingValidator::coverage`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TypeCoverage<'a> {
+	pub file_type: &'a FileType,
+	pub coverage: ValidationCoverage,
+	/// A type ID with a dedicated validator that is named the same as the extension of a file type without one, as it was likely meant
+	/// to be the type_id of the file type (e.g. a PNG type left with `type_id = "unknown"`)
+	pub suggested_type_id: Option<FileTypeId>
+}
+
+impl fmt::Display for TypeCoverage<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} (type_id {}): {}", self.file_type.extension.as_deref().unwrap_or("<no extension>"), self.file_type.type_id, self.coverage)?;
+
+		if let Some(suggested) = self.suggested_type_id {
+			write!(f, " - type_id \"{}\" has a dedicated validator", suggested)?;
+		}
+
+		Ok(())
+	}
 }
 
 pub type Fragment = Range<usize>;
@@ -28,21 +112,147 @@ pub struct FileValidationInfo {
 	/// The result of validating the data - Whether it is recognised as fully present and correct, partial, corrupted, etc
 	pub validation_type: FileValidationType,
 	/// The fragment(s) of file content, expressed in terms of a range of indexes into the file data array, or an empty Vec if there are no recoverable fragments
-	pub fragments: Vec<Fragment>
+	pub fragments: Vec<Fragment>,
+	/// The reason that the data was not validated as correct, if known
+	pub rejection_reason: Option<RejectionReason>,
+	/// Metadata extracted from the file by the validator, keyed by the `METADATA_*` constants. Empty for validators that don't extract any
+	pub metadata: HashMap<String, String>,
+	/// The files embedded in the file that can be extracted from it, e.g. the members of a ZIP archive. Only found if `extract_embedded` is
+	/// configured, and empty for validators that don't support it
+	pub embedded: Vec<EmbeddedFile>
+}
+
+/// The metadata key for the time the file was last modified, as the number of seconds since the Unix epoch
+pub const METADATA_MODIFIED_TIME: &str = "modified_time";
+
+/// The metadata key for the codec of the stream in a container format, e.g. `opus` for an Ogg Opus file
+pub const METADATA_CODEC: &str = "codec";
+
+/// The metadata key for the name of an embedded file as recorded in the file it was extracted from, for embedded files recorded as entries
+/// of the carve log of their own
+pub const METADATA_EMBEDDED_NAME: &str = "embedded_name";
+
+/// The metadata key for the name of the file as recorded in its own content, which may include a path, or otherwise as inferred from it (e.g.
+/// a ZIP archive of a single directory is named after that directory). Carved files are named by it if `original_names` is configured
+pub const METADATA_ORIGINAL_NAME: &str = "original_name";
+
+/// The metadata key for the name that a file named by its `METADATA_ORIGINAL_NAME` would otherwise have had, from `filename_template`
+pub const METADATA_OFFSET_NAME: &str = "offset_name";
+
+/// Validator parameter (an integer) that overrides `SearchlightConfig::max_reconstruction_search_len` for a file type. Understood by the JPEG,
+/// PNG and ZIP validators
+pub const PARAM_MAX_RECONSTRUCTION_SEARCH_LEN: &str = "max_reconstruction_search_len";
+/// Validator parameter (a boolean) that overrides `SearchlightConfig::bound_reconstruction_by_headers` for a file type. Understood by the PNG
+/// validator
+pub const PARAM_BOUND_RECONSTRUCTION_BY_HEADERS: &str = "bound_reconstruction_by_headers";
+/// Validator parameter (a `ZipEntryCountPolicy`) that overrides `SearchlightConfig::zip_entry_count_policy` for a file type. Understood by
+/// the ZIP validator
+pub const PARAM_ENTRY_COUNT_POLICY: &str = "entry_count_policy";
+/// Validator parameter (a boolean) for whether the recognised metadata segments that some software appends after the end of a JPEG file are
+/// carved with it. Defaults to true. Has no effect if `carve_extra_cluster` is set. Understood by the JPEG validator
+pub const PARAM_TRAILING_METADATA: &str = "trailing_metadata";
+/// Validator parameter (an integer) for the maximum length of the data of a chunk, above which the length is taken to be corrupt, and the
+/// chunk is not reconstructed. Understood by the PNG validator
+pub const PARAM_MAX_CHUNK_LEN: &str = "max_chunk_len";
+/// Validator parameter (a boolean) for whether compressed image data is decoded to check that it is intact, rather than only its structure
+/// being checked, which is more thorough but more expensive. Defaults to false. Understood by the GIF validator
+pub const PARAM_DECODE_IMAGE_DATA: &str = "decode_image_data";
+/// Validator parameter (an integer) for the maximum number of gaps, each a run of clusters that don't classify as scan data, that are skipped
+/// over when reconstructing fragmented scan data before the reconstruction fails. Unlimited by default. Understood by the JPEG validator
+pub const PARAM_MAX_SCAN_GAPS: &str = "max_scan_gaps";
+/// Validator parameter (an integer) for the maximum number of unrecognised chunks, i.e. data that isn't a chunk of a known type where a chunk
+/// is expected, that are skipped over by searching for the next intact chunk, rather than validation ending at the first. Files with skipped
+/// chunks are at best Partial. Defaults to 0. Understood by the PNG validator
+pub const PARAM_MAX_SKIPPED_CHUNKS: &str = "max_skipped_chunks";
+/// Validator parameter (a boolean) for whether chunk types that aren't in the list of known types are accepted if they follow the rule of the
+/// spec, i.e. four ASCII letters with the third uppercase, so that files with private or otherwise unknown chunks can be walked. Defaults to
+/// false, as the list rejects text that happens to follow the rule, which is more likely to be mistaken for chunks in reconstruction and
+/// skipping. Understood by the PNG validator
+pub const PARAM_SPEC_CHUNK_TYPES: &str = "spec_chunk_types";
+
+/// The maximum distance that validators search forwards when reconstructing fragmented data of `file_type`, which is its
+/// `PARAM_MAX_RECONSTRUCTION_SEARCH_LEN` parameter if set, or otherwise `config.max_reconstruction_search_len` (unlimited if neither is set)
+fn max_reconstruction_search_len(file_type: &FileType, config: &SearchlightConfig) -> usize {
+	file_type.param::<u64>(PARAM_MAX_RECONSTRUCTION_SEARCH_LEN).or(config.max_reconstruction_search_len).map(|len| len as usize).unwrap_or(usize::MAX)
+}
+
+/// Checks the values of the validator parameters of `file_type` that validators understand, returning the key of each whose value isn't of
+/// the parameter's type along with a description of the problem. `FileType::param` treats such values as unset
+pub fn invalid_params(file_type: &FileType) -> Vec<(&str, String)> {
+	fn check<T: DeserializeOwned>(value: &toml::Value) -> Result<(), String> {
+		value.clone().try_into::<T>().map(|_| ()).map_err(|e| e.to_string())
+	}
+
+	file_type.params.iter().filter_map(|(key, value)| {
+		let result = match key.as_str() {
+			PARAM_MAX_RECONSTRUCTION_SEARCH_LEN | PARAM_MAX_CHUNK_LEN | PARAM_MAX_SCAN_GAPS | PARAM_MAX_SKIPPED_CHUNKS => check::<u64>(value),
+			PARAM_BOUND_RECONSTRUCTION_BY_HEADERS | PARAM_TRAILING_METADATA | PARAM_DECODE_IMAGE_DATA | PARAM_SPEC_CHUNK_TYPES => check::<bool>(value),
+			PARAM_ENTRY_COUNT_POLICY => check::<ZipEntryCountPolicy>(value),
+			_ => Ok(())
+		};
+
+		result.err().map(|e| (key.as_str(), e))
+	}).collect()
 }
 
 impl Default for FileValidationInfo {
 	fn default() -> Self {
 		FileValidationInfo {
 			validation_type: FileValidationType::Unanalysed,
-			fragments: Vec::new()
+			fragments: Vec::new(),
+			rejection_reason: None,
+			metadata: HashMap::new(),
+			embedded: Vec::new()
 		}
 	}
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, strum::Display)]
+/// A file embedded within a validated file, e.g. a member of a ZIP archive, which can be extracted from the file data
+#[derive(Debug, PartialEq, Clone)]
+pub struct EmbeddedFile {
+	/// The name of the file as recorded in the containing file, which may include a path
+	pub name: String,
+	/// The result of validating the embedded file as part of validating the containing file
+	pub validation_type: FileValidationType,
+	/// How the data in `fragments` is encoded
+	pub encoding: EmbeddedEncoding,
+	/// The fragment(s) of the encoded content of the file, in the same terms as `FileValidationInfo::fragments`
+	pub fragments: Vec<Fragment>
+}
+
+/// The ways that the content of an embedded file can be encoded within the containing file. The order is part of the binary carve log
+/// format (see `CarveLog::encode_binary`)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, strum::Display, strum::FromRepr)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
+#[repr(u8)]
+pub enum EmbeddedEncoding {
+	/// The content is stored as-is
+	Stored,
+	/// The content is compressed with raw DEFLATE
+	Deflate
+}
+
+impl EmbeddedEncoding {
+	/// Decodes the encoded content in `fragment_data` (the data of each fragment, in order), writing the decoded content to `out`. Returns
+	/// the number of bytes written, or an error if the content could not be decoded or written
+	pub fn decode(&self, fragment_data: &[&[u8]], out: &mut dyn Write) -> io::Result<u64> {
+		let mut reader: Box<dyn Read + '_> = match self {
+			EmbeddedEncoding::Stored => Box::new(MultiReader::new(fragment_data)),
+			EmbeddedEncoding::Deflate => Box::new(flate2::read::DeflateDecoder::new(MultiReader::new(fragment_data)))
+		};
+
+		io::copy(&mut reader, out)
+	}
+}
+
+/// The outcome of validating a file. Validation types are ordered from best to worst, in the order that they are declared, so that e.g.
+/// `validation_type <= FileValidationType::Partial` is true for Correct and Partial files. The order is also part of the binary carve log
+/// format (see `CarveLog::encode_binary`)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, strum::Display, strum::EnumString, strum::FromRepr)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+#[repr(u8)]
 pub enum FileValidationType { // BUG: Distinction between "Partial" and "Corrupt"?
 	/// Data is recognised as completely valid for the file format
 	Correct,
@@ -52,33 +262,198 @@ pub enum FileValidationType { // BUG: Distinction between "Partial" and "Corrupt
 	FormatError,
 	/// The data is partially recognised, but there are miscellaneous/unknown errors
 	Corrupt,
-	/// The data does not resemble the file format it was supposed to be at all
-	Unrecognised,
 	/// The data has not been analysed, usually due to a missing implementation
-	Unanalysed
+	Unanalysed,
+	/// The data does not resemble the file format it was supposed to be at all
+	Unrecognised
+}
+
+/// The reason a candidate file was rejected or not validated as correct. The displayed form of each reason is prefixed with the format it
+/// is specific to, if any, to make logs easy to search through
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, strum::Display)]
+pub enum RejectionReason {
+	#[strum(to_string = "No validator is implemented for the file type")]
+	NoValidator,
+	#[strum(to_string = "The available data ends before the end of the file")]
+	Truncated,
+	#[strum(to_string = "No footer was found in range of the header")]
+	NoFooterInRange,
+	#[strum(to_string = "The candidate is longer than the configured max_candidate_span")]
+	CandidateTooLarge,
+	#[strum(to_string = "The entropy of the data is outside of the range expected for the file type")]
+	EntropyOutOfRange,
+	#[strum(to_string = "The validator panicked while validating the file")]
+	ValidatorPanicked,
+	#[strum(to_string = "A byte sequence that the file type requires was not found in the file")]
+	RequiredSequenceMissing,
+	#[strum(to_string = "The size field lies outside of the candidate")]
+	SizeFieldOutOfBounds,
+	#[strum(to_string = "The size in the size field is smaller than the field or larger than max_len")]
+	SizeFieldInvalid,
+	#[strum(to_string = "The data appears to be NTFS-compressed (LZNT1), so could not be validated or reconstructed")]
+	PossiblyCompressed,
+	#[strum(to_string = "The candidate overlaps a candidate of the same type that validated better")]
+	OverlapsBetterCandidate,
+	#[strum(to_string = "JPEG: Expected a marker, but found none")]
+	JpegMissingMarker,
+	#[strum(to_string = "JPEG: No APPn or no SOFn segment seen")]
+	JpegMissingMandatorySegment,
+	#[strum(to_string = "JPEG: Scan data reconstruction failed")]
+	JpegScanReconstructionFailed,
+	#[strum(to_string = "PNG: No PNG signature at the start of the file")]
+	PngMissingSignature,
+	#[strum(to_string = "PNG: Unrecognised chunk")]
+	PngUnrecognisedChunk,
+	#[strum(to_string = "PNG: Chunk reconstruction failed")]
+	PngChunkReconstructionFailed,
+	#[strum(to_string = "PNG: Chunk length exceeds max_chunk_len, so is likely corrupt")]
+	PngChunkTooLong,
+	#[strum(to_string = "PNG: IEND chunk CRC mismatch")]
+	PngIendCrcMismatch,
+	#[strum(to_string = "PNG: Chunk CRC mismatch (not reconstructed as CRC verification is disabled)")]
+	PngChunkCrcMismatch,
+	#[strum(to_string = "PNG: Invalid chunk data")]
+	PngInvalidChunkData,
+	#[strum(to_string = "PNG: Required chunks missing or out of order")]
+	PngInvalidChunkOrder,
+	#[strum(to_string = "ZIP: Multi-disk archives are not supported")]
+	ZipMultiDisk,
+	#[strum(to_string = "ZIP: Central directory entry count mismatch")]
+	ZipEntryCountMismatch,
+	#[strum(to_string = "ZIP: Unsupported compression method")]
+	ZipUnsupportedCompression,
+	#[strum(to_string = "ZIP: Possible corruption or out-of-order fragmentation")]
+	ZipOutOfOrderFragmentation,
+	#[strum(to_string = "ZIP: Fragmented file data exceeds max_reconstruction_search_len")]
+	ZipReconstructionSearchLenExceeded,
+	#[strum(to_string = "ZIP: File data reconstruction failed")]
+	ZipFileReconstructionFailed,
+	#[strum(to_string = "ZIP: File data CRC mismatch (not reconstructed as CRC verification is disabled)")]
+	ZipCrcMismatch,
+	#[strum(to_string = "MP3: No frames found")]
+	Mp3NoFrames,
+	#[strum(to_string = "MP3: Too few consistent frames")]
+	Mp3TooFewFrames,
+	#[strum(to_string = "MP3: Invalid frame header")]
+	Mp3InvalidFrameHeader,
+	#[strum(to_string = "EML: Header is in the middle of a header block")]
+	EmlMidHeaderBlock,
+	#[strum(to_string = "EML: Malformed header line")]
+	EmlMalformedHeader,
+	#[strum(to_string = "EML: No MIME closing boundary found")]
+	EmlMissingClosingBoundary,
+	#[strum(to_string = "EML: No plausible end of message found")]
+	EmlNoPlausibleEnd,
+	#[strum(to_string = "PSD: Unsupported version")]
+	PsdUnsupportedVersion,
+	#[strum(to_string = "PSD: Invalid header fields")]
+	PsdInvalidHeaderFields,
+	#[strum(to_string = "PSD: Image data length cannot be determined")]
+	PsdUnknownImageDataLength,
+	#[strum(to_string = "PSD: Image data length is too large to be valid")]
+	PsdImageDataLengthOverflow,
+	#[strum(to_string = "DICOM: No DICM magic after the preamble")]
+	DicomMissingMagic,
+	#[strum(to_string = "DICOM: Invalid file meta information group")]
+	DicomInvalidMetaGroup,
+	#[strum(to_string = "DICOM: Invalid value representation")]
+	DicomInvalidVr,
+	#[strum(to_string = "DICOM: Unsupported (deflated) transfer syntax")]
+	DicomUnsupportedTransferSyntax,
+	#[strum(to_string = "Android sparse: Unsupported version or invalid header fields")]
+	SparseImgInvalidHeader,
+	#[strum(to_string = "Android sparse: Chunk of unrecognised type or with an incorrect size")]
+	SparseImgInvalidChunk,
+	#[strum(to_string = "Android sparse: Chunks don't cover the number of blocks in the header")]
+	SparseImgBlockCountMismatch,
+	#[strum(to_string = "Font: Unrecognised signature or invalid header fields")]
+	FontInvalidHeader,
+	#[strum(to_string = "Font: Invalid table directory")]
+	FontInvalidTableDirectory,
+	#[strum(to_string = "Font: Misaligned or out of place table")]
+	FontInvalidTableRecord,
+	#[strum(to_string = "Font: Table checksum mismatch")]
+	FontChecksumMismatch,
+	#[strum(to_string = "GIF: No GIF signature at the start of the file")]
+	GifMissingSignature,
+	#[strum(to_string = "GIF: Unrecognised block")]
+	GifUnrecognisedBlock,
+	#[strum(to_string = "GIF: Invalid LZW minimum code size")]
+	GifInvalidCodeSize,
+	#[strum(to_string = "GIF: No image in the file")]
+	GifNoImage,
+	#[strum(to_string = "GIF: Image data could not be decoded")]
+	GifImageDataCorrupt,
+	#[strum(to_string = "LNK: Invalid header size or CLSID")]
+	LnkInvalidHeader,
+	#[strum(to_string = "LNK: Structure with an invalid size")]
+	LnkInvalidStructureSize,
+	#[strum(to_string = "JP2: No signature box at the start of the file")]
+	Jp2InvalidSignature,
+	#[strum(to_string = "JP2: File type brand isn't jp2")]
+	Jp2UnsupportedBrand,
+	#[strum(to_string = "JP2: Invalid or out of place box")]
+	Jp2InvalidBoxStructure,
+	#[strum(to_string = "JP2: No JP2 header box before the codestream")]
+	Jp2MissingHeader,
+	#[strum(to_string = "Ogg: Invalid page, or page not where the previous page ends")]
+	OggInvalidPage,
+	#[strum(to_string = "Ogg: Page checksum mismatch")]
+	OggChecksumMismatch,
+	#[strum(to_string = "Ogg: First page isn't a recognised codec identification header")]
+	OggUnrecognisedCodec,
+	#[strum(to_string = "Ogg: Codec isn't that of the file type")]
+	OggCodecMismatch,
+	#[strum(to_string = "Ogg: Invalid codec identification or comment header")]
+	OggInvalidCodecHeader,
+	#[strum(to_string = "WIM: Unsupported version or invalid header fields")]
+	WimInvalidHeader,
+	#[strum(to_string = "WIM: Resource overlapping the header or another resource, beyond the maximum length, or missing XML data")]
+	WimInvalidResource,
+	#[strum(to_string = "RIFF: Not a RIFF header, or form type isn't that of the file type")]
+	RiffInvalidHeader,
+	#[strum(to_string = "RIFF: Chunk extending past the end of the container")]
+	RiffInvalidChunk,
+	#[strum(to_string = "RIFF: Missing or misplaced chunk that the form type requires")]
+	RiffMissingChunk,
+	#[strum(to_string = "Validation took longer than the configured validation_timeout_ms")]
+	ValidationTimedOut,
+}
+
+impl RejectionReason {
+	/// Whether this is the reason for a checksum not matching or fragmented data not being reconstructed, which is how files stored compressed
+	/// by the filesystem fail validation
+	pub fn is_checksum_or_reconstruction_failure(&self) -> bool {
+		matches!(
+			self,
+			RejectionReason::JpegScanReconstructionFailed
+				| RejectionReason::PngChunkReconstructionFailed
+				| RejectionReason::PngIendCrcMismatch
+				| RejectionReason::PngChunkCrcMismatch
+				| RejectionReason::ZipReconstructionSearchLenExceeded
+				| RejectionReason::ZipFileReconstructionFailed
+				| RejectionReason::ZipCrcMismatch
+				| RejectionReason::FontChecksumMismatch
+				| RejectionReason::OggChecksumMismatch
+		)
+	}
 }
 
 impl FileValidationType {
-	/// Like a min for FileValidationType, but Unrecognised and Unanalysed are treated the same, and are always the worst outcome
+	/// Returns the worse of the two validation types, i.e. the greater according to the ordering of FileValidationType
 	pub fn worst_of(self, other: FileValidationType) -> FileValidationType {
-		if self == FileValidationType::Correct {
-			other
-		} else if self == FileValidationType::Partial && other != FileValidationType::Correct {
-			other
-		} else if self == FileValidationType::FormatError && other != FileValidationType::Correct && other != FileValidationType::Partial {
-			other
-		} else if self == FileValidationType::Corrupt && other != FileValidationType::Correct && other != FileValidationType::Partial && other != FileValidationType::FormatError {
-			other
-		} else {
-			self
-		}
+		self.max(other)
 	}
 }
 
 /// This validator, upon construction, instantiates all defined validators and when `validate` is called it will read the file type id from
 /// the file match pair and delegate validation to the appropriate validator, if one is implemented for that type
 pub struct DelegatingValidator {
-	validators: HashMap<FileTypeId, Box<dyn FileValidator>>
+	validators: HashMap<FileTypeId, Box<dyn FileValidator>>,
+	/// Used for file types that don't have a dedicated validator but are configured with a size field
+	size_field_validator: SizeFieldValidator,
+	/// Used for file types that don't have a dedicated validator but are configured with required byte sequences
+	requires_validator: RequiresValidator
 }
 
 impl DelegatingValidator {
@@ -97,20 +472,239 @@ impl DelegatingValidator {
 					FileTypeId::Zip,
 					Box::new(ZipValidator::new()) as Box<dyn FileValidator>
 				),
-			].into()
+				(
+					FileTypeId::Mp3,
+					Box::new(Mp3Validator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Eml,
+					Box::new(EmlValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Psd,
+					Box::new(PsdValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Dicom,
+					Box::new(DicomValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::SparseImg,
+					Box::new(SparseImgValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Font,
+					Box::new(FontValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Gif,
+					Box::new(GifValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Lnk,
+					Box::new(LnkValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Jp2,
+					Box::new(Jp2Validator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Ogg,
+					Box::new(OggValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Opus,
+					Box::new(OggValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Wim,
+					Box::new(WimValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Wav,
+					Box::new(RiffValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Avi,
+					Box::new(RiffValidator::new()) as Box<dyn FileValidator>
+				),
+				(
+					FileTypeId::Webp,
+					Box::new(RiffValidator::new()) as Box<dyn FileValidator>
+				),
+			].into(),
+			size_field_validator: SizeFieldValidator::new(),
+			requires_validator: RequiresValidator::new()
 		}
 	}
+
+	/// Lists the file type IDs that have a dedicated validator, along with a description of each validator, in the order that the IDs are
+	/// declared. File types without one are only validated generically, if they are configured with a size field or required sequences
+	pub fn supported_types(&self) -> Vec<ValidatorInfo> {
+		let mut supported: Vec<ValidatorInfo> = self.validators.iter().map(|(&type_id, validator)| ValidatorInfo {
+			type_id,
+			description: validator.description(),
+			reconstructs_fragmentation: validator.reconstructs_fragmentation()
+		}).collect();
+		supported.sort_by_key(|info| info.type_id as u8);
+
+		supported
+	}
+
+	/// Reports how candidates of each of the file types in `config` will be validated: by a dedicated validator, generically, or not at
+	/// all. Doesn't require searching, so can be used to check a config before carving
+	pub fn coverage<'a>(&self, config: &'a SearchlightConfig) -> Vec<TypeCoverage<'a>> {
+		config.file_types.iter().map(|file_type| {
+			let coverage = if self.validators.contains_key(&file_type.type_id) {
+				ValidationCoverage::Dedicated
+			} else if file_type.size_field.is_some() {
+				ValidationCoverage::SizeField
+			} else if !file_type.requires.is_empty() {
+				ValidationCoverage::Requires
+			} else {
+				ValidationCoverage::None
+			};
+
+			let suggested_type_id = if coverage == ValidationCoverage::Dedicated {
+				None
+			} else {
+				file_type.extension.as_deref().and_then(|ext| {
+					self.validators.keys().find(|type_id| type_id.to_string().eq_ignore_ascii_case(ext)).copied()
+				})
+			};
+
+			TypeCoverage {
+				file_type,
+				coverage,
+				suggested_type_id
+			}
+		}).collect()
+	}
 }
 
 impl FileValidator for DelegatingValidator {
 	fn validate(&self, file_data: &[u8], file_match: &MatchPair, all_matches: &[Match], cluster_size: usize, config: &SearchlightConfig) -> FileValidationInfo {
 		if let Some(validator) = self.validators.get(&file_match.file_type.type_id) {
 			validator.validate(file_data, file_match, all_matches, cluster_size, config)
+		} else if file_match.file_type.size_field.is_some() {
+			self.size_field_validator.validate(file_data, file_match, all_matches, cluster_size, config)
+		} else if !file_match.file_type.requires.is_empty() {
+			self.requires_validator.validate(file_data, file_match, all_matches, cluster_size, config)
 		} else {
 			FileValidationInfo {
 				validation_type: FileValidationType::Unanalysed,
-				fragments: Vec::new()
+				fragments: Vec::new(),
+				rejection_reason: Some(RejectionReason::NoValidator),
+				..Default::default()
 			}
 		}
 	}
+
+	fn known_params(&self, type_id: FileTypeId) -> &'static [&'static str] {
+		self.validators.get(&type_id).map(|validator| validator.known_params(type_id)).unwrap_or(&[])
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::io::Write;
+
+	use crate::searchlight::config::{Endianness, FileType, FileTypeId, SearchlightConfig, SizeField};
+
+	use super::{DelegatingValidator, EmbeddedEncoding, FileValidationType, ValidationCoverage};
+
+	#[test]
+	fn test_validation_type_ordering() {
+		let ordered = [
+			FileValidationType::Correct,
+			FileValidationType::Partial,
+			FileValidationType::FormatError,
+			FileValidationType::Corrupt,
+			FileValidationType::Unanalysed,
+			FileValidationType::Unrecognised,
+		];
+
+		for (i, &a) in ordered.iter().enumerate() {
+			for (j, &b) in ordered.iter().enumerate() {
+				assert_eq!(a.cmp(&b), i.cmp(&j));
+				assert_eq!(a.worst_of(b), ordered[i.max(j)]);
+			}
+		}
+
+		assert!(FileValidationType::Partial <= FileValidationType::Partial);
+		assert!(FileValidationType::FormatError > FileValidationType::Partial);
+	}
+
+	#[test]
+	fn test_supported_types() {
+		let supported = DelegatingValidator::new().supported_types();
+
+		let supported_ids: Vec<FileTypeId> = supported.iter().map(|info| info.type_id).collect();
+		for type_id in [ FileTypeId::Jpeg, FileTypeId::Png, FileTypeId::Zip, FileTypeId::Ogg, FileTypeId::Opus ] {
+			assert!(supported_ids.contains(&type_id), "{type_id} has a dedicated validator");
+		}
+		assert!(!supported_ids.contains(&FileTypeId::Unknown));
+
+		assert!(supported.windows(2).all(|pair| (pair[0].type_id as u8) < (pair[1].type_id as u8)));
+		assert!(supported.iter().all(|info| !info.description.is_empty()));
+
+		let reconstructing: Vec<FileTypeId> = supported.iter().filter(|info| info.reconstructs_fragmentation).map(|info| info.type_id).collect();
+		assert_eq!(reconstructing, vec![ FileTypeId::Jpeg, FileTypeId::Png, FileTypeId::Zip ]);
+	}
+
+	#[test]
+	fn test_coverage() {
+		let file_type = |extension: &str, type_id: FileTypeId| FileType {
+			extension: Some(extension.to_string()),
+			type_id,
+			..Default::default()
+		};
+
+		let config = SearchlightConfig {
+			file_types: vec![
+				file_type("png", FileTypeId::Png),
+				file_type("png", FileTypeId::Unknown),
+				FileType {
+					size_field: Some(SizeField { offset: 2, width: 4, endianness: Endianness::Little, relative: false, addend: 0 }),
+					..file_type("bmp", FileTypeId::Unknown)
+				},
+				file_type("txt", FileTypeId::Unknown),
+			],
+			..Default::default()
+		};
+
+		let coverage: Vec<(ValidationCoverage, Option<FileTypeId>)> = DelegatingValidator::new().coverage(&config).iter()
+			.map(|coverage| (coverage.coverage, coverage.suggested_type_id))
+			.collect();
+
+		assert_eq!(coverage, vec![
+			(ValidationCoverage::Dedicated, None),
+			(ValidationCoverage::None, Some(FileTypeId::Png)),
+			(ValidationCoverage::SizeField, None),
+			(ValidationCoverage::None, None),
+		]);
+	}
+
+	#[test]
+	fn test_embedded_encoding_decode() {
+		let content = b"embedded file content ".repeat(20);
+
+		let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+		encoder.write_all(&content).unwrap();
+		let compressed = encoder.finish().unwrap();
+
+		// The encoded content may be split into any fragments
+		let (first, second) = compressed.split_at(compressed.len() / 2);
+
+		let mut decoded = Vec::new();
+		EmbeddedEncoding::Deflate.decode(&[ first, second ], &mut decoded).unwrap();
+		assert_eq!(decoded, content);
+
+		let mut decoded = Vec::new();
+		EmbeddedEncoding::Stored.decode(&[ &content[..10], &content[10..] ], &mut decoded).unwrap();
+		assert_eq!(decoded, content);
+
+		// Data that isn't valid DEFLATE can't be decoded
+		assert!(EmbeddedEncoding::Deflate.decode(&[ &[ 0xff; 16 ] ], &mut Vec::new()).is_err());
+	}
 }
\ No newline at end of file
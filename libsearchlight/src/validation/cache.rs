@@ -0,0 +1,223 @@
+use std::{collections::{HashMap, VecDeque}, hash::{DefaultHasher, Hash, Hasher}};
+
+use crate::{search::pairing::MatchPair, searchlight::config::FileTypeId};
+
+use super::{EmbeddedFile, FileValidationInfo, Fragment};
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+struct CacheKey {
+	type_id: FileTypeId,
+	/// The length of the candidate, from the start of the header to the end of the footer (or max_len)
+	len: usize,
+	cluster_size: usize,
+	/// The offset of the candidate into its cluster, as reconstruction depends on where cluster boundaries lie
+	cluster_offset: usize,
+	truncated: bool,
+	/// A hash of all of the data of the candidate
+	content_hash: u64
+}
+
+/// A bounded cache of validation results, keyed on the file type, length, cluster alignment and a hash of the data of each candidate, so
+/// that identical candidates (e.g. duplicated files) are not validated multiple times. Fragments are stored relative to the start of the
+/// candidate, and translated to the start of each candidate that hits the cache.
+///
+/// Note that validators can look at data outside of the candidate, such as metadata trailing a JPEG, so there is a possibility that a
+/// cached result is not what validating the candidate would give
+pub struct ValidationCache {
+	capacity: usize,
+	entries: HashMap<CacheKey, (FileValidationInfo, usize)>,
+	/// The order in which entries were inserted, so the oldest can be evicted once the capacity is reached
+	insertion_order: VecDeque<CacheKey>,
+	hits: usize
+}
+
+impl ValidationCache {
+	/// Creates a cache that holds at most `capacity` validation results
+	pub fn new(capacity: usize) -> Self {
+		ValidationCache {
+			capacity,
+			entries: HashMap::new(),
+			insertion_order: VecDeque::new(),
+			hits: 0
+		}
+	}
+
+	/// The number of times that a cached validation result has been used
+	pub fn hits(&self) -> usize {
+		self.hits
+	}
+
+	/// Returns the cached validation result for `file_match` if there is one, otherwise calls `validate` and caches its result
+	pub fn get_or_validate(&mut self, file_data: &[u8], file_match: &MatchPair, cluster_size: usize, validate: impl FnOnce() -> FileValidationInfo) -> FileValidationInfo {
+		let key = Self::key(file_data, file_match, cluster_size);
+
+		if let Some((info, cached_start)) = self.entries.get(&key) {
+			let translate = |fragments: &[Fragment]| -> Option<Vec<Fragment>> {
+				fragments.iter().map(|frag| {
+					let start = (frag.start + file_match.start_idx).checked_sub(*cached_start)?;
+					let end = (frag.end + file_match.start_idx).checked_sub(*cached_start)?;
+
+					if end <= file_data.len() { Some(start..end) } else { None }
+				}).collect()
+			};
+
+			let fragments = translate(&info.fragments);
+			let embedded: Option<Vec<EmbeddedFile>> = info.embedded.iter().map(|file| {
+				Some(EmbeddedFile {
+					fragments: translate(&file.fragments)?,
+					..file.clone()
+				})
+			}).collect();
+
+			// If the cached fragments can't be translated to lie within the data, then validate the candidate normally
+			if let (Some(fragments), Some(embedded)) = (fragments, embedded) {
+				self.hits += 1;
+
+				return FileValidationInfo {
+					validation_type: info.validation_type,
+					fragments,
+					rejection_reason: info.rejection_reason,
+					metadata: info.metadata.clone(),
+					embedded
+				};
+			}
+		}
+
+		let info = validate();
+
+		if self.capacity != 0 {
+			if self.entries.len() >= self.capacity {
+				if let Some(oldest) = self.insertion_order.pop_front() {
+					self.entries.remove(&oldest);
+				}
+			}
+
+			let cached_info = FileValidationInfo {
+				validation_type: info.validation_type,
+				fragments: info.fragments.clone(),
+				rejection_reason: info.rejection_reason,
+				metadata: info.metadata.clone(),
+				embedded: info.embedded.clone()
+			};
+
+			if self.entries.insert(key, (cached_info, file_match.start_idx)).is_none() {
+				self.insertion_order.push_back(key);
+			}
+		}
+
+		info
+	}
+
+	fn key(file_data: &[u8], file_match: &MatchPair, cluster_size: usize) -> CacheKey {
+		let start = file_match.start_idx.min(file_data.len());
+		let end = (file_match.end_idx + 1).min(file_data.len()).max(start);
+
+		let mut hasher = DefaultHasher::new();
+		file_data[start..end].hash(&mut hasher);
+
+		CacheKey {
+			type_id: file_match.file_type.type_id,
+			len: end - start,
+			cluster_size,
+			cluster_offset: start % cluster_size.max(1),
+			truncated: file_match.truncated,
+			content_hash: hasher.finish()
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::cell::Cell;
+
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId}, validation::{FileValidationInfo, FileValidationType}};
+
+	use super::ValidationCache;
+
+	#[test]
+	fn test_validation_cache() {
+		let file_type = FileType {
+			type_id: FileTypeId::Png,
+			..Default::default()
+		};
+
+		// Two identical candidates at 16 and 48, aligned the same way in 16-byte clusters, and a different one at 80
+		let mut data = vec![ 0u8; 112 ];
+		for start in [ 16, 48 ] {
+			data[start..(start + 16)].copy_from_slice(&[ 0x11; 16 ]);
+		}
+		data[80..96].copy_from_slice(&[ 0x22; 16 ]);
+
+		let candidate = |start: usize| MatchPair {
+			file_type: &file_type,
+			start_idx: start,
+			end_idx: start + 15,
+			truncated: false
+		};
+
+		let num_validations = Cell::new(0);
+		let validate = |start: usize| {
+			num_validations.set(num_validations.get() + 1);
+			FileValidationInfo {
+				validation_type: FileValidationType::Correct,
+				fragments: vec![ start..(start + 8), (start + 12)..(start + 16) ],
+				rejection_reason: None,
+				..Default::default()
+			}
+		};
+
+		let mut cache = ValidationCache::new(1);
+
+		let info = cache.get_or_validate(&data, &candidate(16), 16, || validate(16));
+		assert_eq!(info.fragments, vec![ 16..24, 28..32 ]);
+
+		// The identical candidate should use the cached result, with the fragments translated
+		let info = cache.get_or_validate(&data, &candidate(48), 16, || validate(48));
+		assert_eq!(info.fragments, vec![ 48..56, 60..64 ]);
+		assert_eq!(num_validations.get(), 1);
+		assert_eq!(cache.hits(), 1);
+
+		// A different cluster size should not use the cached result
+		cache.get_or_validate(&data, &candidate(48), 32, || validate(48));
+		assert_eq!(num_validations.get(), 2);
+
+		// A different candidate should not, and with a capacity of 1, should evict the first result
+		cache.get_or_validate(&data, &candidate(80), 16, || validate(80));
+		cache.get_or_validate(&data, &candidate(16), 16, || validate(16));
+		assert_eq!(num_validations.get(), 4);
+		assert_eq!(cache.hits(), 1);
+	}
+
+	#[test]
+	fn test_validation_cache_whole_candidate() {
+		let file_type = FileType {
+			type_id: FileTypeId::Png,
+			..Default::default()
+		};
+
+		// Two large candidates that only differ in their last byte
+		const LEN: usize = 1024 * 1024;
+		let mut data = vec![ 0x11u8; LEN * 2 ];
+		data[LEN * 2 - 1] = 0x22;
+
+		let candidate = |start: usize| MatchPair {
+			file_type: &file_type,
+			start_idx: start,
+			end_idx: start + LEN - 1,
+			truncated: false
+		};
+
+		let num_validations = Cell::new(0);
+		let validate = || {
+			num_validations.set(num_validations.get() + 1);
+			FileValidationInfo::default()
+		};
+
+		let mut cache = ValidationCache::new(4);
+
+		cache.get_or_validate(&data, &candidate(0), 1, validate);
+		cache.get_or_validate(&data, &candidate(LEN), 1, validate);
+		assert_eq!(num_validations.get(), 2);
+		assert_eq!(cache.hits(), 0);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,412 @@
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+const DICOM_PREAMBLE_LEN: usize = 128;
+const DICOM_MAGIC: &[u8; 4] = b"DICM";
+
+/// The tag of the file meta information group length element, (0002,0000), which must be the first element after the magic
+const DICOM_META_GROUP_LENGTH_TAG: u32 = 0x0002_0000;
+const DICOM_META_GROUP: u16 = 0x0002;
+/// The tag of the transfer syntax UID element, (0002,0010), which specifies the encoding of the data set following the file meta information
+const DICOM_TRANSFER_SYNTAX_TAG: u32 = 0x0002_0010;
+
+const DICOM_IMPLICIT_VR_LE: &[u8] = b"1.2.840.10008.1.2";
+const DICOM_EXPLICIT_VR_BE: &[u8] = b"1.2.840.10008.1.2.2";
+const DICOM_DEFLATED_EXPLICIT_VR_LE: &[u8] = b"1.2.840.10008.1.2.1.99";
+
+/// The group of the item, item delimitation and sequence delimitation tags, which have no VR in any transfer syntax
+const DICOM_DELIMITER_GROUP: u16 = 0xfffe;
+const DICOM_ITEM_TAG: u32 = 0xfffe_e000;
+const DICOM_ITEM_DELIMITATION_TAG: u32 = 0xfffe_e00d;
+const DICOM_SEQUENCE_DELIMITATION_TAG: u32 = 0xfffe_e0dd;
+
+const DICOM_UNDEFINED_LENGTH: u32 = 0xffff_ffff;
+
+/// All value representations defined by the standard (PS3.5 section 6.2)
+const DICOM_VRS: [&[u8; 2]; 34] = [
+	b"AE", b"AS", b"AT", b"CS", b"DA", b"DS", b"DT", b"FD", b"FL", b"IS", b"LO", b"LT", b"OB", b"OD", b"OF", b"OL", b"OV",
+	b"OW", b"PN", b"SH", b"SL", b"SQ", b"SS", b"ST", b"SV", b"TM", b"UC", b"UI", b"UL", b"UN", b"UR", b"US", b"UT", b"UV",
+];
+
+/// The value representations whose explicit VR encoding has 2 reserved bytes followed by a 4-byte length, rather than a 2-byte length
+const DICOM_LONG_LENGTH_VRS: [&[u8; 2]; 13] = [ b"OB", b"OD", b"OF", b"OL", b"OV", b"OW", b"SQ", b"UC", b"UN", b"UR", b"UT", b"SV", b"UV" ];
+
+pub struct DicomValidator;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum TransferSyntax {
+	ImplicitLittle,
+	ExplicitLittle,
+	ExplicitBig
+}
+
+/// The tag and length of a data element, along with the index of its value
+struct ElementHeader {
+	tag: u32,
+	len: u32,
+	value_idx: usize
+}
+
+enum ElementDecodeError {
+	/// The element header extends past the end of the available data
+	Truncated,
+	/// The element has an explicit VR that is not recognised
+	InvalidVr
+}
+
+impl TransferSyntax {
+	/// Returns the transfer syntax identified by `uid`, or None if it is not supported (i.e. deflated)
+	fn from_uid(uid: &[u8]) -> Option<Self> {
+		// UIDs are padded to an even length with a null byte
+		let uid = uid.strip_suffix(b"\0").unwrap_or(uid);
+
+		match uid {
+			DICOM_IMPLICIT_VR_LE => Some(TransferSyntax::ImplicitLittle),
+			DICOM_EXPLICIT_VR_BE => Some(TransferSyntax::ExplicitBig),
+			DICOM_DEFLATED_EXPLICIT_VR_LE => None,
+			// Every other transfer syntax (including those for encapsulated pixel data) uses explicit VR little endian
+			_ => Some(TransferSyntax::ExplicitLittle)
+		}
+	}
+
+	fn read_u16(self, data: &[u8]) -> u16 {
+		let bytes = data[..2].try_into().unwrap();
+		if self == TransferSyntax::ExplicitBig { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+	}
+
+	fn read_u32(self, data: &[u8]) -> u32 {
+		let bytes = data[..4].try_into().unwrap();
+		if self == TransferSyntax::ExplicitBig { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+	}
+}
+
+impl ElementHeader {
+	/// Decodes the header of the data element at `idx`, which is a tag, followed by a VR (in explicit VR transfer syntaxes) and a length. Items and
+	/// delimiters never have a VR
+	fn decode(data: &[u8], idx: usize, max_idx: usize, syntax: TransferSyntax) -> Result<Self, ElementDecodeError> {
+		if idx + 8 > max_idx {
+			return Err(ElementDecodeError::Truncated);
+		}
+
+		let group = syntax.read_u16(&data[idx..]);
+		let element = syntax.read_u16(&data[(idx + 2)..]);
+		let tag = ((group as u32) << 16) | element as u32;
+
+		if group == DICOM_DELIMITER_GROUP || syntax == TransferSyntax::ImplicitLittle {
+			return Ok(ElementHeader {
+				tag,
+				len: syntax.read_u32(&data[(idx + 4)..]),
+				value_idx: idx + 8
+			});
+		}
+
+		let vr: &[u8; 2] = data[(idx + 4)..(idx + 6)].try_into().unwrap();
+
+		if !DICOM_VRS.contains(&vr) {
+			return Err(ElementDecodeError::InvalidVr);
+		}
+
+		if DICOM_LONG_LENGTH_VRS.contains(&vr) {
+			if idx + 12 > max_idx {
+				return Err(ElementDecodeError::Truncated);
+			}
+
+			Ok(ElementHeader {
+				tag,
+				len: syntax.read_u32(&data[(idx + 8)..]),
+				value_idx: idx + 12
+			})
+		} else {
+			Ok(ElementHeader {
+				tag,
+				len: syntax.read_u16(&data[(idx + 6)..]) as u32,
+				value_idx: idx + 8
+			})
+		}
+	}
+
+	fn group(&self) -> u16 {
+		(self.tag >> 16) as u16
+	}
+}
+
+impl DicomValidator {
+	pub fn new() -> Self {
+		DicomValidator
+	}
+
+	/// Reads the file meta information group starting at `idx`, which is always encoded in explicit VR little endian and starts with its length,
+	/// returning the index just after the group and the transfer syntax of the data set
+	fn read_meta_group(data: &[u8], idx: usize, max_idx: usize) -> Result<(usize, Option<TransferSyntax>), (FileValidationType, RejectionReason)> {
+		let invalid = (FileValidationType::FormatError, RejectionReason::DicomInvalidMetaGroup);
+		let truncated = (FileValidationType::Partial, RejectionReason::Truncated);
+
+		let meta_end = match ElementHeader::decode(data, idx, max_idx, TransferSyntax::ExplicitLittle) {
+			Ok(header) if header.tag == DICOM_META_GROUP_LENGTH_TAG && header.len == 4 => {
+				if header.value_idx + 4 > max_idx {
+					return Err(truncated);
+				}
+				header.value_idx + 4 + TransferSyntax::ExplicitLittle.read_u32(&data[header.value_idx..]) as usize
+			}
+			Err(ElementDecodeError::Truncated) => return Err(truncated),
+			_ => return Err(invalid)
+		};
+
+		if meta_end > max_idx {
+			return Err(truncated);
+		}
+
+		let mut i = idx;
+		let mut transfer_syntax = None;
+
+		while i < meta_end {
+			let header = match ElementHeader::decode(data, i, meta_end, TransferSyntax::ExplicitLittle) {
+				Ok(header) if header.group() == DICOM_META_GROUP && header.len != DICOM_UNDEFINED_LENGTH => header,
+				Err(ElementDecodeError::InvalidVr) => return Err((FileValidationType::FormatError, RejectionReason::DicomInvalidVr)),
+				_ => return Err(invalid)
+			};
+
+			let value_end = header.value_idx + header.len as usize;
+			if value_end > meta_end {
+				return Err(invalid);
+			}
+
+			if header.tag == DICOM_TRANSFER_SYNTAX_TAG {
+				transfer_syntax = Some(TransferSyntax::from_uid(&data[header.value_idx..value_end]));
+			}
+
+			i = value_end;
+		}
+
+		match transfer_syntax {
+			Some(syntax) => Ok((meta_end, syntax)),
+			None => Err(invalid)
+		}
+	}
+}
+
+impl FileValidator for DicomValidator {
+	// Written using https://dicom.nema.org/medical/dicom/current/output/html/part05.html and
+	// https://dicom.nema.org/medical/dicom/current/output/html/part10.html
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+
+		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
+			(start + max_len as usize).min(file_data.len())
+		} else {
+			file_data.len()
+		};
+
+		// The header match is the magic that follows the preamble, so the file starts a preamble before it (see FileType::header_offset)
+		let magic_idx = start + DICOM_PREAMBLE_LEN;
+		if magic_idx + DICOM_MAGIC.len() > max_idx || &file_data[magic_idx..(magic_idx + DICOM_MAGIC.len())] != DICOM_MAGIC {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Unrecognised,
+				fragments: Vec::new(),
+				rejection_reason: Some(RejectionReason::DicomMissingMagic),
+				..Default::default()
+			};
+		}
+
+		let (data_set_idx, syntax) = match Self::read_meta_group(file_data, magic_idx + DICOM_MAGIC.len(), max_idx) {
+			Ok(meta) => meta,
+			Err((validation_type, rejection_reason)) => {
+				return FileValidationInfo {
+					validation_type,
+					fragments: vec![ (start..max_idx) ],
+					rejection_reason: Some(rejection_reason),
+					..Default::default()
+				};
+			}
+		};
+
+		let Some(syntax) = syntax else {
+			// The data set is deflated, so we can't walk its elements to find the end of the file, and all we can do is take everything up to max_len
+			return FileValidationInfo {
+				validation_type: FileValidationType::Partial,
+				fragments: vec![ (start..max_idx) ],
+				rejection_reason: Some(RejectionReason::DicomUnsupportedTransferSyntax),
+				..Default::default()
+			};
+		};
+
+		let mut i = data_set_idx;
+		// The number of undefined-length sequences and items that we are within
+		let mut depth = 0;
+		// Elements of the top-level data set are in ascending tag order, which is used to detect the end of the data set, as there is no
+		// marker of the end of the file
+		let mut prev_tag = 0;
+
+		let (end_idx, validation_type, rejection_reason) = loop {
+			if depth == 0 && i == max_idx {
+				break (i, FileValidationType::Correct, None);
+			}
+
+			let header = match ElementHeader::decode(file_data, i, max_idx, syntax) {
+				Ok(header) => header,
+				Err(_) if depth == 0 && i + 8 > max_idx => {
+					// Not enough data left for another element
+					break (i, FileValidationType::Correct, None);
+				}
+				Err(ElementDecodeError::Truncated) => break (max_idx, FileValidationType::Partial, Some(RejectionReason::Truncated)),
+				Err(ElementDecodeError::InvalidVr) => {
+					// An element that would be out of order is taken to be past the end of the file, even if it's garbage
+					let group = syntax.read_u16(&file_data[i..]) as u32;
+					let element = syntax.read_u16(&file_data[(i + 2)..]) as u32;
+
+					if depth == 0 && ((group << 16) | element) <= prev_tag {
+						break (i, FileValidationType::Correct, None);
+					}
+
+					break (i, FileValidationType::FormatError, Some(RejectionReason::DicomInvalidVr));
+				}
+			};
+
+			if depth == 0 {
+				if header.tag <= prev_tag || header.group() == DICOM_DELIMITER_GROUP {
+					break (i, FileValidationType::Correct, None);
+				}
+
+				prev_tag = header.tag;
+			}
+
+			match header.tag {
+				DICOM_SEQUENCE_DELIMITATION_TAG => {
+					depth -= 1;
+					i = header.value_idx;
+				}
+				DICOM_ITEM_DELIMITATION_TAG => {
+					i = header.value_idx;
+				}
+				_ if header.len == DICOM_UNDEFINED_LENGTH => {
+					// Undefined-length sequences contain items, which are ended by a sequence delimiter, and undefined-length items contain
+					// elements, which are ended by an item delimiter, so walk into them. Items that are fragments of encapsulated pixel data
+					// always have a defined length, and so are skipped over like any other element
+					if header.tag != DICOM_ITEM_TAG {
+						depth += 1;
+					}
+					i = header.value_idx;
+				}
+				_ => {
+					let value_end = header.value_idx + header.len as usize;
+
+					if value_end > max_idx {
+						break (max_idx, FileValidationType::Partial, Some(RejectionReason::Truncated));
+					}
+
+					i = value_end;
+				}
+			}
+		};
+
+		FileValidationInfo {
+			validation_type,
+			fragments: vec![ (start..end_idx) ],
+			rejection_reason,
+			..Default::default()
+		}
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the preamble, magic and file meta information elements, and walks the data elements of the data set"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason}};
+
+	use super::DicomValidator;
+
+	/// Encodes an explicit VR little endian data element
+	fn element(group: u16, element: u16, vr: &[u8; 2], value: &[u8]) -> Vec<u8> {
+		let mut data = Vec::new();
+		data.extend_from_slice(&group.to_le_bytes());
+		data.extend_from_slice(&element.to_le_bytes());
+		data.extend_from_slice(vr);
+
+		if [ b"OB", b"OW", b"SQ", b"UN", b"UT" ].contains(&vr) {
+			data.extend_from_slice(&[ 0, 0 ]);
+			data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+		} else {
+			data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+		}
+		data.extend_from_slice(value);
+
+		data
+	}
+
+	fn synthetic_dicom() -> Vec<u8> {
+		let mut meta = element(0x0002, 0x0001, b"OB", &[ 0x00, 0x01 ]);
+		meta.extend(element(0x0002, 0x0010, b"UI", b"1.2.840.10008.1.2.1\0"));
+
+		let mut dicom = vec![ 0; 128 ];
+		dicom.extend_from_slice(b"DICM");
+		dicom.extend(element(0x0002, 0x0000, b"UL", &(meta.len() as u32).to_le_bytes()));
+		dicom.extend(meta);
+
+		dicom.extend(element(0x0008, 0x0060, b"CS", b"OT"));
+
+		// An undefined-length sequence containing one undefined-length item
+		dicom.extend_from_slice(&[ 0x08, 0x00, 0x15, 0x11, b'S', b'Q', 0, 0, 0xff, 0xff, 0xff, 0xff ]);
+		dicom.extend_from_slice(&[ 0xfe, 0xff, 0x00, 0xe0, 0xff, 0xff, 0xff, 0xff ]);
+		dicom.extend(element(0x0008, 0x1150, b"UI", b"1.2.3\0"));
+		dicom.extend_from_slice(&[ 0xfe, 0xff, 0x0d, 0xe0, 0, 0, 0, 0 ]);
+		dicom.extend_from_slice(&[ 0xfe, 0xff, 0xdd, 0xe0, 0, 0, 0, 0 ]);
+
+		dicom.extend(element(0x0010, 0x0010, b"PN", b"Doe^Jane"));
+
+		dicom.extend(element(0x7fe0, 0x0010, b"OW", &[ 0x55; 32 ]));
+
+		dicom
+	}
+
+	#[test]
+	fn test_dicom_validator() {
+		let file_type = FileType {
+			type_id: FileTypeId::Dicom,
+			max_len: Some(1024 * 1024),
+			header_offset: 128,
+			..Default::default()
+		};
+
+		let dicom = synthetic_dicom();
+
+		let mut data = vec![ 0xaa; 16 ];
+		let start = data.len();
+		data.extend_from_slice(&dicom);
+		let end = data.len();
+		data.extend_from_slice(&[ 0x00; 64 ]);
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: start,
+			end_idx: start + 1024 * 1024,
+			truncated: false
+		};
+
+		let validator = DicomValidator::new();
+
+		let info = validator.validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ (start..end) ]);
+
+		// Truncated in the pixel data
+		let info = validator.validate(&data[..(end - 8)], &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::Truncated));
+
+		// Invalid VR within the sequence
+		let mut bad_data = data.clone();
+		let tag_idx = start + dicom.windows(4).position(|w| w == [ 0x08, 0x00, 0x50, 0x11 ]).unwrap();
+		bad_data[(tag_idx + 4)..(tag_idx + 6)].copy_from_slice(b"ZZ");
+
+		let info = validator.validate(&bad_data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::DicomInvalidVr));
+	}
+}
\ No newline at end of file
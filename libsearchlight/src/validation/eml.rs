@@ -0,0 +1,333 @@
+use std::collections::HashSet;
+
+use crate::{search::{match_id_hash_slice_u16, pairing::MatchPair, Match}, searchlight::config::SearchlightConfig};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+const EML_CONTENT_LENGTH_KEY: &str = "content-length";
+const EML_CONTENT_TYPE_KEY: &str = "content-type";
+const EML_BOUNDARY_PARAM: &str = "boundary=";
+
+pub struct EmlValidator;
+
+/// The outcome of parsing the header block of a message
+enum HeaderBlock {
+	/// The header block was terminated by a blank line. Contains the index of the start of the body and the headers that are of interest
+	Complete {
+		body_idx: usize,
+		content_length: Option<usize>,
+		boundary: Option<Vec<u8>>
+	},
+	/// The data ran out before the end of the header block was found
+	Truncated,
+	/// A line that is not a valid header (or a continuation of one) was found at the given index
+	Malformed(usize)
+}
+
+impl EmlValidator {
+	pub fn new() -> Self {
+		EmlValidator
+	}
+
+	/// Returns the index of the first byte of the line after the line starting at `idx`, and the contents of the line starting at
+	/// `idx` without the line terminator (either CRLF or LF). Returns None if there is no line terminator before `max_idx`
+	fn next_line(data: &[u8], idx: usize, max_idx: usize) -> Option<(usize, &[u8])> {
+		let lf_offset = data[idx..max_idx].iter().position(|&b| b == b'\n')?;
+		let line = &data[idx..(idx + lf_offset)];
+		let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+		Some((idx + lf_offset + 1, line))
+	}
+
+	/// Splits a `Key: value` header line into the key and value, if the key is made up only of printable, non-space, ASCII characters excluding
+	/// the colon (RFC 5322 section 2.2)
+	fn split_header_line(line: &[u8]) -> Option<(&[u8], &[u8])> {
+		let colon_idx = line.iter().position(|&b| b == b':')?;
+		let key = &line[..colon_idx];
+
+		if key.is_empty() || !key.iter().all(|&b| (33..=126).contains(&b)) {
+			return None;
+		}
+
+		Some((key, &line[(colon_idx + 1)..]))
+	}
+
+	/// Parses the header block starting at `idx`, which is a sequence of `Key: value` lines, where a value can be folded onto subsequent lines
+	/// beginning with whitespace, terminated by an empty line
+	fn parse_header_block(data: &[u8], idx: usize, max_idx: usize) -> HeaderBlock {
+		let mut i = idx;
+
+		let mut headers: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+
+		loop {
+			let Some((next_i, line)) = Self::next_line(data, i, max_idx) else {
+				return HeaderBlock::Truncated;
+			};
+
+			if line.is_empty() {
+				if headers.is_empty() {
+					return HeaderBlock::Malformed(i);
+				}
+
+				i = next_i;
+				break;
+			}
+
+			if line[0] == b' ' || line[0] == b'\t' {
+				// Folded continuation of the previous header's value
+				if let Some((_, value)) = headers.last_mut() {
+					value.extend_from_slice(line);
+				} else {
+					return HeaderBlock::Malformed(i);
+				}
+			} else if let Some((key, value)) = Self::split_header_line(line) {
+				headers.push((key.to_ascii_lowercase(), value.to_vec()));
+			} else {
+				return HeaderBlock::Malformed(i);
+			}
+
+			i = next_i;
+		}
+
+		let content_length = headers.iter()
+			.find(|(key, _)| key == EML_CONTENT_LENGTH_KEY.as_bytes())
+			.and_then(|(_, value)| std::str::from_utf8(value).ok()?.trim().parse::<usize>().ok());
+
+		let boundary = headers.iter()
+			.find(|(key, _)| key == EML_CONTENT_TYPE_KEY.as_bytes())
+			.and_then(|(_, value)| Self::extract_boundary(value));
+
+		HeaderBlock::Complete {
+			body_idx: i,
+			content_length,
+			boundary
+		}
+	}
+
+	/// Extracts the value of the `boundary` parameter from a Content-Type header value, removing quotes if present
+	fn extract_boundary(content_type: &[u8]) -> Option<Vec<u8>> {
+		let lowercase = content_type.to_ascii_lowercase();
+		let param_idx = lowercase.windows(EML_BOUNDARY_PARAM.len()).position(|w| w == EML_BOUNDARY_PARAM.as_bytes())? + EML_BOUNDARY_PARAM.len();
+
+		let value = &content_type[param_idx..];
+		let boundary = if let Some(quoted) = value.strip_prefix(b"\"") {
+			&quoted[..quoted.iter().position(|&b| b == b'"')?]
+		} else {
+			let end = value.iter().position(|&b| b == b';' || b.is_ascii_whitespace()).unwrap_or(value.len());
+			&value[..end]
+		};
+
+		if boundary.is_empty() {
+			None
+		} else {
+			Some(boundary.to_vec())
+		}
+	}
+
+	/// Returns the index just after the closing delimiter (`--boundary--`) line of a multipart body that starts at `body_idx`, or None if it is not
+	/// found before `max_idx`
+	fn find_closing_delimiter(data: &[u8], body_idx: usize, max_idx: usize, boundary: &[u8]) -> Option<usize> {
+		let mut i = body_idx;
+
+		while i < max_idx {
+			let (next_i, line) = match Self::next_line(data, i, max_idx) {
+				Some(next) => next,
+				None => (max_idx, &data[i..max_idx])
+			};
+
+			if let Some(rest) = line.strip_prefix(b"--").and_then(|rest| rest.strip_prefix(boundary)) {
+				if rest.starts_with(b"--") {
+					return Some(next_i);
+				}
+			}
+
+			i = next_i;
+		}
+
+		None
+	}
+
+	/// Whether the match at `start` is in the middle of a header block, i.e. the line before it is a header line. This is the case for headers that
+	/// may occur multiple times in a message, such as `Received:`, or when multiple types of header are configured
+	fn is_mid_header_block(data: &[u8], start: usize) -> bool {
+		if start == 0 || data[start - 1] != b'\n' {
+			return false;
+		}
+
+		let prev_line_end = start - 1;
+		let prev_line_start = data[..prev_line_end].iter().rposition(|&b| b == b'\n').map(|i| i + 1).unwrap_or(0);
+		let prev_line = &data[prev_line_start..prev_line_end];
+		let prev_line = prev_line.strip_suffix(b"\r").unwrap_or(prev_line);
+
+		!prev_line.is_empty() && (prev_line[0] == b' ' || prev_line[0] == b'\t' || Self::split_header_line(prev_line).is_some())
+	}
+}
+
+impl FileValidator for EmlValidator {
+	// Written using https://www.rfc-editor.org/rfc/rfc5322 and https://www.rfc-editor.org/rfc/rfc2046#section-5.1
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		// Email has no reliable footer, so the message is bounded by the start of the next message (the next header of this type that
+		// begins a header block), the max_len of the file type, or the end of the data, whichever is first
+		let start = file_match.start_idx;
+
+		if Self::is_mid_header_block(file_data, start) {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Unrecognised,
+				fragments: Vec::new(),
+				rejection_reason: Some(RejectionReason::EmlMidHeaderBlock),
+				..Default::default()
+			};
+		}
+
+		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
+			(start + max_len as usize).min(file_data.len())
+		} else {
+			file_data.len()
+		};
+
+		let (body_idx, content_length, boundary) = match Self::parse_header_block(file_data, start, max_idx) {
+			HeaderBlock::Complete { body_idx, content_length, boundary } => (body_idx, content_length, boundary),
+			HeaderBlock::Truncated => {
+				return FileValidationInfo {
+					validation_type: FileValidationType::Partial,
+					fragments: vec![ (start..max_idx) ],
+					rejection_reason: Some(RejectionReason::Truncated),
+					..Default::default()
+				};
+			}
+			HeaderBlock::Malformed(idx) => {
+				return FileValidationInfo {
+					validation_type: FileValidationType::FormatError,
+					fragments: if idx > start { vec![ (start..idx) ] } else { Vec::new() },
+					rejection_reason: Some(RejectionReason::EmlMalformedHeader),
+					..Default::default()
+				};
+			}
+		};
+
+		let header_ids: HashSet<u64> = file_match.file_type.headers.iter().map(|h| match_id_hash_slice_u16(h)).collect();
+
+		let next_message_idx = all_matches.iter()
+			.filter(|m| header_ids.contains(&m.id) && (m.start_idx as usize) >= body_idx && (m.start_idx as usize) < max_idx)
+			.map(|m| m.start_idx as usize)
+			.find(|&idx| !Self::is_mid_header_block(file_data, idx));
+
+		let end_idx = next_message_idx.unwrap_or(max_idx);
+
+		let (validation_type, end, rejection_reason) = if let Some(content_length) = content_length {
+			if body_idx + content_length <= end_idx {
+				(FileValidationType::Correct, body_idx + content_length, None)
+			} else {
+				(FileValidationType::Partial, end_idx, Some(RejectionReason::Truncated))
+			}
+		} else if let Some(boundary) = boundary {
+			if let Some(end) = Self::find_closing_delimiter(file_data, body_idx, end_idx, &boundary) {
+				(FileValidationType::Correct, end, None)
+			} else {
+				(FileValidationType::Partial, end_idx, Some(RejectionReason::EmlMissingClosingBoundary))
+			}
+		} else if let Some(nul_offset) = file_data[body_idx..end_idx].iter().position(|&b| b == 0) {
+			// Messages are text, so a NUL byte is a plausible end of the message
+			(FileValidationType::Correct, body_idx + nul_offset, None)
+		} else if next_message_idx.is_some() {
+			(FileValidationType::Correct, end_idx, None)
+		} else {
+			(FileValidationType::Partial, end_idx, Some(RejectionReason::EmlNoPlausibleEnd))
+		};
+
+		FileValidationInfo {
+			validation_type,
+			fragments: vec![ (start..end) ],
+			rejection_reason,
+			..Default::default()
+		}
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the RFC 5322 header block of the message, and the boundaries of any MIME multipart body"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::{match_id_hash_slice_u16, pairing::MatchPair, Match}, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator}};
+
+	use super::EmlValidator;
+
+	#[test]
+	fn test_eml_validator() {
+		let file_type = FileType {
+			headers: vec![ "Return-Path: ".into() ],
+			type_id: FileTypeId::Eml,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+		let header_id = match_id_hash_slice_u16(&file_type.headers[0]);
+
+		let multipart = concat!(
+			"Return-Path: <alice@example.com>\r\n",
+			"From: Alice <alice@example.com>\r\n",
+			"Subject: A folded\r\n",
+			"  subject\r\n",
+			"Content-Type: multipart/mixed;\r\n",
+			"\tboundary=\"XYZ\"\r\n",
+			"\r\n",
+			"--XYZ\r\n",
+			"Content-Type: text/plain\r\n",
+			"\r\n",
+			"Hello\r\n",
+			"--XYZ--\r\n"
+		).as_bytes();
+
+		let plain = concat!(
+			"Return-Path: <bob@example.com>\n",
+			"From: Bob <bob@example.com>\n",
+			"\n",
+			"Hi\n"
+		).as_bytes();
+
+		let mut data = vec![ 0xaa; 8 ];
+		let multipart_start = data.len();
+		data.extend_from_slice(multipart);
+		let multipart_end = data.len();
+		data.extend_from_slice(b"trailing garbage\n");
+		let plain_start = data.len();
+		data.extend_from_slice(plain);
+		let plain_end = data.len();
+		data.extend_from_slice(&[ 0; 32 ]);
+
+		let all_matches = [
+			Match::new(header_id, multipart_start as u64, (multipart_start + 12) as u64),
+			Match::new(header_id, plain_start as u64, (plain_start + 12) as u64)
+		];
+
+		let validator = EmlValidator::new();
+
+		let file_match = MatchPair::new_sized(&file_type, &all_matches[0], 1024 * 1024);
+		let info = validator.validate(&data, &file_match, &all_matches, 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ (multipart_start..multipart_end) ]);
+
+		let file_match = MatchPair::new_sized(&file_type, &all_matches[1], 1024 * 1024);
+		let info = validator.validate(&data, &file_match, &all_matches, 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ (plain_start..plain_end) ]);
+
+		// Truncated before the closing delimiter
+		let file_match = MatchPair::new_sized(&file_type, &all_matches[0], 1024 * 1024);
+		let info = validator.validate(&data[..(multipart_end - 4)], &file_match, &all_matches[..1], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+
+		// Malformed header line
+		let malformed = b"Return-Path: <alice@example.com>\r\nNot a header\r\n\r\nBody\r\n";
+		let file_match = MatchPair::new_sized(&file_type, &all_matches[0], 1024 * 1024);
+		let mut malformed_data = data[..multipart_start].to_vec();
+		malformed_data.extend_from_slice(malformed);
+		let info = validator.validate(&malformed_data, &file_match, &all_matches[..1], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+	}
+}
\ No newline at end of file
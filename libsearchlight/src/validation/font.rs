@@ -0,0 +1,512 @@
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+const SFNT_HEADER_SIZE: usize = 12;
+const SFNT_TABLE_RECORD_SIZE: usize = 16;
+/// Fonts rarely have more than a few dozen tables, so a larger count is more likely to be a false positive than a real font. Searchlight.toml
+/// relies on the high byte of the count being 0
+const SFNT_MAX_TABLES: u16 = 255;
+
+/// The sfnt versions of TrueType (`\x00\x01\x00\x00` or `true`) and CFF-based OpenType (`OTTO`) fonts
+const SFNT_VERSIONS: [&[u8; 4]; 3] = [ b"\x00\x01\x00\x00", b"true", b"OTTO" ];
+
+const TTC_SIGNATURE: &[u8; 4] = b"ttcf";
+const TTC_HEADER_SIZE: usize = 12;
+const TTC_DSIG_FIELDS_SIZE: usize = 12;
+const TTC_MAX_FONTS: u32 = 1024;
+
+const WOFF_SIGNATURE: &[u8; 4] = b"wOFF";
+const WOFF_HEADER_SIZE: usize = 44;
+const WOFF_TABLE_ENTRY_SIZE: usize = 20;
+
+const WOFF2_SIGNATURE: &[u8; 4] = b"wOF2";
+const WOFF2_HEADER_SIZE: usize = 48;
+
+/// The offset of the checkSumAdjustment field in the head table, which is excluded from the checksum of the table
+const HEAD_CHECKSUM_ADJUSTMENT_OFFSET: usize = 8;
+
+pub struct FontValidator;
+
+struct TableRecord {
+	tag: [u8; 4],
+	checksum: u32,
+	offset: u32,
+	length: u32
+}
+
+enum DirectoryError {
+	/// The table directory doesn't look like one at all, so the match is probably a false positive
+	Implausible,
+	/// The table directory extends past the end of the available data
+	Truncated
+}
+
+struct WoffHeader {
+	length: u32,
+	num_tables: u16,
+	reserved: u16,
+	total_sfnt_size: u32,
+	/// Only present in WOFF2, 0 for WOFF
+	total_compressed_size: u32
+}
+
+impl TableRecord {
+	fn decode(data: &[u8]) -> Self {
+		TableRecord {
+			tag: data[0..4].try_into().unwrap(),
+			checksum: u32::from_be_bytes(data[4..8].try_into().unwrap()),
+			offset: u32::from_be_bytes(data[8..12].try_into().unwrap()),
+			length: u32::from_be_bytes(data[12..16].try_into().unwrap())
+		}
+	}
+}
+
+impl WoffHeader {
+	fn decode(data: &[u8], woff2: bool) -> Self {
+		WoffHeader {
+			length: u32::from_be_bytes(data[8..12].try_into().unwrap()),
+			num_tables: u16::from_be_bytes(data[12..14].try_into().unwrap()),
+			reserved: u16::from_be_bytes(data[14..16].try_into().unwrap()),
+			total_sfnt_size: u32::from_be_bytes(data[16..20].try_into().unwrap()),
+			total_compressed_size: if woff2 { u32::from_be_bytes(data[20..24].try_into().unwrap()) } else { 0 }
+		}
+	}
+
+	/// Whether the header fields hold values that are allowed by the spec, and the declared length has room for everything that the
+	/// header says follows it
+	fn fields_valid(&self, woff2: bool) -> bool {
+		let min_len = if woff2 {
+			WOFF2_HEADER_SIZE as u64 + self.total_compressed_size as u64
+		} else {
+			(WOFF_HEADER_SIZE + WOFF_TABLE_ENTRY_SIZE * self.num_tables as usize) as u64
+		};
+
+		self.reserved == 0
+			&& self.num_tables > 0
+			&& self.total_sfnt_size as usize >= SFNT_HEADER_SIZE + SFNT_TABLE_RECORD_SIZE * self.num_tables as usize
+			&& self.length as u64 >= min_len
+	}
+}
+
+impl FontValidator {
+	pub fn new() -> Self {
+		FontValidator
+	}
+
+	fn truncated(start: usize, max_idx: usize) -> FileValidationInfo {
+		FileValidationInfo {
+			validation_type: FileValidationType::Partial,
+			fragments: vec![ (start..max_idx) ],
+			rejection_reason: Some(RejectionReason::Truncated),
+			..Default::default()
+		}
+	}
+
+	/// Whether `tag` is a plausible table tag, which are made up of printable ASCII characters
+	fn tag_valid(tag: &[u8]) -> bool {
+		tag.iter().all(|&c| (0x20..=0x7e).contains(&c))
+	}
+
+	/// Reads the sfnt table directory at `idx`, checking that the binary search fields agree with the number of tables, returning the table
+	/// records and the index just after the directory
+	fn read_table_directory(data: &[u8], idx: usize, max_idx: usize) -> Result<(Vec<TableRecord>, usize), DirectoryError> {
+		if idx + SFNT_HEADER_SIZE > max_idx {
+			return Err(DirectoryError::Truncated);
+		}
+
+		let num_tables = u16::from_be_bytes(data[(idx + 4)..(idx + 6)].try_into().unwrap());
+		let search_range = u16::from_be_bytes(data[(idx + 6)..(idx + 8)].try_into().unwrap());
+		let entry_selector = u16::from_be_bytes(data[(idx + 8)..(idx + 10)].try_into().unwrap());
+		let range_shift = u16::from_be_bytes(data[(idx + 10)..(idx + 12)].try_into().unwrap());
+
+		if num_tables == 0 || num_tables > SFNT_MAX_TABLES {
+			return Err(DirectoryError::Implausible);
+		}
+
+		// searchRange is 16 times the largest power of two not greater than numTables, entrySelector is the log2 of that power of two, and
+		// rangeShift is the remainder of numTables * 16
+		let max_pow2 = 1u16 << num_tables.ilog2();
+		if search_range as u32 != max_pow2 as u32 * 16 || entry_selector as u32 != num_tables.ilog2() || range_shift as u32 != num_tables as u32 * 16 - search_range as u32 {
+			return Err(DirectoryError::Implausible);
+		}
+
+		let dir_end = idx + SFNT_HEADER_SIZE + SFNT_TABLE_RECORD_SIZE * num_tables as usize;
+		if dir_end > max_idx {
+			return Err(DirectoryError::Truncated);
+		}
+
+		let tables: Vec<TableRecord> = data[(idx + SFNT_HEADER_SIZE)..dir_end].chunks_exact(SFNT_TABLE_RECORD_SIZE).map(TableRecord::decode).collect();
+
+		if !tables.iter().all(|table| Self::tag_valid(&table.tag)) {
+			return Err(DirectoryError::Implausible);
+		}
+
+		Ok((tables, dir_end))
+	}
+
+	/// Calculates the checksum of a table, which is the sum of its contents as big-endian u32s, with the final partial u32 (if any) padded
+	/// with zeroes. The checkSumAdjustment field of the head table is excluded, as it is calculated after the checksum of the table
+	fn table_checksum(table: &[u8], tag: &[u8; 4]) -> u32 {
+		let sum = table.chunks(4).fold(0u32, |acc, chunk| {
+			let mut word = [ 0; 4 ];
+			word[..chunk.len()].copy_from_slice(chunk);
+			acc.wrapping_add(u32::from_be_bytes(word))
+		});
+
+		if tag == b"head" && table.len() >= HEAD_CHECKSUM_ADJUSTMENT_OFFSET + 4 {
+			sum.wrapping_sub(u32::from_be_bytes(table[HEAD_CHECKSUM_ADJUSTMENT_OFFSET..(HEAD_CHECKSUM_ADJUSTMENT_OFFSET + 4)].try_into().unwrap()))
+		} else {
+			sum
+		}
+	}
+
+	/// Validates a TrueType/OpenType font, or a collection of them, which end at the end of the last of their tables. Table offsets are
+	/// relative to the start of the file, even in collections, where the fonts may share tables
+	fn validate_sfnt(file_data: &[u8], start: usize, max_idx: usize, verify_checksums: bool) -> FileValidationInfo {
+		let mut end = start;
+
+		// A collection has a header listing the offsets of the table directory of each font, and a single font has one directory at the start
+		let dir_offsets = if &file_data[start..(start + 4)] == TTC_SIGNATURE {
+			if start + TTC_HEADER_SIZE > max_idx {
+				return Self::truncated(start, max_idx);
+			}
+
+			let major_version = u16::from_be_bytes(file_data[(start + 4)..(start + 6)].try_into().unwrap());
+			let num_fonts = u32::from_be_bytes(file_data[(start + 8)..(start + 12)].try_into().unwrap());
+
+			if !(major_version == 1 || major_version == 2) || num_fonts == 0 || num_fonts > TTC_MAX_FONTS {
+				return FileValidationInfo {
+					validation_type: FileValidationType::Unrecognised,
+					rejection_reason: Some(RejectionReason::FontInvalidHeader),
+					..Default::default()
+				};
+			}
+
+			let offsets_end = start + TTC_HEADER_SIZE + 4 * num_fonts as usize;
+			let header_end = if major_version == 2 { offsets_end + TTC_DSIG_FIELDS_SIZE } else { offsets_end };
+
+			if header_end > max_idx {
+				return Self::truncated(start, max_idx);
+			}
+
+			// Version 2 collections can have a digital signature table for the whole collection, which is outside any font
+			if major_version == 2 && &file_data[offsets_end..(offsets_end + 4)] == b"DSIG" {
+				let dsig_len = u32::from_be_bytes(file_data[(offsets_end + 4)..(offsets_end + 8)].try_into().unwrap()) as usize;
+				let dsig_offset = u32::from_be_bytes(file_data[(offsets_end + 8)..(offsets_end + 12)].try_into().unwrap()) as usize;
+
+				end = start + dsig_offset + dsig_len;
+			}
+
+			end = end.max(header_end);
+
+			file_data[(start + TTC_HEADER_SIZE)..offsets_end].chunks_exact(4).map(|offset| u32::from_be_bytes(offset.try_into().unwrap()) as usize).collect()
+		} else {
+			vec![ 0 ]
+		};
+
+		let mut checksums_match = true;
+
+		for dir_offset in dir_offsets {
+			let (tables, dir_end) = match Self::read_table_directory(file_data, start + dir_offset, max_idx) {
+				Ok(directory) => directory,
+				Err(DirectoryError::Truncated) => return Self::truncated(start, max_idx),
+				Err(DirectoryError::Implausible) => {
+					return FileValidationInfo {
+						validation_type: FileValidationType::Unrecognised,
+						rejection_reason: Some(RejectionReason::FontInvalidTableDirectory),
+						..Default::default()
+					};
+				}
+			};
+
+			end = end.max(dir_end);
+
+			for table in tables {
+				let table_start = start + table.offset as usize;
+				let table_end = table_start + table.length as usize;
+
+				// Tables are aligned to 4 bytes, and can't overlap the header of the file
+				if table.offset % 4 != 0 || (table.offset as usize) < SFNT_HEADER_SIZE {
+					return FileValidationInfo {
+						validation_type: FileValidationType::FormatError,
+						fragments: vec![ (start..dir_end) ],
+						rejection_reason: Some(RejectionReason::FontInvalidTableRecord),
+						..Default::default()
+					};
+				}
+
+				if table_end > max_idx {
+					return Self::truncated(start, max_idx);
+				}
+
+				end = end.max(table_end);
+
+				if verify_checksums && Self::table_checksum(&file_data[table_start..table_end], &table.tag) != table.checksum {
+					checksums_match = false;
+				}
+			}
+		}
+
+		if end > max_idx {
+			return Self::truncated(start, max_idx);
+		}
+
+		// A table whose checksum doesn't match has probably been overwritten or is fragmented, but the extent of the file is still known
+		let (validation_type, rejection_reason) = if checksums_match {
+			(FileValidationType::Correct, None)
+		} else {
+			(FileValidationType::Partial, Some(RejectionReason::FontChecksumMismatch))
+		};
+
+		FileValidationInfo {
+			validation_type,
+			fragments: vec![ (start..end) ],
+			rejection_reason,
+			..Default::default()
+		}
+	}
+
+	/// Validates a WOFF or WOFF2 web font, the length of which is given in the header. The table directory of WOFF2 is compressed along with
+	/// the tables, so is only checked for WOFF
+	fn validate_woff(file_data: &[u8], start: usize, max_idx: usize, woff2: bool) -> FileValidationInfo {
+		let header_size = if woff2 { WOFF2_HEADER_SIZE } else { WOFF_HEADER_SIZE };
+
+		if start + header_size > max_idx {
+			return Self::truncated(start, max_idx);
+		}
+
+		let header = WoffHeader::decode(&file_data[start..], woff2);
+
+		if !header.fields_valid(woff2) {
+			return FileValidationInfo {
+				validation_type: FileValidationType::FormatError,
+				fragments: vec![ (start..(start + header_size)) ],
+				rejection_reason: Some(RejectionReason::FontInvalidHeader),
+				..Default::default()
+			};
+		}
+
+		let end = start + header.length as usize;
+
+		if end > max_idx {
+			return Self::truncated(start, max_idx);
+		}
+
+		// Each entry gives the offset and compressed length of a table, which must lie within the file, and the compressed length can't be
+		// more than the original length (tables that don't compress are stored uncompressed)
+		let directory_valid = woff2 || file_data[(start + WOFF_HEADER_SIZE)..(start + WOFF_HEADER_SIZE + WOFF_TABLE_ENTRY_SIZE * header.num_tables as usize)].chunks_exact(WOFF_TABLE_ENTRY_SIZE).all(|entry| {
+			let offset = u32::from_be_bytes(entry[4..8].try_into().unwrap()) as u64;
+			let comp_length = u32::from_be_bytes(entry[8..12].try_into().unwrap()) as u64;
+			let orig_length = u32::from_be_bytes(entry[12..16].try_into().unwrap()) as u64;
+
+			Self::tag_valid(&entry[0..4]) && offset >= WOFF_HEADER_SIZE as u64 && offset + comp_length <= header.length as u64 && comp_length <= orig_length
+		});
+
+		let (validation_type, rejection_reason) = if directory_valid {
+			(FileValidationType::Correct, None)
+		} else {
+			(FileValidationType::FormatError, Some(RejectionReason::FontInvalidTableDirectory))
+		};
+
+		FileValidationInfo {
+			validation_type,
+			fragments: vec![ (start..end) ],
+			rejection_reason,
+			..Default::default()
+		}
+	}
+}
+
+impl FileValidator for FontValidator {
+	// Written using https://learn.microsoft.com/en-us/typography/opentype/spec/otff, https://www.w3.org/TR/WOFF/ and https://www.w3.org/TR/WOFF2/
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+
+		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
+			(start + max_len as usize).min(file_data.len())
+		} else {
+			file_data.len()
+		};
+
+		if start + 4 > max_idx {
+			return Self::truncated(start, max_idx);
+		}
+
+		let signature: &[u8; 4] = file_data[start..(start + 4)].try_into().unwrap();
+
+		if signature == WOFF_SIGNATURE || signature == WOFF2_SIGNATURE {
+			Self::validate_woff(file_data, start, max_idx, signature == WOFF2_SIGNATURE)
+		} else if signature == TTC_SIGNATURE || SFNT_VERSIONS.contains(&signature) {
+			Self::validate_sfnt(file_data, start, max_idx, file_match.file_type.should_verify_crc())
+		} else {
+			FileValidationInfo {
+				validation_type: FileValidationType::Unrecognised,
+				rejection_reason: Some(RejectionReason::FontInvalidHeader),
+				..Default::default()
+			}
+		}
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the header, table directory and table checksums of TrueType, OpenType and WOFF fonts"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason}};
+
+	use super::FontValidator;
+
+	/// Builds a font with the given tables, in order after the table directory, with correct checksums
+	fn synthetic_sfnt(tables: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+		let num_tables = tables.len() as u16;
+		let search_range = (1u16 << num_tables.ilog2()) * 16;
+
+		let mut font = b"\x00\x01\x00\x00".to_vec();
+		font.extend_from_slice(&num_tables.to_be_bytes());
+		font.extend_from_slice(&search_range.to_be_bytes());
+		font.extend_from_slice(&(num_tables.ilog2() as u16).to_be_bytes());
+		font.extend_from_slice(&(num_tables * 16 - search_range).to_be_bytes());
+
+		let dir_len = 12 + 16 * tables.len();
+		let mut table_data = Vec::new();
+
+		for (i, (tag, data)) in tables.iter().enumerate() {
+			font.extend_from_slice(*tag);
+			font.extend_from_slice(&FontValidator::table_checksum(data, tag).to_be_bytes());
+			font.extend_from_slice(&((dir_len + table_data.len()) as u32).to_be_bytes());
+			font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+			// Each table is padded to 4 bytes, apart from the last
+			table_data.extend_from_slice(data);
+			if i + 1 < tables.len() {
+				table_data.resize(table_data.len().next_multiple_of(4), 0);
+			}
+		}
+
+		font.extend_from_slice(&table_data);
+		font
+	}
+
+	fn validate(data: &[u8], start: usize) -> (FileValidationType, Vec<std::ops::Range<usize>>, Option<RejectionReason>) {
+		let file_type = FileType {
+			type_id: FileTypeId::Font,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: start,
+			end_idx: start + 1024 * 1024,
+			truncated: false
+		};
+
+		let info = FontValidator::new().validate(data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		(info.validation_type, info.fragments, info.rejection_reason)
+	}
+
+	#[test]
+	fn test_sfnt_validator() {
+		let mut head = vec![ 0x11; 54 ];
+		head[8..12].copy_from_slice(&[ 0xde, 0xad, 0xbe, 0xef ]);
+
+		let font = synthetic_sfnt(&[ (b"cmap", &[ 0x22; 10 ]), (b"head", &head), (b"name", &[ 0x33; 7 ]) ]);
+
+		let mut data = vec![ 0xaa; 16 ];
+		let start = data.len();
+		data.extend_from_slice(&font);
+		let end = data.len();
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		assert_eq!(validate(&data, start), (FileValidationType::Correct, vec![ (start..end) ], None));
+
+		// Truncated in the last table
+		assert_eq!(validate(&data[..(end - 2)], start), (FileValidationType::Partial, vec![ (start..(end - 2)) ], Some(RejectionReason::Truncated)));
+
+		// A corrupted table doesn't match its checksum, but the checkSumAdjustment of the head table isn't included in its checksum
+		let mut bad_data = data.clone();
+		bad_data[end - 3] ^= 0xff;
+		assert_eq!(validate(&bad_data, start), (FileValidationType::Partial, vec![ (start..end) ], Some(RejectionReason::FontChecksumMismatch)));
+
+		let mut adjusted_data = data.clone();
+		let head_offset = start + u32::from_be_bytes(data[(start + 12 + 16 + 8)..(start + 12 + 16 + 12)].try_into().unwrap()) as usize;
+		adjusted_data[head_offset + 8] ^= 0xff;
+		assert_eq!(validate(&adjusted_data, start).0, FileValidationType::Correct);
+
+		// A number of tables that doesn't agree with the binary search fields is likely a false positive
+		let mut bad_data = data.clone();
+		bad_data[start + 5] = 4;
+		assert_eq!(validate(&bad_data, start).0, FileValidationType::Unrecognised);
+	}
+
+	#[test]
+	fn test_ttc_validator() {
+		let font = synthetic_sfnt(&[ (b"glyf", &[ 0x44; 20 ]) ]);
+
+		// A collection of two fonts that share a table, with the directories following the collection header
+		let mut ttc = b"ttcf".to_vec();
+		ttc.extend_from_slice(&1u16.to_be_bytes());
+		ttc.extend_from_slice(&0u16.to_be_bytes());
+		ttc.extend_from_slice(&2u32.to_be_bytes());
+		ttc.extend_from_slice(&20u32.to_be_bytes());
+		ttc.extend_from_slice(&48u32.to_be_bytes());
+
+		for _ in 0..2 {
+			let mut directory = font[..28].to_vec();
+			directory[20..24].copy_from_slice(&76u32.to_be_bytes());
+			ttc.extend_from_slice(&directory);
+		}
+		ttc.extend_from_slice(&font[28..]);
+
+		let mut data = vec![ 0xaa; 16 ];
+		let start = data.len();
+		data.extend_from_slice(&ttc);
+		let end = data.len();
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		assert_eq!(validate(&data, start), (FileValidationType::Correct, vec![ (start..end) ], None));
+	}
+
+	#[test]
+	fn test_woff_validator() {
+		let mut woff = b"wOFF".to_vec();
+		woff.extend_from_slice(b"\x00\x01\x00\x00");
+		woff.extend_from_slice(&84u32.to_be_bytes()); // length
+		woff.extend_from_slice(&1u16.to_be_bytes()); // numTables
+		woff.extend_from_slice(&0u16.to_be_bytes());
+		woff.extend_from_slice(&48u32.to_be_bytes()); // totalSfntSize
+		woff.extend_from_slice(&[ 0; 24 ]);
+
+		// A table stored uncompressed
+		woff.extend_from_slice(b"glyf");
+		woff.extend_from_slice(&64u32.to_be_bytes());
+		woff.extend_from_slice(&20u32.to_be_bytes());
+		woff.extend_from_slice(&20u32.to_be_bytes());
+		woff.extend_from_slice(&0u32.to_be_bytes());
+		woff.extend_from_slice(&[ 0x55; 20 ]);
+
+		let mut data = vec![ 0xaa; 16 ];
+		let start = data.len();
+		data.extend_from_slice(&woff);
+		let end = data.len();
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		assert_eq!(validate(&data, start), (FileValidationType::Correct, vec![ (start..end) ], None));
+
+		assert_eq!(validate(&data[..(end - 1)], start), (FileValidationType::Partial, vec![ (start..(end - 1)) ], Some(RejectionReason::Truncated)));
+
+		// The table lies outside of the file
+		let mut bad_data = data.clone();
+		bad_data[start + 44 + 7] = 65;
+		assert_eq!(validate(&bad_data, start), (FileValidationType::FormatError, vec![ (start..end) ], Some(RejectionReason::FontInvalidTableDirectory)));
+
+		// Reserved field set
+		let mut bad_data = data.clone();
+		bad_data[start + 15] = 1;
+		assert_eq!(validate(&bad_data, start), (FileValidationType::FormatError, vec![ (start..(start + 44)) ], Some(RejectionReason::FontInvalidHeader)));
+	}
+}
\ No newline at end of file
@@ -0,0 +1,398 @@
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::{FileTypeId, SearchlightConfig}};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason, PARAM_DECODE_IMAGE_DATA};
+
+const GIF_SIGNATURES: [&[u8; 6]; 2] = [ b"GIF87a", b"GIF89a" ];
+
+/// The size of the header (signature and version) and logical screen descriptor
+const GIF_HEADER_SIZE: usize = 13;
+/// The size of an image descriptor, including the image separator
+const GIF_IMAGE_DESCRIPTOR_SIZE: usize = 10;
+
+const GIF_EXTENSION_INTRODUCER: u8 = 0x21;
+const GIF_IMAGE_SEPARATOR: u8 = 0x2c;
+const GIF_TRAILER: u8 = 0x3b;
+
+/// The flag in the packed fields of the logical screen descriptor and image descriptors for whether a colour table follows
+const GIF_COLOUR_TABLE_FLAG: u8 = 0x80;
+
+/// The maximum width of an LZW code, which is also what limits the size of the code table to 4096 entries
+const LZW_MAX_CODE_SIZE: u32 = 12;
+
+/// Understands the validator parameter `PARAM_DECODE_IMAGE_DATA`
+pub struct GifValidator;
+
+impl GifValidator {
+	pub fn new() -> Self {
+		GifValidator
+	}
+
+	/// The size of the colour table that follows a descriptor with the packed fields `packed`, which is 0 if there is none
+	fn colour_table_len(packed: u8) -> usize {
+		if packed & GIF_COLOUR_TABLE_FLAG != 0 {
+			3 << ((packed & 0x07) + 1)
+		} else {
+			0
+		}
+	}
+
+	/// Skips the data sub-blocks starting at `idx`, which are each prefixed by their length and terminated by a 0-length block, returning the
+	/// index just after the terminator, or None if the sub-blocks run past `max_idx`
+	fn skip_sub_blocks(data: &[u8], mut idx: usize, max_idx: usize) -> Option<usize> {
+		loop {
+			if idx >= max_idx {
+				return None;
+			}
+
+			let len = data[idx] as usize;
+			idx += 1;
+
+			if len == 0 {
+				return Some(idx);
+			}
+
+			idx += len;
+		}
+	}
+
+	/// Decodes the LZW-compressed image data in the sub-blocks starting at `idx` (which are known to be intact) with the minimum code size
+	/// `min_code_size`, returning whether all the codes are valid and they decode to exactly `num_pixels` pixels. The pixels themselves
+	/// aren't needed, so only the length of the string of each code is tracked
+	fn decode_image_data(data: &[u8], mut idx: usize, min_code_size: u8, num_pixels: usize) -> bool {
+		let mut image_data = Vec::new();
+
+		while data[idx] != 0 {
+			let len = data[idx] as usize;
+			image_data.extend_from_slice(&data[(idx + 1)..(idx + 1 + len)]);
+			idx += 1 + len;
+		}
+
+		let clear_code = 1usize << min_code_size;
+		let end_code = clear_code + 1;
+
+		let mut string_lens = [ 1u32; 1 << LZW_MAX_CODE_SIZE ];
+
+		let mut code_size = min_code_size as u32 + 1;
+		let mut next_code = clear_code + 2;
+		let mut prev_code: Option<usize> = None;
+
+		let mut bit_idx = 0;
+		let mut decoded_pixels = 0;
+
+		// Codes are packed least significant bit first. Running out of data without an end code is tolerated if the image is complete
+		while bit_idx + code_size as usize <= image_data.len() * 8 {
+			let mut code = 0;
+			for i in 0..(code_size as usize) {
+				let bit = bit_idx + i;
+				code |= (((image_data[bit / 8] >> (bit % 8)) & 1) as usize) << i;
+			}
+			bit_idx += code_size as usize;
+
+			if code == clear_code {
+				code_size = min_code_size as u32 + 1;
+				next_code = clear_code + 2;
+				prev_code = None;
+				continue;
+			}
+
+			if code == end_code {
+				break;
+			}
+
+			// A code can only refer to an existing entry, or to the entry that it is about to add (when the string is the previous string
+			// followed by its own first pixel)
+			let string_len = match prev_code {
+				_ if code < next_code => string_lens[code],
+				Some(prev_code) if code == next_code => string_lens[prev_code] + 1,
+				_ => return false
+			};
+
+			if let Some(prev_code) = prev_code {
+				// Once the table is full, encoders keep using it as-is until they send a clear code
+				if next_code < string_lens.len() {
+					string_lens[next_code] = string_lens[prev_code] + 1;
+					next_code += 1;
+
+					if next_code == 1 << code_size && code_size < LZW_MAX_CODE_SIZE {
+						code_size += 1;
+					}
+				}
+			}
+
+			decoded_pixels += string_len as usize;
+			prev_code = Some(code);
+
+			if decoded_pixels > num_pixels {
+				return false;
+			}
+		}
+
+		decoded_pixels == num_pixels
+	}
+}
+
+impl FileValidator for GifValidator {
+	// Written using https://www.w3.org/Graphics/GIF/spec-gif89a.txt
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+
+		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
+			(start + max_len as usize).min(file_data.len())
+		} else {
+			file_data.len()
+		};
+
+		if !file_data.get(start..(start + 6)).is_some_and(|signature| GIF_SIGNATURES.iter().any(|&s| s == signature)) {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Unrecognised,
+				rejection_reason: Some(RejectionReason::GifMissingSignature),
+				..Default::default()
+			};
+		}
+
+		let decode_image_data = file_match.file_type.param(PARAM_DECODE_IMAGE_DATA).unwrap_or(false);
+
+		let truncated = |validation_type: FileValidationType, rejection_reason: Option<RejectionReason>| FileValidationInfo {
+			validation_type: FileValidationType::Partial.worst_of(validation_type),
+			fragments: vec![ start..max_idx ],
+			rejection_reason: rejection_reason.or(Some(RejectionReason::Truncated)),
+			..Default::default()
+		};
+
+		if start + GIF_HEADER_SIZE > max_idx {
+			return truncated(FileValidationType::Correct, None);
+		}
+
+		let mut i = start + GIF_HEADER_SIZE + Self::colour_table_len(file_data[start + 10]);
+
+		let mut validation_type = FileValidationType::Correct;
+		let mut rejection_reason = None;
+
+		let mut seen_image = false;
+
+		loop {
+			if i >= max_idx {
+				break truncated(validation_type, rejection_reason);
+			}
+
+			match file_data[i] {
+				GIF_EXTENSION_INTRODUCER => {
+					// The extension introducer is followed by the extension label, then the extension's data sub-blocks
+					match Self::skip_sub_blocks(file_data, i + 2, max_idx) {
+						Some(end) => i = end,
+						None => break truncated(validation_type, rejection_reason)
+					}
+				}
+				GIF_IMAGE_SEPARATOR => {
+					if i + GIF_IMAGE_DESCRIPTOR_SIZE > max_idx {
+						break truncated(validation_type, rejection_reason);
+					}
+
+					let width = u16::from_le_bytes(file_data[(i + 5)..(i + 7)].try_into().unwrap()) as usize;
+					let height = u16::from_le_bytes(file_data[(i + 7)..(i + 9)].try_into().unwrap()) as usize;
+
+					i += GIF_IMAGE_DESCRIPTOR_SIZE + Self::colour_table_len(file_data[i + 9]);
+
+					if i >= max_idx {
+						break truncated(validation_type, rejection_reason);
+					}
+
+					// The image data is the LZW minimum code size followed by the LZW-compressed data in sub-blocks
+					let min_code_size = file_data[i];
+					let Some(end) = Self::skip_sub_blocks(file_data, i + 1, max_idx) else {
+						break truncated(validation_type, rejection_reason);
+					};
+
+					if !(2..=8).contains(&min_code_size) {
+						validation_type = validation_type.worst_of(FileValidationType::FormatError);
+						rejection_reason = rejection_reason.or(Some(RejectionReason::GifInvalidCodeSize));
+					} else if decode_image_data && !Self::decode_image_data(file_data, i + 1, min_code_size, width * height) {
+						validation_type = validation_type.worst_of(FileValidationType::Corrupt);
+						rejection_reason = rejection_reason.or(Some(RejectionReason::GifImageDataCorrupt));
+					}
+
+					seen_image = true;
+					i = end;
+				}
+				GIF_TRAILER => {
+					if !seen_image {
+						validation_type = validation_type.worst_of(FileValidationType::FormatError);
+						rejection_reason = rejection_reason.or(Some(RejectionReason::GifNoImage));
+					}
+
+					break FileValidationInfo {
+						validation_type,
+						fragments: vec![ start..(i + 1) ],
+						rejection_reason,
+						..Default::default()
+					};
+				}
+				_ => {
+					// Anything else means the data is not (or is no longer) the GIF, e.g. due to fragmentation, so take what was recognised
+					break FileValidationInfo {
+						validation_type: FileValidationType::Corrupt,
+						fragments: vec![ start..i ],
+						rejection_reason: rejection_reason.or(Some(RejectionReason::GifUnrecognisedBlock)),
+						..Default::default()
+					};
+				}
+			}
+		}
+	}
+
+	fn known_params(&self, _type_id: FileTypeId) -> &'static [&'static str] {
+		&[ PARAM_DECODE_IMAGE_DATA ]
+	}
+
+	fn description(&self) -> &'static str {
+		"Walks the blocks and sub-blocks up to the trailer, optionally decoding the LZW image data of each image to check it is intact"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::HashMap;
+
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason, PARAM_DECODE_IMAGE_DATA}};
+
+	use super::GifValidator;
+
+	/// Encodes `pixels` (each less than 4) as LZW with a minimum code size of 2, without compression, by sending a clear code before every
+	/// 2 pixels so that the code size stays at 3 bits
+	fn lzw_encode(pixels: &[u8]) -> Vec<u8> {
+		let mut codes = Vec::new();
+		for pair in pixels.chunks(2) {
+			codes.push(4);
+			codes.extend(pair.iter().map(|&p| p as u32));
+		}
+		codes.push(5);
+
+		let mut data = vec![ 0u8; (codes.len() * 3).div_ceil(8) ];
+		for (i, code) in codes.iter().enumerate() {
+			for bit in 0..3 {
+				if code & (1 << bit) != 0 {
+					data[(i * 3 + bit) / 8] |= 1 << ((i * 3 + bit) % 8);
+				}
+			}
+		}
+
+		data
+	}
+
+	/// A candidate of `file_type` from `start_idx` to the end of data of length `data_len`
+	fn candidate(file_type: &FileType, start_idx: usize, data_len: usize) -> MatchPair<'_> {
+		MatchPair {
+			file_type,
+			start_idx,
+			end_idx: data_len - 1,
+			truncated: false
+		}
+	}
+
+	/// A 4x4 GIF with a global colour table of 4 colours, a graphic control extension and the image data `image_data`
+	fn synthetic_gif(image_data: &[u8]) -> Vec<u8> {
+		let mut gif = b"GIF89a".to_vec();
+		gif.extend_from_slice(&[ 4, 0, 4, 0, 0x81, 0, 0 ]);
+		gif.extend_from_slice(&[ 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0xff ]);
+
+		gif.extend_from_slice(&[ 0x21, 0xf9, 4, 0, 0, 0, 0, 0 ]);
+
+		gif.extend_from_slice(&[ 0x2c, 0, 0, 0, 0, 4, 0, 4, 0, 0 ]);
+		gif.push(2);
+		for block in image_data.chunks(5) {
+			gif.push(block.len() as u8);
+			gif.extend_from_slice(block);
+		}
+		gif.push(0);
+
+		gif.push(0x3b);
+
+		gif
+	}
+
+	#[test]
+	fn test_gif_validator() {
+		let pixels: Vec<u8> = (0..16).map(|i| (i % 4) as u8).collect();
+		let gif = synthetic_gif(&lzw_encode(&pixels));
+
+		let mut data = vec![ 0xaa; 32 ];
+		data.extend_from_slice(&gif);
+		data.extend_from_slice(&[ 0xaa; 32 ]);
+
+		let file_type = FileType {
+			type_id: FileTypeId::Gif,
+			max_len: Some(1024),
+			..Default::default()
+		};
+
+		let file_match = candidate(&file_type, 32, data.len());
+
+		let info = GifValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ 32..(32 + gif.len()) ]);
+
+		// Truncated within the image data
+		let info = GifValidator::new().validate(&data[..(32 + gif.len() - 4)], &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::Truncated));
+
+		// Data that isn't a block after the extension
+		let mut broken = data.clone();
+		broken[32 + 25 + 8] = 0xaa;
+
+		let info = GifValidator::new().validate(&broken, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Corrupt);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::GifUnrecognisedBlock));
+		assert_eq!(info.fragments, vec![ 32..(32 + 25 + 8) ]);
+
+		// Not a GIF at all
+		let info = GifValidator::new().validate(&data, &candidate(&file_type, 0, data.len()), &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Unrecognised);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::GifMissingSignature));
+	}
+
+	#[test]
+	fn test_gif_decode_image_data() {
+		let pixels: Vec<u8> = (0..16).map(|i| (i % 4) as u8).collect();
+
+		// Image data whose codes are valid, but that only decodes to half of the pixels, as if a fragment of it were missing
+		let short = synthetic_gif(&lzw_encode(&pixels[..8]));
+		// Image data with a code (7) that refers to an entry that doesn't exist yet
+		let mut invalid_code = lzw_encode(&pixels);
+		invalid_code[0] = (invalid_code[0] & !0x38) | (7 << 3);
+		let invalid_code = synthetic_gif(&invalid_code);
+
+		let intact = synthetic_gif(&lzw_encode(&pixels));
+
+		let file_type = FileType {
+			type_id: FileTypeId::Gif,
+			max_len: Some(1024),
+			..Default::default()
+		};
+
+		let deep_file_type = FileType {
+			type_id: FileTypeId::Gif,
+			max_len: Some(1024),
+			params: HashMap::from([ (PARAM_DECODE_IMAGE_DATA.to_string(), toml::Value::Boolean(true)) ]),
+			..Default::default()
+		};
+
+		for (gif, deep_validation_type) in [ (&intact, FileValidationType::Correct), (&short, FileValidationType::Corrupt), (&invalid_code, FileValidationType::Corrupt) ] {
+			// The structure is intact, so the image data is only found to be corrupt when decoding it
+			let info = GifValidator::new().validate(gif, &candidate(&file_type, 0, gif.len()), &[], 1, &SearchlightConfig::default());
+			assert_eq!(info.validation_type, FileValidationType::Correct);
+
+			let info = GifValidator::new().validate(gif, &candidate(&deep_file_type, 0, gif.len()), &[], 1, &SearchlightConfig::default());
+			assert_eq!(info.validation_type, deep_validation_type);
+			assert_eq!(info.fragments, vec![ 0..gif.len() ]);
+
+			if deep_validation_type == FileValidationType::Corrupt {
+				assert_eq!(info.rejection_reason, Some(RejectionReason::GifImageDataCorrupt));
+			}
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,232 @@
+/// The size of a box header with a 32-bit size, i.e. the size and type
+pub const BOX_HEADER_SIZE: usize = 8;
+/// The size of the 64-bit size that follows the type of boxes with a size of 1
+const LARGE_SIZE_SIZE: usize = 8;
+/// The size of the extended type that follows the header of `uuid` boxes
+const USER_TYPE_SIZE: usize = 16;
+
+const UUID_BOX_TYPE: &[u8; 4] = b"uuid";
+
+/// A box in an ISO base media file format (ISO-BMFF) file
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct IsoBox {
+	pub box_type: [u8; 4],
+	/// The extended type of the box, if it is a `uuid` box
+	pub user_type: Option<[u8; 16]>,
+	/// The index of the start of the box (its header) in the data
+	pub offset: usize,
+	/// The size of the box, including its header. Boxes with a size of 0 extend to the end of their container, which is resolved here
+	pub size: usize,
+	/// The size of the header of the box, which is larger for boxes with a 64-bit size and for `uuid` boxes
+	pub header_len: usize
+}
+
+impl IsoBox {
+	/// The index of the start of the content of the box, which for container boxes is where their children start
+	pub fn data_start(&self) -> usize {
+		self.offset + self.header_len
+	}
+
+	/// The index of the end of the box, which is where the next box starts
+	pub fn end(&self) -> usize {
+		self.offset + self.size
+	}
+}
+
+/// Why the boxes of an ISO-BMFF file couldn't be walked
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IsoBmffError {
+	/// The available data ends before the end of a box
+	Truncated,
+	/// A box has a size smaller than its header
+	InvalidSize,
+	/// A box extends past the end of its container
+	BoxOutOfBounds
+}
+
+/// Returns an iterator over the boxes of an ISO base media file format (ISO-BMFF) file (e.g. MP4, MOV or HEIC) in `data` from `start` to
+/// `end`, which is the end of their container (or of the file, for top-level boxes), so that validators of ISO-BMFF formats don't have to
+/// walk the boxes themselves. To walk the children of a container box, pass its `data_start()` and `end()`. Each box is a 32-bit big-endian
+/// size followed by a 4-byte type, where a size of 1 means that a 64-bit size follows the type and a size of 0 means that the box extends
+/// to the end of its container, and `uuid` boxes are followed by a 16-byte extended type. If a box extends past the end of the data or
+/// container, or is invalid, the iterator yields an error and then ends
+pub fn boxes(data: &[u8], start: usize, end: usize) -> IsoBoxes<'_> {
+	IsoBoxes {
+		data,
+		idx: start,
+		end,
+		done: false
+	}
+}
+
+/// An iterator over the boxes of an ISO-BMFF file, see `boxes`
+pub struct IsoBoxes<'a> {
+	data: &'a [u8],
+	idx: usize,
+	end: usize,
+	done: bool
+}
+
+impl IsoBoxes<'_> {
+	/// Reads the header of the box at the current index, checking that it is within the data and container
+	fn read_box(&self) -> Result<IsoBox, IsoBmffError> {
+		let i = self.idx;
+
+		let header_in_bounds = |header_len: usize| {
+			if i + header_len > self.end {
+				Err(IsoBmffError::BoxOutOfBounds)
+			} else if i + header_len > self.data.len() {
+				Err(IsoBmffError::Truncated)
+			} else {
+				Ok(())
+			}
+		};
+
+		header_in_bounds(BOX_HEADER_SIZE)?;
+
+		let size = u32::from_be_bytes(self.data[i..(i + 4)].try_into().unwrap()) as u64;
+		let box_type: [u8; 4] = self.data[(i + 4)..(i + 8)].try_into().unwrap();
+
+		let mut header_len = BOX_HEADER_SIZE;
+
+		let size = match size {
+			0 => None,
+			1 => {
+				header_in_bounds(header_len + LARGE_SIZE_SIZE)?;
+				let large_size = u64::from_be_bytes(self.data[(i + header_len)..(i + header_len + LARGE_SIZE_SIZE)].try_into().unwrap());
+				header_len += LARGE_SIZE_SIZE;
+				Some(large_size)
+			}
+			size => Some(size)
+		};
+
+		let user_type = if &box_type == UUID_BOX_TYPE {
+			header_in_bounds(header_len + USER_TYPE_SIZE)?;
+			let user_type = self.data[(i + header_len)..(i + header_len + USER_TYPE_SIZE)].try_into().unwrap();
+			header_len += USER_TYPE_SIZE;
+			Some(user_type)
+		} else {
+			None
+		};
+
+		let size = match size {
+			Some(size) => usize::try_from(size).map_err(|_| IsoBmffError::BoxOutOfBounds)?,
+			None => self.end - i
+		};
+
+		if size < header_len {
+			return Err(IsoBmffError::InvalidSize);
+		}
+
+		// A corrupt 64-bit size can be large enough to overflow
+		if i.checked_add(size).is_none_or(|box_end| box_end > self.end) {
+			return Err(IsoBmffError::BoxOutOfBounds);
+		}
+
+		if i + size > self.data.len() {
+			return Err(IsoBmffError::Truncated);
+		}
+
+		Ok(IsoBox {
+			box_type,
+			user_type,
+			offset: i,
+			size,
+			header_len
+		})
+	}
+}
+
+impl Iterator for IsoBoxes<'_> {
+	type Item = Result<IsoBox, IsoBmffError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done || self.idx >= self.end {
+			return None;
+		}
+
+		let result = self.read_box();
+
+		match &result {
+			Ok(iso_box) => self.idx = iso_box.end(),
+			Err(_) => self.done = true
+		}
+
+		Some(result)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{boxes, IsoBmffError, IsoBox};
+
+	fn iso_box(box_type: &[u8; 4], content: &[u8]) -> Vec<u8> {
+		let mut b = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+		b.extend_from_slice(box_type);
+		b.extend_from_slice(content);
+		b
+	}
+
+	/// An `ftyp` box, a `moov` box containing a `uuid` box, a `free` box with a 64-bit size, and an `mdat` box that extends to the end
+	fn synthetic_isobmff() -> Vec<u8> {
+		let mut data = iso_box(b"ftyp", b"isom\0\0\0\0isomavc1");
+
+		let mut uuid_content = [ 0x5a; 16 ].to_vec();
+		uuid_content.extend_from_slice(&[ 0x01, 0x02 ]);
+		data.extend(iso_box(b"moov", &iso_box(b"uuid", &uuid_content)));
+
+		data.extend_from_slice(&1u32.to_be_bytes());
+		data.extend_from_slice(b"free");
+		data.extend_from_slice(&20u64.to_be_bytes());
+		data.extend_from_slice(&[ 0x00; 4 ]);
+
+		data.extend_from_slice(&0u32.to_be_bytes());
+		data.extend_from_slice(b"mdat");
+		data.extend_from_slice(&[ 0x33; 10 ]);
+
+		data
+	}
+
+	#[test]
+	fn test_isobmff_boxes() {
+		let data = synthetic_isobmff();
+
+		let top_level: Vec<_> = boxes(&data, 0, data.len()).collect::<Result<_, _>>().unwrap();
+		assert_eq!(top_level, vec![
+			IsoBox { box_type: *b"ftyp", user_type: None, offset: 0, size: 24, header_len: 8 },
+			IsoBox { box_type: *b"moov", user_type: None, offset: 24, size: 34, header_len: 8 },
+			IsoBox { box_type: *b"free", user_type: None, offset: 58, size: 20, header_len: 16 },
+			IsoBox { box_type: *b"mdat", user_type: None, offset: 78, size: 18, header_len: 8 }
+		]);
+
+		let moov = top_level[1];
+		let children: Vec<_> = boxes(&data, moov.data_start(), moov.end()).collect::<Result<_, _>>().unwrap();
+		assert_eq!(children, vec![
+			IsoBox { box_type: *b"uuid", user_type: Some([ 0x5a; 16 ]), offset: 32, size: 26, header_len: 24 }
+		]);
+	}
+
+	#[test]
+	fn test_isobmff_truncated() {
+		let data = synthetic_isobmff();
+
+		// Truncated within the moov box, whose size says it extends past the available data
+		let truncated = &data[..50];
+		let result: Vec<_> = boxes(truncated, 0, data.len()).collect();
+		assert_eq!(result, vec![
+			Ok(IsoBox { box_type: *b"ftyp", user_type: None, offset: 0, size: 24, header_len: 8 }),
+			Err(IsoBmffError::Truncated)
+		]);
+
+		// Truncated within the 64-bit size of the free box
+		let result: Vec<_> = boxes(&data[..70], 0, data.len()).collect();
+		assert_eq!(result.last(), Some(&Err(IsoBmffError::Truncated)));
+
+		// A box larger than its container is out of bounds, and one smaller than its header is invalid
+		assert_eq!(boxes(&data, 0, 20).next(), Some(Err(IsoBmffError::BoxOutOfBounds)));
+
+		let mut invalid = data.clone();
+		invalid[0..4].copy_from_slice(&4u32.to_be_bytes());
+		assert_eq!(boxes(&invalid, 0, invalid.len()).collect::<Vec<_>>(), vec![ Err(IsoBmffError::InvalidSize) ]);
+	}
+}
\ No newline at end of file
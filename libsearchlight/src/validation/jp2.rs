@@ -0,0 +1,250 @@
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig};
+
+use super::{isobmff::{self, IsoBmffError, IsoBox}, FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+const JP2_SIGNATURE_BOX_TYPE: &[u8; 4] = b"jP  ";
+/// The content of the signature box, which is always 12 bytes in total
+const JP2_SIGNATURE: [u8; 4] = [ 0x0d, 0x0a, 0x87, 0x0a ];
+const JP2_SIGNATURE_BOX_SIZE: usize = 12;
+
+const JP2_FILE_TYPE_BOX_TYPE: &[u8; 4] = b"ftyp";
+const JP2_BRAND: &[u8; 4] = b"jp2 ";
+const JP2_HEADER_BOX_TYPE: &[u8; 4] = b"jp2h";
+const JP2_CODESTREAM_BOX_TYPE: &[u8; 4] = b"jp2c";
+
+/// The start of codestream (SOC) marker that each codestream starts with
+const J2K_SOC: [u8; 2] = [ 0xff, 0x4f ];
+/// The end of codestream (EOC) marker. Marker codes of 0xff90 and above can't occur in the entropy-coded data, so the first EOC marker in a
+/// codestream is its end
+const J2K_EOC: [u8; 2] = [ 0xff, 0xd9 ];
+
+pub struct Jp2Validator;
+
+impl Jp2Validator {
+	pub fn new() -> Self {
+		Jp2Validator
+	}
+
+	/// Whether a box type is plausible, i.e. made up of printable ASCII characters as all defined box types are, so that the data following
+	/// the last box of a file isn't taken as another box
+	fn plausible_box_type(box_type: &[u8; 4]) -> bool {
+		box_type.iter().all(|b| (0x20..0x7f).contains(b))
+	}
+
+	/// Finds the end of the codestream in a codestream box with a size of 0 (which extends to the end of the file, so has no known size),
+	/// by the EOC marker, returning the index just after it, or None if there is none before `max_idx`
+	fn codestream_end(data: &[u8], codestream_box: &IsoBox, max_idx: usize) -> Option<usize> {
+		data[codestream_box.data_start()..max_idx].windows(2).position(|w| w == J2K_EOC).map(|i| codestream_box.data_start() + i + J2K_EOC.len())
+	}
+}
+
+impl FileValidator for Jp2Validator {
+	// Written using ITU-T T.800 Annex I (JP2 file format syntax)
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+
+		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
+			start.saturating_add(max_len as usize).min(file_data.len())
+		} else {
+			file_data.len()
+		};
+
+		let truncated = || FileValidationInfo {
+			validation_type: FileValidationType::Partial,
+			fragments: vec![ (start..max_idx) ],
+			rejection_reason: Some(RejectionReason::Truncated),
+			..Default::default()
+		};
+
+		let format_error = |reason: RejectionReason| FileValidationInfo {
+			validation_type: FileValidationType::FormatError,
+			fragments: vec![ (start..max_idx) ],
+			rejection_reason: Some(reason),
+			..Default::default()
+		};
+
+		if start + JP2_SIGNATURE_BOX_SIZE > max_idx {
+			return truncated();
+		}
+
+		let mut boxes = isobmff::boxes(file_data, start, max_idx);
+
+		// The signature box is always the first, and its content is fixed
+		match boxes.next() {
+			Some(Ok(iso_box)) if &iso_box.box_type == JP2_SIGNATURE_BOX_TYPE && iso_box.size == JP2_SIGNATURE_BOX_SIZE
+				&& file_data[iso_box.data_start()..iso_box.end()] == JP2_SIGNATURE => (),
+			_ => {
+				return FileValidationInfo {
+					validation_type: FileValidationType::FormatError,
+					fragments: vec![ (start..(start + JP2_SIGNATURE_BOX_SIZE)) ],
+					rejection_reason: Some(RejectionReason::Jp2InvalidSignature),
+					..Default::default()
+				};
+			}
+		}
+
+		// Followed by the file type box, of which the brand is the first field
+		match boxes.next() {
+			Some(Ok(iso_box)) if &iso_box.box_type == JP2_FILE_TYPE_BOX_TYPE && iso_box.size >= iso_box.header_len + JP2_BRAND.len() => {
+				if &file_data[iso_box.data_start()..(iso_box.data_start() + JP2_BRAND.len())] != JP2_BRAND {
+					return format_error(RejectionReason::Jp2UnsupportedBrand);
+				}
+			}
+			Some(Err(IsoBmffError::Truncated | IsoBmffError::BoxOutOfBounds)) => return truncated(),
+			_ => return format_error(RejectionReason::Jp2InvalidBoxStructure)
+		}
+
+		let mut seen_header = false;
+		let mut end = None;
+
+		for iso_box in boxes {
+			let iso_box = match iso_box {
+				Ok(iso_box) if Self::plausible_box_type(&iso_box.box_type) => iso_box,
+				// Once the codestream is complete, whatever follows the last box is not part of the file
+				_ if end.is_some() => break,
+				// The boxes are walked up to max_len, so a box that extends past it is as good as truncated
+				Err(IsoBmffError::Truncated | IsoBmffError::BoxOutOfBounds) => return truncated(),
+				_ => return format_error(RejectionReason::Jp2InvalidBoxStructure)
+			};
+
+			match &iso_box.box_type {
+				JP2_HEADER_BOX_TYPE => seen_header = true,
+				JP2_CODESTREAM_BOX_TYPE => {
+					if !seen_header {
+						return format_error(RejectionReason::Jp2MissingHeader);
+					}
+
+					if iso_box.data_start() + J2K_SOC.len() > max_idx {
+						return truncated();
+					}
+
+					if file_data[iso_box.data_start()..(iso_box.data_start() + J2K_SOC.len())] != J2K_SOC {
+						return format_error(RejectionReason::Jp2InvalidBoxStructure);
+					}
+
+					// A codestream box with a size of 0 is the last box, and its end is that of the codestream
+					if file_data[iso_box.offset..(iso_box.offset + 4)] == [ 0; 4 ] {
+						end = match Self::codestream_end(file_data, &iso_box, max_idx) {
+							Some(codestream_end) => Some(codestream_end),
+							None => return truncated()
+						};
+
+						break;
+					}
+				}
+				_ => ()
+			}
+
+			if end.is_some() || &iso_box.box_type == JP2_CODESTREAM_BOX_TYPE {
+				end = Some(iso_box.end());
+			}
+		}
+
+		match end {
+			Some(end) => FileValidationInfo {
+				validation_type: FileValidationType::Correct,
+				fragments: vec![ (start..end) ],
+				..Default::default()
+			},
+			None if !seen_header => format_error(RejectionReason::Jp2MissingHeader),
+			// The boxes ran up to max_len without a codestream
+			None => truncated()
+		}
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the signature box and file type brand, and walks the boxes to the end of the last box after the codestream"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason}};
+
+	use super::Jp2Validator;
+
+	fn jp2_box(box_type: &[u8; 4], content: &[u8]) -> Vec<u8> {
+		let mut b = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+		b.extend_from_slice(box_type);
+		b.extend_from_slice(content);
+		b
+	}
+
+	/// A JP2 file with a signature box, file type box, header box containing an image header box, codestream box and an XML box after it
+	fn synthetic_jp2() -> Vec<u8> {
+		let mut jp2 = jp2_box(b"jP  ", &[ 0x0d, 0x0a, 0x87, 0x0a ]);
+		jp2.extend(jp2_box(b"ftyp", b"jp2 \0\0\0\0jp2 "));
+		jp2.extend(jp2_box(b"jp2h", &jp2_box(b"ihdr", &[ 0, 0, 0, 16, 0, 0, 0, 16, 0, 3, 7, 7, 0, 0 ])));
+
+		let mut codestream = vec![ 0xff, 0x4f, 0xff, 0x51 ];
+		codestream.extend_from_slice(&[ 0x5a; 32 ]);
+		codestream.extend_from_slice(&[ 0xff, 0xd9 ]);
+		jp2.extend(jp2_box(b"jp2c", &codestream));
+
+		jp2.extend(jp2_box(b"xml ", b"<xml/>"));
+
+		jp2
+	}
+
+	fn validate(data: &[u8], start_idx: usize) -> (FileValidationType, Vec<std::ops::Range<usize>>, Option<RejectionReason>) {
+		let file_type = FileType {
+			type_id: FileTypeId::Jp2,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx,
+			end_idx: data.len() - 1,
+			truncated: false
+		};
+
+		let info = Jp2Validator::new().validate(data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		(info.validation_type, info.fragments, info.rejection_reason)
+	}
+
+	#[test]
+	fn test_jp2_validator() {
+		let jp2 = synthetic_jp2();
+
+		let mut data = vec![ 0xaa; 16 ];
+		data.extend_from_slice(&jp2);
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		assert_eq!(validate(&data, 16), (FileValidationType::Correct, vec![ 16..(16 + jp2.len()) ], None));
+
+		// A codestream box with a size of 0 ends at the end of the codestream
+		let codestream_idx = 16 + 12 + 20 + 30;
+		let mut size_zero = data[..(codestream_idx + 8 + 38)].to_vec();
+		size_zero[codestream_idx..(codestream_idx + 4)].copy_from_slice(&[ 0; 4 ]);
+		size_zero.extend_from_slice(&[ 0x00; 64 ]);
+
+		assert_eq!(validate(&size_zero, 16), (FileValidationType::Correct, vec![ 16..(codestream_idx + 8 + 38) ], None));
+
+		// Truncated within the codestream
+		let (validation_type, _, reason) = validate(&data[..(codestream_idx + 20)], 16);
+		assert_eq!(validation_type, FileValidationType::Partial);
+		assert_eq!(reason, Some(RejectionReason::Truncated));
+
+		// A file type box with a brand other than jp2
+		let mut jpx = data.clone();
+		jpx[(16 + 20)..(16 + 24)].copy_from_slice(b"jpx ");
+		assert_eq!(validate(&jpx, 16).2, Some(RejectionReason::Jp2UnsupportedBrand));
+
+		// A signature box with the wrong content
+		let mut bad_signature = data.clone();
+		bad_signature[16 + 10] = 0x0d;
+
+		let (validation_type, fragments, reason) = validate(&bad_signature, 16);
+		assert_eq!(validation_type, FileValidationType::FormatError);
+		assert_eq!(fragments, vec![ 16..28 ]);
+		assert_eq!(reason, Some(RejectionReason::Jp2InvalidSignature));
+
+		// A codestream box without a header box before it
+		let mut no_header = data.clone();
+		no_header[(16 + 36)..(16 + 40)].copy_from_slice(b"free");
+		assert_eq!(validate(&no_header, 16).2, Some(RejectionReason::Jp2MissingHeader));
+	}
+}
\ No newline at end of file
@@ -1,15 +1,27 @@
-use crate::{classifiers, search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig, utils};
+use crate::{classifiers, search::{pairing::MatchPair, Match}, searchlight::config::{FileTypeId, SearchlightConfig}, utils};
 
-use super::{FileValidationInfo, FileValidationType, FileValidator, Fragment};
+use std::ops::Range;
+
+use super::{max_reconstruction_search_len, EmbeddedEncoding, EmbeddedFile, FileValidationInfo, FileValidationType, FileValidator, Fragment, RejectionReason, PARAM_MAX_RECONSTRUCTION_SEARCH_LEN, PARAM_MAX_SCAN_GAPS, PARAM_TRAILING_METADATA};
 
 // const JPEG_SOI: u8 = 0xd8;
 const JPEG_EOI: u8 = 0xd9;
 const JPEG_APP0: u8 = 0xe0;
 const JPEG_APP1: u8 = 0xe1;
+const JPEG_APP15: u8 = 0xef;
 const JPEG_SOF0: u8 = 0xc0;
 const JPEG_SOF2: u8 = 0xc2;
 const JPEG_SOS: u8 = 0xda;
 
+const EXIF_IDENTIFIER: &[u8] = b"Exif\0\0";
+const JFXX_IDENTIFIER: &[u8] = b"JFXX\0";
+/// The JFXX extension code of a thumbnail stored as a JPEG
+const JFXX_JPEG_THUMBNAIL: u8 = 0x10;
+const TIFF_TAG_JPEG_INTERCHANGE_FORMAT: u16 = 0x0201;
+const TIFF_TAG_JPEG_INTERCHANGE_FORMAT_LENGTH: u16 = 0x0202;
+
+/// Understands the validator parameters `PARAM_MAX_RECONSTRUCTION_SEARCH_LEN`, `PARAM_MAX_SCAN_GAPS` and `PARAM_TRAILING_METADATA`. If `extract_embedded` is
+/// configured, the thumbnails in Exif (APP1) and JFXX (APP0) segments are reported as embedded files
 pub struct JpegValidator;
 
 enum JpegScanReconstructionInfo {
@@ -27,12 +39,116 @@ impl JpegValidator {
 		JpegValidator
 	}
 
+	/// Returns the end of any APPn segments (e.g. Exif metadata) that directly follow the EOI marker ending at `eoi_end`, which some software
+	/// appends to JPEG files, or `eoi_end` if there are none. Segments that extend past `limit` are not included
+	fn trailing_metadata_end(file_data: &[u8], eoi_end: usize, limit: usize) -> usize {
+		let mut i = eoi_end;
+
+		while i + 4 <= limit && file_data[i] == 0xff && (JPEG_APP0..=JPEG_APP15).contains(&file_data[i + 1]) {
+			let segment_len = u16::from_be_bytes(file_data[(i + 2)..(i + 4)].try_into().unwrap()) as usize;
+
+			if segment_len < 2 || i + 2 + segment_len > limit {
+				break;
+			}
+
+			i += 2 + segment_len;
+		}
+
+		i
+	}
+
+	/// Returns the range of the thumbnail JPEG referenced by IFD1 of `exif`, the TIFF-structured data that follows the Exif identifier in an
+	/// APP1 segment, and whether the thumbnail is truncated by the end of `exif`
+	fn exif_thumbnail(exif: &[u8]) -> Option<(Range<usize>, bool)> {
+		let little_endian = match exif.get(0..2)? {
+			b"II" => true,
+			b"MM" => false,
+			_ => return None
+		};
+
+		let read_u16 = |idx: usize| exif.get(idx..(idx + 2)).map(|b| {
+			let b = b.try_into().unwrap();
+			if little_endian { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) }
+		});
+		let read_u32 = |idx: usize| exif.get(idx..(idx + 4)).map(|b| {
+			let b = b.try_into().unwrap();
+			(if little_endian { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) }) as usize
+		});
+
+		if read_u16(2)? != 42 {
+			return None;
+		}
+
+		// The thumbnail is described by IFD1, which is the IFD after IFD0
+		let ifd0 = read_u32(4)?;
+		let ifd1 = read_u32(ifd0 + 2 + read_u16(ifd0)? as usize * 12)?;
+		if ifd1 == 0 {
+			return None;
+		}
+
+		let mut offset = None;
+		let mut len = None;
+
+		for entry in (0..read_u16(ifd1)? as usize).map(|i| ifd1 + 2 + i * 12) {
+			match read_u16(entry)? {
+				TIFF_TAG_JPEG_INTERCHANGE_FORMAT => offset = read_u32(entry + 8),
+				TIFF_TAG_JPEG_INTERCHANGE_FORMAT_LENGTH => len = read_u32(entry + 8),
+				_ => ()
+			}
+		}
+
+		let (offset, len) = (offset?, len?);
+		if offset >= exif.len() || len == 0 {
+			return None;
+		}
+
+		Some((offset..(offset + len).min(exif.len()), offset + len > exif.len()))
+	}
+
+	/// Describes the thumbnail in the APPn segment starting at `segment_idx` as an embedded file, if the segment is an Exif or JFXX segment
+	/// that contains a JPEG thumbnail. A thumbnail that is cut off by the end of the segment or of the data, or that doesn't end in an EOI
+	/// marker, is partial
+	fn appn_thumbnail(file_data: &[u8], segment_idx: usize, segment_len: usize) -> Option<EmbeddedFile> {
+		let data_start = segment_idx + 4;
+		let segment_end = segment_idx + 2 + segment_len;
+		let segment_data = file_data.get(data_start..segment_end.min(file_data.len()))?;
+		let segment_truncated = segment_end > file_data.len();
+
+		let (name, range, truncated) = match file_data[segment_idx + 1] {
+			JPEG_APP1 if segment_data.starts_with(EXIF_IDENTIFIER) => {
+				let exif_start = data_start + EXIF_IDENTIFIER.len();
+				let (range, truncated) = Self::exif_thumbnail(&segment_data[EXIF_IDENTIFIER.len()..])?;
+
+				("exif_thumbnail.jpg", (exif_start + range.start)..(exif_start + range.end), truncated)
+			}
+			JPEG_APP0 if segment_data.starts_with(JFXX_IDENTIFIER) && segment_data.get(JFXX_IDENTIFIER.len()) == Some(&JFXX_JPEG_THUMBNAIL) => {
+				let thumbnail_start = data_start + JFXX_IDENTIFIER.len() + 1;
+
+				("jfxx_thumbnail.jpg", thumbnail_start..(data_start + segment_data.len()), segment_truncated)
+			}
+			_ => return None
+		};
+
+		let thumbnail = &file_data[range.clone()];
+		if !thumbnail.starts_with(&[ 0xff, 0xd8 ]) {
+			return None;
+		}
+
+		Some(EmbeddedFile {
+			name: name.to_string(),
+			validation_type: if !truncated && thumbnail.ends_with(&[ 0xff, JPEG_EOI ]) { FileValidationType::Correct } else { FileValidationType::Partial },
+			encoding: EmbeddedEncoding::Stored,
+			fragments: vec![ range ]
+		})
+	}
+
 	/// Attempt to reconstruct JPEG scan data, assuming that all fragments are in-order, by looping through clusters and attempting to classify them
-	/// as either JPEG scan data or not
+	/// as either JPEG scan data or not. Clusters that don't classify as scan data are skipped over as gaps, which are likely other files that split the
+	/// scan, and the reconstruction fails if more than `max_gaps` gaps are found (unlimited if None)
 	// TODO: We could maybe try and tackle out-of-order JPEG fragmentations using the reset marker orderings (if present)
 	//       although apparently they are only present in ~12% of JPEGs (Uzun and Sencar, 2020, https://doi.org/10.1109/TIFS.2019.2953382)
 	// TODO: Ali and Mohamad (2021) manage to tackle intertwined JPEGs using the Coherence of Euclidean Distance (CED) to detect sharp changes in the image https://doi.org/10.1016/j.jksuci.2018.12.007
-	fn reconstruct_scan_data(file_data: &[u8], scan_marker_idx: usize, cluster_size: usize, config: &SearchlightConfig) -> JpegScanReconstructionInfo {
+	fn reconstruct_scan_data(file_data: &[u8], scan_marker_idx: usize, cluster_size: usize, max_search_len: usize, max_gaps: Option<usize>) -> JpegScanReconstructionInfo {
 		let fragmentation_start = utils::next_multiple_of(scan_marker_idx + 1, cluster_size) as usize;
 
 		let mut fragments = vec![
@@ -41,10 +157,13 @@ impl JpegValidator {
 
 		let mut cluster_idx = fragmentation_start;
 
+		let mut num_gaps = 0;
+		let mut in_gap = false;
+
 		loop {
 			// Check we're in bounds of the reconstruction search length and file
 			let search_offset = (cluster_idx + cluster_size) - scan_marker_idx;
-			if search_offset > config.max_reconstruction_search_len.unwrap_or(u64::MAX) as usize || (cluster_idx + cluster_size) > file_data.len() {
+			if search_offset > max_search_len || (cluster_idx + cluster_size) > file_data.len() {
 				return JpegScanReconstructionInfo::Failure {
 					failure_idx: cluster_idx
 				}
@@ -56,10 +175,21 @@ impl JpegValidator {
 
 			match classification_info {
 				(false, None) => {
-					()
+					if !in_gap {
+						// Entering a new gap - Fail at the start of it if we've already skipped over as many as we're allowed
+						if Some(num_gaps) == max_gaps {
+							return JpegScanReconstructionInfo::Failure {
+								failure_idx: cluster_idx
+							}
+						}
+
+						num_gaps += 1;
+						in_gap = true;
+					}
 				}
 				(true, None) => {
 					fragments.push(cluster_idx..(cluster_idx + cluster_size));
+					in_gap = false;
 				}
 				(true, Some(next_marker)) => {
 					fragments.push((cluster_idx)..(next_marker + cluster_idx));
@@ -83,17 +213,20 @@ impl JpegValidator {
 impl FileValidator for JpegValidator {
 	// Written using https://www.w3.org/Graphics/JPEG/jfif3.pdf,
 	// https://www.w3.org/Graphics/JPEG/itu-t81.pdf and https://stackoverflow.com/questions/32873541/scanning-a-jpeg-file-for-markers
-	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], cluster_size: usize, config: &SearchlightConfig) -> FileValidationInfo {
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, all_matches: &[Match], cluster_size: usize, config: &SearchlightConfig) -> FileValidationInfo {
 		let start = file_match.start_idx as usize;
 
 		// Mandatory segments for a complete JPEG file
 		let mut seen_appn = false; // Whether an APP0 or APP1 segment has been found
 		let mut seen_sofn = false; // Whether a SOF0 or SOF2 segment has been found
 
+		let max_gaps = file_match.file_type.param::<u64>(PARAM_MAX_SCAN_GAPS).map(|gaps| gaps as usize);
+
 		let mut fragments = Vec::new();
+		let mut embedded = Vec::new();
 
 		let mut i = start;
-		loop {
+		let mut info = loop {
 			// Check if we are on a marker - the current byte should be 0xff and the next byte should not be 0x00
 			if file_data[i] == 0xff && file_data[i + 1] != 0x00 {
 				// The SOI and EOI markers don't have lengths after them - I did see someone saying that the whole range 0xd0 to 0xd9 has no lengths
@@ -106,20 +239,43 @@ impl FileValidator for JpegValidator {
 					i += 2;
 					continue;
 				} else if file_data[i + 1] == JPEG_EOI {
-					// NOTE: We're carving an extra cluster here which isn't necessary for the image but often metadata is stored past EOI so this will catch (some of) that
-					// BUG: This doesn't check file bounds
-					fragments.push(i..(i + 2 + cluster_size));
+					let eoi_end = i + 2;
+
+					let end = if file_match.file_type.carve_extra_cluster {
+						// Often metadata is stored past EOI, so carving an extra cluster will catch (some of) that
+						(eoi_end + cluster_size).min(file_data.len())
+					} else if !file_match.file_type.param(PARAM_TRAILING_METADATA).unwrap_or(true) {
+						eoi_end
+					} else {
+						// Otherwise, only carve metadata that we can recognise after EOI, stopping at the next signature, as that is likely the next file
+						let next_match_idx = all_matches.get(all_matches.partition_point(|m| (m.start_idx as usize) < eoi_end)).map(|m| m.start_idx as usize);
+
+						Self::trailing_metadata_end(file_data, eoi_end, next_match_idx.unwrap_or(file_data.len()).min(file_data.len()))
+					};
+
+					fragments.push(i..end);
 					utils::simplify_ranges(&mut fragments);
 
 					// Return that this is a complete file with length start - i
 					// If any of APPn and SOFn segments haven't been seen though return Format Error
-					break FileValidationInfo {
-						validation_type: if seen_appn && seen_sofn { FileValidationType::Correct } else { FileValidationType::FormatError },
-						fragments
+					break if seen_appn && seen_sofn {
+						FileValidationInfo {
+							validation_type: FileValidationType::Correct,
+							fragments,
+							rejection_reason: None,
+							..Default::default()
+						}
+					} else {
+						FileValidationInfo {
+							validation_type: FileValidationType::FormatError,
+							fragments,
+							rejection_reason: Some(RejectionReason::JpegMissingMandatorySegment),
+							..Default::default()
+						}
 					}
 				} else if file_data[i + 1] == JPEG_SOS {
 					// Since we have no way of knowing, really, we treat the following data as if it might be fragmented
-					let recons_info = Self::reconstruct_scan_data(file_data, i, cluster_size as usize, config);
+					let recons_info = Self::reconstruct_scan_data(file_data, i, cluster_size as usize, max_reconstruction_search_len(file_match.file_type, config), max_gaps);
 
 					match recons_info {
 						JpegScanReconstructionInfo::Success { mut chunk_frags, next_chunk_idx } => {
@@ -131,18 +287,25 @@ impl FileValidator for JpegValidator {
 
 							break FileValidationInfo {
 								validation_type: FileValidationType::Partial,
-								fragments
+								fragments,
+								rejection_reason: Some(RejectionReason::JpegScanReconstructionFailed),
+								..Default::default()
 							}
 						}
 					}
 				} else {
+					// Parse the length and skip the segment
+					let segment_len = u16::from_be_bytes(file_data[(i + 2)..=(i + 3)].try_into().unwrap());
+
 					if file_data[i + 1] == JPEG_APP0 || file_data[i + 1] == JPEG_APP1 {
 						seen_appn = true;
+
+						if config.extract_embedded {
+							embedded.extend(Self::appn_thumbnail(file_data, i, segment_len as usize));
+						}
 					} else if file_data[i + 1] == JPEG_SOF0 || file_data[i + 1] == JPEG_SOF2 {
 						seen_sofn = true;
 					}
-					// Parse the length and skip the segment
-					let segment_len = u16::from_be_bytes(file_data[(i + 2)..=(i + 3)].try_into().unwrap());
 
 					fragments.push(i..(i + segment_len as usize + 2));
 					utils::simplify_ranges(&mut fragments);
@@ -155,15 +318,237 @@ impl FileValidator for JpegValidator {
 				if seen_appn || seen_sofn {
 					break FileValidationInfo {
 						validation_type: FileValidationType::Partial,
-						fragments
+						fragments,
+						rejection_reason: Some(RejectionReason::JpegMissingMarker),
+						..Default::default()
 					};
 				} else {
 					break FileValidationInfo {
 						validation_type: FileValidationType::Unrecognised,
-						fragments
+						fragments,
+						rejection_reason: Some(RejectionReason::JpegMissingMarker),
+						..Default::default()
 					}
 				}
 			}
-		}
+		};
+
+		info.embedded = embedded;
+
+		info
+	}
+
+	fn known_params(&self, _type_id: FileTypeId) -> &'static [&'static str] {
+		&[ PARAM_MAX_RECONSTRUCTION_SEARCH_LEN, PARAM_MAX_SCAN_GAPS, PARAM_TRAILING_METADATA ]
+	}
+
+	fn description(&self) -> &'static str {
+		"Walks the marker segments and checks for an end of image marker, reconstructing fragmented scan data"
+	}
+
+	fn reconstructs_fragmentation(&self) -> bool {
+		true
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::{pairing::MatchPair, Match}, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{EmbeddedEncoding, FileValidationType, FileValidator, RejectionReason, PARAM_MAX_SCAN_GAPS}};
+
+	use super::JpegValidator;
+
+	const CLUSTER_SIZE: usize = 512;
+
+	fn synthetic_jpeg(scan_len: usize) -> Vec<u8> {
+		let mut jpeg = vec![ 0xff, 0xd8 ];
+		jpeg.extend_from_slice(&[ 0xff, 0xe0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0x00, 0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00 ]);
+		jpeg.extend_from_slice(&[ 0xff, 0xc0, 0x00, 0x11, 0x08, 0x00, 0x10, 0x00, 0x10, 0x03, 0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01 ]);
+		jpeg.extend_from_slice(&[ 0xff, 0xda, 0x00, 0x0c, 0x03, 0x01, 0x00, 0x02, 0x11, 0x03, 0x11, 0x00, 0x3f, 0x00 ]);
+		jpeg.extend((0..scan_len).map(|i| (i % 251) as u8)); // Never contains 0xff
+		jpeg.extend_from_slice(&[ 0xff, 0xd9 ]);
+		jpeg
+	}
+
+	#[test]
+	fn test_jpeg_trailing_metadata() {
+		let mut file_type = FileType {
+			type_id: FileTypeId::Jpeg,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let jpeg = synthetic_jpeg(1000);
+		let eoi_end = jpeg.len();
+
+		// An APP1 segment of 16 bytes after EOI, followed by unrelated data
+		let mut data = jpeg.clone();
+		data.extend_from_slice(&[ 0xff, 0xe1, 0x00, 0x10 ]);
+		data.extend_from_slice(&[ 0x45; 14 ]);
+		let metadata_end = data.len();
+		data.resize(CLUSTER_SIZE * 8, 0x00);
+
+		let header = Match::new(0, 0, 3);
+
+		let validate = |file_type: &FileType, all_matches: &[Match]| {
+			let file_match = MatchPair::new_sized(file_type, &header, 1024 * 1024);
+			JpegValidator::new().validate(&data, &file_match, all_matches, CLUSTER_SIZE, &SearchlightConfig::default())
+		};
+
+		let info = validate(&file_type, &[ header.clone() ]);
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ 0..metadata_end ]);
+
+		// A signature within the trailing metadata is taken to be the start of the next file
+		let info = validate(&file_type, &[ header.clone(), Match::new(0, eoi_end as u64 + 4, eoi_end as u64 + 7) ]);
+		assert_eq!(info.fragments, vec![ 0..eoi_end ]);
+
+		file_type.carve_extra_cluster = true;
+		let info = validate(&file_type, &[ header.clone() ]);
+		assert_eq!(info.fragments, vec![ 0..(eoi_end + CLUSTER_SIZE) ]);
+	}
+
+	/// Builds a JPEG with an Exif segment containing `thumbnail`, whose length is recorded as `thumbnail_len`, returning it and the offset of
+	/// the thumbnail
+	fn jpeg_with_exif_thumbnail(thumbnail: &[u8], thumbnail_len: u32) -> (Vec<u8>, usize) {
+		let mut exif = b"Exif\0\0II\x2a\x00".to_vec();
+		exif.extend_from_slice(&8u32.to_le_bytes());
+		// IFD0, with no entries
+		exif.extend_from_slice(&0u16.to_le_bytes());
+		exif.extend_from_slice(&14u32.to_le_bytes());
+		// IFD1, with the offset and length of the thumbnail
+		exif.extend_from_slice(&2u16.to_le_bytes());
+		exif.extend_from_slice(&[ 0x01, 0x02, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00 ]);
+		exif.extend_from_slice(&44u32.to_le_bytes());
+		exif.extend_from_slice(&[ 0x02, 0x02, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00 ]);
+		exif.extend_from_slice(&thumbnail_len.to_le_bytes());
+		exif.extend_from_slice(&0u32.to_le_bytes());
+		exif.extend_from_slice(thumbnail);
+
+		let mut jpeg = vec![ 0xff, 0xd8, 0xff, 0xe1 ];
+		jpeg.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+		let thumbnail_offset = jpeg.len() + 6 + 44;
+		jpeg.extend_from_slice(&exif);
+		jpeg.extend_from_slice(&synthetic_jpeg(1000)[2..]);
+
+		(jpeg, thumbnail_offset)
+	}
+
+	#[test]
+	fn test_jpeg_thumbnail() {
+		let file_type = FileType {
+			type_id: FileTypeId::Jpeg,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let config = SearchlightConfig {
+			extract_embedded: true,
+			..Default::default()
+		};
+
+		let header = Match::new(0, 0, 3);
+
+		let validate = |data: &[u8], config: &SearchlightConfig| {
+			let mut data = data.to_vec();
+			data.resize(CLUSTER_SIZE * 8, 0x00);
+
+			let file_match = MatchPair::new_sized(&file_type, &header, 1024 * 1024);
+			JpegValidator::new().validate(&data, &file_match, &[ header.clone() ], CLUSTER_SIZE, config)
+		};
+
+		let thumbnail = synthetic_jpeg(100);
+		let (jpeg, thumbnail_offset) = jpeg_with_exif_thumbnail(&thumbnail, thumbnail.len() as u32);
+
+		let info = validate(&jpeg, &config);
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ 0..jpeg.len() ]);
+		assert_eq!(info.embedded.len(), 1);
+		assert_eq!(info.embedded[0].name, "exif_thumbnail.jpg");
+		assert_eq!(info.embedded[0].validation_type, FileValidationType::Correct);
+		assert_eq!(info.embedded[0].encoding, EmbeddedEncoding::Stored);
+		assert_eq!(info.embedded[0].fragments, vec![ thumbnail_offset..(thumbnail_offset + thumbnail.len()) ]);
+
+		// Thumbnails are only reported if embedded files are being extracted
+		assert!(validate(&jpeg, &SearchlightConfig::default()).embedded.is_empty());
+
+		// A thumbnail that extends past the end of the segment is cut off at the end of it
+		let truncated_thumbnail = &thumbnail[..60];
+		let (jpeg, thumbnail_offset) = jpeg_with_exif_thumbnail(truncated_thumbnail, thumbnail.len() as u32);
+
+		let info = validate(&jpeg, &config);
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.embedded[0].validation_type, FileValidationType::Partial);
+		assert_eq!(info.embedded[0].fragments, vec![ thumbnail_offset..(thumbnail_offset + truncated_thumbnail.len()) ]);
+	}
+
+	#[test]
+	fn test_jpeg_scan_gaps() {
+		let jpeg = synthetic_jpeg(4000);
+
+		// Splits the JPEG at each of `split_offsets` with `clusters` clusters of foreign data, returning the data and the fragments of the JPEG
+		let split = |split_offsets: &[usize], clusters: usize| {
+			let mut data = Vec::new();
+			let mut fragments = Vec::new();
+			let mut prev_offset = 0;
+
+			for &offset in split_offsets.iter().chain([ jpeg.len() ].iter()) {
+				fragments.push(data.len()..(data.len() + offset - prev_offset));
+				data.extend_from_slice(&jpeg[prev_offset..offset]);
+				if offset != jpeg.len() {
+					data.extend(std::iter::repeat_n(0x00, CLUSTER_SIZE * clusters));
+				}
+				prev_offset = offset;
+			}
+
+			data.resize(data.len().next_multiple_of(CLUSTER_SIZE) + CLUSTER_SIZE, 0x00);
+
+			(data, fragments)
+		};
+
+		let header = Match::new(0, 0, 3);
+
+		let validate = |data: &[u8], max_gaps: Option<i64>| {
+			let file_type = FileType {
+				type_id: FileTypeId::Jpeg,
+				max_len: Some(1024 * 1024),
+				params: max_gaps.map(|max_gaps| (PARAM_MAX_SCAN_GAPS.to_string(), toml::Value::Integer(max_gaps))).into_iter().collect(),
+				..Default::default()
+			};
+
+			let file_match = MatchPair::new_sized(&file_type, &header, 1024 * 1024);
+			JpegValidator::new().validate(data, &file_match, &[ header.clone() ], CLUSTER_SIZE, &SearchlightConfig::default())
+		};
+
+		// A scan split by a single foreign cluster is reconstructed around it
+		let (data, fragments) = split(&[ CLUSTER_SIZE * 2 ], 1);
+		let info = validate(&data, Some(1));
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, fragments);
+
+		// As is one split by a run of foreign clusters, which is a single gap
+		let (data, fragments) = split(&[ CLUSTER_SIZE * 2 ], 3);
+		let info = validate(&data, Some(1));
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, fragments);
+
+		// But more gaps than allowed fail the reconstruction at the start of the first gap over the limit
+		let (data, fragments) = split(&[ CLUSTER_SIZE * 2, CLUSTER_SIZE * 4 ], 1);
+		let info = validate(&data, Some(2));
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, fragments);
+
+		// Any number of gaps are skipped over if no limit is set
+		let info = validate(&data, None);
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, fragments);
+
+		let info = validate(&data, Some(1));
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::JpegScanReconstructionFailed));
+		assert_eq!(info.fragments.last().unwrap().end, fragments[1].end);
+
+		let info = validate(&data, Some(0));
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.fragments.last().unwrap().end, fragments[0].end);
 	}
 }
\ No newline at end of file
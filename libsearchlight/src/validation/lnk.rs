@@ -0,0 +1,249 @@
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+/// The size of the ShellLinkHeader, which is also the value of its HeaderSize field
+const LNK_HEADER_SIZE: usize = 0x4c;
+/// The LinkCLSID field of the ShellLinkHeader, which is always 00021401-0000-0000-C000-000000000046
+const LNK_CLSID: [u8; 16] = [ 0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46 ];
+
+const LNK_HAS_LINK_TARGET_ID_LIST: u32 = 0x01;
+const LNK_HAS_LINK_INFO: u32 = 0x02;
+/// The flags for each of the StringData structures that may be present (name, relative path, working directory, arguments and icon
+/// location), in the order that they appear
+const LNK_STRING_DATA_FLAGS: [u32; 5] = [ 0x04, 0x08, 0x10, 0x20, 0x40 ];
+const LNK_IS_UNICODE: u32 = 0x80;
+
+/// The minimum size of a LinkInfo structure, which is the size of its header without the optional unicode offsets
+const LNK_MIN_LINK_INFO_SIZE: usize = 0x1c;
+/// ExtraData blocks with a BlockSize smaller than this are the TerminalBlock that ends the ExtraData
+const LNK_MIN_EXTRA_DATA_BLOCK_SIZE: usize = 0x04;
+
+pub struct LnkValidator;
+
+/// The outcome of walking the structures of the file
+enum LnkEnd {
+	/// The file ends at the contained index
+	At(usize),
+	/// A structure extends past the end of the available data
+	Truncated,
+	/// A structure has a size that isn't allowed by the spec
+	InvalidSize
+}
+
+impl LnkValidator {
+	pub fn new() -> Self {
+		LnkValidator
+	}
+
+	/// Reads the little-endian size field of `width` bytes at `idx`, or returns None if it extends past `max_idx`
+	fn read_size(data: &[u8], idx: usize, width: usize, max_idx: usize) -> Option<usize> {
+		if idx + width > max_idx {
+			return None;
+		}
+
+		Some(data[idx..(idx + width)].iter().rev().fold(0usize, |acc, &b| (acc << 8) | b as usize))
+	}
+
+	/// Walks the structures that follow the header at `start`, which are each present as indicated by `link_flags`, by their size fields
+	fn walk_structures(data: &[u8], start: usize, link_flags: u32, max_idx: usize) -> LnkEnd {
+		let mut i = start + LNK_HEADER_SIZE;
+
+		// The LinkTargetIDList is its size as a u16, followed by that many bytes of item IDs
+		if link_flags & LNK_HAS_LINK_TARGET_ID_LIST != 0 {
+			let Some(size) = Self::read_size(data, i, 2, max_idx) else {
+				return LnkEnd::Truncated;
+			};
+			i += 2 + size;
+		}
+
+		// The LinkInfo is its size as a u32, which includes the size field
+		if link_flags & LNK_HAS_LINK_INFO != 0 {
+			let Some(size) = Self::read_size(data, i, 4, max_idx) else {
+				return LnkEnd::Truncated;
+			};
+
+			if size < LNK_MIN_LINK_INFO_SIZE {
+				return LnkEnd::InvalidSize;
+			}
+
+			i += size;
+		}
+
+		// Each StringData is its length in characters as a u16, followed by the characters, which are 2 bytes each if the strings are unicode
+		let char_size = if link_flags & LNK_IS_UNICODE != 0 { 2 } else { 1 };
+
+		for flag in LNK_STRING_DATA_FLAGS {
+			if link_flags & flag != 0 {
+				let Some(len) = Self::read_size(data, i, 2, max_idx) else {
+					return LnkEnd::Truncated;
+				};
+				i += 2 + len * char_size;
+			}
+		}
+
+		// The ExtraData is a sequence of blocks, each starting with its size as a u32, which includes the size field, ended by a TerminalBlock
+		loop {
+			let Some(size) = Self::read_size(data, i, 4, max_idx) else {
+				return LnkEnd::Truncated;
+			};
+
+			if size < LNK_MIN_EXTRA_DATA_BLOCK_SIZE {
+				i += 4;
+				break;
+			}
+
+			i += size;
+		}
+
+		if i <= max_idx {
+			LnkEnd::At(i)
+		} else {
+			LnkEnd::Truncated
+		}
+	}
+}
+
+impl FileValidator for LnkValidator {
+	// Written using https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-shllink/16cb4ca1-9339-4d0c-a68d-bf1d6cc0f943
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+
+		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
+			(start + max_len as usize).min(file_data.len())
+		} else {
+			file_data.len()
+		};
+
+		if start + LNK_HEADER_SIZE > max_idx {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Partial,
+				fragments: vec![ (start..max_idx) ],
+				rejection_reason: Some(RejectionReason::Truncated),
+				..Default::default()
+			};
+		}
+
+		let header_size = u32::from_le_bytes(file_data[start..(start + 4)].try_into().unwrap()) as usize;
+
+		// Without a recognised header, the flags that determine which structures follow it can't be trusted
+		if header_size != LNK_HEADER_SIZE || file_data[(start + 4)..(start + 20)] != LNK_CLSID {
+			return FileValidationInfo {
+				validation_type: FileValidationType::FormatError,
+				fragments: vec![ (start..(start + LNK_HEADER_SIZE)) ],
+				rejection_reason: Some(RejectionReason::LnkInvalidHeader),
+				..Default::default()
+			};
+		}
+
+		let link_flags = u32::from_le_bytes(file_data[(start + 20)..(start + 24)].try_into().unwrap());
+
+		match Self::walk_structures(file_data, start, link_flags, max_idx) {
+			LnkEnd::At(end) => FileValidationInfo {
+				validation_type: FileValidationType::Correct,
+				fragments: vec![ (start..end) ],
+				..Default::default()
+			},
+			LnkEnd::Truncated => FileValidationInfo {
+				validation_type: FileValidationType::Partial,
+				fragments: vec![ (start..max_idx) ],
+				rejection_reason: Some(RejectionReason::Truncated),
+				..Default::default()
+			},
+			LnkEnd::InvalidSize => FileValidationInfo {
+				validation_type: FileValidationType::FormatError,
+				fragments: vec![ (start..max_idx) ],
+				rejection_reason: Some(RejectionReason::LnkInvalidStructureSize),
+				..Default::default()
+			}
+		}
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the header size and CLSID, and walks the optional structures by their size fields to the terminal ExtraData block"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason}};
+
+	use super::{LnkValidator, LNK_CLSID};
+
+	/// A shortcut with a LinkTargetIDList, a LinkInfo, unicode name and arguments strings, and an ExtraData block
+	fn synthetic_lnk() -> Vec<u8> {
+		let mut lnk = 0x4cu32.to_le_bytes().to_vec();
+		lnk.extend_from_slice(&LNK_CLSID);
+		lnk.extend_from_slice(&(0x01u32 | 0x02 | 0x04 | 0x20 | 0x80).to_le_bytes());
+		lnk.extend_from_slice(&[ 0; 52 ]);
+
+		lnk.extend_from_slice(&6u16.to_le_bytes());
+		lnk.extend_from_slice(&[ 0x22; 6 ]);
+
+		lnk.extend_from_slice(&0x20u32.to_le_bytes());
+		lnk.extend_from_slice(&[ 0x33; 0x1c ]);
+
+		for s in [ "name", "--args" ] {
+			lnk.extend_from_slice(&(s.len() as u16).to_le_bytes());
+			lnk.extend(s.encode_utf16().flat_map(|c| c.to_le_bytes()));
+		}
+
+		lnk.extend_from_slice(&0x10u32.to_le_bytes());
+		lnk.extend_from_slice(&[ 0x44; 12 ]);
+		lnk.extend_from_slice(&0u32.to_le_bytes());
+
+		lnk
+	}
+
+	#[test]
+	fn test_lnk_validator() {
+		let lnk = synthetic_lnk();
+
+		let mut data = vec![ 0xaa; 16 ];
+		data.extend_from_slice(&lnk);
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		let file_type = FileType {
+			type_id: FileTypeId::Lnk,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: 16,
+			end_idx: data.len() - 1,
+			truncated: false
+		};
+
+		let info = LnkValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ 16..(16 + lnk.len()) ]);
+
+		// Truncated before the terminal block
+		let info = LnkValidator::new().validate(&data[..(16 + lnk.len() - 2)], &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::Truncated));
+
+		// A LinkInfo that is too small to hold its header
+		let mut bad_link_info = data.clone();
+		bad_link_info[16 + 76 + 8] = 0x08;
+
+		let info = LnkValidator::new().validate(&bad_link_info, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::LnkInvalidStructureSize));
+
+		// A CLSID that isn't that of a shell link
+		let mut bad_clsid = data.clone();
+		bad_clsid[16 + 4] = 0x02;
+
+		let info = LnkValidator::new().validate(&bad_clsid, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::LnkInvalidHeader));
+		assert_eq!(info.fragments, vec![ 16..(16 + 76) ]);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,295 @@
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+const ID3V2_SIGNATURE: &[u8; 3] = b"ID3";
+const ID3V2_HEADER_SIZE: usize = 10;
+const ID3V2_FOOTER_FLAG: u8 = 0x10;
+
+const ID3V1_SIGNATURE: &[u8; 3] = b"TAG";
+const ID3V1_SIZE: usize = 128;
+
+const MP3_FRAME_HEADER_SIZE: usize = 4;
+
+/// The number of consecutive, consistent frames that need to be seen before the end of a run of frames is considered a plausible end of the file
+const MIN_CONSISTENT_FRAMES: usize = 4;
+
+/// Bitrates in kbps, indexed by \[version/layer combination\]\[bitrate index\]. Index 0 is "free" and 15 is invalid, both of which are not supported.
+/// Combinations are: MPEG-1 Layer I, MPEG-1 Layer II, MPEG-1 Layer III, MPEG-2/2.5 Layer I, MPEG-2/2.5 Layer II & III
+const MP3_BITRATES: [[u32; 16]; 5] = [
+	[ 0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448, 0 ],
+	[ 0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 0 ],
+	[ 0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 0 ],
+	[ 0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256, 0 ],
+	[ 0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 0 ],
+];
+
+/// Sample rates in Hz, indexed by \[version\]\[sample rate index\], where version is 0 for MPEG-1, 1 for MPEG-2, and 2 for MPEG-2.5
+const MP3_SAMPLE_RATES: [[u32; 3]; 3] = [
+	[ 44100, 48000, 32000 ],
+	[ 22050, 24000, 16000 ],
+	[ 11025, 12000, 8000 ],
+];
+
+pub struct Mp3Validator;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum MpegVersion {
+	Mpeg1,
+	Mpeg2,
+	Mpeg25
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum MpegLayer {
+	Layer1,
+	Layer2,
+	Layer3
+}
+
+#[derive(Debug)]
+struct FrameHeader {
+	version: MpegVersion,
+	layer: MpegLayer,
+	sample_rate: u32,
+	/// The length of the whole frame, including the header
+	len: usize
+}
+
+enum FrameHeaderDecodeError {
+	/// There is no frame sync at this position
+	NoSync,
+	/// There is a frame sync but the header contains reserved or unsupported values
+	Invalid
+}
+
+impl FrameHeader {
+	/// Decodes a MPEG audio frame header, calculating the length of the frame from the bitrate, sample rate and padding
+	fn decode(data: &[u8]) -> Result<Self, FrameHeaderDecodeError> {
+		if data[0] != 0xff || (data[1] & 0xe0) != 0xe0 {
+			return Err(FrameHeaderDecodeError::NoSync);
+		}
+
+		let version = match (data[1] >> 3) & 0b11 {
+			0b00 => MpegVersion::Mpeg25,
+			0b10 => MpegVersion::Mpeg2,
+			0b11 => MpegVersion::Mpeg1,
+			_ => return Err(FrameHeaderDecodeError::Invalid)
+		};
+
+		let layer = match (data[1] >> 1) & 0b11 {
+			0b01 => MpegLayer::Layer3,
+			0b10 => MpegLayer::Layer2,
+			0b11 => MpegLayer::Layer1,
+			_ => return Err(FrameHeaderDecodeError::Invalid)
+		};
+
+		let bitrate_idx = (data[2] >> 4) as usize;
+		let sample_rate_idx = ((data[2] >> 2) & 0b11) as usize;
+		let padding = ((data[2] >> 1) & 0b1) as usize;
+
+		if sample_rate_idx == 3 {
+			return Err(FrameHeaderDecodeError::Invalid);
+		}
+
+		let bitrates = match (version, layer) {
+			(MpegVersion::Mpeg1, MpegLayer::Layer1) => &MP3_BITRATES[0],
+			(MpegVersion::Mpeg1, MpegLayer::Layer2) => &MP3_BITRATES[1],
+			(MpegVersion::Mpeg1, MpegLayer::Layer3) => &MP3_BITRATES[2],
+			(_, MpegLayer::Layer1) => &MP3_BITRATES[3],
+			(_, _) => &MP3_BITRATES[4],
+		};
+
+		// Free-format (0) bitrates are not supported as the frame length cannot be calculated from the header alone
+		let bitrate = bitrates[bitrate_idx] * 1000;
+		if bitrate == 0 {
+			return Err(FrameHeaderDecodeError::Invalid);
+		}
+
+		let sample_rate = match version {
+			MpegVersion::Mpeg1 => MP3_SAMPLE_RATES[0][sample_rate_idx],
+			MpegVersion::Mpeg2 => MP3_SAMPLE_RATES[1][sample_rate_idx],
+			MpegVersion::Mpeg25 => MP3_SAMPLE_RATES[2][sample_rate_idx],
+		};
+
+		let len = match (version, layer) {
+			(_, MpegLayer::Layer1) => ((12 * bitrate / sample_rate) as usize + padding) * 4,
+			(MpegVersion::Mpeg1, _) | (_, MpegLayer::Layer2) => (144 * bitrate / sample_rate) as usize + padding,
+			(_, MpegLayer::Layer3) => (72 * bitrate / sample_rate) as usize + padding,
+		};
+
+		Ok(FrameHeader {
+			version,
+			layer,
+			sample_rate,
+			len
+		})
+	}
+
+	/// Whether this frame header could belong to the same stream as `other`
+	fn consistent_with(&self, other: &FrameHeader) -> bool {
+		self.version == other.version && self.layer == other.layer && self.sample_rate == other.sample_rate
+	}
+}
+
+impl Mp3Validator {
+	pub fn new() -> Self {
+		Mp3Validator
+	}
+
+	/// Returns the total length of the ID3v2 tag at the start of `data`, including the header and footer (if present), by decoding the synchsafe size
+	fn id3v2_len(data: &[u8]) -> usize {
+		let flags = data[5];
+		let size = data[6..10].iter().fold(0usize, |acc, &b| (acc << 7) | (b & 0x7f) as usize);
+
+		ID3V2_HEADER_SIZE + size + if flags & ID3V2_FOOTER_FLAG != 0 { ID3V2_HEADER_SIZE } else { 0 }
+	}
+}
+
+impl FileValidator for Mp3Validator {
+	// Written using https://id3.org/id3v2.4.0-structure, https://id3.org/ID3v1 and http://www.mp3-tech.org/programmer/frame_header.html
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+
+		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
+			(start + max_len as usize).min(file_data.len())
+		} else {
+			file_data.len()
+		};
+
+		let mut i = start;
+
+		// Skip over the ID3v2 tag, if present
+		if i + ID3V2_HEADER_SIZE <= max_idx && &file_data[i..(i + 3)] == ID3V2_SIGNATURE {
+			i += Self::id3v2_len(&file_data[i..]);
+
+			if i > max_idx {
+				return FileValidationInfo {
+					validation_type: FileValidationType::Partial,
+					fragments: vec![ (start..max_idx) ],
+					rejection_reason: Some(RejectionReason::Truncated),
+					..Default::default()
+				};
+			}
+		}
+
+		let mut first_frame: Option<FrameHeader> = None;
+		let mut num_frames = 0;
+
+		let (validation_type, rejection_reason) = loop {
+			if i + MP3_FRAME_HEADER_SIZE > max_idx {
+				// We've reached the end of the available data while still reading frames, so the file is probably truncated
+				break if num_frames > 0 {
+					(FileValidationType::Partial, Some(RejectionReason::Truncated))
+				} else {
+					(FileValidationType::Unrecognised, Some(RejectionReason::Mp3NoFrames))
+				};
+			}
+
+			match FrameHeader::decode(&file_data[i..]) {
+				Ok(header) if first_frame.as_ref().is_none_or(|first| header.consistent_with(first)) => {
+					if i + header.len > max_idx {
+						i = max_idx;
+						break (FileValidationType::Partial, Some(RejectionReason::Truncated));
+					}
+
+					i += header.len;
+					num_frames += 1;
+
+					if first_frame.is_none() {
+						first_frame = Some(header);
+					}
+				}
+				Ok(_) | Err(FrameHeaderDecodeError::NoSync) => {
+					// The run of frames has ended, so this is either the end of the file or something has gone wrong, which we decide based on
+					// the number of frames that have been seen
+					if i + ID3V1_SIZE <= max_idx && &file_data[i..(i + 3)] == ID3V1_SIGNATURE {
+						i += ID3V1_SIZE;
+					}
+
+					break if num_frames >= MIN_CONSISTENT_FRAMES {
+						(FileValidationType::Correct, None)
+					} else if num_frames > 0 {
+						(FileValidationType::Partial, Some(RejectionReason::Mp3TooFewFrames))
+					} else {
+						(FileValidationType::Unrecognised, Some(RejectionReason::Mp3NoFrames))
+					};
+				}
+				Err(FrameHeaderDecodeError::Invalid) => {
+					break if num_frames > 0 {
+						(FileValidationType::FormatError, Some(RejectionReason::Mp3InvalidFrameHeader))
+					} else {
+						(FileValidationType::Unrecognised, Some(RejectionReason::Mp3NoFrames))
+					};
+				}
+			}
+		};
+
+		FileValidationInfo {
+			validation_type,
+			fragments: if i > start { vec![ (start..i) ] } else { Vec::new() },
+			rejection_reason,
+			..Default::default()
+		}
+	}
+
+	fn description(&self) -> &'static str {
+		"Skips any ID3v2 tag and checks for a run of consistent MPEG audio frame headers, ending at an ID3v1 tag or the end of the frames"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason}};
+
+	use super::Mp3Validator;
+
+	#[test]
+	fn test_mp3_validator() {
+		// MPEG-1 Layer III, 128 kbps, 44100 Hz, no padding: 144 * 128000 / 44100 = 417 bytes per frame
+		let frame_header = [ 0xff, 0xfb, 0x90, 0x00 ];
+		let frame_len = 417;
+
+		let mut data = vec![ 0xaa; 16 ];
+		let start = data.len();
+
+		// ID3v2 tag with a synchsafe size of 0x81 (129)
+		data.extend_from_slice(b"ID3\x04\x00\x00\x00\x00\x01\x01");
+		data.extend_from_slice(&[ 0; 129 ]);
+
+		for _ in 0..6 {
+			data.extend_from_slice(&frame_header);
+			data.extend_from_slice(&vec![ 0x55; frame_len - 4 ]);
+		}
+
+		data.extend_from_slice(b"TAG");
+		data.extend_from_slice(&[ 0; 125 ]);
+		let end = data.len();
+
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		let file_type = FileType {
+			type_id: FileTypeId::Mp3,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: start,
+			end_idx: start + 1024 * 1024,
+			truncated: false
+		};
+
+		let info = Mp3Validator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ (start..end) ]);
+
+		// Truncate the last frame
+		let info = Mp3Validator::new().validate(&data[..(end - 200)], &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::Truncated));
+	}
+}
\ No newline at end of file
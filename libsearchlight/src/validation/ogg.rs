@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::{FileTypeId, SearchlightConfig}};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason, METADATA_CODEC};
+
+const OGG_CAPTURE_PATTERN: &[u8; 4] = b"OggS";
+/// The length of a page header up to its segment table, which has an entry (the length of the segment) for each segment of the page
+const OGG_PAGE_HEADER_LEN: usize = 27;
+/// The offset of the CRC in a page header, which is taken as zero when calculating the CRC
+const OGG_CRC_OFFSET: usize = 22;
+
+const OGG_FLAG_CONTINUED: u8 = 0x01;
+const OGG_FLAG_BOS: u8 = 0x02;
+const OGG_FLAG_EOS: u8 = 0x04;
+
+/// The table of the CRC-32 of Ogg pages, which has the usual polynomial (0x04c11db7), but unlike the usual CRC-32 is not reflected, and has
+/// no initial value or final XOR
+const OGG_CRC_TABLE: [u32; 256] = {
+	let mut table = [0u32; 256];
+	let mut i = 0;
+
+	while i < 256 {
+		let mut crc = (i as u32) << 24;
+		let mut bit = 0;
+
+		while bit < 8 {
+			crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 };
+			bit += 1;
+		}
+
+		table[i] = crc;
+		i += 1;
+	}
+
+	table
+};
+
+/// A codec of the logical bitstreams in Ogg files, recognised by its identification header, which is the only packet in the first page of a
+/// stream, and its comment header, which starts the second page. Other codecs (e.g. Vorbis or Theora) are recognised by adding them to
+/// `OGG_CODECS`
+struct OggCodec {
+	/// The name of the codec, which is recorded in the metadata of the files recognised
+	name: &'static str,
+	/// The file type ID of files of this codec. Candidates of a type other than the generic Ogg type must be of the codec of their type
+	type_id: FileTypeId,
+	id_header_magic: &'static [u8],
+	comment_header_magic: &'static [u8],
+	/// Checks the fields of the identification header (which starts with `id_header_magic`), e.g. the version
+	valid_id_header: fn(&[u8]) -> bool
+}
+
+const OGG_CODECS: &[OggCodec] = &[
+	OggCodec {
+		name: "opus",
+		type_id: FileTypeId::Opus,
+		id_header_magic: b"OpusHead",
+		comment_header_magic: b"OpusTags",
+		valid_id_header: valid_opus_id_header
+	}
+];
+
+/// Checks an Opus identification header (RFC 7845 section 5.1): the major version (the upper 4 bits of the version) must be 0, there must be
+/// at least one channel (and at most 2 for channel mapping family 0), and the channel mapping table must be present for other families
+fn valid_opus_id_header(header: &[u8]) -> bool {
+	if header.len() < 19 {
+		return false;
+	}
+
+	let (version, channels, mapping_family) = (header[8], header[9] as usize, header[18]);
+
+	version >> 4 == 0 && channels > 0 && match mapping_family {
+		0 => channels <= 2,
+		_ => header.len() >= 21 + channels
+	}
+}
+
+/// Calculates the CRC of the Ogg page `page`, with its CRC field taken as zero
+fn ogg_crc(page: &[u8]) -> u32 {
+	page.iter().enumerate().fold(0u32, |crc, (i, &b)| {
+		let b = if (OGG_CRC_OFFSET..(OGG_CRC_OFFSET + 4)).contains(&i) { 0 } else { b };
+		(crc << 8) ^ OGG_CRC_TABLE[((crc >> 24) as u8 ^ b) as usize]
+	})
+}
+
+#[derive(Debug, PartialEq)]
+enum OggPageError {
+	/// The page extends past the end of the data
+	Truncated,
+	/// There is no page at the offset, or it has an unsupported version
+	Invalid
+}
+
+struct OggPage {
+	offset: usize,
+	header_type: u8,
+	serial: u32,
+	sequence: u32,
+	crc: u32,
+	data_start: usize,
+	end: usize
+}
+
+impl OggPage {
+	/// Parses the page at `offset` in `data`, which must end before `max_idx`
+	fn parse(data: &[u8], offset: usize, max_idx: usize) -> Result<OggPage, OggPageError> {
+		if offset + OGG_PAGE_HEADER_LEN > max_idx {
+			return Err(OggPageError::Truncated);
+		}
+
+		let header = &data[offset..(offset + OGG_PAGE_HEADER_LEN)];
+
+		if &header[0..4] != OGG_CAPTURE_PATTERN || header[4] != 0 {
+			return Err(OggPageError::Invalid);
+		}
+
+		let num_segments = header[26] as usize;
+		let data_start = offset + OGG_PAGE_HEADER_LEN + num_segments;
+
+		if data_start > max_idx {
+			return Err(OggPageError::Truncated);
+		}
+
+		let end = data_start + data[(offset + OGG_PAGE_HEADER_LEN)..data_start].iter().map(|&len| len as usize).sum::<usize>();
+
+		if end > max_idx {
+			return Err(OggPageError::Truncated);
+		}
+
+		Ok(OggPage {
+			offset,
+			header_type: header[5],
+			serial: u32::from_le_bytes(header[14..18].try_into().unwrap()),
+			sequence: u32::from_le_bytes(header[18..22].try_into().unwrap()),
+			crc: u32::from_le_bytes(header[22..26].try_into().unwrap()),
+			data_start,
+			end
+		})
+	}
+
+	/// Whether the page consists of exactly one packet, i.e. its last segment is the only one shorter than 255 bytes
+	fn is_single_packet(&self, data: &[u8]) -> bool {
+		let lacing = &data[(self.offset + OGG_PAGE_HEADER_LEN)..self.data_start];
+
+		lacing.last().is_some_and(|&len| len < 255) && lacing[..(lacing.len() - 1)].iter().all(|&len| len == 255)
+	}
+}
+
+pub struct OggValidator;
+
+impl OggValidator {
+	pub fn new() -> Self {
+		OggValidator
+	}
+}
+
+impl FileValidator for OggValidator {
+	// Written using RFC 3533 (The Ogg Encapsulation Format) and RFC 7845 (Ogg Encapsulation for the Opus Audio Codec)
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+
+		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
+			(start + max_len as usize).min(file_data.len())
+		} else {
+			file_data.len()
+		};
+
+		let format_error = |reason: RejectionReason| FileValidationInfo {
+			validation_type: FileValidationType::FormatError,
+			fragments: vec![ (start..max_idx) ],
+			rejection_reason: Some(reason),
+			..Default::default()
+		};
+
+		// The pages up to `end` are intact, but the rest of the stream is missing or not where it should be, e.g. due to fragmentation
+		let partial = |end: usize, reason: RejectionReason| FileValidationInfo {
+			validation_type: FileValidationType::Partial,
+			fragments: vec![ (start..end) ],
+			rejection_reason: Some(reason),
+			..Default::default()
+		};
+
+		let first_page = match OggPage::parse(file_data, start, max_idx) {
+			Ok(page) => page,
+			Err(OggPageError::Truncated) => return partial(max_idx, RejectionReason::Truncated),
+			Err(OggPageError::Invalid) => return format_error(RejectionReason::OggInvalidPage)
+		};
+
+		// The first page of a stream begins it, and contains only the identification header of its codec
+		let id_header = &file_data[first_page.data_start..first_page.end];
+
+		let codec = match OGG_CODECS.iter().find(|codec| id_header.starts_with(codec.id_header_magic)) {
+			Some(codec) if first_page.header_type & OGG_FLAG_BOS != 0 && first_page.header_type & OGG_FLAG_CONTINUED == 0 => codec,
+			_ => return format_error(RejectionReason::OggUnrecognisedCodec)
+		};
+
+		if file_match.file_type.type_id != FileTypeId::Ogg && file_match.file_type.type_id != codec.type_id {
+			return format_error(RejectionReason::OggCodecMismatch);
+		}
+
+		if !first_page.is_single_packet(file_data) || !(codec.valid_id_header)(id_header) {
+			return format_error(RejectionReason::OggInvalidCodecHeader);
+		}
+
+		let verify_crc = file_match.file_type.should_verify_crc();
+		let mut crc_mismatch = verify_crc && ogg_crc(&file_data[start..first_page.end]) != first_page.crc;
+
+		// The streams that have begun but not ended, which are multiplexed with the first if there are several
+		let mut open_streams = vec![ first_page.serial ];
+		let mut seen_comment_header = false;
+		let mut idx = first_page.end;
+
+		while !open_streams.is_empty() {
+			let page = match OggPage::parse(file_data, idx, max_idx) {
+				Ok(page) => page,
+				Err(OggPageError::Truncated) => return partial(max_idx, RejectionReason::Truncated),
+				Err(OggPageError::Invalid) if seen_comment_header => return partial(idx, RejectionReason::OggInvalidPage),
+				Err(OggPageError::Invalid) => return format_error(RejectionReason::OggInvalidCodecHeader)
+			};
+
+			if page.header_type & OGG_FLAG_BOS != 0 {
+				if open_streams.contains(&page.serial) {
+					return partial(idx, RejectionReason::OggInvalidPage);
+				}
+
+				open_streams.push(page.serial);
+			} else if !open_streams.contains(&page.serial) {
+				// A page of a stream that hasn't begun is most likely from another file
+				return partial(idx, RejectionReason::OggInvalidPage);
+			}
+
+			// The second page of the first stream starts with the comment header
+			if page.serial == first_page.serial && !seen_comment_header {
+				if page.sequence != first_page.sequence.wrapping_add(1) || !file_data[page.data_start..page.end].starts_with(codec.comment_header_magic) {
+					return format_error(RejectionReason::OggInvalidCodecHeader);
+				}
+
+				seen_comment_header = true;
+			}
+
+			crc_mismatch |= verify_crc && ogg_crc(&file_data[page.offset..page.end]) != page.crc;
+
+			if page.header_type & OGG_FLAG_EOS != 0 {
+				open_streams.retain(|&serial| serial != page.serial);
+			}
+
+			idx = page.end;
+		}
+
+		if !seen_comment_header {
+			return format_error(RejectionReason::OggInvalidCodecHeader);
+		}
+
+		FileValidationInfo {
+			validation_type: if crc_mismatch { FileValidationType::Corrupt } else { FileValidationType::Correct },
+			fragments: vec![ (start..idx) ],
+			rejection_reason: crc_mismatch.then_some(RejectionReason::OggChecksumMismatch),
+			metadata: HashMap::from([ (METADATA_CODEC.to_string(), codec.name.to_string()) ]),
+			..Default::default()
+		}
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the codec identification and comment headers (Opus), and walks the pages to the end of the stream, verifying their CRCs"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason, METADATA_CODEC}};
+
+	use super::{ogg_crc, OggValidator};
+
+	fn ogg_page(header_type: u8, serial: u32, sequence: u32, data: &[u8]) -> Vec<u8> {
+		assert!(data.len() < 255);
+
+		let mut page = b"OggS\x00".to_vec();
+		page.push(header_type);
+		page.extend_from_slice(&0u64.to_le_bytes());
+		page.extend_from_slice(&serial.to_le_bytes());
+		page.extend_from_slice(&sequence.to_le_bytes());
+		page.extend_from_slice(&[ 0; 4 ]);
+		page.extend_from_slice(&[ 1, data.len() as u8 ]);
+		page.extend_from_slice(data);
+
+		let crc = ogg_crc(&page);
+		page[22..26].copy_from_slice(&crc.to_le_bytes());
+
+		page
+	}
+
+	/// An Ogg Opus file with an identification header, comment header and two pages of audio, the last of which ends the stream
+	fn synthetic_opus() -> Vec<u8> {
+		let mut opus = ogg_page(0x02, 7, 0, b"OpusHead\x01\x02\x38\x01\x80\xbb\x00\x00\x00\x00\x00");
+		opus.extend(ogg_page(0x00, 7, 1, b"OpusTags\x08\x00\x00\x00searchlt\x00\x00\x00\x00"));
+		opus.extend(ogg_page(0x00, 7, 2, &[ 0x5a; 100 ]));
+		opus.extend(ogg_page(0x04, 7, 3, &[ 0x5a; 60 ]));
+		opus
+	}
+
+	fn validate(data: &[u8], type_id: FileTypeId) -> FileValidationInfo {
+		let file_type = FileType {
+			type_id,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: 16,
+			end_idx: data.len() - 1,
+			truncated: false
+		};
+
+		OggValidator::new().validate(data, &file_match, &[], 1, &SearchlightConfig::default())
+	}
+
+	#[test]
+	fn test_ogg_validator() {
+		let opus = synthetic_opus();
+
+		let mut data = vec![ 0xaa; 16 ];
+		data.extend_from_slice(&opus);
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		// Recognised as Opus by both the generic Ogg type and the Opus type, ending at the end of the stream
+		for type_id in [ FileTypeId::Ogg, FileTypeId::Opus ] {
+			let info = validate(&data, type_id);
+
+			assert_eq!(info.validation_type, FileValidationType::Correct);
+			assert_eq!(info.fragments, vec![ 16..(16 + opus.len()) ]);
+			assert_eq!(info.metadata.get(METADATA_CODEC).map(|codec| codec.as_str()), Some("opus"));
+		}
+
+		// Truncated within the audio pages
+		let info = validate(&data[..(16 + opus.len() - 20)], FileTypeId::Opus);
+		assert_eq!((info.validation_type, info.rejection_reason), (FileValidationType::Partial, Some(RejectionReason::Truncated)));
+
+		// A first page that isn't a recognised codec header, e.g. that of Vorbis
+		let mut vorbis = vec![ 0xaa; 16 ];
+		vorbis.extend(ogg_page(0x02, 7, 0, b"\x01vorbis\x00\x00\x00\x00\x02\x44\xac\x00\x00"));
+		vorbis.extend_from_slice(&opus[47..]);
+		let info = validate(&vorbis, FileTypeId::Ogg);
+		assert_eq!((info.validation_type, info.rejection_reason), (FileValidationType::FormatError, Some(RejectionReason::OggUnrecognisedCodec)));
+
+		// An Opus identification header with an unsupported major version
+		let mut bad_version = vec![ 0xaa; 16 ];
+		bad_version.extend(ogg_page(0x02, 7, 0, b"OpusHead\x10\x02\x38\x01\x80\xbb\x00\x00\x00\x00\x00"));
+		bad_version.extend_from_slice(&opus[47..]);
+		assert_eq!(validate(&bad_version, FileTypeId::Opus).rejection_reason, Some(RejectionReason::OggInvalidCodecHeader));
+
+		// No comment header in the second page
+		let mut no_tags = data.clone();
+		no_tags[(16 + 47 + 28)..(16 + 47 + 36)].copy_from_slice(b"OpusTagz");
+		assert_eq!(validate(&no_tags, FileTypeId::Opus).rejection_reason, Some(RejectionReason::OggInvalidCodecHeader));
+
+		// Damaged audio data is detected by the page CRC
+		let mut damaged = data.clone();
+		damaged[16 + opus.len() - 10] ^= 0xff;
+		let info = validate(&damaged, FileTypeId::Opus);
+		assert_eq!((info.validation_type, info.rejection_reason), (FileValidationType::Corrupt, Some(RejectionReason::OggChecksumMismatch)));
+
+		// A page that is not where the previous one ends, e.g. due to fragmentation
+		let audio_page_idx = 16 + 47 + 52;
+		let mut lost_sync = data.clone();
+		lost_sync[audio_page_idx] = 0;
+		let info = validate(&lost_sync, FileTypeId::Opus);
+		assert_eq!((info.validation_type, info.fragments, info.rejection_reason), (FileValidationType::Partial, vec![ 16..audio_page_idx ], Some(RejectionReason::OggInvalidPage)));
+	}
+}
\ No newline at end of file
@@ -1,9 +1,12 @@
-use crate::{search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig, utils::{self, fragments_index::FragmentsIndex}};
+use std::collections::{HashMap, HashSet};
 
-use super::{FileValidationInfo, FileValidationType, FileValidator, Fragment};
+use crate::{search::{match_id_hash_slice_u16, pairing::MatchPair, Match}, searchlight::config::{FileTypeId, SearchlightConfig}, utils::{self, fragments_index::FragmentsIndex}};
 
-// List of known PNG chunks. Source: https://github.com/ImageMagick/ImageMagick/blob/main/coders/png.c
-const PNG_CHUNK_TYPES: [u32; 50] = [
+use super::{max_reconstruction_search_len, FileValidationInfo, FileValidationType, FileValidator, Fragment, RejectionReason, METADATA_MODIFIED_TIME, PARAM_BOUND_RECONSTRUCTION_BY_HEADERS, PARAM_MAX_CHUNK_LEN, PARAM_MAX_RECONSTRUCTION_SEARCH_LEN, PARAM_MAX_SKIPPED_CHUNKS, PARAM_SPEC_CHUNK_TYPES};
+
+// List of known PNG chunks. Source: https://github.com/ImageMagick/ImageMagick/blob/main/coders/png.c, plus the APNG chunks (acTL, fcTL, fdAT)
+const PNG_CHUNK_TYPES: [u32; 53] = [
+	u32::from_be_bytes(*b"acTL"),
 	u32::from_be_bytes(*b"BACK"),
 	u32::from_be_bytes(*b"BASI"),
 	u32::from_be_bytes(*b"bKGD"),
@@ -16,6 +19,8 @@ const PNG_CHUNK_TYPES: [u32; 50] = [
 	u32::from_be_bytes(*b"DISC"),
 	u32::from_be_bytes(*b"ENDL"),
 	u32::from_be_bytes(*b"eXIf"),
+	u32::from_be_bytes(*b"fcTL"),
+	u32::from_be_bytes(*b"fdAT"),
 	u32::from_be_bytes(*b"FRAM"),
 	u32::from_be_bytes(*b"gAMA"),
 	u32::from_be_bytes(*b"hIST"),
@@ -57,13 +62,24 @@ const PNG_CHUNK_TYPES: [u32; 50] = [
 ];
 
 // Some particular PNG chunks
+const PNG_SIGNATURE: [u8; 8] = [ 0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a ];
+
 const PNG_IHDR: u32 = 0x49484452; // "IHDR" as u32
 const PNG_IDAT: u32 = 0x49444154; // "IDAT" as u32
 const PNG_PLTE: u32 = 0x504C5445; // "PLTE" as u32
 const PNG_IEND: u32 = 0x49454E44; // "IEND" as u32
+const PNG_TIME: u32 = 0x74494D45; // "tIME" as u32
+const PNG_FDAT: u32 = 0x66644154; // "fdAT" as u32
 
 const PNG_IHDR_LEN: u32 = 13;
+const PNG_TIME_LEN: usize = 7;
+
+/// The default maximum length of the data of a chunk (or the max_len of the file type, if less), see `PARAM_MAX_CHUNK_LEN`. The PNG format
+/// allows chunks of up to 2^31 - 1 bytes, but in practice they are much smaller, as encoders split image data across multiple IDAT chunks
+pub const DEFAULT_MAX_CHUNK_LEN: u64 = 64 * 1024 * 1024;
 
+/// Understands the validator parameters `PARAM_MAX_RECONSTRUCTION_SEARCH_LEN`, `PARAM_BOUND_RECONSTRUCTION_BY_HEADERS`, `PARAM_MAX_CHUNK_LEN`,
+/// `PARAM_MAX_SKIPPED_CHUNKS` and `PARAM_SPEC_CHUNK_TYPES`
 pub struct PngValidator;
 
 struct ChunkValidationInfo {
@@ -71,6 +87,8 @@ struct ChunkValidationInfo {
 	chunk_type: u32,
 	chunk_frags: Vec<Fragment>,
 	next_chunk_idx: Option<usize>,
+	/// Why the chunk was not validated, if that isn't apparent from the validation type
+	rejection_reason: Option<RejectionReason>
 }
 
 impl ChunkValidationInfo {
@@ -81,7 +99,8 @@ impl ChunkValidationInfo {
 			validation_type,
 			chunk_type,
 			chunk_frags: vec![chunk_idx..next_chunk_idx],
-			next_chunk_idx: if should_continue { Some(chunk_idx + 12 + data_len as usize) } else { None }
+			next_chunk_idx: if should_continue { Some(chunk_idx + 12 + data_len as usize) } else { None },
+			rejection_reason: None
 		}
 	}
 
@@ -90,7 +109,8 @@ impl ChunkValidationInfo {
 			validation_type,
 			chunk_type,
 			chunk_frags: fragments,
-			next_chunk_idx
+			next_chunk_idx,
+			rejection_reason: None
 		}
 	}
 }
@@ -110,7 +130,12 @@ impl PngValidator {
 
 	/// Validates and reconstructs PNG chunk at `chunk_idx` in `file_data`, where `file_data` has a cluster size of `cluster_size`, so files can be assumed
 	/// to be allocated in blocks of `cluster_size`. `chunk_idx` refers to the very start of a chunk, where a chunk is \[`len`\]\[`type`\]\[`data`\]\[`crc`\].
-	fn validate_chunk(requires_plte: &mut bool, plte_forbidden: &mut bool, file_data: &[u8], chunk_idx: usize, cluster_size: usize, max_search_len: usize) -> ChunkValidationInfo {
+	/// If `verify_crc` is false, chunks with a CRC mismatch are not reconstructed, but instead assumed to be unfragmented and returned as Partial.
+	/// If `next_header_idx` is given, reconstruction doesn't search for the next chunk past it, see `reconstruct_chunk`. Chunks with a data length
+	/// over `max_chunk_len` are assumed to have a corrupt length, and are returned as Partial without being read or reconstructed. Chunk types
+	/// are checked as by `validate_chunk_type`, with `spec_chunk_types`
+	#[allow(clippy::too_many_arguments)]
+	fn validate_chunk(requires_plte: &mut bool, plte_forbidden: &mut bool, file_data: &[u8], chunk_idx: usize, cluster_size: usize, max_search_len: usize, next_header_idx: Option<usize>, verify_crc: bool, max_chunk_len: usize, spec_chunk_types: bool) -> ChunkValidationInfo {
 		/// Macro to make extracting fields a bit more readable: file_data[(chunk_idx + 4)..(chunk_idx + 8)] -> chunk_data[4, 8]
 		macro_rules! chunk_data {
 			[$start: expr, $end: expr] => {
@@ -121,7 +146,21 @@ impl PngValidator {
 		let chunk_data_len = u32::from_be_bytes(chunk_data![0, 4].try_into().unwrap());
 		let chunk_type = u32::from_be_bytes(chunk_data![4, 8].try_into().unwrap());
 
-		let chunk_type_valid = Self::validate_chunk_type(&chunk_data![4, 8]);
+		let chunk_type_valid = Self::validate_chunk_type(&chunk_data![4, 8], spec_chunk_types);
+
+		// A bogus length would otherwise have the CRC calculated over, and reconstruction search through, a great deal of unrelated data
+		if chunk_type_valid && chunk_data_len as usize > max_chunk_len {
+			return ChunkValidationInfo {
+				rejection_reason: Some(RejectionReason::PngChunkTooLong),
+				..ChunkValidationInfo::new_unfragmented(
+					FileValidationType::Partial,
+					chunk_type,
+					chunk_idx,
+					0,
+					false
+				)
+			};
+		}
 
 		if !chunk_type_valid || chunk_idx + chunk_data_len as usize + 12 > file_data.len() {
 			// trace!("Chunk unrecognised: type {chunk_type}")
@@ -158,8 +197,19 @@ impl PngValidator {
 				);
 			}
 
+			// If we're not verifying CRCs, skip reconstruction and carry on from just after the chunk as if it were unfragmented
+			if !verify_crc {
+				return ChunkValidationInfo::new_unfragmented(
+					FileValidationType::Partial,
+					chunk_type,
+					chunk_idx,
+					chunk_data_len,
+					true
+				);
+			}
+
 			// Attempt to reconstruct the chunk
-			let recons_info = Self::reconstruct_chunk(file_data, chunk_idx, chunk_data_len as usize, cluster_size, max_search_len);
+			let recons_info = Self::reconstruct_chunk(file_data, chunk_idx, chunk_data_len as usize, cluster_size, max_search_len, next_header_idx, spec_chunk_types);
 
 			match recons_info {
 				ChunkReconstructionInfo::Failure => {
@@ -205,19 +255,46 @@ impl PngValidator {
 		)
 	}
 
+	/// Decodes a tIME chunk, which holds the UTC time of the last modification of the image, to seconds since the Unix epoch
+	fn decode_time_chunk(chunk: FragmentsIndex) -> Option<i64> {
+		if chunk.len() != PNG_TIME_LEN + 12 {
+			return None;
+		}
+
+		// Skip the length and chunk type
+		let data = |i: usize| chunk[8 + i];
+
+		let year = u16::from_be_bytes([ data(0), data(1) ]) as i64;
+
+		utils::unix_timestamp(year, data(2) as u32, data(3) as u32, data(4) as u32, data(5) as u32, data(6) as u32)
+	}
+
 	/// Attempts to reconstruct a fragmented PNG chunk, assuming that the length, chunk type, and CRC are not fragmented and that all
 	/// fragments of the chunk are in-order (limitations) by searching forwards for a valid chunk type, decoding the CRC that should occur just before it,
-	/// and enumerating some possible cluster arrangements between the start of the chunk data and the decoded CRC for a matching calculated CRC
-	fn reconstruct_chunk(file_data: &[u8], chunk_idx: usize, chunk_data_len: usize, cluster_size: usize, max_search_len: usize) -> ChunkReconstructionInfo {
+	/// and enumerating some possible cluster arrangements between the start of the chunk data and the decoded CRC for a matching calculated CRC.
+	/// If `next_header_idx` is given, the search for the next chunk type stops at the header there, as the data after it most likely belongs to
+	/// another file, otherwise it continues until the end of the data or `max_search_len`
+	fn reconstruct_chunk(file_data: &[u8], chunk_idx: usize, chunk_data_len: usize, cluster_size: usize, max_search_len: usize, next_header_idx: Option<usize>, spec_chunk_types: bool) -> ChunkReconstructionInfo {
 		let unfrag_crc_offset = chunk_idx + chunk_data_len + 8;
 
 		let mut next_chunk_type_offset = unfrag_crc_offset + 8;
 
+		// The next chunk (and the CRC before it) can't be past the start of another file
+		let header_limit = next_header_idx.unwrap_or(usize::MAX);
+
 		// Find the next valid chunk type
-		// NOTE: Currently, we're checking against a list of known valid chunk types. This can't be exhaustive though so will miss valid chunks
+		// NOTE: Unless spec_chunk_types is set, we're checking against a list of known valid chunk types. This can't be exhaustive though so will miss valid chunks
 		//       Perhaps an alternative method that could stop text files being counted be checking that the CRC and length are not ASCII (alphabetical?)?
 		//       Course, they may be in a valid file, but are unlikely to be
-		while !Self::validate_chunk_type(&file_data[next_chunk_type_offset..(next_chunk_type_offset + 4)]) {
+		loop {
+			if next_chunk_type_offset + 4 > header_limit {
+				return ChunkReconstructionInfo::Failure;
+			}
+
+			if Self::validate_chunk_type(&file_data[next_chunk_type_offset..(next_chunk_type_offset + 4)], spec_chunk_types) {
+				break;
+			}
+
 			next_chunk_type_offset += cluster_size as usize;
 
 			// If we're now out of bounds (or will be upon attempting to read the chunk data len) then return with failure
@@ -279,11 +356,34 @@ impl PngValidator {
 		}
 	}
 
+	/// Finds the start of the first intact chunk, i.e. one of a known type whose CRC matches, at or after `from` and ending before `limit`,
+	/// for skipping over unrecognised data where a chunk was expected. Chunk types are checked as by `validate_chunk_type`, with `spec_chunk_types`
+	fn find_next_chunk(file_data: &[u8], from: usize, limit: usize, spec_chunk_types: bool) -> Option<usize> {
+		let limit = limit.min(file_data.len());
+
+		(from..limit).take_while(|&idx| idx + 12 <= limit).find(|&idx| {
+			if !Self::validate_chunk_type(&file_data[(idx + 4)..(idx + 8)], spec_chunk_types) {
+				return false;
+			}
+
+			let chunk_data_len = u32::from_be_bytes(file_data[idx..(idx + 4)].try_into().unwrap()) as usize;
+			let crc_offset = idx + 8 + chunk_data_len;
+
+			crc_offset + 4 <= limit && u32::from_be_bytes(file_data[crc_offset..(crc_offset + 4)].try_into().unwrap()) == crc32fast::hash(&file_data[(idx + 4)..crc_offset])
+		})
+	}
+
 	/// In the PNG spec, a valid chunk type must have each byte match \[a-zA-Z\]. However, this could mean that plain text files are caught,
-	/// so instead of simply checking whether a chunk type is \[a-zA-Z\] we check it against a list of known PNG chunk types
-	fn validate_chunk_type(chunk_type: &[u8]) -> bool {
+	/// so instead of simply checking whether a chunk type is \[a-zA-Z\] we check it against a list of known PNG chunk types. If `spec_chunk_types`,
+	/// chunk types that aren't in the list are then checked against the rule of the spec, with the reserved bit (the case of the third letter)
+	/// unset, as all chunk types defined so far have
+	fn validate_chunk_type(chunk_type: &[u8], spec_chunk_types: bool) -> bool {
 		let chunk_type_u32 = u32::from_be_bytes(chunk_type.try_into().unwrap());
-		return PNG_CHUNK_TYPES.contains(&chunk_type_u32);
+		if PNG_CHUNK_TYPES.contains(&chunk_type_u32) {
+			return true;
+		}
+
+		spec_chunk_types && chunk_type.iter().all(u8::is_ascii_alphabetic) && chunk_type[2].is_ascii_uppercase()
 	}
 
 	fn validate_chunk_data(chunk_type: u32, data: FragmentsIndex, requires_plte: &mut bool, plte_forbidden: &mut bool) -> bool {
@@ -337,7 +437,17 @@ impl PngValidator {
 impl FileValidator for PngValidator {
 	// Written using https://www.w3.org/TR/png-3/
 	// TODO: Could try reconstructing out-of-order fragmentations by finding a chunk type and testing X clusters after this chunk type and Y clusters after that chunk type (where X+Y is the number of clusters needed)
-	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], cluster_size: usize, config: &SearchlightConfig) -> FileValidationInfo {
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, all_matches: &[Match], cluster_size: usize, config: &SearchlightConfig) -> FileValidationInfo {
+		// Chunks are read from just after the signature, so if the header that was matched wasn't the signature (e.g. due to the configured
+		// header), reading chunks would only misinterpret whatever data is there
+		if file_data.get(file_match.start_idx..(file_match.start_idx + PNG_SIGNATURE.len())) != Some(&PNG_SIGNATURE) {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Unrecognised,
+				rejection_reason: Some(RejectionReason::PngMissingSignature),
+				..Default::default()
+			};
+		}
+
 		let mut chunk_idx = file_match.start_idx as usize + 8;
 
 		let mut requires_plte = false;
@@ -346,13 +456,21 @@ impl FileValidator for PngValidator {
 		let mut seen_ihdr = false;
 		let mut seen_plte = false;
 		let mut seen_idat = false;
+		let mut seen_fdat = false;
 
 		let mut idat_out_of_order = false;
+		let mut crc_mismatch = false;
 
 		let mut prev_chunk_type = None;
 
 		let mut worst_chunk_validation = FileValidationType::Correct;
 
+		let max_skipped_chunks = file_match.file_type.param::<u64>(PARAM_MAX_SKIPPED_CHUNKS).unwrap_or(0) as usize;
+		let spec_chunk_types = file_match.file_type.param(PARAM_SPEC_CHUNK_TYPES).unwrap_or(false);
+		let mut skipped_chunks = 0;
+
+		let mut metadata = HashMap::new();
+
 		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
 			file_match.start_idx as usize + max_len as usize
 		} else {
@@ -362,8 +480,59 @@ impl FileValidator for PngValidator {
 		// Initialise fragments to contain the signature
 		let mut fragments: Vec<Fragment> = vec![ file_match.start_idx..(file_match.start_idx + 8) ];
 
+		// The ids of the headers of all file types, which bound reconstruction if configured
+		let header_ids: HashSet<u64> = if file_match.file_type.param(PARAM_BOUND_RECONSTRUCTION_BY_HEADERS).unwrap_or(config.bound_reconstruction_by_headers) {
+			config.file_types.iter().flat_map(|ft| &ft.headers).map(|h| match_id_hash_slice_u16(h)).collect()
+		} else {
+			HashSet::new()
+		};
+
+		// The start of the first header found after `idx`, looked up from the matches (which are sorted by start) that follow it
+		let next_header_after = |idx: usize| -> Option<usize> {
+			if header_ids.is_empty() {
+				return None;
+			}
+
+			all_matches[all_matches.partition_point(|m| m.start_idx as usize <= idx)..].iter().find(|m| header_ids.contains(&m.id)).map(|m| m.start_idx as usize)
+		};
+
+		let max_search_len = max_reconstruction_search_len(file_match.file_type, config);
+
+		let max_chunk_len = file_match.file_type.param::<u64>(PARAM_MAX_CHUNK_LEN)
+			.unwrap_or(file_match.file_type.max_len.map_or(DEFAULT_MAX_CHUNK_LEN, |max_len| max_len.min(DEFAULT_MAX_CHUNK_LEN))) as usize;
+
 		loop {
-			let mut chunk_info = Self::validate_chunk(&mut requires_plte, &mut plte_forbidden, &file_data, chunk_idx, cluster_size, config.max_reconstruction_search_len.map(|len| len as usize).unwrap_or(usize::MAX));
+			let next_header_idx = next_header_after(chunk_idx);
+
+			let mut chunk_info = Self::validate_chunk(&mut requires_plte, &mut plte_forbidden, &file_data, chunk_idx, cluster_size, max_search_len, next_header_idx, file_match.file_type.should_verify_crc(), max_chunk_len, spec_chunk_types);
+
+			// If configured, unrecognised data where a chunk was expected is skipped over, up to the next intact chunk, in case the chunks after it
+			// can still be recovered. The search for that chunk is bounded like reconstruction
+			if chunk_info.validation_type == FileValidationType::Unrecognised && skipped_chunks < max_skipped_chunks {
+				let limit = max_idx.min(chunk_idx.saturating_add(max_search_len)).min(next_header_idx.unwrap_or(usize::MAX));
+
+				if let Some(next_chunk_idx) = Self::find_next_chunk(file_data, chunk_idx + 1, limit, spec_chunk_types) {
+					skipped_chunks += 1;
+
+					fragments.push(chunk_idx..next_chunk_idx);
+					utils::simplify_ranges(&mut fragments);
+
+					worst_chunk_validation = worst_chunk_validation.worst_of(FileValidationType::Partial);
+					chunk_idx = next_chunk_idx;
+					continue;
+				}
+			}
+
+			// Partial chunks that validation can continue past are those that had a CRC mismatch but weren't reconstructed
+			if chunk_info.validation_type == FileValidationType::Partial && chunk_info.next_chunk_idx.is_some() {
+				crc_mismatch = true;
+			}
+
+			if chunk_info.chunk_type == PNG_TIME && chunk_info.validation_type == FileValidationType::Correct {
+				if let Some(mod_time) = Self::decode_time_chunk(FragmentsIndex::new(file_data, &chunk_info.chunk_frags)) {
+					metadata.insert(METADATA_MODIFIED_TIME.to_string(), mod_time.to_string());
+				}
+			}
 
 			fragments.append(&mut chunk_info.chunk_frags);
 			utils::simplify_ranges(&mut fragments);
@@ -373,7 +542,10 @@ impl FileValidator for PngValidator {
 			if worst_chunk_validation == FileValidationType::Unrecognised {
 				break FileValidationInfo {
 					validation_type: FileValidationType::Partial,
-					fragments
+					fragments,
+					rejection_reason: Some(RejectionReason::PngUnrecognisedChunk),
+					metadata,
+					..Default::default()
 				}
 			}
 
@@ -385,23 +557,46 @@ impl FileValidator for PngValidator {
 					seen_plte = true;
 				}
 				PNG_IDAT => {
-					if seen_idat && !prev_chunk_type.is_some_and(|t| t == PNG_IDAT) {
+					if (seen_idat && !prev_chunk_type.is_some_and(|t| t == PNG_IDAT)) || seen_fdat {
 						idat_out_of_order = true;
 					}
 					seen_idat = true;
 				}
+				PNG_FDAT => {
+					// APNG frame data is treated like IDAT, except that runs of it are separated by fcTL chunks, and it must come after the IDATs
+					if !seen_idat {
+						idat_out_of_order = true;
+					}
+					seen_fdat = true;
+				}
 				PNG_IEND => { // If we've reached the end of the image...
+					let chunk_order_valid = seen_ihdr && seen_idat && ((!seen_plte && !requires_plte) || (seen_plte && !plte_forbidden)) && !idat_out_of_order;
+
 					let validation_type = {
-						if seen_ihdr && seen_idat && ((!seen_plte && !requires_plte) || (seen_plte && !plte_forbidden)) && !idat_out_of_order {
+						if chunk_order_valid {
 							FileValidationType::Correct
 						} else {
 							FileValidationType::FormatError
 						}
 					};
 
+					// Any chunks that failed reconstruction would've ended validation early, so the only chunk that may be Partial is IEND,
+					// other than those with a CRC mismatch that weren't reconstructed, and skipped unrecognised chunks
+					let rejection_reason = match worst_chunk_validation {
+						_ if !chunk_order_valid => Some(RejectionReason::PngInvalidChunkOrder),
+						FileValidationType::FormatError => Some(RejectionReason::PngInvalidChunkData),
+						FileValidationType::Partial if skipped_chunks > 0 => Some(RejectionReason::PngUnrecognisedChunk),
+						FileValidationType::Partial if crc_mismatch => Some(RejectionReason::PngChunkCrcMismatch),
+						FileValidationType::Partial => Some(RejectionReason::PngIendCrcMismatch),
+						_ => None
+					};
+
 					break FileValidationInfo {
 						validation_type: validation_type.worst_of(worst_chunk_validation),
-						fragments
+						fragments,
+						rejection_reason,
+						metadata,
+						..Default::default()
 					};
 				}
 				_ => ()
@@ -416,22 +611,46 @@ impl FileValidator for PngValidator {
 			} else {
 				break FileValidationInfo {
 					validation_type: FileValidationType::Partial,
-					fragments
+					fragments,
+					rejection_reason: Some(chunk_info.rejection_reason.unwrap_or(RejectionReason::PngChunkReconstructionFailed)),
+					metadata,
+					..Default::default()
 				}
 			};
 
 			if (chunk_idx + 12) >= max_idx {
 				break FileValidationInfo {
 					validation_type: FileValidationType::Partial,
-					fragments
+					fragments,
+					rejection_reason: Some(RejectionReason::Truncated),
+					metadata,
+					..Default::default()
 				}
 			}
 		}
 	}
+
+	fn known_params(&self, _type_id: FileTypeId) -> &'static [&'static str] {
+		&[ PARAM_MAX_RECONSTRUCTION_SEARCH_LEN, PARAM_BOUND_RECONSTRUCTION_BY_HEADERS, PARAM_MAX_CHUNK_LEN, PARAM_MAX_SKIPPED_CHUNKS, PARAM_SPEC_CHUNK_TYPES ]
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the CRC and type of each chunk, the content of critical chunks and the order of the chunks, reconstructing fragmented chunks"
+	}
+
+	fn reconstructs_fragmentation(&self) -> bool {
+		true
+	}
 }
 
 #[cfg(test)]
 mod test {
+	use std::collections::HashMap;
+
+	use crate::{search::{match_id_hash_slice_u16, pairing::MatchPair, Match}, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason, PARAM_MAX_CHUNK_LEN, PARAM_MAX_SKIPPED_CHUNKS, PARAM_SPEC_CHUNK_TYPES}};
+
+	use super::PngValidator;
+
 	#[test]
 	fn test_crc32() {
 		let ihdr_dat: [u8; 17] = [ 0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x06, 0x40, 0x00, 0x00, 0x04, 0xB0, 0x08, 0x02, 0x00, 0x00, 0x00 ];
@@ -442,4 +661,327 @@ mod test {
 
 		assert_eq!(expected_crc, calc_crc);
 	}
+
+	#[test]
+	fn test_apng() {
+		// A 2-frame APNG: IHDR, acTL, fcTL, IDAT, fcTL, fdAT, IEND
+		let apng = include_bytes!("../../../test_data/corpus/animated.png");
+
+		let file_type = FileType {
+			type_id: FileTypeId::Png,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let mut data = vec![ 0xaa; 16 ];
+		let start = data.len();
+		data.extend_from_slice(apng);
+		let end = data.len();
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: start,
+			end_idx: end - 1,
+			truncated: false
+		};
+
+		let info = PngValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ (start..end) ]);
+	}
+
+	#[test]
+	fn test_png_no_verify_crc() {
+		let apng = include_bytes!("../../../test_data/corpus/animated.png");
+
+		let file_type = FileType {
+			type_id: FileTypeId::Png,
+			max_len: Some(1024 * 1024),
+			verify_crc: Some(false),
+			..Default::default()
+		};
+
+		// Corrupt the CRC of the acTL chunk, which follows the signature and IHDR chunk
+		let mut data = apng.to_vec();
+		data[8 + 25 + 12 + 7] ^= 0xff;
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: 0,
+			end_idx: data.len() - 1,
+			truncated: false
+		};
+
+		let info = PngValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		// The chunk is accepted structurally rather than reconstructed, so validation continues to IEND
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PngChunkCrcMismatch));
+		assert_eq!(info.fragments, vec![ (0..data.len()) ]);
+	}
+
+	#[test]
+	fn test_png_missing_signature() {
+		let apng = include_bytes!("../../../test_data/corpus/animated.png");
+
+		let file_type = FileType {
+			type_id: FileTypeId::Png,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		// The candidate starts at the IHDR chunk rather than the signature
+		let data = apng[8..].to_vec();
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: 0,
+			end_idx: data.len() - 1,
+			truncated: false
+		};
+
+		let info = PngValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Unrecognised);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PngMissingSignature));
+		assert!(info.fragments.is_empty());
+
+		// Too little data for a signature at all
+		let info = PngValidator::new().validate(&apng[..4], &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Unrecognised);
+	}
+
+	#[test]
+	fn test_png_bound_reconstruction_by_headers() {
+		let apng = include_bytes!("../../../test_data/corpus/animated.png");
+
+		const CLUSTER_SIZE: usize = 16;
+
+		// Fragment the data of the first fcTL chunk (53..91) around a cluster that starts with the header of another file
+		let mut data = apng[..80].to_vec();
+		data.extend_from_slice(b"\xff\xd8\xff\xe0");
+		data.extend_from_slice(&[ 0xaa; CLUSTER_SIZE - 4 ]);
+		data.extend_from_slice(&apng[80..]);
+
+		let png_type = FileType {
+			type_id: FileTypeId::Png,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let mut config = SearchlightConfig {
+			file_types: vec![
+				FileType {
+					headers: vec![ "\\xff\\xd8\\xff\\xe0".into() ],
+					type_id: FileTypeId::Jpeg,
+					max_len: Some(1024),
+					..Default::default()
+				}
+			],
+			..Default::default()
+		};
+
+		let all_matches = [ Match::new(match_id_hash_slice_u16(&config.file_types[0].headers[0]), 80, 83) ];
+
+		let file_match = MatchPair {
+			file_type: &png_type,
+			start_idx: 0,
+			end_idx: data.len() - 1,
+			truncated: false
+		};
+
+		// Unbounded, the chunk is reconstructed around the other file's cluster
+		let info = PngValidator::new().validate(&data, &file_match, &all_matches, CLUSTER_SIZE, &config);
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ 0..80, 96..data.len() ]);
+
+		// Bounded, the search for the next chunk stops at the other file's header
+		config.bound_reconstruction_by_headers = true;
+		let info = PngValidator::new().validate(&data, &file_match, &all_matches, CLUSTER_SIZE, &config);
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PngChunkReconstructionFailed));
+	}
+
+	#[test]
+	fn test_png_absurd_chunk_len() {
+		let apng = include_bytes!("../../../test_data/corpus/animated.png");
+
+		let file_type = FileType {
+			type_id: FileTypeId::Png,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		// Corrupt the length of the acTL chunk, which follows the signature and IHDR chunk, to something absurd
+		let mut data = apng.to_vec();
+		data[33..37].copy_from_slice(&0x7fff0000u32.to_be_bytes());
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: 0,
+			end_idx: data.len() - 1,
+			truncated: false
+		};
+
+		let info = PngValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PngChunkTooLong));
+		assert_eq!(info.fragments, vec![ 0..45 ]);
+
+		// A length that fits in the data is reconstructed unless it exceeds the configured maximum
+		data[33..37].copy_from_slice(&1000u32.to_be_bytes());
+		data.extend_from_slice(&[ 0xaa; 2048 ]);
+
+		let file_match = MatchPair { end_idx: data.len() - 1, ..file_match };
+		let info = PngValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PngChunkReconstructionFailed));
+
+		let capped_type = FileType {
+			type_id: FileTypeId::Png,
+			max_len: Some(1024 * 1024),
+			params: HashMap::from([ (PARAM_MAX_CHUNK_LEN.to_string(), toml::Value::Integer(512)) ]),
+			..Default::default()
+		};
+
+		let file_match = MatchPair { file_type: &capped_type, ..file_match };
+		let info = PngValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PngChunkTooLong));
+	}
+
+	#[test]
+	fn test_png_skip_unrecognised_chunks() {
+		let apng = include_bytes!("../../../test_data/corpus/animated.png");
+
+		// Insert a corrupt chunk of an unknown type before the IEND chunk
+		let junk_chunk = b"\x00\x00\x00\x08\x00\x01\x02\x03junkjunk\xde\xad\xbe\xef";
+		let iend_idx = apng.len() - 12;
+
+		let mut data = apng[..iend_idx].to_vec();
+		data.extend_from_slice(junk_chunk);
+		data.extend_from_slice(&apng[iend_idx..]);
+
+		let file_type = FileType {
+			type_id: FileTypeId::Png,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: 0,
+			end_idx: data.len() - 1,
+			truncated: false
+		};
+
+		// By default, validation ends at the unrecognised chunk
+		let info = PngValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PngUnrecognisedChunk));
+		assert_eq!(info.fragments, vec![ 0..(iend_idx + 12) ]);
+
+		// If configured, the chunk is skipped and the IEND after it is found
+		let skipping_type = FileType {
+			type_id: FileTypeId::Png,
+			max_len: Some(1024 * 1024),
+			params: HashMap::from([ (PARAM_MAX_SKIPPED_CHUNKS.to_string(), toml::Value::Integer(1)) ]),
+			..Default::default()
+		};
+
+		let file_match = MatchPair { file_type: &skipping_type, ..file_match };
+		let info = PngValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PngUnrecognisedChunk));
+		assert_eq!(info.fragments, vec![ 0..data.len() ]);
+
+		// But no more chunks than configured are skipped. Contiguous unrecognised data is skipped at once, so the corrupt chunks are
+		// separated by an intact tEXt chunk
+		let mut text_chunk = 5u32.to_be_bytes().to_vec();
+		text_chunk.extend_from_slice(b"tEXta\0bcd");
+		text_chunk.extend_from_slice(&crc32fast::hash(&text_chunk[4..]).to_be_bytes());
+
+		let mut data = apng[..iend_idx].to_vec();
+		data.extend_from_slice(junk_chunk);
+		data.extend_from_slice(&text_chunk);
+		data.extend_from_slice(junk_chunk);
+		data.extend_from_slice(&apng[iend_idx..]);
+
+		let file_match = MatchPair { end_idx: data.len() - 1, ..file_match };
+		let info = PngValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PngUnrecognisedChunk));
+		assert_eq!(info.fragments, vec![ 0..(iend_idx + junk_chunk.len() + text_chunk.len() + 12) ]);
+	}
+
+	#[test]
+	fn test_png_spec_chunk_types() {
+		let apng = include_bytes!("../../../test_data/corpus/animated.png");
+		let iend_idx = apng.len() - 12;
+
+		// Insert an intact chunk of each of the given types, which aren't in the list of known types, before the IEND chunk
+		let with_chunk = |chunk_type: &[u8; 4]| {
+			let mut chunk = 4u32.to_be_bytes().to_vec();
+			chunk.extend_from_slice(chunk_type);
+			chunk.extend_from_slice(b"data");
+			chunk.extend_from_slice(&crc32fast::hash(&chunk[4..]).to_be_bytes());
+
+			let mut data = apng[..iend_idx].to_vec();
+			data.extend_from_slice(&chunk);
+			data.extend_from_slice(&apng[iend_idx..]);
+			data
+		};
+
+		let strict_type = FileType {
+			type_id: FileTypeId::Png,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let spec_type = FileType {
+			type_id: FileTypeId::Png,
+			max_len: Some(1024 * 1024),
+			params: HashMap::from([ (PARAM_SPEC_CHUNK_TYPES.to_string(), toml::Value::Boolean(true)) ]),
+			..Default::default()
+		};
+
+		let validate = |data: &[u8], file_type: &FileType| {
+			let file_match = MatchPair {
+				file_type,
+				start_idx: 0,
+				end_idx: data.len() - 1,
+				truncated: false
+			};
+
+			PngValidator::new().validate(data, &file_match, &[], 1, &SearchlightConfig::default())
+		};
+
+		// A private ancillary chunk is only accepted by the rule of the spec
+		let data = with_chunk(b"prVt");
+
+		let info = validate(&data, &strict_type);
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PngUnrecognisedChunk));
+
+		let info = validate(&data, &spec_type);
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ 0..data.len() ]);
+
+		// Chunk types with the reserved bit set, or that aren't all letters, are rejected either way
+		for chunk_type in [ b"prvt", b"pr1t" ] {
+			let info = validate(&with_chunk(chunk_type), &spec_type);
+			assert_eq!(info.validation_type, FileValidationType::Partial);
+			assert_eq!(info.rejection_reason, Some(RejectionReason::PngUnrecognisedChunk));
+		}
+	}
 }
\ No newline at end of file
@@ -0,0 +1,319 @@
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+const PSD_HEADER_SIZE: usize = 26;
+
+const PSD_VERSION_PSD: u16 = 1;
+const PSD_VERSION_PSB: u16 = 2;
+
+const PSD_MAX_CHANNELS: u16 = 56;
+const PSD_DEPTHS: [u16; 4] = [ 1, 8, 16, 32 ];
+
+const PSD_COMPRESSION_RAW: u16 = 0;
+const PSD_COMPRESSION_RLE: u16 = 1;
+
+pub struct PsdValidator;
+
+struct PsdHeader {
+	version: u16,
+	channels: u16,
+	height: u32,
+	width: u32,
+	depth: u16
+}
+
+/// The outcome of reading a section of the file
+enum SectionEnd {
+	/// The section ends at the contained index
+	At(usize),
+	/// The section extends past the end of the available data
+	Truncated,
+	/// The end of the section can't be determined from the file data
+	Unknown,
+	/// The lengths in the file that the end of the section is calculated from overflow when combined, so must be corrupt
+	Overflow
+}
+
+impl PsdHeader {
+	fn decode(data: &[u8]) -> Self {
+		PsdHeader {
+			version: u16::from_be_bytes(data[4..6].try_into().unwrap()),
+			channels: u16::from_be_bytes(data[12..14].try_into().unwrap()),
+			height: u32::from_be_bytes(data[14..18].try_into().unwrap()),
+			width: u32::from_be_bytes(data[18..22].try_into().unwrap()),
+			depth: u16::from_be_bytes(data[22..24].try_into().unwrap())
+		}
+	}
+
+	fn is_psb(&self) -> bool {
+		self.version == PSD_VERSION_PSB
+	}
+
+	/// Whether the header fields hold values that are allowed by the spec
+	fn fields_valid(&self) -> bool {
+		(self.version == PSD_VERSION_PSD || self.version == PSD_VERSION_PSB)
+			&& (1..=PSD_MAX_CHANNELS).contains(&self.channels)
+			&& self.height > 0
+			&& self.width > 0
+			&& PSD_DEPTHS.contains(&self.depth)
+	}
+}
+
+impl PsdValidator {
+	pub fn new() -> Self {
+		PsdValidator
+	}
+
+	/// Reads a section at `idx` that is prefixed by its length, which is `len_size` bytes long, returning the index just after the section
+	fn read_length_prefixed_section(data: &[u8], idx: usize, len_size: usize, max_idx: usize) -> SectionEnd {
+		if idx + len_size > max_idx {
+			return SectionEnd::Truncated;
+		}
+
+		let len = data[idx..(idx + len_size)].iter().fold(0u64, |acc, &b| (acc << 8) | b as u64) as usize;
+
+		match (idx + len_size).checked_add(len) {
+			Some(end) if end <= max_idx => SectionEnd::At(end),
+			_ => SectionEnd::Truncated
+		}
+	}
+
+	/// Reads the image data section at `idx`, which is not length-prefixed, but instead is prefixed by the compression method, the length
+	/// being calculated from the image dimensions (raw) or the scanline byte counts (RLE)
+	fn read_image_data_section(data: &[u8], idx: usize, header: &PsdHeader, max_idx: usize) -> SectionEnd {
+		if idx + 2 > max_idx {
+			return SectionEnd::Truncated;
+		}
+
+		let compression = u16::from_be_bytes(data[idx..(idx + 2)].try_into().unwrap());
+		let data_idx = idx + 2;
+
+		// The dimensions and byte counts come from the file, so may be corrupt and overflow when combined
+		let Some(num_scanlines) = (header.channels as usize).checked_mul(header.height as usize) else {
+			return SectionEnd::Overflow;
+		};
+
+		let data_len = match compression {
+			PSD_COMPRESSION_RAW => {
+				(header.width as usize).checked_mul(header.depth as usize)
+					.and_then(|scanline_bits| scanline_bits.div_ceil(8).checked_mul(num_scanlines))
+			}
+			PSD_COMPRESSION_RLE => {
+				// The RLE data is preceded by the byte counts of each scanline, which are 4 bytes each in PSB and 2 in PSD
+				let count_size = if header.is_psb() { 4 } else { 2 };
+				let Some(counts_len) = num_scanlines.checked_mul(count_size) else {
+					return SectionEnd::Overflow;
+				};
+
+				if data_idx.saturating_add(counts_len) > max_idx {
+					return SectionEnd::Truncated;
+				}
+
+				data[data_idx..(data_idx + counts_len)].chunks_exact(count_size)
+					.map(|count| count.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize))
+					.try_fold(counts_len, |len, count| len.checked_add(count))
+			}
+			_ => {
+				// ZIP-compressed data (with or without prediction) isn't preceded by any lengths, and other values aren't recognised
+				return SectionEnd::Unknown;
+			}
+		};
+
+		match data_len.and_then(|data_len| data_idx.checked_add(data_len)) {
+			Some(end) if end <= max_idx => SectionEnd::At(end),
+			Some(_) => SectionEnd::Truncated,
+			None => SectionEnd::Overflow
+		}
+	}
+}
+
+impl FileValidator for PsdValidator {
+	// Written using https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+
+		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
+			start.saturating_add(max_len as usize).min(file_data.len())
+		} else {
+			file_data.len()
+		};
+
+		if start + PSD_HEADER_SIZE > max_idx {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Partial,
+				fragments: vec![ (start..max_idx) ],
+				rejection_reason: Some(RejectionReason::Truncated),
+				..Default::default()
+			};
+		}
+
+		let header = PsdHeader::decode(&file_data[start..]);
+
+		// We can't be sure of the section layout without a recognised version, so there's nothing more we can do
+		if header.version != PSD_VERSION_PSD && header.version != PSD_VERSION_PSB {
+			return FileValidationInfo {
+				validation_type: FileValidationType::FormatError,
+				fragments: vec![ (start..(start + PSD_HEADER_SIZE)) ],
+				rejection_reason: Some(RejectionReason::PsdUnsupportedVersion),
+				..Default::default()
+			};
+		}
+
+		let (header_validation, header_rejection_reason) = if header.fields_valid() {
+			(FileValidationType::Correct, None)
+		} else {
+			(FileValidationType::FormatError, Some(RejectionReason::PsdInvalidHeaderFields))
+		};
+
+		// The colour mode data and image resources sections have 4-byte lengths, and the layer and mask information section has a 4-byte
+		// length in PSD and an 8-byte length in PSB
+		let section_len_sizes = [ 4, 4, if header.is_psb() { 8 } else { 4 } ];
+
+		let mut i = start + PSD_HEADER_SIZE;
+
+		for len_size in section_len_sizes {
+			match Self::read_length_prefixed_section(file_data, i, len_size, max_idx) {
+				SectionEnd::At(end) => i = end,
+				_ => {
+					return FileValidationInfo {
+						validation_type: FileValidationType::Partial.worst_of(header_validation),
+						fragments: vec![ (start..max_idx) ],
+						rejection_reason: header_rejection_reason.or(Some(RejectionReason::Truncated)),
+						..Default::default()
+					};
+				}
+			}
+		}
+
+		match Self::read_image_data_section(file_data, i, &header, max_idx) {
+			SectionEnd::At(end) => {
+				FileValidationInfo {
+					validation_type: header_validation,
+					fragments: vec![ (start..end) ],
+					rejection_reason: header_rejection_reason,
+					..Default::default()
+				}
+			}
+			SectionEnd::Overflow => {
+				FileValidationInfo {
+					validation_type: FileValidationType::FormatError,
+					fragments: vec![ (start..i) ],
+					rejection_reason: Some(RejectionReason::PsdImageDataLengthOverflow),
+					..Default::default()
+				}
+			}
+			section_end => {
+				// If the end of the image data can't be calculated, all we can do is take everything up to max_len
+				let rejection_reason = match section_end {
+					SectionEnd::Unknown => RejectionReason::PsdUnknownImageDataLength,
+					_ => RejectionReason::Truncated
+				};
+
+				FileValidationInfo {
+					validation_type: FileValidationType::Partial.worst_of(header_validation),
+					fragments: vec![ (start..max_idx) ],
+					rejection_reason: header_rejection_reason.or(Some(rejection_reason)),
+					..Default::default()
+				}
+			}
+		}
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the file header and the lengths of each section up to the image data"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason}};
+
+	use super::PsdValidator;
+
+	fn synthetic_psd(channels: u16, depth: u16) -> Vec<u8> {
+		let (width, height) = (4u32, 3u32);
+
+		let mut psd = b"8BPS".to_vec();
+		psd.extend_from_slice(&1u16.to_be_bytes());
+		psd.extend_from_slice(&[ 0; 6 ]);
+		psd.extend_from_slice(&channels.to_be_bytes());
+		psd.extend_from_slice(&height.to_be_bytes());
+		psd.extend_from_slice(&width.to_be_bytes());
+		psd.extend_from_slice(&depth.to_be_bytes());
+		psd.extend_from_slice(&3u16.to_be_bytes()); // RGB
+
+		// Colour mode data (empty), image resources (8 bytes), layer and mask information (empty)
+		psd.extend_from_slice(&0u32.to_be_bytes());
+		psd.extend_from_slice(&8u32.to_be_bytes());
+		psd.extend_from_slice(&[ 0x11; 8 ]);
+		psd.extend_from_slice(&0u32.to_be_bytes());
+
+		// RLE image data, with each scanline encoded as a single literal run of 2 bytes, so 3 bytes per scanline
+		psd.extend_from_slice(&1u16.to_be_bytes());
+		for _ in 0..(channels as u32 * height) {
+			psd.extend_from_slice(&3u16.to_be_bytes());
+		}
+		for _ in 0..(channels as u32 * height) {
+			psd.extend_from_slice(&[ 0x01, 0x22, 0x33 ]);
+		}
+
+		psd
+	}
+
+	#[test]
+	fn test_psd_validator() {
+		let file_type = FileType {
+			type_id: FileTypeId::Psd,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let psd = synthetic_psd(3, 8);
+
+		let mut data = vec![ 0xaa; 16 ];
+		let start = data.len();
+		data.extend_from_slice(&psd);
+		let end = data.len();
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: start,
+			end_idx: start + 1024 * 1024,
+			truncated: false
+		};
+
+		let validator = PsdValidator::new();
+
+		let info = validator.validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ (start..end) ]);
+		assert_eq!(info.rejection_reason, None);
+
+		// Truncated in the image data
+		let info = validator.validate(&data[..(end - 5)], &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+
+		// Invalid depth
+		let mut bad_data = data[..start].to_vec();
+		bad_data.extend_from_slice(&synthetic_psd(3, 7));
+		let info = validator.validate(&bad_data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PsdInvalidHeaderFields));
+
+		// Dimensions whose raw image data length overflows
+		let mut bad_data = data[..start].to_vec();
+		let mut psd = synthetic_psd(3, 32);
+		psd[14..22].fill(0xff);
+		psd[46..48].copy_from_slice(&0u16.to_be_bytes());
+		bad_data.extend_from_slice(&psd);
+		let info = validator.validate(&bad_data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::PsdImageDataLengthOverflow));
+	}
+}
\ No newline at end of file
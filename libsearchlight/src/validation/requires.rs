@@ -0,0 +1,108 @@
+use crate::{search::{pairing::MatchPair, search_common::MATCH_ALL_VALUE, Match}, searchlight::config::SearchlightConfig};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+/// A generic validator for configured file types that don't have a dedicated validator, which checks that each of the byte sequences in the
+/// file type's `requires` appears somewhere in the candidate. This gives basic structural validation to formats that lack a bespoke
+/// validator, e.g. requiring an IDAT chunk type for a PNG-like format
+pub struct RequiresValidator;
+
+impl RequiresValidator {
+	pub fn new() -> Self {
+		RequiresValidator
+	}
+
+	/// Whether `pattern`, which can contain "match all" values, appears anywhere in `data`
+	pub(super) fn contains_pattern(data: &[u8], pattern: &[u16]) -> bool {
+		if pattern.is_empty() {
+			return true;
+		}
+
+		data.windows(pattern.len()).any(|window| {
+			window.iter().zip(pattern).all(|(&b, &v)| v == MATCH_ALL_VALUE || v == b as u16)
+		})
+	}
+}
+
+impl FileValidator for RequiresValidator {
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+		let end = (file_match.end_idx + 1).min(file_data.len());
+
+		let span = &file_data[start..end];
+
+		let all_present = file_match.file_type.requires.iter().all(|required| Self::contains_pattern(span, required));
+
+		let (validation_type, rejection_reason) = if !all_present {
+			(FileValidationType::FormatError, Some(RejectionReason::RequiredSequenceMissing))
+		} else if file_match.truncated {
+			(FileValidationType::Partial, Some(RejectionReason::NoFooterInRange))
+		} else {
+			(FileValidationType::Correct, None)
+		};
+
+		FileValidationInfo {
+			validation_type,
+			fragments: vec![ start..end ],
+			rejection_reason,
+			..Default::default()
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, MatchString, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason}};
+
+	use super::RequiresValidator;
+
+	#[test]
+	fn test_requires_validator() {
+		let file_type = FileType {
+			type_id: FileTypeId::Unknown,
+			requires: vec![ MatchString::from("IDAT"), MatchString::from("\\x00.\\xff") ],
+			max_len: Some(1024),
+			..Default::default()
+		};
+
+		let mut data = vec![ 0xaa; 16 ];
+		data.extend_from_slice(b"HEAD....IDAT....\x00\x12\xff....FOOT");
+		let end = data.len();
+		data.extend_from_slice(&[ 0xaa; 16 ]);
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: 16,
+			end_idx: end - 1,
+			truncated: false
+		};
+
+		let info = RequiresValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ 16..end ]);
+
+		// A candidate truncated at max_len can't be complete, even if it has everything that's required
+		let truncated_match = MatchPair {
+			truncated: true,
+			..file_match
+		};
+
+		let info = RequiresValidator::new().validate(&data, &truncated_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::NoFooterInRange));
+		assert_eq!(info.fragments, vec![ 16..end ]);
+
+		// The required sequence lies outside the candidate
+		let file_match = MatchPair {
+			end_idx: 16 + 8,
+			..file_match
+		};
+
+		let info = RequiresValidator::new().validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::RequiredSequenceMissing));
+	}
+}
\ No newline at end of file
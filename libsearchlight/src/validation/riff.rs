@@ -0,0 +1,390 @@
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::{FileTypeId, SearchlightConfig}};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+/// The size of the RIFF header, i.e. the `RIFF` signature, size and form type
+pub const RIFF_HEADER_SIZE: usize = 12;
+/// The size of the header of each chunk, i.e. its ID and size
+pub const RIFF_CHUNK_HEADER_SIZE: usize = 8;
+
+const RIFF_SIGNATURE: &[u8] = b"RIFF";
+
+const WAVE_FORM_TYPE: &[u8; 4] = b"WAVE";
+const AVI_FORM_TYPE: &[u8; 4] = b"AVI ";
+const WEBP_FORM_TYPE: &[u8; 4] = b"WEBP";
+
+const LIST_CHUNK_ID: &[u8; 4] = b"LIST";
+/// The size of the list type at the start of the data of a LIST chunk
+const LIST_TYPE_SIZE: usize = 4;
+
+/// A chunk in a RIFF container
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RiffChunk {
+	pub id: [u8; 4],
+	/// The index of the start of the chunk (its header) in the data
+	pub offset: usize,
+	/// The length of the data of the chunk, not including its header or padding
+	pub len: usize
+}
+
+impl RiffChunk {
+	/// The index of the start of the data of the chunk
+	pub fn data_start(&self) -> usize {
+		self.offset + RIFF_CHUNK_HEADER_SIZE
+	}
+
+	/// The index of the end of the chunk, including any padding byte, which is where the next chunk starts
+	pub fn end(&self) -> usize {
+		self.data_start() + self.len + (self.len & 1)
+	}
+}
+
+/// Why a RIFF container couldn't be walked
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RiffError {
+	/// The data doesn't start with a RIFF header
+	NotRiff,
+	/// The available data ends before the end of the container
+	Truncated,
+	/// A chunk extends past the end of the container, as given by the size in the RIFF header
+	ChunkOutOfBounds
+}
+
+/// A RIFF container (e.g. WAV, AVI or WebP) in some data, from which its chunks can be iterated over, so that validators of RIFF-based formats
+/// don't have to walk the chunks themselves. RIFF containers are a 12-byte header of `RIFF`, the size of the rest of the container as a
+/// little-endian u32 and a 4-byte form type, followed by chunks of a 4-byte ID, the size of the chunk data as a little-endian u32 and the
+/// data, padded to an even length
+#[derive(Debug, Clone, Copy)]
+pub struct Riff<'a> {
+	data: &'a [u8],
+	pub form_type: [u8; 4],
+	/// The index of the start of the RIFF header in the data
+	pub start: usize,
+	/// The index of the end of the container, as given by the size in the RIFF header, which is past the end of the data if it is truncated
+	pub end: usize
+}
+
+impl<'a> Riff<'a> {
+	/// Parses the RIFF header at `start` in `data`
+	pub fn parse(data: &'a [u8], start: usize) -> Result<Self, RiffError> {
+		if start + RIFF_SIGNATURE.len() <= data.len() && &data[start..(start + RIFF_SIGNATURE.len())] != RIFF_SIGNATURE {
+			return Err(RiffError::NotRiff);
+		}
+
+		if start + RIFF_HEADER_SIZE > data.len() {
+			return Err(RiffError::Truncated);
+		}
+
+		let size = u32::from_le_bytes(data[(start + 4)..(start + 8)].try_into().unwrap()) as usize;
+
+		// The size includes the form type
+		if size < 4 {
+			return Err(RiffError::NotRiff);
+		}
+
+		Ok(Riff {
+			data,
+			form_type: data[(start + 8)..(start + 12)].try_into().unwrap(),
+			start,
+			end: start + 8 + size
+		})
+	}
+
+	/// Whether the available data ends before the end of the container
+	pub fn is_truncated(&self) -> bool {
+		self.end > self.data.len()
+	}
+
+	/// Returns an iterator over the top-level chunks of the container, in order. If a chunk extends past the end of the data or container,
+	/// the iterator yields an error and then ends
+	pub fn chunks(&self) -> RiffChunks<'a> {
+		RiffChunks {
+			data: self.data,
+			idx: self.start + RIFF_HEADER_SIZE,
+			end: self.end,
+			done: false
+		}
+	}
+}
+
+/// An iterator over the chunks of a RIFF container, see `Riff::chunks`
+pub struct RiffChunks<'a> {
+	data: &'a [u8],
+	idx: usize,
+	end: usize,
+	done: bool
+}
+
+impl Iterator for RiffChunks<'_> {
+	type Item = Result<RiffChunk, RiffError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done || self.idx >= self.end {
+			return None;
+		}
+
+		let header_end = self.idx + RIFF_CHUNK_HEADER_SIZE;
+
+		let result = if header_end > self.end {
+			Err(RiffError::ChunkOutOfBounds)
+		} else if header_end > self.data.len() {
+			Err(RiffError::Truncated)
+		} else {
+			let chunk = RiffChunk {
+				id: self.data[self.idx..(self.idx + 4)].try_into().unwrap(),
+				offset: self.idx,
+				len: u32::from_le_bytes(self.data[(self.idx + 4)..header_end].try_into().unwrap()) as usize
+			};
+
+			// The padding byte of the last chunk may be left out by some writers, so only the data has to be within the container
+			if chunk.data_start() + chunk.len > self.end {
+				Err(RiffError::ChunkOutOfBounds)
+			} else if chunk.data_start() + chunk.len > self.data.len() {
+				Err(RiffError::Truncated)
+			} else {
+				self.idx = chunk.end();
+				Ok(chunk)
+			}
+		};
+
+		self.done = result.is_err();
+
+		Some(result)
+	}
+}
+
+pub struct RiffValidator;
+
+impl RiffValidator {
+	pub fn new() -> Self {
+		RiffValidator
+	}
+
+	/// The form type of the RIFF-based file type `type_id`, or None if it isn't one
+	fn form_type(type_id: FileTypeId) -> Option<&'static [u8; 4]> {
+		match type_id {
+			FileTypeId::Wav => Some(WAVE_FORM_TYPE),
+			FileTypeId::Avi => Some(AVI_FORM_TYPE),
+			FileTypeId::Webp => Some(WEBP_FORM_TYPE),
+			_ => None
+		}
+	}
+
+	/// The list type of a LIST chunk, or None if the chunk isn't a LIST chunk
+	fn list_type<'a>(data: &'a [u8], chunk: &RiffChunk) -> Option<&'a [u8]> {
+		if &chunk.id == LIST_CHUNK_ID && chunk.len >= LIST_TYPE_SIZE {
+			Some(&data[chunk.data_start()..(chunk.data_start() + LIST_TYPE_SIZE)])
+		} else {
+			None
+		}
+	}
+
+	/// Whether the top-level chunks of a container of `form_type` include those that the form type requires: A `fmt ` chunk before the
+	/// `data` chunk for WAV, a `hdrl` list first and a `movi` list for AVI, and a `VP8 `, `VP8L` or `VP8X` chunk first for WebP
+	fn has_required_chunks(data: &[u8], form_type: &[u8; 4], chunks: &[RiffChunk]) -> bool {
+		match form_type {
+			WAVE_FORM_TYPE => {
+				let fmt_idx = chunks.iter().position(|chunk| &chunk.id == b"fmt ");
+				let data_idx = chunks.iter().position(|chunk| &chunk.id == b"data");
+
+				matches!((fmt_idx, data_idx), (Some(fmt_idx), Some(data_idx)) if fmt_idx < data_idx)
+			}
+			AVI_FORM_TYPE => {
+				chunks.first().and_then(|chunk| Self::list_type(data, chunk)) == Some(b"hdrl")
+					&& chunks.iter().any(|chunk| Self::list_type(data, chunk) == Some(b"movi"))
+			}
+			WEBP_FORM_TYPE => {
+				chunks.first().is_some_and(|chunk| matches!(&chunk.id, b"VP8 " | b"VP8L" | b"VP8X"))
+			}
+			_ => false
+		}
+	}
+}
+
+impl FileValidator for RiffValidator {
+	// Written using the Multimedia Programming Interface and Data Specifications 1.0 (RIFF and WAVE), the OpenDML AVI File Format Extensions
+	// and the WebP Container Specification
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+
+		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
+			start.saturating_add(max_len as usize).min(file_data.len())
+		} else {
+			file_data.len()
+		};
+
+		let truncated = || FileValidationInfo {
+			validation_type: FileValidationType::Partial,
+			fragments: vec![ (start..max_idx) ],
+			rejection_reason: Some(RejectionReason::Truncated),
+			..Default::default()
+		};
+
+		let format_error = |reason: RejectionReason| FileValidationInfo {
+			validation_type: FileValidationType::FormatError,
+			fragments: vec![ (start..max_idx) ],
+			rejection_reason: Some(reason),
+			..Default::default()
+		};
+
+		let data = &file_data[..max_idx];
+
+		let riff = match Riff::parse(data, start) {
+			Ok(riff) => riff,
+			Err(RiffError::Truncated) => return truncated(),
+			Err(_) => return format_error(RejectionReason::RiffInvalidHeader)
+		};
+
+		if Self::form_type(file_match.file_type.type_id).is_none_or(|form_type| form_type != &riff.form_type) {
+			return format_error(RejectionReason::RiffInvalidHeader);
+		}
+
+		let mut chunks = Vec::new();
+		for chunk in riff.chunks() {
+			match chunk {
+				Ok(chunk) => chunks.push(chunk),
+				Err(RiffError::Truncated) => return truncated(),
+				Err(_) => return format_error(RejectionReason::RiffInvalidChunk)
+			}
+		}
+
+		if !Self::has_required_chunks(data, &riff.form_type, &chunks) {
+			return format_error(RejectionReason::RiffMissingChunk);
+		}
+
+		FileValidationInfo {
+			validation_type: FileValidationType::Correct,
+			// The padding byte of the last chunk may be past the end of the data
+			fragments: vec![ (start..riff.end.min(max_idx)) ],
+			..Default::default()
+		}
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the form type and the chunks it requires (fmt and data for WAV, hdrl and movi lists for AVI, VP8/VP8L/VP8X for WebP), and walks the chunks to the end of the container"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason}};
+
+	use super::{Riff, RiffChunk, RiffError, RiffValidator};
+
+	/// A RIFF container of form type `WAVE`, with a chunk of odd length, so padded, and one of even length
+	fn synthetic_riff() -> Vec<u8> {
+		let mut riff = b"RIFF".to_vec();
+		riff.extend_from_slice(&(4u32 + 8 + 3 + 1 + 8 + 4).to_le_bytes());
+		riff.extend_from_slice(b"WAVE");
+		riff.extend_from_slice(b"fmt ");
+		riff.extend_from_slice(&3u32.to_le_bytes());
+		riff.extend_from_slice(&[ 0x11, 0x22, 0x33, 0x00 ]);
+		riff.extend_from_slice(b"data");
+		riff.extend_from_slice(&4u32.to_le_bytes());
+		riff.extend_from_slice(&[ 0x44; 4 ]);
+		riff
+	}
+
+	#[test]
+	fn test_riff_chunks() {
+		let mut data = vec![ 0xaa; 16 ];
+		data.extend_from_slice(&synthetic_riff());
+		data.extend_from_slice(&[ 0xaa; 16 ]);
+
+		let riff = Riff::parse(&data, 16).unwrap();
+
+		assert_eq!(&riff.form_type, b"WAVE");
+		assert_eq!(riff.end, 16 + 36);
+		assert!(!riff.is_truncated());
+
+		let chunks: Vec<_> = riff.chunks().collect();
+		assert_eq!(chunks, vec![
+			Ok(RiffChunk { id: *b"fmt ", offset: 28, len: 3 }),
+			Ok(RiffChunk { id: *b"data", offset: 40, len: 4 })
+		]);
+		assert_eq!(chunks[1].unwrap().end(), riff.end);
+
+		assert_eq!(Riff::parse(&data, 0).unwrap_err(), RiffError::NotRiff);
+	}
+
+	#[test]
+	fn test_riff_truncated() {
+		let riff_data = synthetic_riff();
+
+		// Truncated within the data of the last chunk
+		let truncated = &riff_data[..(riff_data.len() - 2)];
+		let riff = Riff::parse(truncated, 0).unwrap();
+
+		assert!(riff.is_truncated());
+		assert_eq!(riff.chunks().collect::<Vec<_>>(), vec![
+			Ok(RiffChunk { id: *b"fmt ", offset: 12, len: 3 }),
+			Err(RiffError::Truncated)
+		]);
+
+		// Truncated within the RIFF header
+		assert_eq!(Riff::parse(&riff_data[..8], 0).unwrap_err(), RiffError::Truncated);
+
+		// A chunk that is larger than the container is out of bounds, even if the data is available
+		let mut oversized = riff_data.clone();
+		oversized[16..20].copy_from_slice(&0x100u32.to_le_bytes());
+		oversized.resize(0x200, 0);
+
+		assert_eq!(Riff::parse(&oversized, 0).unwrap().chunks().collect::<Vec<_>>(), vec![ Err(RiffError::ChunkOutOfBounds) ]);
+	}
+
+	fn validate(data: &[u8], start_idx: usize, type_id: FileTypeId) -> (FileValidationType, Vec<std::ops::Range<usize>>, Option<RejectionReason>) {
+		let file_type = FileType {
+			type_id,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx,
+			end_idx: data.len() - 1,
+			truncated: false
+		};
+
+		let info = RiffValidator::new().validate(data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		(info.validation_type, info.fragments, info.rejection_reason)
+	}
+
+	#[test]
+	fn test_riff_validator() {
+		let mut data = vec![ 0xaa; 16 ];
+		data.extend_from_slice(&synthetic_riff());
+		data.extend_from_slice(&[ 0xaa; 16 ]);
+
+		assert_eq!(validate(&data, 16, FileTypeId::Wav), (FileValidationType::Correct, vec![ 16..(16 + 36) ], None));
+
+		// The form type must be that of the file type
+		assert_eq!(validate(&data, 16, FileTypeId::Webp).2, Some(RejectionReason::RiffInvalidHeader));
+
+		// Truncated within the data chunk
+		let (validation_type, _, reason) = validate(&data[..(16 + 34)], 16, FileTypeId::Wav);
+		assert_eq!(validation_type, FileValidationType::Partial);
+		assert_eq!(reason, Some(RejectionReason::Truncated));
+
+		// A WAV file without a fmt chunk
+		let mut no_fmt = data.clone();
+		no_fmt[(16 + 12)..(16 + 16)].copy_from_slice(b"JUNK");
+		assert_eq!(validate(&no_fmt, 16, FileTypeId::Wav).2, Some(RejectionReason::RiffMissingChunk));
+
+		// An AVI file with a hdrl list and a movi list
+		let mut avi = b"RIFF".to_vec();
+		avi.extend_from_slice(&(4u32 + 12 + 12).to_le_bytes());
+		avi.extend_from_slice(b"AVI ");
+		for list_type in [ b"hdrl", b"movi" ] {
+			avi.extend_from_slice(b"LIST");
+			avi.extend_from_slice(&4u32.to_le_bytes());
+			avi.extend_from_slice(list_type);
+		}
+
+		assert_eq!(validate(&avi, 0, FileTypeId::Avi), (FileValidationType::Correct, vec![ 0..avi.len() ], None));
+
+		avi[(12 + 8)..(12 + 12)].copy_from_slice(b"INFO");
+		assert_eq!(validate(&avi, 0, FileTypeId::Avi).2, Some(RejectionReason::RiffMissingChunk));
+	}
+}
\ No newline at end of file
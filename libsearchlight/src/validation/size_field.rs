@@ -0,0 +1,191 @@
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig};
+
+use super::{requires::RequiresValidator, FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+/// A generic validator for configured file types that don't have a dedicated validator but declare a `size_field`, which reads the size of
+/// the file from the field and carves that many bytes from the start of the file, letting size-driven formats (e.g. BMP, WAV) be carved
+/// without code changes. The footer of the candidate, if any, only bounds where the field can be. Any byte sequences in the file type's
+/// `requires` must also appear within the file for it to be Correct
+pub struct SizeFieldValidator;
+
+impl SizeFieldValidator {
+	pub fn new() -> Self {
+		SizeFieldValidator
+	}
+}
+
+impl FileValidator for SizeFieldValidator {
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let file_type = file_match.file_type;
+
+		let Some(size_field) = file_type.size_field else {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Unanalysed,
+				rejection_reason: Some(RejectionReason::NoValidator),
+				..Default::default()
+			};
+		};
+
+		let start = file_match.start_idx;
+		let field_start = start + size_field.offset as usize;
+		let field_end = field_start + size_field.width as usize;
+
+		// The field has to be part of the candidate for the candidate to be a file of this type
+		if field_end > file_match.end_idx + 1 {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Unrecognised,
+				rejection_reason: Some(RejectionReason::SizeFieldOutOfBounds),
+				..Default::default()
+			};
+		}
+
+		if field_end > file_data.len() {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Partial,
+				fragments: vec![ start..file_data.len() ],
+				rejection_reason: Some(RejectionReason::Truncated),
+				..Default::default()
+			};
+		}
+
+		// The width is checked when the config is validated, but a field of any other width can't be decoded
+		let Some(value) = size_field.endianness.decode(&file_data[field_start..field_end]) else {
+			return FileValidationInfo {
+				validation_type: FileValidationType::FormatError,
+				fragments: vec![ start..field_end ],
+				rejection_reason: Some(RejectionReason::SizeFieldInvalid),
+				..Default::default()
+			};
+		};
+
+		let size = value as i128 + size_field.addend as i128 + if size_field.relative { (field_end - start) as i128 } else { 0 };
+
+		// A size that doesn't even include the field itself, or is larger than any file of the type, can't be right
+		if size < (field_end - start) as i128 || file_type.max_len.is_some_and(|max_len| size > max_len as i128) {
+			return FileValidationInfo {
+				validation_type: FileValidationType::FormatError,
+				fragments: vec![ start..field_end ],
+				rejection_reason: Some(RejectionReason::SizeFieldInvalid),
+				..Default::default()
+			};
+		}
+
+		let end = start.saturating_add(size.try_into().unwrap_or(usize::MAX));
+
+		if end > file_data.len() {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Partial,
+				fragments: vec![ start..file_data.len() ],
+				rejection_reason: Some(RejectionReason::Truncated),
+				..Default::default()
+			};
+		}
+
+		let all_present = file_type.requires.iter().all(|required| RequiresValidator::contains_pattern(&file_data[start..end], required));
+
+		let (validation_type, rejection_reason) = if all_present {
+			(FileValidationType::Correct, None)
+		} else {
+			(FileValidationType::FormatError, Some(RejectionReason::RequiredSequenceMissing))
+		};
+
+		FileValidationInfo {
+			validation_type,
+			fragments: vec![ start..end ],
+			rejection_reason,
+			..Default::default()
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{Endianness, FileType, FileTypeId, MatchString, SearchlightConfig, SizeField}, validation::{FileValidationType, FileValidator, RejectionReason}};
+
+	use super::SizeFieldValidator;
+
+	#[test]
+	fn test_size_field_validator() {
+		// A RIFF file, whose size field is of the data following it
+		let mut riff = b"RIFF".to_vec();
+		riff.extend_from_slice(&100u32.to_le_bytes());
+		riff.extend_from_slice(b"WAVE");
+		riff.extend_from_slice(&[ 0x55; 96 ]);
+
+		let mut data = vec![ 0xaa; 16 ];
+		data.extend_from_slice(&riff);
+		let end = data.len();
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		let file_type = FileType {
+			type_id: FileTypeId::Unknown,
+			max_len: Some(1024),
+			requires: vec![ MatchString::from("WAVE") ],
+			size_field: Some(SizeField {
+				offset: 4,
+				width: 4,
+				endianness: Endianness::Little,
+				relative: true,
+				addend: 0
+			}),
+			..Default::default()
+		};
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: 16,
+			end_idx: 16 + 1024,
+			truncated: false
+		};
+
+		let validator = SizeFieldValidator::new();
+
+		let info = validator.validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ 16..end ]);
+
+		// The data ends before the end of the file
+		let info = validator.validate(&data[..(end - 10)], &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.fragments, vec![ 16..(end - 10) ]);
+
+		// A big-endian, absolute size that is larger than max_len
+		let file_type = FileType {
+			type_id: FileTypeId::Unknown,
+			max_len: Some(1024),
+			size_field: Some(SizeField {
+				offset: 4,
+				width: 4,
+				endianness: Endianness::Big,
+				relative: false,
+				addend: 0
+			}),
+			..Default::default()
+		};
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: 16,
+			end_idx: 16 + 1024,
+			truncated: false
+		};
+
+		let info = validator.validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::SizeFieldInvalid));
+
+		// The field lies past the end of the candidate
+		let file_match = MatchPair {
+			end_idx: 16 + 5,
+			..file_match
+		};
+
+		let info = validator.validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Unrecognised);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::SizeFieldOutOfBounds));
+	}
+}
\ No newline at end of file
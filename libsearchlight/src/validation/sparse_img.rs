@@ -0,0 +1,270 @@
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+const SPARSE_MAGIC: u32 = 0xed26ff3a;
+const SPARSE_MAJOR_VERSION: u16 = 1;
+
+const SPARSE_FILE_HEADER_SIZE: usize = 28;
+const SPARSE_CHUNK_HEADER_SIZE: usize = 12;
+
+const SPARSE_CHUNK_TYPE_RAW: u16 = 0xcac1;
+const SPARSE_CHUNK_TYPE_FILL: u16 = 0xcac2;
+const SPARSE_CHUNK_TYPE_DONT_CARE: u16 = 0xcac3;
+const SPARSE_CHUNK_TYPE_CRC32: u16 = 0xcac4;
+
+pub struct SparseImgValidator;
+
+struct SparseHeader {
+	magic: u32,
+	major_version: u16,
+	file_header_size: u16,
+	chunk_header_size: u16,
+	block_size: u32,
+	total_blocks: u32,
+	total_chunks: u32
+}
+
+struct ChunkHeader {
+	chunk_type: u16,
+	/// The number of blocks of the output image that the chunk covers
+	num_blocks: u32,
+	/// The size of the chunk in the sparse file, including the chunk header
+	total_size: u32
+}
+
+impl SparseHeader {
+	fn decode(data: &[u8]) -> Self {
+		SparseHeader {
+			magic: u32::from_le_bytes(data[0x00..0x04].try_into().unwrap()),
+			major_version: u16::from_le_bytes(data[0x04..0x06].try_into().unwrap()),
+			file_header_size: u16::from_le_bytes(data[0x08..0x0a].try_into().unwrap()),
+			chunk_header_size: u16::from_le_bytes(data[0x0a..0x0c].try_into().unwrap()),
+			block_size: u32::from_le_bytes(data[0x0c..0x10].try_into().unwrap()),
+			total_blocks: u32::from_le_bytes(data[0x10..0x14].try_into().unwrap()),
+			total_chunks: u32::from_le_bytes(data[0x14..0x18].try_into().unwrap())
+		}
+	}
+
+	/// Whether the header fields hold values that are allowed by the format. Headers and chunk headers may be larger than the sizes that
+	/// are known, with the extra bytes skipped, but not smaller
+	fn fields_valid(&self) -> bool {
+		self.magic == SPARSE_MAGIC
+			&& self.major_version == SPARSE_MAJOR_VERSION
+			&& self.file_header_size as usize >= SPARSE_FILE_HEADER_SIZE
+			&& self.chunk_header_size as usize >= SPARSE_CHUNK_HEADER_SIZE
+			&& self.block_size > 0
+			&& self.block_size % 4 == 0
+	}
+}
+
+impl ChunkHeader {
+	fn decode(data: &[u8]) -> Self {
+		ChunkHeader {
+			chunk_type: u16::from_le_bytes(data[0x00..0x02].try_into().unwrap()),
+			num_blocks: u32::from_le_bytes(data[0x04..0x08].try_into().unwrap()),
+			total_size: u32::from_le_bytes(data[0x08..0x0c].try_into().unwrap())
+		}
+	}
+
+	/// The size of the data that should follow the chunk header for a chunk of this type, or None if the chunk type is not recognised
+	fn expected_data_size(&self, block_size: u32) -> Option<u64> {
+		match self.chunk_type {
+			SPARSE_CHUNK_TYPE_RAW => Some(self.num_blocks as u64 * block_size as u64),
+			SPARSE_CHUNK_TYPE_FILL | SPARSE_CHUNK_TYPE_CRC32 => Some(4),
+			SPARSE_CHUNK_TYPE_DONT_CARE => Some(0),
+			_ => None
+		}
+	}
+}
+
+impl SparseImgValidator {
+	pub fn new() -> Self {
+		SparseImgValidator
+	}
+}
+
+impl FileValidator for SparseImgValidator {
+	// Written using https://android.googlesource.com/platform/system/core/+/refs/heads/main/libsparse/sparse_format.h
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+
+		let max_idx = if let Some(max_len) = file_match.file_type.max_len {
+			(start + max_len as usize).min(file_data.len())
+		} else {
+			file_data.len()
+		};
+
+		if start + SPARSE_FILE_HEADER_SIZE > max_idx {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Partial,
+				fragments: vec![ (start..max_idx) ],
+				rejection_reason: Some(RejectionReason::Truncated),
+				..Default::default()
+			};
+		}
+
+		let header = SparseHeader::decode(&file_data[start..]);
+
+		// We can't walk the chunks without knowing the layout of the headers, so there's nothing more we can do
+		if !header.fields_valid() {
+			return FileValidationInfo {
+				validation_type: FileValidationType::FormatError,
+				fragments: vec![ (start..(start + SPARSE_FILE_HEADER_SIZE)) ],
+				rejection_reason: Some(RejectionReason::SparseImgInvalidHeader),
+				..Default::default()
+			};
+		}
+
+		let chunk_header_size = header.chunk_header_size as usize;
+
+		let mut i = start + header.file_header_size as usize;
+		let mut num_blocks = 0u64;
+
+		for _ in 0..header.total_chunks {
+			if i + chunk_header_size > max_idx {
+				return FileValidationInfo {
+					validation_type: FileValidationType::Partial,
+					fragments: vec![ (start..max_idx) ],
+					rejection_reason: Some(RejectionReason::Truncated),
+					..Default::default()
+				};
+			}
+
+			let chunk = ChunkHeader::decode(&file_data[i..]);
+
+			// The size of each chunk is given by its type, so a chunk whose size doesn't match is not a valid chunk, and the following
+			// chunk can't be found reliably
+			let size_valid = chunk.expected_data_size(header.block_size).is_some_and(|data_size| chunk.total_size as u64 == chunk_header_size as u64 + data_size);
+			if !size_valid {
+				return FileValidationInfo {
+					validation_type: FileValidationType::FormatError,
+					fragments: vec![ (start..i) ],
+					rejection_reason: Some(RejectionReason::SparseImgInvalidChunk),
+					..Default::default()
+				};
+			}
+
+			if i + chunk.total_size as usize > max_idx {
+				return FileValidationInfo {
+					validation_type: FileValidationType::Partial,
+					fragments: vec![ (start..max_idx) ],
+					rejection_reason: Some(RejectionReason::Truncated),
+					..Default::default()
+				};
+			}
+
+			i += chunk.total_size as usize;
+			num_blocks += chunk.num_blocks as u64;
+		}
+
+		// The chunks should cover every block of the output image
+		let (validation_type, rejection_reason) = if num_blocks == header.total_blocks as u64 {
+			(FileValidationType::Correct, None)
+		} else {
+			(FileValidationType::FormatError, Some(RejectionReason::SparseImgBlockCountMismatch))
+		};
+
+		FileValidationInfo {
+			validation_type,
+			fragments: vec![ (start..i) ],
+			rejection_reason,
+			..Default::default()
+		}
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the sparse image header, and that the chunks are of known types and sizes and cover the blocks in the header"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason}};
+
+	use super::SparseImgValidator;
+
+	fn chunk(chunk_type: u16, num_blocks: u32, data: &[u8]) -> Vec<u8> {
+		let mut chunk = chunk_type.to_le_bytes().to_vec();
+		chunk.extend_from_slice(&[ 0; 2 ]);
+		chunk.extend_from_slice(&num_blocks.to_le_bytes());
+		chunk.extend_from_slice(&(12 + data.len() as u32).to_le_bytes());
+		chunk.extend_from_slice(data);
+		chunk
+	}
+
+	fn synthetic_sparse_img(total_blocks: u32) -> Vec<u8> {
+		let block_size = 16u32;
+
+		let mut img = vec![ 0x3a, 0xff, 0x26, 0xed ];
+		img.extend_from_slice(&1u16.to_le_bytes());
+		img.extend_from_slice(&0u16.to_le_bytes());
+		img.extend_from_slice(&28u16.to_le_bytes());
+		img.extend_from_slice(&12u16.to_le_bytes());
+		img.extend_from_slice(&block_size.to_le_bytes());
+		img.extend_from_slice(&total_blocks.to_le_bytes());
+		img.extend_from_slice(&4u32.to_le_bytes());
+		img.extend_from_slice(&0u32.to_le_bytes());
+
+		// 2 raw blocks, 3 filled blocks, 1 skipped block, and a CRC
+		img.extend_from_slice(&chunk(0xcac1, 2, &[ 0x55; 32 ]));
+		img.extend_from_slice(&chunk(0xcac2, 3, &[ 0xde, 0xad, 0xbe, 0xef ]));
+		img.extend_from_slice(&chunk(0xcac3, 1, &[]));
+		img.extend_from_slice(&chunk(0xcac4, 0, &[ 0; 4 ]));
+
+		img
+	}
+
+	#[test]
+	fn test_sparse_img_validator() {
+		let file_type = FileType {
+			type_id: FileTypeId::SparseImg,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let img = synthetic_sparse_img(6);
+
+		let mut data = vec![ 0xaa; 16 ];
+		let start = data.len();
+		data.extend_from_slice(&img);
+		let end = data.len();
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: start,
+			end_idx: start + 1024 * 1024,
+			truncated: false
+		};
+
+		let validator = SparseImgValidator::new();
+
+		let info = validator.validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ (start..end) ]);
+
+		// Truncated in the raw chunk
+		let info = validator.validate(&data[..(start + 50)], &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::Truncated));
+
+		// The chunks don't cover all of the blocks in the header
+		let mut bad_data = data[..start].to_vec();
+		bad_data.extend_from_slice(&synthetic_sparse_img(7));
+		let info = validator.validate(&bad_data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::SparseImgBlockCountMismatch));
+
+		// Unsupported major version
+		let mut bad_data = data.clone();
+		bad_data[start + 4] = 2;
+		let info = validator.validate(&bad_data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::SparseImgInvalidHeader));
+	}
+}
\ No newline at end of file
@@ -0,0 +1,272 @@
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig};
+
+use super::{FileValidationInfo, FileValidationType, FileValidator, RejectionReason};
+
+const WIM_MAGIC: &[u8] = b"MSWIM\0\0\0";
+const WIM_HEADER_SIZE: usize = 208;
+
+/// The versions of the format written by imagex/DISM, older versions of imagex, and for solid (ESD-style) archives
+const WIM_VERSIONS: &[u32] = &[ 0x00010d00, 0x00010a00, 0x00000e00 ];
+
+const WIM_RESHDR_SIZE: usize = 24;
+
+/// The offsets of the resource headers in the WIM header: the lookup table, the XML data, the boot metadata and the integrity table
+const WIM_RESHDR_OFFSETS: [usize; 4] = [ 0x30, 0x48, 0x60, 0x7c ];
+const WIM_XML_DATA_RESHDR_IDX: usize = 1;
+
+pub struct WimValidator;
+
+struct WimHeader {
+	magic: [u8; 8],
+	header_size: u32,
+	version: u32,
+	part_number: u16,
+	total_parts: u16,
+	resources: [ResourceHeader; 4]
+}
+
+/// A pointer to a resource in the file
+#[derive(Clone, Copy)]
+struct ResourceHeader {
+	/// The size of the resource in the file, which is the lower 56 bits of the field, the upper 8 being flags
+	size: u64,
+	offset: u64
+}
+
+impl WimHeader {
+	fn decode(data: &[u8]) -> Self {
+		WimHeader {
+			magic: data[0x00..0x08].try_into().unwrap(),
+			header_size: u32::from_le_bytes(data[0x08..0x0c].try_into().unwrap()),
+			version: u32::from_le_bytes(data[0x0c..0x10].try_into().unwrap()),
+			part_number: u16::from_le_bytes(data[0x28..0x2a].try_into().unwrap()),
+			total_parts: u16::from_le_bytes(data[0x2a..0x2c].try_into().unwrap()),
+			resources: WIM_RESHDR_OFFSETS.map(|offset| ResourceHeader::decode(&data[offset..(offset + WIM_RESHDR_SIZE)]))
+		}
+	}
+
+	/// Whether the header fields hold values that are allowed by the format
+	fn fields_valid(&self) -> bool {
+		self.magic == WIM_MAGIC
+			&& self.header_size as usize == WIM_HEADER_SIZE
+			&& WIM_VERSIONS.contains(&self.version)
+			&& self.part_number > 0
+			&& self.part_number <= self.total_parts
+	}
+}
+
+impl ResourceHeader {
+	fn decode(data: &[u8]) -> Self {
+		ResourceHeader {
+			size: u64::from_le_bytes(data[0x00..0x08].try_into().unwrap()) & 0x00ff_ffff_ffff_ffff,
+			offset: u64::from_le_bytes(data[0x08..0x10].try_into().unwrap())
+		}
+	}
+
+	/// The end of the resource relative to the start of the file, or None if it overflows
+	fn end(&self) -> Option<u64> {
+		self.offset.checked_add(self.size)
+	}
+}
+
+impl WimValidator {
+	pub fn new() -> Self {
+		WimValidator
+	}
+}
+
+impl FileValidator for WimValidator {
+	// Written using the wimlib documentation of the format, https://wimlib.net/ (include/wimlib/header.h and resource.h)
+	fn validate(&self, file_data: &[u8], file_match: &MatchPair, _all_matches: &[Match], _cluster_size: usize, _config: &SearchlightConfig) -> FileValidationInfo {
+		let start = file_match.start_idx;
+
+		let max_len = file_match.file_type.max_len.unwrap_or((file_data.len() - start) as u64);
+		let max_idx = (start as u64).saturating_add(max_len).min(file_data.len() as u64) as usize;
+
+		if start + WIM_HEADER_SIZE > max_idx {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Partial,
+				fragments: vec![ (start..max_idx) ],
+				rejection_reason: Some(RejectionReason::Truncated),
+				..Default::default()
+			};
+		}
+
+		let header = WimHeader::decode(&file_data[start..]);
+
+		if !header.fields_valid() {
+			return FileValidationInfo {
+				validation_type: FileValidationType::FormatError,
+				fragments: vec![ (start..(start + WIM_HEADER_SIZE)) ],
+				rejection_reason: Some(RejectionReason::WimInvalidHeader),
+				..Default::default()
+			};
+		}
+
+		// Resources with a size of 0 are absent. The present ones must lie after the header, within max_len, and not overlap each other
+		let mut resources: Vec<ResourceHeader> = header.resources.iter().copied().filter(|res| res.size > 0).collect();
+		resources.sort_by_key(|res| res.offset);
+
+		let resources_valid = header.resources[WIM_XML_DATA_RESHDR_IDX].size > 0
+			&& resources.iter().all(|res| res.offset >= WIM_HEADER_SIZE as u64 && res.end().is_some_and(|end| end <= max_len))
+			&& resources.windows(2).all(|pair| pair[0].end().unwrap() <= pair[1].offset);
+
+		if !resources_valid {
+			return FileValidationInfo {
+				validation_type: FileValidationType::FormatError,
+				fragments: vec![ (start..(start + WIM_HEADER_SIZE)) ],
+				rejection_reason: Some(RejectionReason::WimInvalidResource),
+				..Default::default()
+			};
+		}
+
+		// The file ends at the end of the furthest resource, which is within max_len, but may be past the end of the data
+		let end = start + resources.iter().map(|res| res.end().unwrap() as usize).max().unwrap_or(WIM_HEADER_SIZE);
+
+		if end > max_idx {
+			return FileValidationInfo {
+				validation_type: FileValidationType::Partial,
+				fragments: vec![ (start..max_idx) ],
+				rejection_reason: Some(RejectionReason::Truncated),
+				..Default::default()
+			};
+		}
+
+		// The XML data is stored uncompressed as UTF-16LE, optionally with a byte order mark, so should start with either that or a '<'
+		let xml_start = start + header.resources[WIM_XML_DATA_RESHDR_IDX].offset as usize;
+		let (validation_type, rejection_reason) = match file_data.get(xml_start..(xml_start + 2)) {
+			Some([ 0xff, 0xfe ] | [ b'<', 0x00 ]) => (FileValidationType::Correct, None),
+			_ => (FileValidationType::FormatError, Some(RejectionReason::WimInvalidResource))
+		};
+
+		FileValidationInfo {
+			validation_type,
+			fragments: vec![ (start..end) ],
+			rejection_reason,
+			..Default::default()
+		}
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the WIM header, and that the lookup table, XML data, boot metadata and integrity table lie after it without overlapping, finding the end of the file from the furthest of them"
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{search::pairing::MatchPair, searchlight::config::{FileType, FileTypeId, SearchlightConfig}, validation::{FileValidationType, FileValidator, RejectionReason}};
+
+	use super::WimValidator;
+
+	fn reshdr(size: u64, offset: u64) -> Vec<u8> {
+		// The upper byte of the size field holds flags, which are set to check that they are excluded from the size
+		let mut reshdr = (size | (0x04 << 56)).to_le_bytes().to_vec();
+		reshdr.extend_from_slice(&offset.to_le_bytes());
+		reshdr.extend_from_slice(&size.to_le_bytes());
+		reshdr
+	}
+
+	/// A WIM with a lookup table at `lookup_table_offset` and the XML data after the header and lookup table, and no boot metadata or
+	/// integrity table
+	fn synthetic_wim(header_size: u32, lookup_table_offset: u64) -> Vec<u8> {
+		let lookup_table = [ 0x11; 50 ];
+		let xml: Vec<u8> = "\u{feff}<WIM><TOTALBYTES>0</TOTALBYTES></WIM>".encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+
+		let mut wim = b"MSWIM\0\0\0".to_vec();
+		wim.extend_from_slice(&header_size.to_le_bytes());
+		wim.extend_from_slice(&0x00010d00u32.to_le_bytes());
+		wim.extend_from_slice(&0u32.to_le_bytes());
+		wim.extend_from_slice(&32768u32.to_le_bytes());
+		wim.extend_from_slice(&[ 0xab; 16 ]);
+		wim.extend_from_slice(&1u16.to_le_bytes());
+		wim.extend_from_slice(&1u16.to_le_bytes());
+		wim.extend_from_slice(&1u32.to_le_bytes());
+		wim.extend_from_slice(&reshdr(lookup_table.len() as u64, lookup_table_offset));
+		wim.extend_from_slice(&reshdr(xml.len() as u64, 208 + lookup_table.len() as u64));
+		wim.extend_from_slice(&reshdr(0, 0));
+		wim.extend_from_slice(&0u32.to_le_bytes());
+		wim.extend_from_slice(&reshdr(0, 0));
+		wim.extend_from_slice(&[ 0; 60 ]);
+
+		wim.extend_from_slice(&lookup_table);
+		wim.extend_from_slice(&xml);
+
+		wim
+	}
+
+	#[test]
+	fn test_wim_validator() {
+		let file_type = FileType {
+			type_id: FileTypeId::Wim,
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let wim = synthetic_wim(208, 208);
+
+		let mut data = vec![ 0xaa; 16 ];
+		let start = data.len();
+		data.extend_from_slice(&wim);
+		let end = data.len();
+		data.extend_from_slice(&[ 0xaa; 64 ]);
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: start,
+			end_idx: start + 1024 * 1024,
+			truncated: false
+		};
+
+		let validator = WimValidator::new();
+
+		let info = validator.validate(&data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.fragments, vec![ (start..end) ]);
+
+		// Truncated in the XML data
+		let info = validator.validate(&data[..(end - 10)], &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::Truncated));
+		assert_eq!(info.fragments, vec![ (start..(end - 10)) ]);
+
+		// Truncated in the header
+		let info = validator.validate(&data[..(start + 100)], &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::Truncated));
+
+		// Invalid header size
+		let mut bad_data = data[..start].to_vec();
+		bad_data.extend_from_slice(&synthetic_wim(200, 208));
+		let info = validator.validate(&bad_data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::WimInvalidHeader));
+
+		// Unsupported version
+		let mut bad_data = data.clone();
+		bad_data[start + 0x0e] = 2;
+		let info = validator.validate(&bad_data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::WimInvalidHeader));
+
+		// A resource overlapping the header
+		let mut bad_data = data[..start].to_vec();
+		bad_data.extend_from_slice(&synthetic_wim(208, 100));
+		let info = validator.validate(&bad_data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::WimInvalidResource));
+
+		// A resource beyond max_len
+		let mut bad_data = data[..start].to_vec();
+		bad_data.extend_from_slice(&synthetic_wim(208, 2 * 1024 * 1024));
+		let info = validator.validate(&bad_data, &file_match, &[], 1, &SearchlightConfig::default());
+
+		assert_eq!(info.validation_type, FileValidationType::FormatError);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::WimInvalidResource));
+	}
+}
\ No newline at end of file
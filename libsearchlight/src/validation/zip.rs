@@ -1,10 +1,10 @@
-use std::io::Read;
+use std::{collections::HashMap, io::Read};
 
-use log::warn;
+use log::{info, warn};
 
-use crate::{search::{pairing::MatchPair, Match}, searchlight::config::SearchlightConfig, utils::{self, multi_reader::MultiReader}};
+use crate::{search::{pairing::MatchPair, Match}, searchlight::config::{FileTypeId, SearchlightConfig, ZipEntryCountPolicy}, utils::{self, multi_reader::MultiReader}};
 
-use super::{FileValidationInfo, FileValidationType, FileValidator, Fragment};
+use super::{max_reconstruction_search_len, EmbeddedEncoding, EmbeddedFile, FileValidationInfo, FileValidationType, FileValidator, Fragment, RejectionReason, METADATA_MODIFIED_TIME, METADATA_ORIGINAL_NAME, PARAM_ENTRY_COUNT_POLICY, PARAM_MAX_RECONSTRUCTION_SEARCH_LEN};
 
 const ZIP_LOCAL_FILE_HEADER_SIG: u32 = 0x04034b50;
 const ZIP_CENTRAL_DIR_HEADER_SIG: u32 = 0x02014b50;
@@ -25,11 +25,15 @@ const ZIP_COMPRESSION_METHOD_DEFLATE: u16 = 8;
 
 const DECOMPRESS_BUFFER_SIZE: usize = 1024 * 1024;
 
+/// Understands the validator parameters `PARAM_MAX_RECONSTRUCTION_SEARCH_LEN` and `PARAM_ENTRY_COUNT_POLICY`
 pub struct ZipValidator;
 
 struct LocalFileValidationInfo {
 	validation_type: FileValidationType,
-	frags: Vec<Fragment>
+	frags: Vec<Fragment>,
+	/// The fragments of just the (possibly compressed) file data, excluding the local file header and data descriptor
+	data_frags: Vec<Fragment>,
+	rejection_reason: Option<RejectionReason>
 }
 
 enum FileDataReconstructionInfo {
@@ -59,6 +63,8 @@ struct LocalFileHeader<'a> {
 	compressed_size: u32,
 	file_name: &'a [u8],
 	// extra_field: &'a [u8],
+	/// The last modification time of the file, as seconds since the Unix epoch, if the stored date and time are valid
+	mod_time: Option<i64>,
 	offset: u32, // From CD
 	len: usize
 }
@@ -73,6 +79,20 @@ enum CrcCalcError {
 	DecompressionError,
 }
 
+/// Converts a MS-DOS date and time, as stored in ZIP file headers, to seconds since the Unix epoch. MS-DOS times are in local time with
+/// no indication of the time zone, so this treats them as UTC
+fn dos_timestamp(time: u16, date: u16) -> Option<i64> {
+	let year = 1980 + (date >> 9) as i64;
+	let month = ((date >> 5) & 0x0f) as u32;
+	let day = (date & 0x1f) as u32;
+
+	let hour = (time >> 11) as u32;
+	let minute = ((time >> 5) & 0x3f) as u32;
+	let second = ((time & 0x1f) * 2) as u32;
+
+	utils::unix_timestamp(year, month, day, hour, minute, second)
+}
+
 /// Calculates the CRC of input data slices, which depends on the compression method: For store, you can just calculate the CRC
 /// on the bytes directly, for deflate (or any other compression scheme but we're only supporting deflate cause it's the most
 /// widely used) you need to decompress first
@@ -156,6 +176,10 @@ impl<'a> LocalFileHeader<'a> {
 		let has_data_descriptor = (flags & ZIP_DATA_DESCRIPTOR_FLAG) > 0;
 
 		let compression_method = u16::from_le_bytes(data[0x08..0x0a].try_into().unwrap());
+		let mod_time = dos_timestamp(
+			u16::from_le_bytes(data[0x0a..0x0c].try_into().unwrap()),
+			u16::from_le_bytes(data[0x0c..0x0e].try_into().unwrap())
+		);
 		let crc = u32::from_le_bytes(data[0x0e..0x12].try_into().unwrap());
 		let compressed_size = u32::from_le_bytes(data[0x12..0x16].try_into().unwrap());
 		let file_name_len = u16::from_le_bytes(data[0x1a..0x1c].try_into().unwrap()) as usize;
@@ -172,6 +196,7 @@ impl<'a> LocalFileHeader<'a> {
 			compressed_size,
 			file_name,
 			// extra_field,
+			mod_time,
 			offset: 0,
 			len: ZIP_LOCAL_FILE_HEADER_SIZE + file_name_len + extra_field_len
 		})
@@ -216,7 +241,8 @@ impl ZipValidator {
 		ZipValidator
 	}
 
-	fn validate_file(file_data: &[u8], header: &LocalFileHeader, next_header_idx: usize, cluster_size: usize, config: &SearchlightConfig) -> LocalFileValidationInfo {
+	/// Validates the file data following the local file header `header`, attempting to reconstruct it if the CRC doesn't match, unless `verify_crc` is false
+	fn validate_file(file_data: &[u8], header: &LocalFileHeader, next_header_idx: usize, cluster_size: usize, max_search_len: usize, verify_crc: bool) -> LocalFileValidationInfo {
 		let data_idx = header.idx + header.len;
 
 		let data_descriptor_len = if header.has_data_descriptor {
@@ -240,15 +266,18 @@ impl ZipValidator {
 		};
 
 		let unfrag_end = data_idx + header.compressed_size as usize + data_descriptor_len;
+		let unfrag_data = data_idx..(data_idx + header.compressed_size as usize);
 
-		let unfrag_crc = match zip_crc_calc(&[&file_data[data_idx..(data_idx + header.compressed_size as usize)]], header.compression_method) {
+		let unfrag_crc = match zip_crc_calc(&[&file_data[unfrag_data.clone()]], header.compression_method) {
 			Ok(crc) => crc,
 			Err(CrcCalcError::UnsupportedCompressionMethod) => {
 				// If we encounter an unsupported compression method, just return the data as if it was unfragmented cause we can't reconstruct it
 				warn!("ZIP: Unsupported compression method ({}) may cause errors (header at {:#0x})", header.compression_method, header.idx);
 				return LocalFileValidationInfo {
 					validation_type: FileValidationType::Unanalysed,
-					frags: vec![ (header.idx as usize..unfrag_end) ]
+					frags: vec![ (header.idx as usize..unfrag_end) ],
+					data_frags: vec![ unfrag_data ],
+					rejection_reason: Some(RejectionReason::ZipUnsupportedCompression)
 				}
 			}
 			Err(CrcCalcError::DecompressionError) => {
@@ -267,58 +296,117 @@ impl ZipValidator {
 				warn!("ZIP: Possible corruption or out-of-order fragmentation detected (header at {:#0x})", header.idx);
 				return LocalFileValidationInfo {
 					validation_type: FileValidationType::Corrupt,
-					frags: vec![ (header.idx as usize..unfrag_end) ]
+					frags: vec![ (header.idx as usize..unfrag_end) ],
+					data_frags: vec![ unfrag_data ],
+					rejection_reason: Some(RejectionReason::ZipOutOfOrderFragmentation)
+				}
+			}
+
+			// If we're not verifying CRCs, then don't attempt reconstruction, and just return the data as if it was unfragmented
+			if !verify_crc {
+				return LocalFileValidationInfo {
+					validation_type: FileValidationType::Partial,
+					frags: vec![ (header.idx..unfrag_end) ],
+					data_frags: vec![ unfrag_data ],
+					rejection_reason: Some(RejectionReason::ZipCrcMismatch)
 				}
 			}
 
 			// If, however, the max reconstruction search len is set to a value smaller than the distance between the next_header_idx and this header idx,
 			// then we move on, emitting a warning
-			if next_header_idx - header.idx > config.max_reconstruction_search_len.map(|len| len as usize).unwrap_or(usize::MAX) {
+			if next_header_idx - header.idx > max_search_len {
 				warn!("ZIP: Not attempting to reconstruct possibly fragmented file data due to max_reconstruction_search_len (header at {:#0x})", header.idx);
 				return LocalFileValidationInfo {
 					validation_type: FileValidationType::Unanalysed,
-					frags: vec![ (header.idx as usize..unfrag_end) ]
+					frags: vec![ (header.idx as usize..unfrag_end) ],
+					data_frags: vec![ unfrag_data ],
+					rejection_reason: Some(RejectionReason::ZipReconstructionSearchLenExceeded)
 				}
 			}
 
 			let recons_info = Self::reconstruct_file_data(file_data, header, data_idx, next_header_idx, cluster_size);
 
 			match recons_info {
-				FileDataReconstructionInfo::Success { mut data_frags, end_idx } => {
+				FileDataReconstructionInfo::Success { data_frags, end_idx } => {
 					let header_frag = header.idx..data_idx;
-					data_frags.insert(0, header_frag);
+					let mut file_frags = data_frags.clone();
+					file_frags.insert(0, header_frag);
 
 					if header.has_data_descriptor {
 						let data_descriptor = DataDescriptor::decode(&file_data[end_idx..]);
 						let data_desc_frag = end_idx..(end_idx + data_descriptor.len);
-						data_frags.push(data_desc_frag);
+						file_frags.push(data_desc_frag);
 					}
 
-					utils::simplify_ranges(&mut data_frags);
+					utils::simplify_ranges(&mut file_frags);
 
 					LocalFileValidationInfo {
 						validation_type: FileValidationType::Correct,
-						frags: data_frags
+						frags: file_frags,
+						data_frags,
+						rejection_reason: None
 					}
 				}
 				FileDataReconstructionInfo::Failure => {
 					LocalFileValidationInfo {
 						validation_type: FileValidationType::Partial,
-						frags: vec![ (header.idx as usize..unfrag_end) ]
+						frags: vec![ (header.idx as usize..unfrag_end) ],
+						data_frags: vec![ unfrag_data ],
+						rejection_reason: Some(RejectionReason::ZipFileReconstructionFailed)
 					}
 				}
 			}
 		} else {
 			LocalFileValidationInfo {
 				validation_type: FileValidationType::Correct,
-				frags: vec![ (header.idx as usize..unfrag_end) ]
+				frags: vec![ (header.idx as usize..unfrag_end) ],
+				data_frags: vec![ unfrag_data ],
+				rejection_reason: None
 			}
 		}
 	}
 
+	/// Describes the file of the local file header `header` as an embedded file of the archive, so that it can be extracted, unless it is a
+	/// directory or its compression method is not supported
+	fn embedded_member(header: &LocalFileHeader, validation_info: &LocalFileValidationInfo) -> Option<EmbeddedFile> {
+		let name = String::from_utf8_lossy(header.file_name).into_owned();
+
+		// Directories are stored as empty files with a trailing slash, so there is nothing to extract
+		if name.ends_with('/') {
+			return None;
+		}
+
+		let encoding = match header.compression_method {
+			ZIP_COMPRESSION_METHOD_STORE => EmbeddedEncoding::Stored,
+			ZIP_COMPRESSION_METHOD_DEFLATE => EmbeddedEncoding::Deflate,
+			_ => {
+				info!("ZIP: Not extracting \"{}\" as its compression method ({}) is unsupported (header at {:#0x})", name, header.compression_method, header.idx);
+				return None;
+			}
+		};
+
+		Some(EmbeddedFile {
+			name,
+			validation_type: validation_info.validation_type,
+			encoding,
+			fragments: validation_info.data_frags.clone()
+		})
+	}
+
 	/// Attempts to reconstruct ZIP file data, given an assumed unfragmented local file header, and the index of either the next header, assuming ZIP segments
 	/// are tightly packed, or the central directory if no header was found after this one, by enumerating some possible cluster arrangements between the start
 	/// of the file data and the next header index for a calculated CRC that matches that in the header
+	/// Returns the directory that all of the files in the archive are in, if they are all in the same top-level directory
+	fn top_level_dir(headers: &[LocalFileHeader]) -> Option<String> {
+		fn dir_of(file_name: &[u8]) -> Option<&[u8]> {
+			file_name.iter().position(|&b| b == b'/').map(|end| &file_name[..end])
+		}
+
+		let dir = dir_of(headers.first()?.file_name)?;
+
+		(!dir.is_empty() && headers.iter().all(|header| dir_of(header.file_name) == Some(dir))).then(|| String::from_utf8_lossy(dir).into_owned())
+	}
+
 	fn reconstruct_file_data(file_data: &[u8], header: &LocalFileHeader, data_idx: usize, next_header_idx: usize, cluster_size: usize) -> FileDataReconstructionInfo {
 		let data_descriptor_len = {
 			if header.has_data_descriptor {
@@ -428,6 +516,7 @@ impl FileValidator for ZipValidator {
 		if (eocd_idx + ZIP_END_OF_CENTRAL_DIR_SIZE) > file_data.len() {
 			return FileValidationInfo {
 				validation_type: FileValidationType::Partial,
+				rejection_reason: Some(RejectionReason::Truncated),
 				..Default::default()
 			}
 		}
@@ -447,6 +536,7 @@ impl FileValidator for ZipValidator {
 		if cd_diskno != cd_start_diskno || cd_diskno > 0 {
 			return FileValidationInfo {
 				validation_type: FileValidationType::Unanalysed,
+				rejection_reason: Some(RejectionReason::ZipMultiDisk),
 				..Default::default()
 			}
 		}
@@ -502,30 +592,264 @@ impl FileValidator for ZipValidator {
 
 		let mut file_frags = vec![ frag_cd_eocd ];
 		let mut worst_file_validation = FileValidationType::Correct;
+		let mut rejection_reason = None;
+		let mut embedded = Vec::new();
 
 		for i in 0..local_file_headers.len() {
 			// Calculate the next header offset, or the central directory index if there are no more files between the header and central directory
 			let next_header_idx = local_file_headers.get(i + 1).map(|header| header.idx as usize).unwrap_or(central_directory_idx);
-			let mut validation_info = Self::validate_file(file_data, &local_file_headers[i], next_header_idx, cluster_size, config);
+			let mut validation_info = Self::validate_file(file_data, &local_file_headers[i], next_header_idx, cluster_size, max_reconstruction_search_len(file_match.file_type, config), file_match.file_type.should_verify_crc());
 
 			if validation_info.validation_type != FileValidationType::Unrecognised {
 				file_frags.append(&mut validation_info.frags);
-				worst_file_validation = worst_file_validation.worst_of(validation_info.validation_type);
+
+				if config.extract_embedded {
+					if let Some(member) = Self::embedded_member(&local_file_headers[i], &validation_info) {
+						embedded.push(member);
+					}
+				}
+
+				// Report the reason for the worst validation of the files
+				let new_worst_file_validation = worst_file_validation.worst_of(validation_info.validation_type);
+				if new_worst_file_validation != worst_file_validation {
+					rejection_reason = validation_info.rejection_reason;
+				}
+				worst_file_validation = new_worst_file_validation;
 			}
 		}
 
-		// Since the files could be in any order, we sort by start of fragment, and then simplify of course
+		// Since the files could be in any order, we sort by start of fragment, and then merge, as the fragments of reconstructed files may
+		// overlap those of other files
 		file_frags.sort_by_key(|range| range.start);
-		utils::simplify_ranges(&mut file_frags);
+		utils::merge_ranges(&mut file_frags);
 
 		if cd_total_entries as usize != local_file_headers.len() {
-			warn!("ZIP: Not all files were found for ZIP archive - Is '\\x50\\x4B\\x03\\x04' declared in the config as a ZIP header? (central directory at {:#0x})", central_directory_idx);
-			worst_file_validation = worst_file_validation.worst_of(FileValidationType::Corrupt);
+			let shortfall = local_file_headers.len() < cd_total_entries as usize;
+
+			if shortfall {
+				warn!("ZIP: Not all files were found for ZIP archive - Is '\\x50\\x4B\\x03\\x04' declared in the config as a ZIP header? (central directory at {:#0x})", central_directory_idx);
+			} else {
+				warn!("ZIP: More files were found for ZIP archive than are in its central directory (central directory at {:#0x})", central_directory_idx);
+			}
+
+			let entry_count_policy = file_match.file_type.param(PARAM_ENTRY_COUNT_POLICY).unwrap_or(config.zip_entry_count_policy);
+
+			let mismatch_validation = match (entry_count_policy, shortfall) {
+				(ZipEntryCountPolicy::Partial, true) => Some(FileValidationType::Partial),
+				(ZipEntryCountPolicy::ExcessOnly, true) => None,
+				_ => Some(FileValidationType::Corrupt)
+			};
+
+			if let Some(mismatch_validation) = mismatch_validation {
+				let new_worst_file_validation = worst_file_validation.worst_of(mismatch_validation);
+				if new_worst_file_validation != worst_file_validation {
+					rejection_reason = Some(RejectionReason::ZipEntryCountMismatch);
+				}
+				worst_file_validation = new_worst_file_validation;
+			}
+		}
+
+		// The archive was last modified when the most recently modified file in it was
+		let mut metadata = HashMap::new();
+		if let Some(mod_time) = local_file_headers.iter().filter_map(|header| header.mod_time).max() {
+			metadata.insert(METADATA_MODIFIED_TIME.to_string(), mod_time.to_string());
+		}
+
+		// Archives of a single directory are usually named after it, e.g. project-1.0.zip containing project-1.0/
+		if let Some(dir) = Self::top_level_dir(&local_file_headers) {
+			metadata.insert(METADATA_ORIGINAL_NAME.to_string(), format!("{dir}.{}", file_match.file_type.extension.as_deref().unwrap_or("zip")));
 		}
 
 		FileValidationInfo {
 			validation_type: worst_file_validation,
-			fragments: file_frags
+			fragments: file_frags,
+			rejection_reason,
+			metadata,
+			embedded
+		}
+	}
+
+	fn known_params(&self, _type_id: FileTypeId) -> &'static [&'static str] {
+		&[ PARAM_MAX_RECONSTRUCTION_SEARCH_LEN, PARAM_ENTRY_COUNT_POLICY ]
+	}
+
+	fn description(&self) -> &'static str {
+		"Checks the local file headers against the central directory, and the CRC of each file's data, reconstructing fragmented file data"
+	}
+
+	fn reconstructs_fragmentation(&self) -> bool {
+		true
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::HashMap;
+
+	use crate::{search::{pairing::MatchPair, Match}, searchlight::config::{FileType, FileTypeId, MatchString, SearchlightConfig, ZipEntryCountPolicy}, validation::{FileValidationType, FileValidator, RejectionReason, METADATA_ORIGINAL_NAME, PARAM_ENTRY_COUNT_POLICY}};
+
+	use super::{dos_timestamp, ZipValidator, ZIP_LOCAL_FILE_HEADER_SIG_ID};
+
+	/// Builds a ZIP archive of stored files, returning it along with the offset of each local file header
+	fn synthetic_zip(files: &[(&[u8], &[u8])]) -> (Vec<u8>, Vec<usize>) {
+		let mut zip = Vec::new();
+		let mut central_directory = Vec::new();
+		let mut offsets = Vec::new();
+
+		for (file_name, content) in files {
+			let crc = crc32fast::hash(content).to_le_bytes();
+			let size = (content.len() as u32).to_le_bytes();
+			let name_len = (file_name.len() as u16).to_le_bytes();
+
+			offsets.push(zip.len());
+
+			central_directory.extend_from_slice(&[ 0x50, 0x4b, 0x01, 0x02, 0x14, 0x00, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 ]);
+			central_directory.extend_from_slice(&crc);
+			central_directory.extend_from_slice(&size);
+			central_directory.extend_from_slice(&size);
+			central_directory.extend_from_slice(&name_len);
+			central_directory.extend_from_slice(&[ 0x00; 12 ]); // Extra field len, comment len, disk number, and attributes
+			central_directory.extend_from_slice(&(zip.len() as u32).to_le_bytes());
+			central_directory.extend_from_slice(file_name);
+
+			zip.extend_from_slice(&[ 0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 ]);
+			zip.extend_from_slice(&crc);
+			zip.extend_from_slice(&size);
+			zip.extend_from_slice(&size);
+			zip.extend_from_slice(&name_len);
+			zip.extend_from_slice(&[ 0x00, 0x00 ]);
+			zip.extend_from_slice(file_name);
+			zip.extend_from_slice(content);
 		}
+
+		let cd_offset = zip.len() as u32;
+		zip.extend_from_slice(&central_directory);
+
+		let num_entries = (files.len() as u16).to_le_bytes();
+
+		zip.extend_from_slice(&[ 0x50, 0x4b, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00 ]);
+		zip.extend_from_slice(&num_entries);
+		zip.extend_from_slice(&num_entries);
+		zip.extend_from_slice(&(central_directory.len() as u32).to_le_bytes());
+		zip.extend_from_slice(&cd_offset.to_le_bytes());
+		zip.extend_from_slice(&[ 0x00, 0x00 ]);
+
+		(zip, offsets)
+	}
+
+	#[test]
+	fn test_zip_entry_count_policy() {
+		let (zip, offsets) = synthetic_zip(&[ (b"a.txt", &[ b'a'; 100 ]), (b"b.txt", &[ b'b'; 50 ]) ]);
+
+		let mut data = vec![ 0xaa; 16 ];
+		let start = data.len();
+		data.extend_from_slice(&zip);
+		let end = data.len();
+		data.extend_from_slice(&[ 0xaa; 16 ]);
+
+		let file_type = FileType {
+			type_id: FileTypeId::Zip,
+			headers: vec![ MatchString::from("\\x50\\x4B\\x03\\x04") ],
+			footers: vec![ MatchString::from("\\x50\\x4B\\x05\\x06..................") ],
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let file_match = MatchPair {
+			file_type: &file_type,
+			start_idx: start,
+			end_idx: end - 1,
+			truncated: false
+		};
+
+		let header_matches: Vec<Match> = offsets.iter().map(|&offset| Match::new(ZIP_LOCAL_FILE_HEADER_SIG_ID, (start + offset) as u64, (start + offset + 3) as u64)).collect();
+
+		let validate = |all_matches: &[Match], policy: ZipEntryCountPolicy| {
+			let config = SearchlightConfig {
+				zip_entry_count_policy: policy,
+				..Default::default()
+			};
+
+			ZipValidator::new().validate(&data, &file_match, all_matches, 1, &config)
+		};
+
+		assert_eq!(validate(&header_matches, ZipEntryCountPolicy::Strict).validation_type, FileValidationType::Correct);
+
+		// The local file header of the second file is absent from the matches
+		let info = validate(&header_matches[..1], ZipEntryCountPolicy::Strict);
+		assert_eq!(info.validation_type, FileValidationType::Corrupt);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::ZipEntryCountMismatch));
+
+		let info = validate(&header_matches[..1], ZipEntryCountPolicy::Partial);
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+		assert_eq!(info.rejection_reason, Some(RejectionReason::ZipEntryCountMismatch));
+
+		let info = validate(&header_matches[..1], ZipEntryCountPolicy::ExcessOnly);
+		assert_eq!(info.validation_type, FileValidationType::Correct);
+		assert_eq!(info.rejection_reason, None);
+
+		// The policy of the type, given as a validator parameter, takes precedence over the global policy
+		let param_file_type = FileType {
+			type_id: FileTypeId::Zip,
+			headers: file_type.headers.clone(),
+			footers: file_type.footers.clone(),
+			max_len: Some(1024 * 1024),
+			params: HashMap::from([ (PARAM_ENTRY_COUNT_POLICY.to_string(), toml::Value::String("partial".to_string())) ]),
+			..Default::default()
+		};
+
+		let file_match = MatchPair {
+			file_type: &param_file_type,
+			..file_match
+		};
+
+		let info = ZipValidator::new().validate(&data, &file_match, &header_matches[..1], 1, &SearchlightConfig::default());
+		assert_eq!(info.validation_type, FileValidationType::Partial);
+	}
+
+	#[test]
+	fn test_zip_original_name() {
+		let file_type = FileType {
+			type_id: FileTypeId::Zip,
+			extension: Some("zip".to_string()),
+			headers: vec![ MatchString::from("\\x50\\x4B\\x03\\x04") ],
+			footers: vec![ MatchString::from("\\x50\\x4B\\x05\\x06..................") ],
+			max_len: Some(1024 * 1024),
+			..Default::default()
+		};
+
+		let original_name = |files: &[(&[u8], &[u8])]| {
+			let (zip, offsets) = synthetic_zip(files);
+
+			let file_match = MatchPair {
+				file_type: &file_type,
+				start_idx: 0,
+				end_idx: zip.len() - 1,
+				truncated: false
+			};
+
+			let header_matches: Vec<Match> = offsets.iter().map(|&offset| Match::new(ZIP_LOCAL_FILE_HEADER_SIG_ID, offset as u64, offset as u64 + 3)).collect();
+
+			ZipValidator::new().validate(&zip, &file_match, &header_matches, 1, &SearchlightConfig::default()).metadata.get(METADATA_ORIGINAL_NAME).cloned()
+		};
+
+		// An archive of a single directory is named after it
+		assert_eq!(original_name(&[ (b"project-1.0/", &[]), (b"project-1.0/src/a.txt", b"a") ]).as_deref(), Some("project-1.0.zip"));
+
+		// Otherwise, its name can't be recovered
+		assert_eq!(original_name(&[ (b"project-1.0/a.txt", b"a"), (b"b.txt", b"b") ]), None);
+		assert_eq!(original_name(&[ (b"a/a.txt", b"a"), (b"b/b.txt", b"b") ]), None);
+	}
+
+	#[test]
+	fn test_dos_timestamp() {
+		// 2023-11-14 22:13:20
+		let date = ((2023 - 1980) << 9) | (11 << 5) | 14;
+		let time = (22 << 11) | (13 << 5) | (20 / 2);
+
+		assert_eq!(dos_timestamp(time, date), Some(1700000000));
+
+		// A zeroed date has a month and day of 0
+		assert_eq!(dos_timestamp(0, 0), None);
 	}
 }
\ No newline at end of file
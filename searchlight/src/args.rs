@@ -1,16 +1,21 @@
-use std::str::FromStr;
+use std::{num::ParseIntError, ops::Range, str::FromStr};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use clap_verbosity_flag::InfoLevel;
+use libsearchlight::searchlight::config::{IoStrategy, LogFormat, OffsetUnit, OutputLayout, ProgressMode};
 
 // TODO: Add a "quick search" option to only look for headers at the start of clusters... but still need to find footers...
 // TODO: Add in-place carving with FUSE/WinFsp
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+	#[command(subcommand)]
+	pub command: Option<Command>,
 	#[command(flatten)]
 	pub verbose: clap_verbosity_flag::Verbosity<InfoLevel>,
-	/// If specified, will read the target disk image file and attempt to carve files from it, using the default or specified configuration file and the default or specified cluster size
+	/// If specified, will read the target disk image file and attempt to carve files from it, using the default or specified configuration file and the default or specified cluster size.
+	/// Specify "-" to read the image from stdin, in which case it is searched as a stream and only a log of the potential files is output, as random-access carving isn't available. The
+	/// cluster size, region and partition options have no effect on stdin
 	#[arg(short, long)]
 	pub image: Option<String>,
 	/// The cluster size that the filesystem that is/was present in the disk image allocated files in, i.e. all valid non-embedded file headers will be found at multiples of this value.
@@ -30,6 +35,151 @@ pub struct Args {
 	/// If specified, will read the target log file and carve the files indicated in it. Doesn't require a config. If specified alongside input, will perform both carving operations separately
 	#[arg(short = 'l', long)]
 	pub carve_log: Option<String>,
+	/// The number of worker threads that searching on the CPU and validating share. Overrides the value in the config file, if present. Defaults to the available parallelism
+	#[arg(short = 't', long)]
+	pub threads: Option<usize>,
+	/// How the progress of searching and carving is reported: "continuous" (rewriting a line of stderr, only if it is a terminal, otherwise as
+	/// milestones), "milestones" (logging at 25%, 50%, 75% and 100%) or "silent". Overrides the value in the config file, if present. Defaults
+	/// to continuous
+	#[arg(long)]
+	pub progress: Option<ProgressMode>,
+	/// Only carve the file types with these extensions or type IDs, separated by commas, e.g. "jpg,png", rather than all configured file types.
+	/// Overrides the value in the config file, if present
+	#[arg(long, value_delimiter = ',')]
+	pub only: Vec<String>,
+	/// Don't carve the file types with these extensions or type IDs, separated by commas, e.g. "zip". Overrides the value in the config file,
+	/// if present
+	#[arg(long, value_delimiter = ',')]
+	pub exclude_types: Vec<String>,
+	/// How image files are read: "mmap" (memory mapped, which is fastest) or "stream" (a block at a time, in which case the found files are
+	/// only logged, not validated or carved). Overrides the value in the config file, if present. Defaults to mmap
+	#[arg(long)]
+	pub io_strategy: Option<IoStrategy>,
+	/// If specified, only the region of the image between the two byte offsets, specified as "<start>..<end>", will be searched and carved from.
+	/// Offsets in the output and carve log remain relative to the start of the image. Has no effect when processing a log
+	#[arg(short, long)]
+	pub region: Option<RegionArg>,
+	/// Whether to look for a MBR/GPT partition table at the start of the image and, if found, search and carve each partition separately, which allows
+	/// the cluster size to be estimated per partition. The space outside of the partitions is searched separately too. Falls back to processing the whole image if no recognised partition table is found. Has no effect
+	/// when processing a log
+	#[arg(short, long)]
+	pub partitions: bool,
+	/// Whether to also output a mactime bodyfile, for use with timelining tools, of the carved files that have a modification time that could be extracted
+	/// (e.g. from ZIP file headers or PNG tIME chunks). Has no effect when processing a log or reading from stdin
+	#[arg(short, long)]
+	pub bodyfile: bool,
+	/// Whether to carve into the output directory even if it already exists, instead of erroring. Carved files whose names are already taken are given
+	/// a numeric suffix, and the carve log is merged into the existing log of the same image, or written as a separate log_<n>.json if there is none.
+	/// Has no effect when processing a log or reading from stdin
+	#[arg(short, long)]
+	pub append: bool,
+	/// Whether to also output a log of the candidates that were not carved as they were unrecognised by validation or dropped in pairing,
+	/// with the reason, as rejects.jsonl, for investigating near misses. Overrides the value in the config file, if set. Has no effect when
+	/// processing a log or reading from stdin
+	#[arg(long)]
+	pub rejects_log: bool,
+	/// Whether to also output a run-length encoded map of which parts of the image were claimed by carved files, and of what type, as
+	/// carve_map.json. Overrides the value in the config file, if set. Has no effect when processing a log or reading from stdin
+	#[arg(long)]
+	pub carve_map: bool,
+	/// The number of bytes that each cell of the carve map covers. Overrides the value in the config file, if present. Defaults to the cluster
+	/// size if one is given, otherwise 4096
+	#[arg(long)]
+	pub carve_map_resolution: Option<u64>,
+	/// Whether to also extract the members of carved ZIP archives and the thumbnails of carved JPEGs into a directory alongside each file,
+	/// recording them under the file in the carve log. Overrides the value in the config file, if set
+	#[arg(long)]
+	pub extract_embedded: bool,
+	/// Whether to record the files extracted by --extract-embedded as entries of their own in the carve log, referencing the entry of the file
+	/// they were extracted from as their parent, rather than under it. Overrides the value in the config file, if set
+	#[arg(long)]
+	pub embedded_entries: bool,
+	/// Whether to name carved files by the names recovered from them where possible, e.g. extracting ZIP members to the paths recorded in the
+	/// archive, falling back to the usual name if there isn't one or it's taken. Overrides the value in the config file, if set
+	#[arg(long)]
+	pub original_names: bool,
+	/// Whether to record how fragmented each carved file is (its number of fragments, and the bytes between them) in the carve log, and
+	/// report the mean and maximum. Overrides the value in the config file, if set. Has no effect when processing a log
+	#[arg(long)]
+	pub fragmentation_stats: bool,
+	/// Whether to also carve the file slack of each carved file, i.e. the rest of the cluster after the end of the file, as <filename>.slack,
+	/// recording its range in the carve log. Overrides the value in the config file, if set
+	#[arg(long)]
+	pub slack: bool,
+	/// Whether to also carve any data appended after the end of each correct file, up to the next header found, as <filename>.appended,
+	/// recording its range in the carve log. Overrides the value in the config file, if set
+	#[arg(long)]
+	pub appended_data: bool,
+	/// Whether to measure and report the time spent in each phase of processing an image (searching, estimating the cluster size, pairing,
+	/// validating and carving), and the search throughput. Overrides the value in the config file, if set
+	#[arg(long)]
+	pub timings: bool,
+	/// Whether to read the cluster size from a FAT, exFAT or NTFS boot sector at the start of the image (or each partition), if the cluster
+	/// size is unknown, before estimating it. Overrides the value in the config file, if set
+	#[arg(long)]
+	pub boot_sector_cluster_size: bool,
+	/// Whether to record the CRC-32 of each fragment of each carved file in the carve log, so that other tools can check that the files
+	/// were assembled correctly. Overrides the value in the config file, if set. Has no effect when processing a log
+	#[arg(long)]
+	pub fragment_checksums: bool,
+	/// The unit of the offsets in the filenames of carved files: "bytes", "sectors" (of 512 bytes) or "clusters", e.g. to cross-reference with
+	/// sector-based tools. Overrides the value in the config file, if present. Defaults to bytes
+	#[arg(long)]
+	pub filename_offset_unit: Option<OffsetUnit>,
+	/// How carved files are arranged in subdirectories of the output directory: "validation" (<validation>/<file>), "extension_validation"
+	/// (<extension>/<validation>/<file>) or "validation_extension" (<validation>/<extension>/<file>). Overrides the value in the config file,
+	/// if present. Defaults to validation
+	#[arg(long)]
+	pub output_layout: Option<OutputLayout>,
+	/// Whether to validate and carve files while the image is still being searched, rather than once the search is complete. Requires a
+	/// known cluster size (see --cluster-size and --boot-sector-cluster-size). Overrides the value in the config file, if set
+	#[arg(long)]
+	pub pipelined_validation: bool,
+	/// The number of milliseconds that each candidate may be validated for before it is given up on and carved unanalysed. Overrides the
+	/// value in the config file, if present. Defaults to no limit
+	#[arg(long)]
+	pub validation_timeout_ms: Option<u64>,
+	/// Whether to fail instead of falling back to searching on the CPU if the GPU can't be used. Overrides the value in the config file, if set
+	#[arg(long)]
+	pub require_gpu: bool,
+	/// The number of seconds to wait for each search on the GPU to complete before it times out. Overrides the value in the config file, if
+	/// present. Defaults to 30
+	#[arg(long)]
+	pub gpu_timeout: Option<u64>,
+	/// Whether to switch to searching on the CPU for the rest of the run if a search on the GPU times out, rather than failing. Overrides the
+	/// value in the config file, if set
+	#[arg(long)]
+	pub gpu_timeout_fallback: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+	/// Runs the validators against each sample file in a directory, printing the validation result of each and whether it was as expected.
+	/// The file type of each sample is taken from its extension, using the default or specified configuration file. The expected result
+	/// (e.g. "correct", "partial", "format_error") can be given in a manifest file "expected.toml" in the directory, mapping file names to
+	/// results, or in the file name itself, as the last component before the extension (e.g. "sample.correct.jpg")
+	TestValidators {
+		/// The directory of sample files
+		dir: String
+	},
+	/// Lists the file type IDs that have a dedicated validator, with what each validator checks and whether it reconstructs fragmented files.
+	/// Other file types are only validated if configured with a size field or required byte sequences
+	ListValidators,
+	/// Reports how each file type in the default or specified configuration file will be validated: by a dedicated validator, generically
+	/// by a size field or required sequences, or not at all (carved by the span from header to footer). File types that aren't validated by a
+	/// dedicated validator but have an extension naming a type ID that is (e.g. a "png" type with type_id "unknown") are pointed out
+	ConfigCoverage,
+	/// Converts a carve log to another format, e.g. a binary log to JSON, so it can be read by other tools or edited. The format of the input
+	/// log is detected from its content. CSV logs can't be converted back, as they can't be read
+	ConvertLog {
+		/// The carve log to convert
+		input: String,
+		/// The path to write the converted log to
+		output: String,
+		/// The format to convert the log to: "json", "binary" or "csv"
+		#[arg(short = 'F', long, default_value = "json")]
+		format: LogFormat
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -59,4 +209,29 @@ impl ClusterSizeArg {
 			ClusterSizeArg::Known(val) => Some(*val)
 		}
 	}
+}
+
+#[derive(Debug, Clone)]
+pub struct RegionArg {
+	pub start: u64,
+	pub end: u64
+}
+
+impl FromStr for RegionArg {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (start, end) = s.trim().split_once("..").ok_or("Region must be specified as <start>..<end>".to_string())?;
+
+		Ok(RegionArg {
+			start: start.trim().parse().map_err(|e: ParseIntError| e.to_string())?,
+			end: end.trim().parse().map_err(|e: ParseIntError| e.to_string())?
+		})
+	}
+}
+
+impl RegionArg {
+	pub fn as_range(&self) -> Range<u64> {
+		self.start..self.end
+	}
 }
\ No newline at end of file
@@ -1,17 +1,18 @@
 mod args;
+mod validator_tests;
 
-use std::{fs, io::Write, time::SystemTime};
+use std::{fs, io::Write, path::Path, process, time::SystemTime};
 
-use args::Args;
+use args::{Args, Command};
 use clap::Parser;
-use libsearchlight::searchlight::{CarveOperationInfo, Searchlight};
+use libsearchlight::{searchlight::{carve_log::CarveLog, config::SearchlightConfig, CarveOperationInfo, ImageOptions, Searchlight}, validation::DelegatingValidator};
 use log::{debug, error, info};
 
 #[cfg(not(target_pointer_width = "64"))]
 compile_error!("Target architecture is not 64-bit - This software is only supported on 64-bit platforms");
 
 fn main() {
-	let mut args = Args::parse();
+	let args = Args::parse();
 
 	env_logger::Builder::new()
 		.filter_level(args.verbose.log_level_filter())
@@ -25,31 +26,177 @@ fn main() {
 
 	let mut searchlight = Searchlight::default();
 
-	if let Some(image_path) = args.image {
-		args.config = Some(args.config.unwrap_or("Searchlight.toml".to_string()));
-
-		let config = match fs::read_to_string(args.config.as_ref().unwrap()) {
-			Ok(config_string) => match toml::from_str(&config_string) {
-				Ok(config) => config,
-				Err(e) => {
-					error!("Error processing config file \"{}\": {}", args.config.unwrap(), e);
-					return;
-				}
-			},
+	if let Some(Command::TestValidators { dir }) = &args.command {
+		let Some(config) = load_config(args.config.as_deref().unwrap_or("Searchlight.toml")) else {
+			return;
+		};
+
+		if !validator_tests::test_validators(&mut searchlight, dir, &config, args.cluster_size.as_option().unwrap_or(1) as usize) {
+			process::exit(1);
+		}
+
+		return;
+	}
+
+	if let Some(Command::ListValidators) = &args.command {
+		for validator in DelegatingValidator::new().supported_types() {
+			println!("{}{}: {}", validator.type_id, if validator.reconstructs_fragmentation { " (reconstructs fragmentation)" } else { "" }, validator.description);
+		}
+
+		return;
+	}
+
+	if let Some(Command::ConfigCoverage) = &args.command {
+		let Some(config) = load_config(args.config.as_deref().unwrap_or("Searchlight.toml")) else {
+			return;
+		};
+
+		for coverage in DelegatingValidator::new().coverage(&config) {
+			println!("{}", coverage);
+		}
+
+		return;
+	}
+
+	if let Some(Command::ConvertLog { input, output, format }) = &args.command {
+		match CarveLog::read(input).and_then(|log| log.write_to(Path::new(output), *format).map_err(|e| e.into())) {
+			Ok(()) => info!("Converted carve log \"{}\" to \"{}\"", input, output),
 			Err(e) => {
-				error!("Could not open config file \"{}\": {}", args.config.unwrap(), e);
-				return;
+				error!("Failed to convert carve log \"{}\": {}", input, e);
+				process::exit(1);
 			}
+		}
+
+		return;
+	}
+
+	if let Some(image_path) = args.image {
+		let Some(mut config) = load_config(args.config.as_deref().unwrap_or("Searchlight.toml")) else {
+			return;
 		};
 
+		if let Some(threads) = args.threads {
+			config.threads = Some(threads);
+		}
+
+		if let Some(progress) = args.progress {
+			config.progress = progress;
+		}
+
+		if let Some(io_strategy) = args.io_strategy {
+			config.io_strategy = io_strategy;
+		}
+
+		if args.rejects_log {
+			config.rejects_log = true;
+		}
+
+		if args.carve_map {
+			config.carve_map = true;
+		}
+
+		if let Some(carve_map_resolution) = args.carve_map_resolution {
+			config.carve_map_resolution = Some(carve_map_resolution);
+		}
+
+		if args.extract_embedded {
+			config.extract_embedded = true;
+		}
+
+		if args.embedded_entries {
+			config.embedded_entries = true;
+		}
+
+		if args.original_names {
+			config.original_names = true;
+		}
+
+		if args.fragmentation_stats {
+			config.fragmentation_stats = true;
+		}
+
+		if args.slack {
+			config.carve_slack = true;
+		}
+
+		if args.appended_data {
+			config.appended_data = true;
+		}
+
+		if args.timings {
+			config.timings = true;
+		}
+
+		if args.boot_sector_cluster_size {
+			config.boot_sector_cluster_size = true;
+		}
+
+		if args.fragment_checksums {
+			config.fragment_checksums = true;
+		}
+
+		if let Some(filename_offset_unit) = args.filename_offset_unit {
+			config.filename_offset_unit = filename_offset_unit;
+		}
+
+		if let Some(output_layout) = args.output_layout {
+			config.output_layout = output_layout;
+		}
+
+		if args.pipelined_validation {
+			config.pipelined_validation = true;
+		}
+
+		if let Some(validation_timeout_ms) = args.validation_timeout_ms {
+			config.validation_timeout_ms = Some(validation_timeout_ms);
+		}
+
+		if args.require_gpu {
+			config.require_gpu = true;
+		}
+
+		if let Some(gpu_timeout) = args.gpu_timeout {
+			config.gpu_timeout = Some(gpu_timeout);
+		}
+
+		if args.gpu_timeout_fallback {
+			config.gpu_timeout_fallback = true;
+		}
+
+		if !args.only.is_empty() {
+			config.only_types = args.only;
+		}
+
+		if !args.exclude_types.is_empty() {
+			config.exclude_types = args.exclude_types;
+		}
+
 		debug!("Config: {:?}", config);
 
-		searchlight.add_operation(CarveOperationInfo::Image {
-			path: image_path,
-			config,
-			cluster_size: args.cluster_size.as_option(),
-			skip_carving: args.skip_carving
-		});
+		for coverage in DelegatingValidator::new().coverage(&config) {
+			debug!("Validation coverage: {}", coverage);
+		}
+
+		if image_path == "-" {
+			info!("Reading image from stdin - Files will not be validated or carved, only a log of the potential files will be output");
+
+			searchlight.add_operation(CarveOperationInfo::Stdin {
+				config
+			});
+		} else {
+			searchlight.add_operation(CarveOperationInfo::Image {
+				path: image_path,
+				config,
+				cluster_size: args.cluster_size.as_option(),
+				skip_carving: args.skip_carving,
+				options: ImageOptions {
+					region: args.region.as_ref().map(|region| region.as_range()),
+					scan_partitions: args.partitions,
+					write_bodyfile: args.bodyfile,
+					append: args.append
+				}
+			});
+		}
 	}
 
 	if let Some(log_path) = args.carve_log {
@@ -62,6 +209,10 @@ fn main() {
 		match searchlight.process_file(args.out_dir.clone().unwrap_or(humantime::format_rfc3339(SystemTime::now()).to_string())) {
 			(Some(info), Ok(true)) => {
 				info!("Finished processing file \"{}\"", info.path());
+
+				if let Some(backend) = searchlight.active_backend() {
+					info!("Searched using the {} searcher", backend);
+				}
 			}
 			(_, Ok(false)) => {
 				info!("No files left to process, exiting");
@@ -75,4 +226,31 @@ fn main() {
 			}
 		}
 	}
+}
+
+/// Reads and parses the config file at `config_path`, and loads the pattern files it references, logging an error and returning None
+/// if any of that fails
+fn load_config(config_path: &str) -> Option<SearchlightConfig> {
+	let mut config: SearchlightConfig = match fs::read_to_string(config_path) {
+		Ok(config_string) => match toml::from_str(&config_string) {
+			Ok(config) => config,
+			Err(e) => {
+				error!("Error processing config file \"{}\": {}", config_path, e);
+				return None;
+			}
+		},
+		Err(e) => {
+			error!("Could not open config file \"{}\": {}", config_path, e);
+			return None;
+		}
+	};
+
+	// Pattern files referenced by the config are relative to the directory of the config file
+	let config_dir = Path::new(config_path).parent().unwrap_or(Path::new(""));
+	if let Err(e) = config.load_pattern_files(config_dir) {
+		error!("Error loading patterns for config file \"{}\": {}", config_path, e);
+		return None;
+	}
+
+	Some(config)
 }
\ No newline at end of file
@@ -0,0 +1,100 @@
+use std::{collections::HashMap, fs, path::Path, str::FromStr};
+
+use libsearchlight::{searchlight::{config::SearchlightConfig, Searchlight}, validation::FileValidationType};
+use log::{error, warn};
+
+/// The name of the manifest file, mapping sample file names to their expected validation results
+const MANIFEST_FILENAME: &str = "expected.toml";
+
+/// Runs the validator of `searchlight` against each sample file in `dir`, printing the result for each and a summary. Returns whether every
+/// sample with an expected result validated as expected
+pub fn test_validators(searchlight: &mut Searchlight, dir: &str, config: &SearchlightConfig, cluster_size: usize) -> bool {
+	let manifest: HashMap<String, FileValidationType> = match fs::read_to_string(Path::new(dir).join(MANIFEST_FILENAME)) {
+		Ok(manifest_string) => match toml::from_str(&manifest_string) {
+			Ok(manifest) => manifest,
+			Err(e) => {
+				error!("Error processing manifest file \"{}\": {}", MANIFEST_FILENAME, e);
+				return false;
+			}
+		},
+		Err(_) => HashMap::new()
+	};
+
+	let mut paths: Vec<_> = match fs::read_dir(dir) {
+		Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.is_file()).collect(),
+		Err(e) => {
+			error!("Could not read directory \"{}\": {}", dir, e);
+			return false;
+		}
+	};
+	paths.sort();
+
+	let (mut num_passed, mut num_failed, mut num_unchecked) = (0, 0, 0);
+
+	for path in paths {
+		let filename = path.file_name().unwrap().to_string_lossy().to_string();
+
+		if filename == MANIFEST_FILENAME {
+			continue;
+		}
+
+		let Some(extension) = path.extension().map(|ext| ext.to_string_lossy().to_lowercase()) else {
+			warn!("Skipping \"{}\": No extension to identify the file type by", filename);
+			continue;
+		};
+
+		let Some(file_type) = config.file_types.iter().find(|ft| ft.extension.as_ref().is_some_and(|ft_ext| ft_ext.to_lowercase() == extension)) else {
+			warn!("Skipping \"{}\": No file type with extension \"{}\" in the config", filename, extension);
+			continue;
+		};
+
+		let data = match fs::read(&path) {
+			Ok(data) if !data.is_empty() => data,
+			Ok(_) => {
+				warn!("Skipping \"{}\": File is empty", filename);
+				continue;
+			}
+			Err(e) => {
+				error!("Could not read \"{}\": {}", filename, e);
+				num_failed += 1;
+				continue;
+			}
+		};
+
+		// The expected result is given by the manifest, or otherwise by the file name, e.g. "sample.correct.jpg"
+		let expected = manifest.get(&filename).copied().or_else(|| {
+			let stem = path.file_stem()?.to_string_lossy().to_string();
+			FileValidationType::from_str(stem.rsplit('.').next()?).ok()
+		});
+
+		let validation = match searchlight.validate_sample(&data, file_type, config, cluster_size) {
+			Ok(validation) => validation,
+			Err(e) => {
+				error!("Could not validate \"{}\": {}", filename, e);
+				num_failed += 1;
+				continue;
+			}
+		};
+
+		let reason = validation.rejection_reason.map(|reason| format!(" ({reason})")).unwrap_or_default();
+
+		match expected {
+			Some(expected) if expected == validation.validation_type => {
+				num_passed += 1;
+				println!("PASS {filename}: {}{reason}", validation.validation_type);
+			}
+			Some(expected) => {
+				num_failed += 1;
+				println!("FAIL {filename}: {}{reason}, expected {expected}", validation.validation_type);
+			}
+			None => {
+				num_unchecked += 1;
+				println!("---- {filename}: {}{reason}", validation.validation_type);
+			}
+		}
+	}
+
+	println!("{num_passed} passed, {num_failed} failed, {num_unchecked} without an expected result");
+
+	num_failed == 0
+}
\ No newline at end of file